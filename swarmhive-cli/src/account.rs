@@ -0,0 +1,103 @@
+//! On-chain signer account state
+//!
+//! A cheap anti-sybil heuristic for coordinators: a throwaway key has no
+//! balance and has never sent a transaction. Either can be required of a
+//! snapshot's signer once its signature has checked out. The lookups are
+//! behind `AccountReader` so an RPC provider can be swapped for a mock.
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::core::types::{Address, U256};
+use ethers::providers::{JsonRpcClient, Middleware, Provider};
+use ethers::utils::{format_ether, parse_units};
+
+/// Source of account balances and nonces
+#[async_trait]
+pub trait AccountReader {
+    /// Balance of `addr` in wei at the latest block
+    async fn balance(&self, addr: Address) -> Result<U256>;
+    /// Number of transactions `addr` has sent
+    async fn transaction_count(&self, addr: Address) -> Result<U256>;
+}
+
+#[async_trait]
+impl<P: JsonRpcClient> AccountReader for Provider<P> {
+    async fn balance(&self, addr: Address) -> Result<U256> {
+        Middleware::get_balance(self, addr, None)
+            .await
+            .map_err(|e| anyhow!("balance lookup for {addr:?} failed: {e}"))
+    }
+
+    async fn transaction_count(&self, addr: Address) -> Result<U256> {
+        Middleware::get_transaction_count(self, addr, None)
+            .await
+            .map_err(|e| anyhow!("transaction count lookup for {addr:?} failed: {e}"))
+    }
+}
+
+/// Require `signer` to hold at least `min_balance` wei and, with
+/// `require_activity`, to have sent at least one transaction
+pub async fn check_activity<R: AccountReader + ?Sized>(
+    signer: Address,
+    min_balance: Option<U256>,
+    require_activity: bool,
+    reader: &R,
+) -> Result<()> {
+    if let Some(min) = min_balance {
+        let balance = reader.balance(signer).await?;
+        if balance < min {
+            return Err(anyhow!(
+                "signer {signer:?} holds {} ETH, below the required {} ETH",
+                format_ether(balance),
+                format_ether(min)
+            ));
+        }
+    }
+    if require_activity && reader.transaction_count(signer).await?.is_zero() {
+        return Err(anyhow!("signer {signer:?} has never sent a transaction"));
+    }
+    Ok(())
+}
+
+/// Parse an amount of ether given with an eth, gwei or wei suffix (e.g.
+/// 0.01eth, 5gwei); a bare number is ether
+pub fn parse_eth_amount(s: &str) -> Result<U256, String> {
+    let s = s.trim();
+    let (amount, unit) = match s.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(i) => s.split_at(i),
+        None => (s, "eth"),
+    };
+    let unit = match unit.to_ascii_lowercase().as_str() {
+        "eth" | "ether" => "ether",
+        "gwei" => "gwei",
+        "wei" => "wei",
+        _ => {
+            return Err(format!(
+                "invalid amount unit in {s:?} (use eth, gwei or wei)"
+            ))
+        }
+    };
+    let amount = amount.trim();
+    if amount.is_empty() {
+        return Err(format!("invalid amount: {s:?}"));
+    }
+    parse_units(amount, unit)
+        .map(Into::into)
+        .map_err(|_| format!("invalid amount: {s:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts_parse_in_each_unit() {
+        let gwei = U256::exp10(9);
+        assert_eq!(parse_eth_amount("0.01eth").unwrap(), gwei * 10_000_000);
+        assert_eq!(parse_eth_amount("1").unwrap(), U256::exp10(18));
+        assert_eq!(parse_eth_amount("5gwei").unwrap(), gwei * 5);
+        assert_eq!(parse_eth_amount("42wei").unwrap(), U256::from(42));
+        assert!(parse_eth_amount("1btc").is_err());
+        assert!(parse_eth_amount("eth").is_err());
+    }
+}
@@ -0,0 +1,86 @@
+//! Public key to address mapping
+//!
+//! Recovery yields a secp256k1 public key; turning it into an address is
+//! chain-specific. Ethereum takes the last 20 bytes of keccak256 of the
+//! uncompressed key, which is the default everywhere. Chains that derive
+//! addresses differently supply their own `AddressDeriver`.
+
+use anyhow::{anyhow, Result};
+use ethers::core::k256::ecdsa::{RecoveryId, Signature as EcdsaSignature, VerifyingKey};
+use ethers::core::types::{Address, Signature};
+use ethers::utils::keccak256;
+
+/// Maps a recovered public key to a chain address
+pub trait AddressDeriver {
+    /// `public_key` is the 64-byte uncompressed key without the 0x04 tag
+    fn derive(&self, public_key: &[u8; 64]) -> Address;
+}
+
+/// keccak256(public key)[12..]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EthereumDeriver;
+
+impl AddressDeriver for EthereumDeriver {
+    fn derive(&self, public_key: &[u8; 64]) -> Address {
+        Address::from_slice(&keccak256(public_key)[12..])
+    }
+}
+
+/// Recover the uncompressed public key that produced `sig` over `prehash`
+pub fn recover_public_key(prehash: [u8; 32], sig: &Signature) -> Result<[u8; 64]> {
+    let mut r = [0u8; 32];
+    let mut s = [0u8; 32];
+    sig.r.to_big_endian(&mut r);
+    sig.s.to_big_endian(&mut s);
+    let ecdsa = EcdsaSignature::from_scalars(r, s)
+        .map_err(|e| anyhow!("invalid signature scalars: {e}"))?;
+
+    let v = if sig.v >= 27 { sig.v - 27 } else { sig.v };
+    let recovery_id = u8::try_from(v)
+        .ok()
+        .and_then(RecoveryId::from_byte)
+        .ok_or_else(|| anyhow!("invalid recovery id v={}", sig.v))?;
+
+    let key = VerifyingKey::recover_from_prehash(&prehash, &ecdsa, recovery_id)
+        .map_err(|e| anyhow!("public key recovery failed: {e}"))?;
+    let point = key.to_encoded_point(false);
+    let mut out = [0u8; 64];
+    out.copy_from_slice(&point.as_bytes()[1..]);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::{self, recover_signer_with};
+    use crate::test_support::{wallet, KEY_A};
+    use ethers::signers::Signer;
+    use serde_json::json;
+
+    /// Stand-in for a chain that takes the leading bytes of the raw key
+    struct FirstBytesDeriver;
+
+    impl AddressDeriver for FirstBytesDeriver {
+        fn derive(&self, public_key: &[u8; 64]) -> Address {
+            Address::from_slice(&public_key[..20])
+        }
+    }
+
+    #[tokio::test]
+    async fn recovery_maps_through_custom_deriver() {
+        let wallet = wallet(KEY_A);
+        let mut snap = json!({ "id": "snap-1", "body": { "k": 1 } });
+        signing::sign_snapshot(&mut snap, &wallet).await.unwrap();
+
+        let public_key = wallet.signer().verifying_key().to_encoded_point(false);
+        let expected = Address::from_slice(&public_key.as_bytes()[1..21]);
+
+        assert_eq!(
+            recover_signer_with(&snap, &FirstBytesDeriver).unwrap(),
+            expected
+        );
+        assert_ne!(expected, wallet.address());
+        // Default stays Ethereum
+        assert_eq!(signing::recover_signer(&snap).unwrap(), wallet.address());
+    }
+}
@@ -0,0 +1,157 @@
+//! On-chain anchors
+//!
+//! A producer can anchor a snapshot by storing its payload hash in a
+//! contract under a key, `anchors(bytes32 key) returns (bytes32)`.
+//! `check_anchor` recomputes a local snapshot's payload hash and compares
+//! it with the anchored value, tying the content to that on-chain
+//! commitment. A key that was never written reads as the zero hash and is
+//! reported as unanchored rather than as a mismatch.
+
+use crate::quorum::eth_call;
+use crate::signing::{hash_str, payload_hash_keccak};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::core::types::Address;
+use ethers::providers::{JsonRpcClient, Provider};
+use ethers::utils::keccak256;
+use serde_json::Value;
+use std::fmt;
+
+/// `anchors(bytes32)` selector
+const ANCHORS: [u8; 4] = [0xb0, 0x1b, 0x6d, 0x53];
+
+/// Source of anchored hashes
+#[async_trait]
+pub trait AnchorReader {
+    /// The hash `contract` holds under `key`; all zeros if none
+    async fn anchored_hash(&self, contract: Address, key: [u8; 32]) -> Result<[u8; 32]>;
+}
+
+#[async_trait]
+impl<P: JsonRpcClient> AnchorReader for Provider<P> {
+    async fn anchored_hash(&self, contract: Address, key: [u8; 32]) -> Result<[u8; 32]> {
+        let mut data = ANCHORS.to_vec();
+        data.extend_from_slice(&key);
+        let out = eth_call(self, contract, data).await?;
+        out.as_ref()
+            .try_into()
+            .map_err(|_| anyhow!("anchors returned {} bytes, expected 32", out.len()))
+    }
+}
+
+/// The bytes32 key for `id`: a 0x-prefixed 32-byte hex string is used as
+/// is, anything else is keccak256 of its UTF-8 bytes
+pub fn anchor_key(id: &str) -> [u8; 32] {
+    match id
+        .strip_prefix("0x")
+        .filter(|hex| hex.len() == 64)
+        .and_then(|hex| hex::decode(hex).ok())
+    {
+        Some(bytes) => bytes.try_into().expect("64 hex digits are 32 bytes"),
+        None => keccak256(id.as_bytes()),
+    }
+}
+
+/// How a local snapshot compares with its anchor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnchorStatus {
+    /// The anchored hash is the snapshot's payload hash
+    Matches([u8; 32]),
+    /// Something else is anchored under the key
+    Mismatch { local: [u8; 32], anchored: [u8; 32] },
+    /// Nothing is anchored under the key
+    Unanchored { local: [u8; 32] },
+}
+
+impl fmt::Display for AnchorStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnchorStatus::Matches(hash) => write!(f, "anchored hash matches {}", hash_str(*hash)),
+            AnchorStatus::Mismatch { local, anchored } => write!(
+                f,
+                "anchored hash {} does not match the snapshot's {}",
+                hash_str(*anchored),
+                hash_str(*local)
+            ),
+            AnchorStatus::Unanchored { local } => {
+                write!(f, "nothing anchored for {}", hash_str(*local))
+            }
+        }
+    }
+}
+
+/// Compare `snapshot`'s recomputed payload hash with the one `contract`
+/// holds under `key`
+pub async fn check_anchor<R: AnchorReader + ?Sized>(
+    snapshot: &Value,
+    contract: Address,
+    key: [u8; 32],
+    reader: &R,
+) -> Result<AnchorStatus> {
+    let local = payload_hash_keccak(snapshot)?;
+    let anchored = reader.anchored_hash(contract, key).await?;
+    Ok(if anchored == [0; 32] {
+        AnchorStatus::Unanchored { local }
+    } else if anchored == local {
+        AnchorStatus::Matches(local)
+    } else {
+        AnchorStatus::Mismatch { local, anchored }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    struct MockAnchors(HashMap<[u8; 32], [u8; 32]>);
+
+    #[async_trait]
+    impl AnchorReader for MockAnchors {
+        async fn anchored_hash(&self, _contract: Address, key: [u8; 32]) -> Result<[u8; 32]> {
+            Ok(self.0.get(&key).copied().unwrap_or([0; 32]))
+        }
+    }
+
+    #[test]
+    fn selector_and_keys() {
+        assert_eq!(keccak256("anchors(bytes32)")[..4], ANCHORS);
+        let raw = format!("0x{}", "11".repeat(32));
+        assert_eq!(anchor_key(&raw), [0x11; 32]);
+        assert_eq!(anchor_key("epoch-7"), keccak256("epoch-7"));
+    }
+
+    #[tokio::test]
+    async fn compares_with_anchored_hash() {
+        let snap = json!({ "id": "epoch-7-seal", "body": { "root": "0xabc" }, "signing": { "scheme": "eip191" } });
+        let local = payload_hash_keccak(&snap).unwrap();
+        let contract = Address::repeat_byte(0xac);
+        let anchors = MockAnchors(HashMap::from([
+            (anchor_key("epoch-7"), local),
+            (anchor_key("epoch-8"), [0x99; 32]),
+        ]));
+
+        let status = check_anchor(&snap, contract, anchor_key("epoch-7"), &anchors)
+            .await
+            .unwrap();
+        assert_eq!(status, AnchorStatus::Matches(local));
+
+        let status = check_anchor(&snap, contract, anchor_key("epoch-8"), &anchors)
+            .await
+            .unwrap();
+        assert_eq!(
+            status,
+            AnchorStatus::Mismatch {
+                local,
+                anchored: [0x99; 32]
+            }
+        );
+        assert!(status.to_string().contains("does not match"), "{status}");
+
+        let status = check_anchor(&snap, contract, anchor_key("epoch-9"), &anchors)
+            .await
+            .unwrap();
+        assert_eq!(status, AnchorStatus::Unanchored { local });
+    }
+}
@@ -0,0 +1,118 @@
+//! External artifacts bound to a snapshot by hash
+//!
+//! Large blobs (model weights, datasets) stay outside the snapshot; the
+//! snapshot lists them in a top-level `artifacts` array before signing:
+//!
+//! ```json
+//! "artifacts": [{ "name": "weights.bin", "hash": "keccak256:..." }]
+//! ```
+//!
+//! The array is ordinary content, so the signature covers the references.
+//! A verifier re-hashes its copy of the file and looks the hash up by name.
+
+use crate::hash::{hash_str, parse_payload_hash};
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use tiny_keccak::{Hasher, Keccak};
+
+/// keccak256 of a file, read in chunks so large blobs are not loaded whole
+pub fn hash_file(path: &Path) -> Result<[u8; 32]> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Keccak::v256();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file
+            .read(&mut buf)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    let mut out = [0u8; 32];
+    hasher.finalize(&mut out);
+    Ok(out)
+}
+
+/// Name an artifact is listed under: its file name
+fn artifact_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("{} has no usable file name", path.display()))
+}
+
+/// Hash `path` and record it in `artifacts`, replacing an entry of the
+/// same name. Must happen before signing.
+pub fn attach_artifact(snapshot: &mut Value, path: &Path) -> Result<[u8; 32]> {
+    let name = artifact_name(path)?;
+    let hash = hash_file(path)?;
+    let artifacts = snapshot
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("snapshot must be a JSON object"))?
+        .entry("artifacts")
+        .or_insert_with(|| json!([]))
+        .as_array_mut()
+        .ok_or_else(|| anyhow!("artifacts must be an array"))?;
+    artifacts.retain(|a| a.get("name").and_then(|v| v.as_str()) != Some(name.as_str()));
+    artifacts.push(json!({ "name": name, "hash": hash_str(hash) }));
+    Ok(hash)
+}
+
+/// The file at `path` must match the artifact of the same name
+pub fn check_artifact(snapshot: &Value, path: &Path) -> Result<()> {
+    let name = artifact_name(path)?;
+    let entry = snapshot
+        .get("artifacts")
+        .and_then(|v| v.as_array())
+        .and_then(|artifacts| {
+            artifacts
+                .iter()
+                .find(|a| a.get("name").and_then(|v| v.as_str()) == Some(name.as_str()))
+        })
+        .ok_or_else(|| anyhow!("snapshot has no artifact named {name}"))?;
+    let signed = entry
+        .get("hash")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("artifact {name} has no hash"))?;
+    if hash_file(path)? != parse_payload_hash(signed)? {
+        return Err(anyhow!(
+            "{} does not match the signed hash of artifact {name}",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attach_then_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("weights.bin");
+        std::fs::write(&path, vec![7u8; 200_000]).unwrap();
+
+        let mut snap = json!({ "id": "snap-1", "body": {} });
+        let hash = attach_artifact(&mut snap, &path).unwrap();
+        assert_eq!(hash, crate::hash::keccak256(vec![7u8; 200_000]));
+        attach_artifact(&mut snap, &path).unwrap();
+        assert_eq!(snap["artifacts"].as_array().unwrap().len(), 1);
+        check_artifact(&snap, &path).unwrap();
+
+        std::fs::write(&path, b"tampered").unwrap();
+        let err = check_artifact(&snap, &path).unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{err}");
+
+        let err = check_artifact(&snap, &dir.path().join("other.bin")).unwrap_err();
+        assert!(
+            err.to_string().contains("no artifact named other.bin"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! BLS12-381 aggregate signatures (feature `bls`)
+//!
+//! For large committees one aggregate signature over the payload hash
+//! replaces a signature per member. Every member signs the same payload
+//! hash; the signatures and the members' public keys are each aggregated,
+//! and verification is a single pairing check.
+//!
+//! A `bls` snapshot records `signing.scheme = "bls"` and the committee's
+//! aggregate key in `signing.aggregate_pubkey` before hashing, so the hash
+//! is bound to the committee. Signatures are stored as `bls:0x<96 bytes>`.
+//! Uses the min-pk ciphersuite with proof-of-possession; member keys must be
+//! checked for possession before they are aggregated, or a rogue key can
+//! forge the aggregate. BLS snapshots do not recover to an address, so the
+//! ECDSA helpers in `signing` reject them.
+
+use crate::signing::{self, hash_str, parse_payload_hash, payload_hash_keccak};
+use anyhow::{anyhow, Result};
+use blst::min_pk::{AggregatePublicKey, AggregateSignature};
+use blst::BLST_ERROR;
+use serde_json::{Map, Value};
+
+pub use blst::min_pk::{PublicKey, SecretKey, Signature};
+
+/// Value of signing.scheme for BLS snapshots
+pub const SCHEME: &str = "bls";
+
+/// Ciphersuite domain separation tag
+const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+/// Derive a secret key from at least 32 bytes of input keying material
+pub fn secret_key_from_ikm(ikm: &[u8]) -> Result<SecretKey> {
+    SecretKey::key_gen(ikm, &[]).map_err(|e| anyhow!("BLS key generation failed: {e:?}"))
+}
+
+/// Aggregate member public keys into the committee key
+pub fn aggregate_public_keys(keys: &[PublicKey]) -> Result<PublicKey> {
+    let refs: Vec<&PublicKey> = keys.iter().collect();
+    AggregatePublicKey::aggregate(&refs, true)
+        .map(|agg| agg.to_public_key())
+        .map_err(|e| anyhow!("public key aggregation failed: {e:?}"))
+}
+
+/// Aggregate member signatures over the same payload hash
+pub fn aggregate_signatures(sigs: &[Signature]) -> Result<Signature> {
+    let refs: Vec<&Signature> = sigs.iter().collect();
+    AggregateSignature::aggregate(&refs, true)
+        .map(|agg| agg.to_signature())
+        .map_err(|e| anyhow!("signature aggregation failed: {e:?}"))
+}
+
+/// Mark `snapshot` as a BLS snapshot for the committee `aggregate_pubkey`.
+/// Must happen before any member signs, as both fields are hashed.
+pub fn prepare(snapshot: &mut Value, aggregate_pubkey: &PublicKey) -> Result<()> {
+    let signing = snapshot
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("snapshot must be a JSON object"))?
+        .entry("signing")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("signing must be object"))?;
+    signing.insert("scheme".to_string(), Value::String(SCHEME.to_string()));
+    signing.insert(
+        "aggregate_pubkey".to_string(),
+        Value::String(pubkey_str(aggregate_pubkey)),
+    );
+    Ok(())
+}
+
+/// One member's signature over the snapshot's payload hash
+pub fn sign_bls(snapshot: &Value, sk: &SecretKey) -> Result<Signature> {
+    require_bls(snapshot)?;
+    Ok(sk.sign(&payload_hash_keccak(snapshot)?, DST, &[]))
+}
+
+/// Store the aggregate signature and payload hash
+pub fn attach_bls_signature(snapshot: &mut Value, aggregate: &Signature) -> Result<()> {
+    let hash = payload_hash_keccak(snapshot)?;
+    let signing = snapshot
+        .get_mut("signing")
+        .and_then(|v| v.as_object_mut())
+        .ok_or_else(|| anyhow!("snapshot missing signing object"))?;
+    signing.insert("payload_hash".to_string(), Value::String(hash_str(hash)));
+    signing.insert(
+        "signature".to_string(),
+        Value::String(format!("bls:0x{}", hex::encode(aggregate.to_bytes()))),
+    );
+    Ok(())
+}
+
+/// Verify the aggregate signature against `aggregate_pubkey`, which must be
+/// the key recorded in signing.aggregate_pubkey, over the recomputed hash.
+pub fn verify_bls_aggregate(snapshot: &Value, aggregate_pubkey: &PublicKey) -> Result<()> {
+    require_bls(snapshot)?;
+    let signing = signing::signing_object(snapshot)?;
+
+    let recorded = signing
+        .get("aggregate_pubkey")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("missing signing.aggregate_pubkey"))?;
+    if recorded != pubkey_str(aggregate_pubkey) {
+        return Err(anyhow!(
+            "aggregate public key does not match signing.aggregate_pubkey"
+        ));
+    }
+
+    let stored = signing::string_field(signing, "payload_hash")?
+        .ok_or_else(|| anyhow!("missing signing.payload_hash"))?;
+    let hash = payload_hash_keccak(snapshot)?;
+    if parse_payload_hash(stored)? != hash {
+        return Err(anyhow!("payload_hash mismatch: snapshot content changed"));
+    }
+
+    let sig_hex = signing
+        .get("signature")
+        .and_then(|v| v.as_str())
+        .and_then(|s| s.strip_prefix("bls:0x"))
+        .ok_or_else(|| anyhow!("signing.signature must start with bls:0x"))?;
+    let sig = Signature::from_bytes(&hex::decode(sig_hex)?)
+        .map_err(|e| anyhow!("malformed BLS signature: {e:?}"))?;
+
+    match sig.verify(true, &hash, DST, &[], aggregate_pubkey, true) {
+        BLST_ERROR::BLST_SUCCESS => Ok(()),
+        e => Err(anyhow!("BLS aggregate signature does not verify: {e:?}")),
+    }
+}
+
+/// "0x<48 bytes>" compressed public key
+pub fn pubkey_str(pk: &PublicKey) -> String {
+    format!("0x{}", hex::encode(pk.compress()))
+}
+
+fn require_bls(snapshot: &Value) -> Result<()> {
+    match signing::string_field(signing::signing_object(snapshot)?, "scheme")? {
+        Some(SCHEME) => Ok(()),
+        other => Err(anyhow!(
+            "expected signing.scheme {SCHEME:?}, found {other:?}"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn committee() -> Vec<SecretKey> {
+        [[1u8; 32], [2u8; 32]]
+            .iter()
+            .map(|ikm| secret_key_from_ikm(ikm).unwrap())
+            .collect()
+    }
+
+    fn signed() -> (Value, PublicKey) {
+        let members = committee();
+        let pubkeys: Vec<PublicKey> = members.iter().map(|sk| sk.sk_to_pk()).collect();
+        let agg_pk = aggregate_public_keys(&pubkeys).unwrap();
+
+        let mut snap = json!({ "id": "epoch-9-seal", "body": { "epoch": 9 } });
+        prepare(&mut snap, &agg_pk).unwrap();
+        let sigs: Vec<Signature> = members
+            .iter()
+            .map(|sk| sign_bls(&snap, sk).unwrap())
+            .collect();
+        attach_bls_signature(&mut snap, &aggregate_signatures(&sigs).unwrap()).unwrap();
+        (snap, agg_pk)
+    }
+
+    #[test]
+    fn aggregate_of_two_signers_verifies() {
+        let (snap, agg_pk) = signed();
+        verify_bls_aggregate(&snap, &agg_pk).unwrap();
+
+        // A single member's key is not the committee key
+        let solo = committee()[0].sk_to_pk();
+        assert!(verify_bls_aggregate(&snap, &solo).is_err());
+    }
+
+    #[test]
+    fn tampered_message_is_rejected() {
+        let (mut snap, agg_pk) = signed();
+        snap["body"]["epoch"] = json!(10);
+        let err = verify_bls_aggregate(&snap, &agg_pk).unwrap_err();
+        assert!(err.to_string().contains("payload_hash mismatch"), "{err}");
+
+        // Rewriting payload_hash to match does not help the signature
+        let hash = payload_hash_keccak(&snap).unwrap();
+        snap["signing"]["payload_hash"] = json!(hash_str(hash));
+        let err = verify_bls_aggregate(&snap, &agg_pk).unwrap_err();
+        assert!(err.to_string().contains("does not verify"), "{err}");
+
+        snap["signing"]["payload_hash"] = json!(["0x00"]);
+        let err = verify_bls_aggregate(&snap, &agg_pk).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "signing.payload_hash must be a string, found array"
+        );
+    }
+}
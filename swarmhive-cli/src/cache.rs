@@ -0,0 +1,133 @@
+//! Reusable canonical bytes for a shared snapshot section
+//!
+//! High-throughput producers sign many snapshots that differ only in a
+//! small part while sharing a large, immutable section (a model manifest, a
+//! node inventory). `CanonicalCache` canonicalizes that section once; each
+//! `hash_with_base` call then only canonicalizes the changing part and
+//! splices the cached bytes in. The result is exactly `payload_hash_keccak`
+//! of the combined snapshot. A snapshot recording a
+//! `signing.canonical_profile` is rewritten as a whole by that profile, so
+//! it is hashed in full, without the cached bytes.
+
+use crate::hash::{self, keccak256, CanonicalVersion};
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// Canonical bytes of a base section that lives at one top-level key
+#[derive(Debug, Clone)]
+pub struct CanonicalCache {
+    key: String,
+    version: CanonicalVersion,
+    base: Value,
+    base_bytes: Vec<u8>,
+}
+
+impl CanonicalCache {
+    /// Canonicalize `base`, which will appear as the top-level member `key`
+    /// of snapshots hashed under `version`
+    pub fn new(key: impl Into<String>, base: &Value, version: CanonicalVersion) -> Result<Self> {
+        let key = key.into();
+        if key == "signing" {
+            return Err(anyhow!(
+                "the signing object cannot be cached; it is always stripped and rehashed"
+            ));
+        }
+        Ok(Self {
+            base_bytes: hash::canonical_json_bytes_with(base, version)?,
+            base: base.clone(),
+            key,
+            version,
+        })
+    }
+
+    /// The precomputed canonical bytes of the base section
+    pub fn base_bytes(&self) -> &[u8] {
+        &self.base_bytes
+    }
+
+    /// Payload hash of `changing` with the cached base inserted at the
+    /// cache's key. `changing` must be an object without that key, using
+    /// the canonical version the cache was built for.
+    pub fn hash_with_base(&self, changing: &Value) -> Result<[u8; 32]> {
+        let map = changing
+            .as_object()
+            .ok_or_else(|| anyhow!("snapshot must be a JSON object"))?;
+        if map.contains_key(&self.key) {
+            return Err(anyhow!(
+                "snapshot already has {:?}; it comes from the cache",
+                self.key
+            ));
+        }
+        let version = hash::snapshot_canonical_version(changing)?;
+        if version != self.version {
+            return Err(anyhow!(
+                "cache holds canonical_version {} bytes but the snapshot uses {}",
+                self.version.as_u64(),
+                version.as_u64()
+            ));
+        }
+        if hash::snapshot_canonical_profile(changing)?.is_some() {
+            let mut full = map.clone();
+            full.insert(self.key.clone(), self.base.clone());
+            return hash::payload_hash_keccak(&Value::Object(full));
+        }
+        let preimage = hash::spliced_preimage(map, &self.key, &self.base_bytes, version)?;
+        Ok(keccak256(
+            hash::snapshot_payload_encoding(changing)?.encode(preimage)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::payload_hash_keccak;
+    use serde_json::json;
+
+    fn base() -> Value {
+        let layers: Vec<Value> = (0..200)
+            .map(|i| json!({ "layer": i, "sha": format!("{i:064x}") }))
+            .collect();
+        json!({ "model": "llama-7b", "layers": layers, "ratio": 0.5, "license": null })
+    }
+
+    #[test]
+    fn cached_hash_matches_full_recompute() {
+        let variations = [
+            json!({ "id": "a", "body": { "n": 1 } }),
+            json!({ "id": "b", "body": { "n": 2 }, "signing": { "scheme": "eip191", "signature": "ignored" } }),
+            // Sorts before and after the cached key
+            json!({ "aaa": true, "zzz": [1, 2, 3], "id": "c" }),
+            json!({ "id": "d", "signing": { "canonical_version": 2, "issued_at": 1_700_000_000 } }),
+            // The recorded profile drops the base's null and changes key order
+            json!({
+                "id": "e",
+                "body": { "10": 1, "9": null },
+                "signing": { "canonical_profile": { "drop_nulls": true, "numeric_key_sort": true } }
+            }),
+        ];
+
+        for changing in variations {
+            let version = hash::snapshot_canonical_version(&changing).unwrap();
+            let cache = CanonicalCache::new("manifest", &base(), version).unwrap();
+
+            let mut full = changing.clone();
+            full["manifest"] = base();
+            assert_eq!(
+                cache.hash_with_base(&changing).unwrap(),
+                payload_hash_keccak(&full).unwrap(),
+                "{changing}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_conflicts() {
+        let cache = CanonicalCache::new("manifest", &base(), CanonicalVersion::V1).unwrap();
+        assert!(cache.hash_with_base(&json!({ "manifest": 1 })).is_err());
+        assert!(cache
+            .hash_with_base(&json!({ "signing": { "canonical_version": 2 } }))
+            .is_err());
+        assert!(CanonicalCache::new("signing", &base(), CanonicalVersion::V1).is_err());
+    }
+}
@@ -0,0 +1,922 @@
+//! Canonicalization profiles
+//!
+//! A profile is a set of opt-in rewrites applied to a snapshot before it is
+//! canonicalized and hashed. The default profile changes nothing; signer and
+//! verifier must agree on the profile or the payload hash will differ. A
+//! signer can record its profile in `signing.canonical_profile` (see
+//! `to_value`), which is hashed like any signing field and then applied
+//! automatically on verification.
+
+use anyhow::{anyhow, Result};
+use base64::alphabet::URL_SAFE;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig, URL_SAFE_NO_PAD};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use serde_json::{Map, Value};
+
+/// How a numeric timestamp at a configured path is interpreted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeUnit {
+    #[default]
+    Seconds,
+    Millis,
+}
+
+/// A JSON Pointer whose value is a timestamp: RFC 3339 text with any
+/// offset, or a Unix number in `unit`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimestampPath {
+    pub pointer: String,
+    pub unit: TimeUnit,
+}
+
+impl TimestampPath {
+    pub fn new(pointer: impl Into<String>, unit: TimeUnit) -> Self {
+        Self {
+            pointer: pointer.into(),
+            unit,
+        }
+    }
+}
+
+/// What a value at a coercion path is normalized to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoerceTo {
+    /// `true`/`false`, from a boolean, the strings `"true"`/`"false"`
+    /// (any case) or `"1"`/`"0"`, or the numbers `1`/`0`
+    Boolean,
+    /// A JSON integer, from an integer or a string of decimal digits with
+    /// an optional leading `-`
+    Integer,
+}
+
+impl CoerceTo {
+    fn name(self) -> &'static str {
+        match self {
+            CoerceTo::Boolean => "boolean",
+            CoerceTo::Integer => "integer",
+        }
+    }
+}
+
+/// A JSON Pointer whose value is coerced to one canonical type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoercePath {
+    pub pointer: String,
+    pub to: CoerceTo,
+}
+
+impl CoercePath {
+    pub fn new(pointer: impl Into<String>, to: CoerceTo) -> Self {
+        Self {
+            pointer: pointer.into(),
+            to,
+        }
+    }
+}
+
+/// A JSON Pointer whose float value is rounded to `digits` significant
+/// digits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoundPath {
+    pub pointer: String,
+    pub digits: u32,
+}
+
+impl RoundPath {
+    pub fn new(pointer: impl Into<String>, digits: u32) -> Self {
+        Self {
+            pointer: pointer.into(),
+            digits,
+        }
+    }
+}
+
+/// Significant digits an f64 can carry; more would not round anything
+const MAX_SIGNIFICANT_DIGITS: u32 = 17;
+
+/// Opt-in normalization rules applied before hashing
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CanonicalProfile {
+    /// Recursively drop every object key starting with `_` (local-only fields)
+    pub ignore_underscore_keys: bool,
+    /// Lowercase string values that are exactly a 0x-prefixed 20-byte address,
+    /// so checksummed and lowercase spellings hash the same
+    pub normalize_addresses: bool,
+    /// Drop object members whose value is null
+    pub drop_nulls: bool,
+    /// Drop object members whose value is `{}` or `[]`, after null-dropping,
+    /// so a member emptied by the other rules disappears too
+    pub drop_empty: bool,
+    /// Rewrite the timestamps at these paths to RFC 3339 UTC (`Z`), after
+    /// the other rules. Only listed paths are touched, so ordinary numbers
+    /// are never mistaken for times; values that do not parse are left as is.
+    pub timestamp_paths: Vec<TimestampPath>,
+    /// Treat `{"$uint256": "<decimal>"}` as a tagged unsigned integer: the
+    /// string must be decimal digits fitting in 256 bits, and is hashed
+    /// with leading zeros removed. Values that do not fit are an error.
+    pub tagged_uint256: bool,
+    /// Treat `{"$bytes": "<base64url>"}` as tagged binary data: padded and
+    /// unpadded base64url both decode, and the bytes are hashed re-encoded
+    /// as unpadded base64url. Values that do not decode are an error.
+    pub tagged_bytes: bool,
+    /// Order the members of an object whose keys all parse as integers by
+    /// numeric value ("1", "2", "10") instead of by bytes ("1", "10", "2").
+    /// Objects with any other key keep the canonical version's order.
+    pub numeric_key_sort: bool,
+    /// Coerce the values at these paths to the given type, after the other
+    /// rules, so `"true"`, `1` and `true` hash alike where a producer is
+    /// known to be lax. Only listed paths are touched; values with no
+    /// unambiguous coercion are left as is.
+    pub coerce: Vec<CoercePath>,
+    /// Round the floats at these paths to a number of significant digits,
+    /// last, so values computed on different platforms that differ only in
+    /// their final bits hash alike. Integers and non-numbers are untouched.
+    pub round_floats: Vec<RoundPath>,
+}
+
+impl CanonicalProfile {
+    /// Deterministic object form for `signing.canonical_profile`: only the
+    /// active rules appear, so the default profile is `{}`
+    pub fn to_value(&self) -> Value {
+        let mut out = Map::new();
+        for (key, on) in [
+            ("drop_empty", self.drop_empty),
+            ("drop_nulls", self.drop_nulls),
+            ("ignore_underscore_keys", self.ignore_underscore_keys),
+            ("normalize_addresses", self.normalize_addresses),
+            ("numeric_key_sort", self.numeric_key_sort),
+            ("tagged_bytes", self.tagged_bytes),
+            ("tagged_uint256", self.tagged_uint256),
+        ] {
+            if on {
+                out.insert(key.to_string(), Value::Bool(true));
+            }
+        }
+        if !self.coerce.is_empty() {
+            let paths = self
+                .coerce
+                .iter()
+                .map(|p| serde_json::json!({ "pointer": p.pointer, "to": p.to.name() }))
+                .collect();
+            out.insert("coerce".to_string(), Value::Array(paths));
+        }
+        if !self.round_floats.is_empty() {
+            let paths = self
+                .round_floats
+                .iter()
+                .map(|p| serde_json::json!({ "pointer": p.pointer, "digits": p.digits }))
+                .collect();
+            out.insert("round_floats".to_string(), Value::Array(paths));
+        }
+        if !self.timestamp_paths.is_empty() {
+            let paths = self
+                .timestamp_paths
+                .iter()
+                .map(|p| {
+                    let unit = match p.unit {
+                        TimeUnit::Seconds => "seconds",
+                        TimeUnit::Millis => "millis",
+                    };
+                    serde_json::json!({ "pointer": p.pointer, "unit": unit })
+                })
+                .collect();
+            out.insert("timestamp_paths".to_string(), Value::Array(paths));
+        }
+        Value::Object(out)
+    }
+
+    /// Parse the `to_value` form. Unknown rules are an error rather than
+    /// ignored, so a verifier never hashes with fewer rules than the signer.
+    pub fn from_value(v: &Value) -> Result<Self> {
+        let map = v
+            .as_object()
+            .ok_or_else(|| anyhow!("canonical_profile must be an object"))?;
+        let mut profile = CanonicalProfile::default();
+        for (key, value) in map {
+            let flag = match key.as_str() {
+                "drop_empty" => &mut profile.drop_empty,
+                "drop_nulls" => &mut profile.drop_nulls,
+                "ignore_underscore_keys" => &mut profile.ignore_underscore_keys,
+                "normalize_addresses" => &mut profile.normalize_addresses,
+                "numeric_key_sort" => &mut profile.numeric_key_sort,
+                "tagged_bytes" => &mut profile.tagged_bytes,
+                "tagged_uint256" => &mut profile.tagged_uint256,
+                "timestamp_paths" => {
+                    profile.timestamp_paths = parse_timestamp_paths(value)?;
+                    continue;
+                }
+                "coerce" => {
+                    profile.coerce = parse_coerce_paths(value)?;
+                    continue;
+                }
+                "round_floats" => {
+                    profile.round_floats = parse_round_paths(value)?;
+                    continue;
+                }
+                other => return Err(anyhow!("unknown canonical_profile rule {other:?}")),
+            };
+            *flag = value
+                .as_bool()
+                .ok_or_else(|| anyhow!("canonical_profile.{key} must be a boolean"))?;
+        }
+        Ok(profile)
+    }
+
+    /// Apply this profile's rewrites, returning the value to canonicalize
+    pub fn apply(&self, v: &Value) -> Result<Value> {
+        let mut out = self.rewrite(v)?;
+        for path in &self.timestamp_paths {
+            if let Some(slot) = out.pointer_mut(&path.pointer) {
+                if let Some(ts) = normalize_timestamp(slot, path.unit) {
+                    *slot = Value::String(ts);
+                }
+            }
+        }
+        for path in &self.coerce {
+            if let Some(slot) = out.pointer_mut(&path.pointer) {
+                if let Some(coerced) = coerce(slot, path.to) {
+                    *slot = coerced;
+                }
+            }
+        }
+        for path in &self.round_floats {
+            if path.digits == 0 || path.digits > MAX_SIGNIFICANT_DIGITS {
+                return Err(anyhow!(
+                    "round_floats digits for {} must be 1 to {MAX_SIGNIFICANT_DIGITS}",
+                    path.pointer
+                ));
+            }
+            if let Some(slot) = out.pointer_mut(&path.pointer) {
+                if let Some(rounded) = round_float(slot, path.digits) {
+                    *slot = rounded;
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    fn rewrite(&self, v: &Value) -> Result<Value> {
+        Ok(match v {
+            Value::Object(map)
+                if self.tagged_bytes && map.len() == 1 && map.contains_key(BYTES_TAG) =>
+            {
+                let encoded = map[BYTES_TAG]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("{BYTES_TAG} value must be a base64url string"))?;
+                let mut out = Map::new();
+                out.insert(
+                    BYTES_TAG.to_string(),
+                    Value::String(normalize_bytes(encoded)?),
+                );
+                Value::Object(out)
+            }
+            Value::Object(map)
+                if self.tagged_uint256 && map.len() == 1 && map.contains_key(UINT256_TAG) =>
+            {
+                let digits = map[UINT256_TAG]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("{UINT256_TAG} value must be a decimal string"))?;
+                let mut out = Map::new();
+                out.insert(
+                    UINT256_TAG.to_string(),
+                    Value::String(normalize_uint256(digits)?),
+                );
+                Value::Object(out)
+            }
+            Value::Object(map) => {
+                let mut out = Map::new();
+                for (k, child) in map {
+                    if self.ignore_underscore_keys && k.starts_with('_') {
+                        continue;
+                    }
+                    // Children first, so emptiness is judged after their rewrites
+                    let child = self.rewrite(child)?;
+                    if self.drop_nulls && child.is_null() {
+                        continue;
+                    }
+                    if self.drop_empty && is_empty_container(&child) {
+                        continue;
+                    }
+                    out.insert(k.clone(), child);
+                }
+                Value::Object(out)
+            }
+            Value::Array(arr) => Value::Array(
+                arr.iter()
+                    .map(|child| self.rewrite(child))
+                    .collect::<Result<_>>()?,
+            ),
+            Value::String(s) if self.normalize_addresses && is_hex_address(s) => {
+                Value::String(s.to_ascii_lowercase())
+            }
+            _ => v.clone(),
+        })
+    }
+}
+
+/// Key of the tagged big-integer convention
+pub const UINT256_TAG: &str = "$uint256";
+
+/// 2^256 - 1 in decimal
+const UINT256_MAX: &str =
+    "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+
+/// `digits` without leading zeros ("0" for zero), if it is a decimal
+/// number no larger than 2^256 - 1
+fn normalize_uint256(digits: &str) -> Result<String> {
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(anyhow!(
+            "{UINT256_TAG} value {digits:?} is not a decimal integer"
+        ));
+    }
+    let trimmed = digits.trim_start_matches('0');
+    let trimmed = if trimmed.is_empty() { "0" } else { trimmed };
+    // Same length strings of digits compare like the numbers they spell
+    if trimmed.len() > UINT256_MAX.len()
+        || (trimmed.len() == UINT256_MAX.len() && trimmed > UINT256_MAX)
+    {
+        return Err(anyhow!(
+            "{UINT256_TAG} value {digits:?} does not fit in 256 bits"
+        ));
+    }
+    Ok(trimmed.to_string())
+}
+
+/// Key of the tagged binary-data convention
+pub const BYTES_TAG: &str = "$bytes";
+
+/// base64url with or without padding. Non-zero trailing bits are rejected,
+/// so each byte string has exactly one accepted spelling per padding style.
+const URL_SAFE_ANY_PAD: GeneralPurpose = GeneralPurpose::new(
+    &URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Unpadded base64url form of the bytes `encoded` decodes to
+fn normalize_bytes(encoded: &str) -> Result<String> {
+    let bytes = URL_SAFE_ANY_PAD
+        .decode(encoded)
+        .map_err(|e| anyhow!("{BYTES_TAG} value {encoded:?} is not base64url: {e}"))?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+fn parse_timestamp_paths(v: &Value) -> Result<Vec<TimestampPath>> {
+    v.as_array()
+        .ok_or_else(|| anyhow!("canonical_profile.timestamp_paths must be an array"))?
+        .iter()
+        .map(|entry| {
+            let pointer = entry["pointer"]
+                .as_str()
+                .ok_or_else(|| anyhow!("timestamp path missing pointer"))?;
+            let unit = match entry["unit"].as_str() {
+                Some("seconds") => TimeUnit::Seconds,
+                Some("millis") => TimeUnit::Millis,
+                other => {
+                    return Err(anyhow!(
+                        "bad timestamp path unit {other:?} (seconds or millis)"
+                    ))
+                }
+            };
+            Ok(TimestampPath::new(pointer, unit))
+        })
+        .collect()
+}
+
+fn parse_coerce_paths(v: &Value) -> Result<Vec<CoercePath>> {
+    v.as_array()
+        .ok_or_else(|| anyhow!("canonical_profile.coerce must be an array"))?
+        .iter()
+        .map(|entry| {
+            let pointer = entry["pointer"]
+                .as_str()
+                .ok_or_else(|| anyhow!("coerce path missing pointer"))?;
+            let to = match entry["to"].as_str() {
+                Some("boolean") => CoerceTo::Boolean,
+                Some("integer") => CoerceTo::Integer,
+                other => return Err(anyhow!("bad coerce type {other:?} (boolean or integer)")),
+            };
+            Ok(CoercePath::new(pointer, to))
+        })
+        .collect()
+}
+
+fn parse_round_paths(v: &Value) -> Result<Vec<RoundPath>> {
+    v.as_array()
+        .ok_or_else(|| anyhow!("canonical_profile.round_floats must be an array"))?
+        .iter()
+        .map(|entry| {
+            let pointer = entry["pointer"]
+                .as_str()
+                .ok_or_else(|| anyhow!("round_floats path missing pointer"))?;
+            let digits = entry["digits"]
+                .as_u64()
+                .and_then(|d| u32::try_from(d).ok())
+                .ok_or_else(|| anyhow!("round_floats path {pointer} missing digits"))?;
+            if digits == 0 || digits > MAX_SIGNIFICANT_DIGITS {
+                return Err(anyhow!(
+                    "round_floats digits for {pointer} must be 1 to {MAX_SIGNIFICANT_DIGITS}"
+                ));
+            }
+            Ok(RoundPath::new(pointer, digits))
+        })
+        .collect()
+}
+
+/// A float `v` rounded to the nearest value with `digits` significant
+/// digits, or `None` for anything that is not a float
+fn round_float(v: &Value, digits: u32) -> Option<Value> {
+    let n = v.as_number().filter(|n| n.is_f64())?.as_f64()?;
+    let rounded: f64 = format!("{:.*e}", digits as usize - 1, n).parse().ok()?;
+    serde_json::Number::from_f64(rounded).map(Value::Number)
+}
+
+/// `v` as a value of type `to`, if it has one unambiguous reading
+fn coerce(v: &Value, to: CoerceTo) -> Option<Value> {
+    match (to, v) {
+        (CoerceTo::Boolean, Value::Bool(_)) => Some(v.clone()),
+        (CoerceTo::Boolean, Value::Number(n)) => match n.as_u64()? {
+            0 => Some(Value::Bool(false)),
+            1 => Some(Value::Bool(true)),
+            _ => None,
+        },
+        (CoerceTo::Boolean, Value::String(s)) => match s.to_ascii_lowercase().as_str() {
+            "true" | "1" => Some(Value::Bool(true)),
+            "false" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        (CoerceTo::Integer, Value::Number(n)) => (n.is_i64() || n.is_u64()).then(|| v.clone()),
+        (CoerceTo::Integer, Value::String(s)) => {
+            let digits = s.strip_prefix('-').unwrap_or(s);
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            s.parse::<i64>()
+                .map(Value::from)
+                .or_else(|_| s.parse::<u64>().map(Value::from))
+                .ok()
+        }
+        _ => None,
+    }
+}
+
+/// RFC 3339 UTC form of a timestamp value, with fractional seconds only
+/// when they are non-zero
+fn normalize_timestamp(v: &Value, unit: TimeUnit) -> Option<String> {
+    let instant: DateTime<Utc> = match v {
+        Value::String(s) => DateTime::parse_from_rfc3339(s).ok()?.with_timezone(&Utc),
+        Value::Number(n) => {
+            let n = n.as_i64()?;
+            match unit {
+                TimeUnit::Seconds => Utc.timestamp_opt(n, 0).single()?,
+                TimeUnit::Millis => Utc.timestamp_millis_opt(n).single()?,
+            }
+        }
+        _ => return None,
+    };
+    Some(instant.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+}
+
+/// Array elements are never dropped: their positions are significant.
+fn is_empty_container(v: &Value) -> bool {
+    match v {
+        Value::Object(map) => map.is_empty(),
+        Value::Array(arr) => arr.is_empty(),
+        _ => false,
+    }
+}
+
+/// `0x` followed by exactly 40 hex digits. Longer hex strings such as 32-byte
+/// hashes deliberately do not match.
+fn is_hex_address(s: &str) -> bool {
+    s.len() == 42 && s.starts_with("0x") && s[2..].bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::payload_hash_keccak_with_profile;
+    use serde_json::json;
+
+    fn profile() -> CanonicalProfile {
+        CanonicalProfile {
+            ignore_underscore_keys: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn underscore_keys_ignored_at_every_level() {
+        let plain = json!({
+            "id": "snap-1",
+            "body": { "nodes": [{ "addr": "a" }], "meta": { "k": 1 } },
+            "signing": { "scheme": "eip191" }
+        });
+        let noted = json!({
+            "_note": "top",
+            "id": "snap-1",
+            "body": {
+                "_note": "body",
+                "nodes": [{ "addr": "a", "_note": "in array" }],
+                "meta": { "k": 1, "_note": { "deep": true } }
+            },
+            "signing": { "scheme": "eip191", "_note": "signing" }
+        });
+
+        let p = profile();
+        assert_eq!(
+            payload_hash_keccak_with_profile(&plain, &p).unwrap(),
+            payload_hash_keccak_with_profile(&noted, &p).unwrap()
+        );
+    }
+
+    #[test]
+    fn default_profile_keeps_underscore_keys() {
+        let plain = json!({ "body": { "k": 1 } });
+        let noted = json!({ "body": { "k": 1, "_note": "x" } });
+
+        let p = CanonicalProfile::default();
+        assert_ne!(
+            payload_hash_keccak_with_profile(&plain, &p).unwrap(),
+            payload_hash_keccak_with_profile(&noted, &p).unwrap()
+        );
+    }
+
+    #[test]
+    fn non_underscore_change_still_changes_hash() {
+        let a = json!({ "body": { "k": 1, "_note": "x" } });
+        let b = json!({ "body": { "k": 2, "_note": "x" } });
+
+        let p = profile();
+        assert_ne!(
+            payload_hash_keccak_with_profile(&a, &p).unwrap(),
+            payload_hash_keccak_with_profile(&b, &p).unwrap()
+        );
+    }
+
+    const CHECKSUMMED: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const LOWERCASE: &str = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+
+    fn address_profile() -> CanonicalProfile {
+        CanonicalProfile {
+            normalize_addresses: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn checksummed_and_lowercase_addresses_hash_identically() {
+        let a = json!({ "body": { "miner": CHECKSUMMED, "peers": [CHECKSUMMED] } });
+        let b = json!({ "body": { "miner": LOWERCASE, "peers": [LOWERCASE] } });
+
+        let p = address_profile();
+        assert_eq!(
+            payload_hash_keccak_with_profile(&a, &p).unwrap(),
+            payload_hash_keccak_with_profile(&b, &p).unwrap()
+        );
+
+        let default = CanonicalProfile::default();
+        assert_ne!(
+            payload_hash_keccak_with_profile(&a, &default).unwrap(),
+            payload_hash_keccak_with_profile(&b, &default).unwrap()
+        );
+    }
+
+    #[test]
+    fn non_address_hex_is_untouched() {
+        let p = address_profile();
+        let hash = "0xABCDEF0000000000000000000000000000000000000000000000000000000001";
+        let short = "0xABCDEF";
+        let not_hex = "0xZZf39Fd6e51aad88F6F4ce6aB8827279cffFb922";
+        let no_prefix = "f39Fd6e51aad88F6F4ce6aB8827279cffFb92266AB";
+        for s in [hash, short, not_hex, no_prefix] {
+            assert_eq!(p.apply(&json!(s)).unwrap(), json!(s));
+        }
+        assert_eq!(p.apply(&json!(CHECKSUMMED)).unwrap(), json!(LOWERCASE));
+    }
+
+    fn hash(v: &Value, p: &CanonicalProfile) -> [u8; 32] {
+        payload_hash_keccak_with_profile(v, p).unwrap()
+    }
+
+    #[test]
+    fn drop_empty_makes_empty_members_disappear() {
+        let with_empty = json!({ "a": {}, "b": [] });
+        let without = json!({});
+
+        assert_ne!(
+            hash(&with_empty, &CanonicalProfile::default()),
+            hash(&without, &CanonicalProfile::default())
+        );
+
+        let p = CanonicalProfile {
+            drop_empty: true,
+            ..Default::default()
+        };
+        assert_eq!(hash(&with_empty, &p), hash(&without, &p));
+
+        // Recursive: a member that only held empties is itself empty
+        assert_eq!(
+            hash(&json!({ "a": { "b": { "c": [] } } }), &p),
+            hash(&without, &p)
+        );
+        // Array positions are kept
+        assert_eq!(
+            p.apply(&json!({ "a": [{}, 1] })).unwrap(),
+            json!({ "a": [{}, 1] })
+        );
+    }
+
+    #[test]
+    fn drop_empty_runs_after_drop_nulls() {
+        let v = json!({ "a": { "b": null }, "c": 1 });
+
+        let only_empty = CanonicalProfile {
+            drop_empty: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            only_empty.apply(&v).unwrap(),
+            json!({ "a": { "b": null }, "c": 1 })
+        );
+
+        let both = CanonicalProfile {
+            drop_nulls: true,
+            drop_empty: true,
+            ..Default::default()
+        };
+        assert_eq!(both.apply(&v).unwrap(), json!({ "c": 1 }));
+    }
+
+    #[test]
+    fn equivalent_instants_hash_identically_at_configured_paths() {
+        let p = CanonicalProfile {
+            timestamp_paths: vec![
+                TimestampPath::new("/body/started", TimeUnit::Seconds),
+                TimestampPath::new("/body/finished", TimeUnit::Millis),
+            ],
+            ..Default::default()
+        };
+        let utc = json!({ "body": {
+            "started": "2023-11-14T22:13:20Z",
+            "finished": "2023-11-14T22:13:20.5Z",
+            "count": 1_700_000_000
+        } });
+        let mixed = json!({ "body": {
+            "started": 1_700_000_000,
+            "finished": "2023-11-15T00:13:20.500+02:00",
+            "count": 1_700_000_000
+        } });
+        let millis = json!({ "body": {
+            "started": "2023-11-15T00:13:20+02:00",
+            "finished": 1_700_000_000_500i64,
+            "count": 1_700_000_000
+        } });
+
+        assert_eq!(hash(&utc, &p), hash(&mixed, &p));
+        assert_eq!(hash(&utc, &p), hash(&millis, &p));
+        // Unlisted numbers are left alone
+        assert_eq!(
+            p.apply(&mixed).unwrap()["body"]["count"],
+            json!(1_700_000_000)
+        );
+        assert_ne!(
+            hash(&utc, &CanonicalProfile::default()),
+            hash(&mixed, &CanonicalProfile::default())
+        );
+    }
+
+    fn uint256_profile() -> CanonicalProfile {
+        CanonicalProfile {
+            tagged_uint256: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tagged_uint256_at_the_limit_is_accepted() {
+        let p = uint256_profile();
+        let max = json!({ "body": { "amount": { "$uint256": UINT256_MAX } } });
+        assert_eq!(p.apply(&max).unwrap(), max);
+        payload_hash_keccak_with_profile(&max, &p).unwrap();
+    }
+
+    #[test]
+    fn tagged_uint256_leading_zeros_are_normalized() {
+        let p = uint256_profile();
+        let plain =
+            json!({ "body": { "amount": { "$uint256": "123456789012345678901234567890" } } });
+        let padded =
+            json!({ "body": { "amount": { "$uint256": "000123456789012345678901234567890" } } });
+        assert_eq!(hash(&plain, &p), hash(&padded, &p));
+        assert_eq!(
+            p.apply(&json!({ "$uint256": "000" })).unwrap(),
+            json!({ "$uint256": "0" })
+        );
+        assert_ne!(
+            hash(&plain, &CanonicalProfile::default()),
+            hash(&padded, &CanonicalProfile::default())
+        );
+    }
+
+    #[test]
+    fn tagged_uint256_overflow_is_rejected() {
+        let p = uint256_profile();
+        // 2^256
+        let over = json!({ "body": { "amount": {
+            "$uint256": "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+        } } });
+        let err = payload_hash_keccak_with_profile(&over, &p).unwrap_err();
+        assert!(
+            err.to_string().contains("does not fit in 256 bits"),
+            "{err}"
+        );
+        assert!(p.apply(&json!({ "$uint256": "12a" })).is_err());
+        assert!(p.apply(&json!({ "$uint256": 12 })).is_err());
+        // Objects with other members are not tagged integers
+        assert!(p.apply(&json!({ "$uint256": "x", "note": 1 })).is_ok());
+    }
+
+    #[test]
+    fn numeric_key_sort_orders_integer_keys_by_value() {
+        let snap = json!({ "body": { "2": "b", "10": "c", "1": "a" } });
+        let preimage = |snap: &Value, p: &CanonicalProfile| {
+            String::from_utf8(crate::hash::payload_preimage(snap, p).unwrap()).unwrap()
+        };
+        let p = CanonicalProfile {
+            numeric_key_sort: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            preimage(&snap, &CanonicalProfile::default()),
+            r#"{"body":{"1":"a","10":"c","2":"b"}}"#
+        );
+        assert_eq!(
+            preimage(&snap, &p),
+            r#"{"body":{"1":"a","2":"b","10":"c"}}"#
+        );
+        // One non-integer key and the object keeps byte order
+        assert_eq!(
+            preimage(&json!({ "2": 0, "10": 0, "x": 0 }), &p),
+            r#"{"10":0,"2":0,"x":0}"#
+        );
+
+        // Recorded in the profile, the rule is applied on verification
+        let mut recorded = snap.clone();
+        recorded["signing"] = json!({ "canonical_profile": p.to_value() });
+        assert_eq!(p.to_value(), json!({ "numeric_key_sort": true }));
+        assert!(preimage(&recorded, &CanonicalProfile::default())
+            .starts_with(r#"{"body":{"1":"a","2":"b","10":"c"}"#));
+    }
+
+    #[test]
+    fn tagged_bytes_hash_the_same_padded_or_not() {
+        let p = CanonicalProfile {
+            tagged_bytes: true,
+            ..Default::default()
+        };
+        // b"proof" is cHJvb2Y unpadded, cHJvb2Y= padded
+        let unpadded = json!({ "body": { "proof": { "$bytes": "cHJvb2Y" } } });
+        let padded = json!({ "body": { "proof": { "$bytes": "cHJvb2Y=" } } });
+        assert_eq!(
+            payload_hash_keccak_with_profile(&unpadded, &p).unwrap(),
+            payload_hash_keccak_with_profile(&padded, &p).unwrap()
+        );
+        assert_eq!(p.apply(&padded).unwrap(), unpadded);
+        // Without the rule they are different strings
+        assert_ne!(
+            payload_hash_keccak_with_profile(&unpadded, &CanonicalProfile::default()).unwrap(),
+            payload_hash_keccak_with_profile(&padded, &CanonicalProfile::default()).unwrap()
+        );
+
+        // Recorded in the profile and applied on verification
+        let mut recorded = padded.clone();
+        recorded["signing"] = json!({ "canonical_profile": p.to_value() });
+        assert_eq!(p.to_value(), json!({ "tagged_bytes": true }));
+        assert!(crate::hash::payload_preimage(&recorded, &CanonicalProfile::default()).is_ok());
+
+        for bad in [
+            json!("not base64!"),
+            json!("cHJvb2Z"),
+            json!("cHJvb2Y=="),
+            json!("cHJ+b2Y"),
+            json!(12),
+        ] {
+            let err = p.apply(&json!({ "$bytes": bad })).unwrap_err();
+            assert!(err.to_string().contains("$bytes"), "{bad}: {err}");
+        }
+    }
+
+    #[test]
+    fn coerced_booleans_hash_identically_at_configured_paths() {
+        let p = CanonicalProfile {
+            coerce: vec![
+                CoercePath::new("/body/enabled", CoerceTo::Boolean),
+                CoercePath::new("/body/replicas", CoerceTo::Integer),
+            ],
+            ..Default::default()
+        };
+        let canonical = json!({ "body": { "enabled": true, "replicas": 3, "flag": "true" } });
+        for (enabled, replicas) in [
+            (json!("true"), json!("3")),
+            (json!(1), json!(3)),
+            (json!(true), json!("3")),
+        ] {
+            let lax =
+                json!({ "body": { "enabled": enabled, "replicas": replicas, "flag": "true" } });
+            assert_eq!(p.apply(&lax).unwrap(), canonical);
+            assert_eq!(hash(&lax, &p), hash(&canonical, &p));
+        }
+
+        // Unlisted paths and values without a reading are left alone
+        let odd = json!({ "body": { "enabled": 2, "replicas": "3.5", "flag": "true" } });
+        assert_eq!(p.apply(&odd).unwrap(), odd);
+        assert_ne!(
+            hash(
+                &json!({ "body": { "enabled": "true" } }),
+                &CanonicalProfile::default()
+            ),
+            hash(
+                &json!({ "body": { "enabled": true } }),
+                &CanonicalProfile::default()
+            )
+        );
+    }
+
+    #[test]
+    fn near_equal_floats_round_to_the_same_hash() {
+        let p = CanonicalProfile {
+            round_floats: vec![RoundPath::new("/body/score", 12)],
+            ..Default::default()
+        };
+        // 0.1 + 0.2 on one platform, the literal on another
+        let a = json!({ "body": { "score": 0.1 + 0.2, "other": 0.1 + 0.2 } });
+        let b = json!({ "body": { "score": 0.3, "other": 0.1 + 0.2 } });
+        assert_ne!(
+            hash(&a, &CanonicalProfile::default()),
+            hash(&b, &CanonicalProfile::default())
+        );
+        assert_eq!(p.apply(&a).unwrap()["body"]["score"], json!(0.3));
+        assert_eq!(hash(&a, &p), hash(&b, &p));
+
+        // Only the configured path moves, and integers never do
+        assert_eq!(p.apply(&a).unwrap()["body"]["other"], json!(0.1 + 0.2));
+        let int = json!({ "body": { "score": 123_456_789_012_345u64 } });
+        assert_eq!(p.apply(&int).unwrap(), int);
+        assert_eq!(
+            CanonicalProfile {
+                round_floats: vec![RoundPath::new("/body/score", 3)],
+                ..Default::default()
+            }
+            .apply(&json!({ "body": { "score": -1234.5 } }))
+            .unwrap(),
+            json!({ "body": { "score": -1230.0 } })
+        );
+        assert!(CanonicalProfile {
+            round_floats: vec![RoundPath::new("/body/score", 0)],
+            ..Default::default()
+        }
+        .apply(&a)
+        .is_err());
+    }
+
+    #[test]
+    fn profile_value_round_trips() {
+        let p = CanonicalProfile {
+            drop_nulls: true,
+            normalize_addresses: true,
+            timestamp_paths: vec![TimestampPath::new("/body/t", TimeUnit::Millis)],
+            coerce: vec![CoercePath::new("/body/on", CoerceTo::Boolean)],
+            round_floats: vec![RoundPath::new("/body/x", 9)],
+            ..Default::default()
+        };
+        let v = p.to_value();
+        assert_eq!(
+            v,
+            json!({
+                "coerce": [{ "pointer": "/body/on", "to": "boolean" }],
+                "drop_nulls": true,
+                "normalize_addresses": true,
+                "round_floats": [{ "pointer": "/body/x", "digits": 9 }],
+                "timestamp_paths": [{ "pointer": "/body/t", "unit": "millis" }]
+            })
+        );
+        assert_eq!(CanonicalProfile::from_value(&v).unwrap(), p);
+        assert_eq!(CanonicalProfile::default().to_value(), json!({}));
+        assert!(CanonicalProfile::from_value(&json!({ "typed_numbers": true })).is_err());
+    }
+
+    #[test]
+    fn out_of_range_round_digits_are_rejected_at_parse() {
+        for digits in [0, 18, 99] {
+            let v = json!({ "round_floats": [{ "pointer": "/body/x", "digits": digits }] });
+            let err = CanonicalProfile::from_value(&v).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "round_floats digits for /body/x must be 1 to 17"
+            );
+        }
+    }
+}
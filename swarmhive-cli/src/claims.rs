@@ -0,0 +1,187 @@
+//! Capability claims
+//!
+//! A signed snapshot can act as an authorization token: `signing.claims`
+//! lists the scopes and resources the signer grants. The claims live inside
+//! the signing object, so they are covered by the payload hash and any edit
+//! to them breaks the signature. `ScopedAllowList` additionally limits which
+//! scopes each signer is trusted to grant.
+
+use crate::signing;
+use anyhow::{anyhow, Result};
+use ethers::core::types::Address;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+/// Contents of `signing.claims`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claims {
+    /// Granted scopes, e.g. "jobs:submit"
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Resource ids the scopes apply to; empty means unrestricted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resources: Vec<String>,
+}
+
+impl Claims {
+    pub fn new<S: Into<String>>(scopes: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            scopes: scopes.into_iter().map(Into::into).collect(),
+            resources: Vec::new(),
+        }
+    }
+
+    /// Restrict the grant to the given resource ids
+    pub fn resources<S: Into<String>>(mut self, resources: impl IntoIterator<Item = S>) -> Self {
+        self.resources = resources.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+/// The snapshot's signing.claims, unverified
+pub fn snapshot_claims(snapshot: &Value) -> Result<Claims> {
+    let claims = signing::signing_object(snapshot)?
+        .get("claims")
+        .ok_or_else(|| anyhow!("snapshot carries no signing.claims"))?;
+    serde_json::from_value(claims.clone()).map_err(|e| anyhow!("malformed signing.claims: {e}"))
+}
+
+/// Check the signature and content hash, then that the signed claims grant
+/// `required_scope`. Returns the claims on success.
+pub fn verify_claims(snapshot: &Value, required_scope: &str) -> Result<Claims> {
+    signing::recover_signer(snapshot)?;
+    let claims = snapshot_claims(snapshot)?;
+    if !claims.has_scope(required_scope) {
+        return Err(anyhow!(
+            "signed claims do not grant scope {required_scope:?}"
+        ));
+    }
+    Ok(claims)
+}
+
+/// Which scopes each signer may grant
+#[derive(Debug, Clone, Default)]
+pub struct ScopedAllowList {
+    grants: HashMap<Address, BTreeSet<String>>,
+}
+
+impl ScopedAllowList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Authorize `signer` to grant `scopes` (added to any already allowed)
+    pub fn allow<S: Into<String>>(
+        mut self,
+        signer: Address,
+        scopes: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.grants
+            .entry(signer)
+            .or_default()
+            .extend(scopes.into_iter().map(Into::into));
+        self
+    }
+
+    /// Scopes `signer` may grant, if it is listed at all
+    pub fn scopes_for(&self, signer: &Address) -> Option<&BTreeSet<String>> {
+        self.grants.get(signer)
+    }
+}
+
+/// Check the signature and that the recovered signer is authorized for
+/// every scope in the snapshot's signing.claims. A snapshot without claims
+/// grants nothing, so only the signer must be listed. Returns the signer.
+pub fn verify_scoped_allowlist(snapshot: &Value, policy: &ScopedAllowList) -> Result<Address> {
+    let signer = signing::recover_signer(snapshot)?;
+    let allowed = policy
+        .scopes_for(&signer)
+        .ok_or_else(|| anyhow!("signer {signer:?} is not on the allow-list"))?;
+
+    let claims = match signing::signing_object(snapshot)?.get("claims") {
+        Some(_) => snapshot_claims(snapshot)?,
+        None => Claims::default(),
+    };
+    if let Some(scope) = claims.scopes.iter().find(|s| !allowed.contains(*s)) {
+        return Err(anyhow!(
+            "signer {signer:?} is not authorized to grant scope {scope:?}"
+        ));
+    }
+    Ok(signer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::SnapshotBuilder;
+    use crate::test_support::{address, signed, KEY_A};
+    use serde_json::json;
+
+    async fn grant(claims: Claims) -> Value {
+        let snap = SnapshotBuilder::new()
+            .id("grant-node-7")
+            .field("body", json!({ "node": "node-7" }))
+            .claims(claims)
+            .build()
+            .unwrap();
+        signed(snap, KEY_A).await
+    }
+
+    fn signer() -> Address {
+        address(KEY_A)
+    }
+
+    #[tokio::test]
+    async fn authorized_grant_passes_allowlist() {
+        let snap = grant(Claims::new(["jobs:claim"])).await;
+        let policy = ScopedAllowList::new().allow(signer(), ["jobs:claim", "jobs:submit"]);
+        assert_eq!(verify_scoped_allowlist(&snap, &policy).unwrap(), signer());
+    }
+
+    #[tokio::test]
+    async fn unauthorized_scope_is_named() {
+        let snap = grant(Claims::new(["jobs:claim", "treasury:spend"])).await;
+        let policy = ScopedAllowList::new().allow(signer(), ["jobs:claim"]);
+        let err = verify_scoped_allowlist(&snap, &policy).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("not authorized to grant scope \"treasury:spend\""),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_signer_is_rejected() {
+        let snap = grant(Claims::new(["jobs:claim"])).await;
+        let policy = ScopedAllowList::new().allow(Address::repeat_byte(0x11), ["jobs:claim"]);
+        let err = verify_scoped_allowlist(&snap, &policy).unwrap_err();
+        assert!(err.to_string().contains("not on the allow-list"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn granted_scope_passes() {
+        let snap = grant(Claims::new(["jobs:claim", "jobs:submit"]).resources(["job-1"])).await;
+        let claims = verify_claims(&snap, "jobs:submit").unwrap();
+        assert_eq!(claims.resources, vec!["job-1"]);
+    }
+
+    #[tokio::test]
+    async fn missing_scope_is_rejected() {
+        let snap = grant(Claims::new(["jobs:claim"])).await;
+        let err = verify_claims(&snap, "jobs:submit").unwrap_err();
+        assert!(err.to_string().contains("do not grant scope"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn tampered_claims_break_signature() {
+        let mut snap = grant(Claims::new(["jobs:claim"])).await;
+        snap["signing"]["claims"]["scopes"] = json!(["jobs:claim", "jobs:submit"]);
+        let err = verify_claims(&snap, "jobs:submit").unwrap_err();
+        assert!(err.to_string().contains("payload_hash mismatch"), "{err}");
+    }
+}
@@ -1,4 +1,8 @@
-use clap::{Parser, Subcommand};
+use crate::account::parse_eth_amount;
+use crate::signing::{CanonicalVersion, SignatureEncoding, SigningScheme};
+use clap::{Parser, Subcommand, ValueEnum};
+use ethers::core::types::U256;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "swarmhive")]
@@ -13,7 +17,7 @@ pub struct Cli {
 pub enum Commands {
     /// Initialize a new miner identity
     Init(InitCmd),
-    /// Watch the pool for available jobs
+    /// Watch the pool for available jobs, or a signed snapshot URL
     Watch(WatchCmd),
     /// Submit a job snapshot
     Submit(SubmitCmd),
@@ -23,6 +27,52 @@ pub enum Commands {
     Prove(ProveCmd),
     /// Seal an epoch (controller only)
     Seal(SealCmd),
+    /// Merge co-signatures from copies of the same snapshot
+    Merge(MergeCmd),
+    /// Sign a snapshot with a local key
+    Sign(SignCmd),
+    /// Verify a signed snapshot
+    Verify(VerifyCmd),
+    /// Verify newline-delimited snapshots read from stdin
+    VerifyStream(VerifyStreamCmd),
+    /// Report signature expiry across a snapshot archive
+    AuditAges(AuditAgesCmd),
+    /// Rewrite signatures in a directory to another encoding
+    Reencode(ReencodeCmd),
+    /// Verify every snapshot in a directory in parallel
+    VerifyBatch(VerifyBatchCmd),
+    /// Generate a signing key into an encrypted keystore
+    Keygen(KeygenCmd),
+    /// Verify a snapshot if it is signed, otherwise offer to sign it
+    Auto(AutoCmd),
+    /// Package a signed snapshot's preimage and signer for independent verification
+    Bundle(BundleCmd),
+    /// Check that every snapshot in a directory is stored in canonical form
+    Lint(LintCmd),
+    /// Refresh a snapshot's issued_at and re-sign it
+    Touch(TouchCmd),
+    /// Check two snapshots carry the same signed content under any encoding
+    Same(SameCmd),
+    /// Show a snapshot's signing metadata, including any signing.reason and provenance
+    Inspect(InspectCmd),
+    /// Sign every snapshot in a directory plus a manifest of their payload hashes
+    SignManifest(SignManifestCmd),
+    /// Re-sign v1-canonicalized snapshots under canonical_version 2 (JCS)
+    MigrateCanonical(MigrateCanonicalCmd),
+    /// Show how a snapshot's canonical bytes and hash differ between canonical_version 1 and 2
+    CanonDiff(CanonDiffCmd),
+    /// Strip signatures, keeping the payload hash the content must still match
+    Unsign(UnsignCmd),
+    /// Count how many snapshots in a directory each signer signed
+    Signers(SignersCmd),
+    /// Print a snapshot's content only if its signature verifies
+    Extract(ExtractCmd),
+    /// Publish a signed list of authorized signers
+    KeyringExport(KeyringExportCmd),
+    /// Verify every snapshot in a tar archive without extracting it
+    VerifyArchive(VerifyArchiveCmd),
+    /// Compare a snapshot's payload hash with the one anchored on-chain
+    CheckAnchor(CheckAnchorCmd),
 }
 
 #[derive(Parser)]
@@ -38,13 +88,25 @@ pub struct InitCmd {
 
 #[derive(Parser)]
 pub struct WatchCmd {
+    /// Instead of the pool, poll the signed snapshot served at this URL and
+    /// report when it turns invalid, stale or unreachable
+    pub url: Option<String>,
+
     /// Pool to watch
-    #[arg(long, default_value = "swarmpool.eth")]
+    #[arg(long, default_value = "swarmpool.eth", conflicts_with = "url")]
     pub pool: String,
 
-    /// Sync interval in seconds
-    #[arg(long, default_value_t = 10)]
-    pub interval: u64,
+    /// Poll interval (e.g. 10, 30s, 5m)
+    #[arg(long, value_parser = parse_duration, default_value = "10")]
+    pub interval: Duration,
+
+    /// Expected signer of the snapshot at <URL>
+    #[arg(long, requires = "url")]
+    pub address: Option<String>,
+
+    /// Maximum age of the snapshot's signing.issued_at before it counts as stale
+    #[arg(long, value_parser = parse_duration, requires = "url")]
+    pub max_age: Option<Duration>,
 }
 
 #[derive(Parser)]
@@ -83,3 +145,516 @@ pub struct SealCmd {
     #[arg(long)]
     pub epoch: u64,
 }
+
+#[derive(Parser)]
+pub struct MergeCmd {
+    /// Signed copies of the same snapshot
+    #[arg(required = true, num_args = 2..)]
+    pub files: Vec<String>,
+
+    /// Output path for the merged snapshot
+    #[arg(short, long)]
+    pub out: String,
+}
+
+#[derive(Parser)]
+pub struct SignCmd {
+    /// Snapshot JSON files to sign
+    #[arg(required = true)]
+    pub files: Vec<String>,
+
+    /// File holding the hex private key
+    #[arg(
+        long,
+        required_unless_present_any = ["keystore", "mnemonic", "remote_signer", "pkcs11_module"],
+        conflicts_with_all = ["keystore", "mnemonic", "remote_signer", "pkcs11_module"]
+    )]
+    pub key_file: Option<String>,
+
+    /// Encrypted JSON keystore to sign with
+    #[arg(long, conflicts_with_all = ["mnemonic", "remote_signer", "pkcs11_module"])]
+    pub keystore: Option<String>,
+
+    /// Read the keystore password from this file instead of prompting
+    #[arg(long, requires = "keystore")]
+    pub password_file: Option<String>,
+
+    /// File holding a BIP-39 mnemonic to sign with
+    #[arg(long)]
+    pub mnemonic: Option<String>,
+
+    /// Account index under m/44'/60'/0'/0 for --mnemonic
+    #[arg(long, default_value_t = 0, requires = "mnemonic")]
+    pub index: u32,
+
+    /// Sign through this HTTP signing service instead of a local key (http-signer builds)
+    #[arg(long, conflicts_with_all = ["mnemonic", "pkcs11_module"], requires = "remote_address")]
+    pub remote_signer: Option<String>,
+
+    /// Address the --remote-signer service signs as; every signature is checked against it
+    #[arg(long, requires = "remote_signer")]
+    pub remote_address: Option<String>,
+
+    /// Extra header for --remote-signer requests, e.g. "Authorization: Bearer ..." (repeatable)
+    #[arg(long, requires = "remote_signer")]
+    pub remote_header: Vec<String>,
+
+    /// Sign with a key in a PKCS#11 HSM, loading this vendor module (pkcs11 builds)
+    #[arg(long, conflicts_with = "mnemonic", requires_all = ["pkcs11_slot", "pkcs11_label"])]
+    pub pkcs11_module: Option<String>,
+
+    /// Slot id of the token holding the --pkcs11-module key
+    #[arg(long, requires = "pkcs11_module")]
+    pub pkcs11_slot: Option<u64>,
+
+    /// CKA_LABEL of the --pkcs11-module key pair
+    #[arg(long, requires = "pkcs11_module")]
+    pub pkcs11_label: Option<String>,
+
+    /// Read the token's user PIN from this file instead of prompting
+    #[arg(long, requires = "pkcs11_module")]
+    pub pkcs11_pin_file: Option<String>,
+
+    /// Output path (defaults to signing in place; single file only)
+    #[arg(short, long)]
+    pub out: Option<String>,
+
+    /// Also write the exact canonical bytes that were hashed (single file only)
+    #[arg(long)]
+    pub canonical_out: Option<String>,
+
+    /// Replace an existing valid signature
+    #[arg(long, conflicts_with = "cosign")]
+    pub resign: bool,
+
+    /// Keep an existing valid signature and add ours to signing.signatures
+    #[arg(long)]
+    pub cosign: bool,
+
+    /// Record why this is being signed in signing.reason (covered by the hash)
+    #[arg(long, conflicts_with = "cosign")]
+    pub reason: Option<String>,
+
+    /// Record the producer's git commit and build in signing.provenance
+    /// (covered by the hash), from SWARMHIVE_GIT_COMMIT, SWARMHIVE_BUILDER
+    /// and SWARMHIVE_BUILT_AT, or CI defaults
+    #[arg(long, conflicts_with = "cosign")]
+    pub provenance: bool,
+
+    /// At most this many signer calls in flight at once (default unlimited)
+    #[arg(long)]
+    pub max_concurrency: Option<usize>,
+
+    /// Minimum time between signer calls (e.g. 250ms, 1s)
+    #[arg(long, value_parser = parse_duration)]
+    pub min_interval: Option<Duration>,
+
+    /// Bind an external file: record its keccak256 in artifacts[] before signing (repeatable)
+    #[arg(long, conflicts_with = "cosign")]
+    pub attach_file: Vec<String>,
+
+    /// Syntax of the input files; the signed output is always JSON
+    #[arg(long, value_enum, default_value_t = InputFormat::Json)]
+    pub input_format: InputFormat,
+
+    /// Sign under the first of these schemes the key backend supports, eip191 if none
+    /// (comma-separated: eip191, eip191-prehash, trezor); sets signing.scheme
+    #[arg(long, value_delimiter = ',', value_parser = parse_signing_scheme)]
+    pub prefer_scheme: Vec<SigningScheme>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    /// Plain JSON
+    Json,
+    /// JSON5 (comments, trailing commas, unquoted keys); comments are dropped
+    Json5,
+}
+
+#[derive(Parser)]
+pub struct VerifyStreamCmd {
+    /// Require every snapshot to be signed by this address
+    #[arg(long)]
+    pub address: Option<String>,
+}
+
+#[derive(Parser)]
+pub struct VerifyCmd {
+    /// Path to signed snapshot JSON
+    pub file: String,
+
+    /// Expected signer address
+    #[arg(long)]
+    pub address: Option<String>,
+
+    /// Maximum age of signing.issued_at (e.g. 300, 15m, 1h, 7d)
+    #[arg(long, value_parser = parse_duration)]
+    pub max_age: Option<Duration>,
+
+    /// Allowance for a signing.issued_at ahead of this machine's clock
+    #[arg(long, value_parser = parse_duration)]
+    pub clock_skew: Option<Duration>,
+
+    /// Required signing.chain_id
+    #[arg(long)]
+    pub chain_id: Option<u64>,
+
+    /// Required signing.content_type (e.g. application/vnd.swarmhive.snapshot+json)
+    #[arg(long)]
+    pub content_type: Option<String>,
+
+    /// Reject snapshots not signed under this signing.canonical_version (absent means 1)
+    #[arg(long, value_parser = parse_canonical_version)]
+    pub require_canonical_version: Option<CanonicalVersion>,
+
+    /// Require signing.nonce to be unused by the signer, recording it in this file
+    #[arg(long)]
+    pub nonce_store: Option<String>,
+
+    /// Re-hash this file and require it to match the signed artifact of the same name (repeatable)
+    #[arg(long)]
+    pub check_file: Vec<String>,
+
+    /// Require this ENS name to resolve to the signer (checked only with --rpc-url)
+    #[arg(long)]
+    pub ens: Option<String>,
+
+    /// Require the signer to hold at least this much ETH, e.g. 0.01eth or 5gwei (checked only with --rpc-url)
+    #[arg(long, value_parser = parse_eth_amount)]
+    pub min_balance: Option<U256>,
+
+    /// Require the signer to have sent at least one transaction (checked only with --rpc-url)
+    #[arg(long)]
+    pub require_activity: bool,
+
+    /// Ethereum RPC endpoint for on-chain checks and the signer's reverse-ENS name
+    #[arg(long)]
+    pub rpc_url: Option<String>,
+
+    /// Tries per on-chain check call before an RPC error fails it, with
+    /// exponential backoff between them
+    #[arg(long, default_value_t = 3)]
+    pub rpc_attempts: u32,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub report: ReportFormat,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object
+    Json,
+}
+
+#[derive(Parser)]
+pub struct AuditAgesCmd {
+    /// Directory of snapshots
+    pub dir: String,
+
+    /// Flag snapshots expiring within this window (e.g. 7d)
+    #[arg(long, value_parser = parse_duration, default_value = "7d")]
+    pub warn_within: Duration,
+}
+
+#[derive(Parser)]
+pub struct ReencodeCmd {
+    /// Directory of snapshots
+    pub dir: String,
+
+    /// Target encoding: hex, compact, rsv or b64url
+    #[arg(long)]
+    pub to: SignatureEncoding,
+}
+
+#[derive(Parser)]
+pub struct VerifyBatchCmd {
+    /// Directory of snapshots
+    pub dir: String,
+
+    /// Expected signer address
+    #[arg(long)]
+    pub address: Option<String>,
+
+    /// Maximum age of signing.issued_at (e.g. 300, 15m, 1h, 7d)
+    #[arg(long, value_parser = parse_duration)]
+    pub max_age: Option<Duration>,
+
+    /// Allowance for a signing.issued_at ahead of this machine's clock
+    #[arg(long, value_parser = parse_duration)]
+    pub clock_skew: Option<Duration>,
+
+    /// Required signing.chain_id
+    #[arg(long)]
+    pub chain_id: Option<u64>,
+
+    /// Required signing.content_type (e.g. application/vnd.swarmhive.snapshot+json)
+    #[arg(long)]
+    pub content_type: Option<String>,
+
+    /// Do not draw a progress bar
+    #[arg(long)]
+    pub no_progress: bool,
+}
+
+#[derive(Parser)]
+pub struct KeygenCmd {
+    /// Keystore file to write
+    #[arg(long)]
+    pub out: String,
+
+    /// Read the password from this file instead of prompting
+    #[arg(long)]
+    pub password_file: Option<String>,
+
+    /// Overwrite an existing file
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Parser)]
+pub struct AutoCmd {
+    /// Path to snapshot JSON
+    pub file: String,
+
+    /// Key used if the snapshot is unsigned (defaults to the configured key)
+    #[arg(long)]
+    pub key_file: Option<String>,
+
+    /// Sign without asking
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+}
+
+#[derive(Parser)]
+pub struct BundleCmd {
+    /// Path to signed snapshot JSON
+    pub file: String,
+
+    /// Bundle file to write (default: stdout)
+    #[arg(long, short = 'o')]
+    pub out: Option<String>,
+}
+
+#[derive(Parser)]
+pub struct LintCmd {
+    /// Directory of snapshots
+    pub dir: String,
+}
+
+#[derive(Parser)]
+pub struct TouchCmd {
+    /// Path to signed snapshot JSON
+    pub file: String,
+
+    /// File holding the hex private key
+    #[arg(long)]
+    pub key_file: String,
+
+    /// Also set signing.valid_until to now plus this window (e.g. 15m, 1h)
+    #[arg(long, value_parser = parse_duration)]
+    pub extend: Option<Duration>,
+
+    /// Re-sign even if the current signature is invalid or from another key
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Parser)]
+pub struct SameCmd {
+    /// First snapshot (.json, or .cbor)
+    pub a: String,
+
+    /// Second snapshot (.json, or .cbor)
+    pub b: String,
+}
+
+#[derive(Parser)]
+pub struct InspectCmd {
+    /// Path to snapshot JSON
+    pub file: String,
+
+    /// Ethereum RPC endpoint for showing the signer's reverse-ENS name
+    #[arg(long)]
+    pub rpc_url: Option<String>,
+}
+
+#[derive(Parser)]
+pub struct SignManifestCmd {
+    /// Directory of snapshots to sign
+    pub dir: String,
+
+    /// File holding the hex private key
+    #[arg(long)]
+    pub key_file: String,
+
+    /// Where to write the signed manifest
+    #[arg(short, long)]
+    pub out: String,
+}
+
+#[derive(Parser)]
+pub struct MigrateCanonicalCmd {
+    /// Directory of v1-signed snapshots, rewritten in place
+    pub dir: String,
+
+    /// File holding the hex private key to re-sign with
+    #[arg(long)]
+    pub key_file: String,
+}
+
+#[derive(Parser)]
+pub struct CanonDiffCmd {
+    /// Path to snapshot JSON
+    pub file: String,
+}
+
+#[derive(Parser)]
+pub struct UnsignCmd {
+    /// Path to signed snapshot JSON
+    pub file: String,
+
+    /// Output path (defaults to rewriting the file in place)
+    #[arg(short, long)]
+    pub out: Option<String>,
+}
+
+#[derive(Parser)]
+pub struct SignersCmd {
+    /// Directory of snapshots
+    pub dir: String,
+
+    /// Ethereum RPC endpoint for labelling signers with their reverse-ENS names
+    #[arg(long)]
+    pub rpc_url: Option<String>,
+}
+
+#[derive(Parser)]
+pub struct ExtractCmd {
+    /// Path to signed snapshot JSON
+    pub file: String,
+
+    /// Require the snapshot to be signed by this address
+    #[arg(long)]
+    pub address: Option<String>,
+
+    /// Drop the signing object from the output
+    #[arg(long)]
+    pub strip_signing: bool,
+}
+
+#[derive(Parser)]
+pub struct KeyringExportCmd {
+    /// File of authorized signer addresses, one per line
+    #[arg(long)]
+    pub signers: String,
+
+    /// File holding the coordinator's hex private key
+    #[arg(long)]
+    pub key_file: String,
+
+    /// Where to write the signed keyring
+    #[arg(short, long)]
+    pub out: String,
+}
+
+#[derive(Parser)]
+pub struct VerifyArchiveCmd {
+    /// Tar archive of snapshots (.tar, or gzipped .tar.gz / .tgz)
+    pub file: String,
+
+    /// Expected signer address
+    #[arg(long)]
+    pub address: Option<String>,
+}
+
+#[derive(Parser)]
+pub struct CheckAnchorCmd {
+    /// Path to snapshot JSON
+    pub file: String,
+
+    /// Anchor contract holding payload hashes
+    #[arg(long)]
+    pub contract: String,
+
+    /// Anchor key: 0x-prefixed 32-byte hex, or an id hashed with keccak256
+    #[arg(long)]
+    pub key: String,
+
+    /// Ethereum RPC endpoint
+    #[arg(long)]
+    pub rpc_url: String,
+
+    /// Tries per RPC call before an error fails the check
+    #[arg(long, default_value_t = 3)]
+    pub rpc_attempts: u32,
+}
+
+/// Parse a duration given as plain seconds or with an ms/s/m/h/d suffix.
+/// Durations are compared with i64 timestamps, so the seconds must fit one.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (digits, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let n: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid duration: {s:?}"))?;
+    let secs = match unit {
+        "ms" => return Ok(Duration::from_millis(n)),
+        "s" => Some(n),
+        "m" => n.checked_mul(60),
+        "h" => n.checked_mul(3600),
+        "d" => n.checked_mul(86400),
+        _ => {
+            return Err(format!(
+                "invalid duration unit in {s:?} (use ms, s, m, h or d)"
+            ))
+        }
+    };
+    match secs.filter(|&secs| i64::try_from(secs).is_ok()) {
+        Some(secs) => Ok(Duration::from_secs(secs)),
+        None => Err("duration too large".to_string()),
+    }
+}
+
+/// Parse a signing.scheme name
+pub fn parse_signing_scheme(s: &str) -> Result<SigningScheme, String> {
+    s.trim().parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// Parse a canonical_version number
+pub fn parse_canonical_version(s: &str) -> Result<CanonicalVersion, String> {
+    let n: u64 = s
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid canonical version: {s:?}"))?;
+    CanonicalVersion::from_u64(n).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_parse_and_overflow_is_an_error() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(
+            parse_duration("2d").unwrap(),
+            Duration::from_secs(2 * 86400)
+        );
+        assert_eq!(
+            parse_duration("999999999999999999d").unwrap_err(),
+            "duration too large"
+        );
+        assert_eq!(
+            parse_duration(&u64::MAX.to_string()).unwrap_err(),
+            "duration too large"
+        );
+        assert!(parse_duration("5w").is_err());
+    }
+}
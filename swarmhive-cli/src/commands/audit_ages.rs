@@ -0,0 +1,177 @@
+//! swarmhive audit-ages - Report signature expiry across an archive
+
+use crate::cli::AuditAgesCmd;
+use crate::commands::{read_snapshot, snapshot_files};
+use crate::signing;
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use ethers::core::types::Address;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Where a snapshot stands relative to its signing.valid_until
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgeStatus {
+    Expired,
+    ExpiringSoon,
+    Valid,
+    NoExpiry,
+}
+
+impl AgeStatus {
+    fn label(&self) -> &'static str {
+        match self {
+            AgeStatus::Expired => "expired",
+            AgeStatus::ExpiringSoon => "expiring",
+            AgeStatus::Valid => "valid",
+            AgeStatus::NoExpiry => "no expiry",
+        }
+    }
+}
+
+/// One row of the report
+#[derive(Debug, Clone)]
+pub struct AgeEntry {
+    pub path: PathBuf,
+    pub status: AgeStatus,
+    pub issued_at: Option<i64>,
+    pub valid_until: Option<i64>,
+    /// None when the snapshot is unsigned or its signature does not verify
+    pub signer: Option<Address>,
+}
+
+pub async fn run(cmd: AuditAgesCmd) -> Result<()> {
+    let entries = audit_dir(Path::new(&cmd.dir), Utc::now().timestamp(), cmd.warn_within)?;
+
+    println!(
+        "{:<10} {:<22} {:<44} FILE",
+        "STATUS", "VALID_UNTIL", "SIGNER"
+    );
+    for entry in &entries {
+        let until = entry
+            .valid_until
+            .and_then(|ts| Utc.timestamp_opt(ts, 0).single())
+            .map(|t| t.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let signer = entry
+            .signer
+            .map(|a| format!("{a:?}"))
+            .unwrap_or_else(|| "(unverified)".to_string());
+        println!(
+            "{:<10} {:<22} {:<44} {}",
+            entry.status.label(),
+            until,
+            signer,
+            entry.path.display()
+        );
+    }
+
+    let count = |status| entries.iter().filter(|e| e.status == status).count();
+    println!();
+    println!(
+        "{} snapshots: {} expired, {} expiring within {}s, {} valid, {} no expiry",
+        entries.len(),
+        count(AgeStatus::Expired),
+        count(AgeStatus::ExpiringSoon),
+        cmd.warn_within.as_secs(),
+        count(AgeStatus::Valid),
+        count(AgeStatus::NoExpiry)
+    );
+    Ok(())
+}
+
+/// Whole seconds of `d`, saturating at i64::MAX
+fn secs_i64(d: Duration) -> i64 {
+    i64::try_from(d.as_secs()).unwrap_or(i64::MAX)
+}
+
+/// Classify every snapshot under `dir` at time `now`
+pub fn audit_dir(dir: &Path, now: i64, warn_within: Duration) -> Result<Vec<AgeEntry>> {
+    let mut entries = Vec::new();
+    for path in snapshot_files(dir)? {
+        let snapshot = read_snapshot(&path.to_string_lossy())?;
+        let field = |key: &str| {
+            snapshot
+                .get("signing")
+                .and_then(|s| s.get(key))
+                .and_then(|v| v.as_i64())
+        };
+        let valid_until = field("valid_until");
+
+        let status = match valid_until {
+            None => AgeStatus::NoExpiry,
+            Some(until) if until < now => AgeStatus::Expired,
+            Some(until) if until <= now.saturating_add(secs_i64(warn_within)) => {
+                AgeStatus::ExpiringSoon
+            }
+            Some(_) => AgeStatus::Valid,
+        };
+
+        entries.push(AgeEntry {
+            status,
+            issued_at: field("issued_at"),
+            valid_until,
+            signer: signing::recover_signer(&snapshot).ok(),
+            path,
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{wallet, KEY_A};
+    use serde_json::json;
+
+    const NOW: i64 = 1_700_000_000;
+    const DAY: i64 = 86_400;
+
+    #[tokio::test]
+    async fn categorizes_mixed_expiry_states() {
+        let dir = tempfile::tempdir().unwrap();
+        let wallet = wallet(KEY_A);
+
+        let fixtures = [
+            ("a_expired.json", Some(NOW - DAY)),
+            ("b_expiring.json", Some(NOW + 2 * DAY)),
+            ("c_valid.json", Some(NOW + 30 * DAY)),
+            ("d_forever.json", None),
+        ];
+        for (name, valid_until) in fixtures {
+            let mut snap = json!({
+                "id": name,
+                "signing": { "scheme": "eip191", "issued_at": NOW - 10 * DAY }
+            });
+            if let Some(until) = valid_until {
+                snap["signing"]["valid_until"] = json!(until);
+            }
+            signing::sign_snapshot(&mut snap, &wallet).await.unwrap();
+            std::fs::write(dir.path().join(name), snap.to_string()).unwrap();
+        }
+        // Unsigned and missing both fields
+        std::fs::write(
+            dir.path().join("e_unsigned.json"),
+            json!({ "id": "e" }).to_string(),
+        )
+        .unwrap();
+
+        let entries = audit_dir(dir.path(), NOW, Duration::from_secs(7 * DAY as u64)).unwrap();
+        let statuses: Vec<AgeStatus> = entries.iter().map(|e| e.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                AgeStatus::Expired,
+                AgeStatus::ExpiringSoon,
+                AgeStatus::Valid,
+                AgeStatus::NoExpiry,
+                AgeStatus::NoExpiry
+            ]
+        );
+
+        let addr = ethers::signers::Signer::address(&wallet);
+        assert!(entries[..4].iter().all(|e| e.signer == Some(addr)));
+        assert_eq!(entries[4].signer, None);
+        assert_eq!(entries[0].issued_at, Some(NOW - 10 * DAY));
+    }
+}
@@ -0,0 +1,146 @@
+//! swarmhive auto - Verify a signed snapshot or offer to sign an unsigned one
+
+use crate::cli::AutoCmd;
+use crate::commands::{load_key_file, read_snapshot};
+use crate::config;
+use crate::signing;
+use anyhow::Result;
+use ethers::core::types::Address;
+use serde_json::Value;
+use std::io::{BufRead, Write};
+
+/// What `auto` found in signing.signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureState {
+    /// No signature (missing or empty)
+    Absent,
+    /// Signature present, content intact; recovered signer
+    Valid(Address),
+    /// Signature present but it does not verify
+    Invalid(String),
+}
+
+pub async fn run(cmd: AutoCmd) -> Result<()> {
+    let mut snapshot = read_snapshot(&cmd.file)?;
+
+    match detect(&snapshot) {
+        SignatureState::Valid(signer) => {
+            println!("{}: signed and valid", cmd.file);
+            println!("Signer: {:?}", signer);
+            Ok(())
+        }
+        SignatureState::Invalid(reason) => {
+            println!("{}: signed but INVALID", cmd.file);
+            println!("  error: {reason}");
+            anyhow::bail!("verification failed");
+        }
+        SignatureState::Absent => {
+            println!("{}: not signed", cmd.file);
+            let key_file = match cmd.key_file {
+                Some(path) => path,
+                None => config::load()?
+                    .identity
+                    .key_path
+                    .to_string_lossy()
+                    .to_string(),
+            };
+            if !cmd.yes && !confirm(&format!("Sign with {key_file}? [y/N] "))? {
+                println!("Left unsigned");
+                return Ok(());
+            }
+
+            let wallet = load_key_file(&key_file)?;
+            let (hash, addr) = signing::sign_snapshot(&mut snapshot, &wallet).await?;
+            std::fs::write(&cmd.file, serde_json::to_string_pretty(&snapshot)?)?;
+            println!("Signer: {:?}", addr);
+            println!("Payload hash: {}", signing::hash_str(hash));
+            println!("Signed: {}", cmd.file);
+            Ok(())
+        }
+    }
+}
+
+/// Classify the snapshot's primary signature
+pub fn detect(snapshot: &Value) -> SignatureState {
+    let present = snapshot
+        .get("signing")
+        .and_then(|s| s.get("signature"))
+        .is_some_and(signing::signature_present);
+    if !present {
+        return SignatureState::Absent;
+    }
+    match signing::recover_signer(snapshot) {
+        Ok(signer) => SignatureState::Valid(signer),
+        Err(e) => SignatureState::Invalid(e.to_string()),
+    }
+}
+
+fn confirm(prompt: &str) -> Result<bool> {
+    print!("{prompt}");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{address, wallet, KEY_A};
+    use ethers::signers::Signer;
+    use serde_json::json;
+
+    fn unsigned() -> Value {
+        json!({
+            "id": "proof-job-1",
+            "body": { "compute_seconds": 42 },
+            "signing": { "scheme": "eip191", "payload_hash": "", "signature": "" }
+        })
+    }
+
+    #[test]
+    fn absent_signature() {
+        assert_eq!(detect(&unsigned()), SignatureState::Absent);
+        assert_eq!(detect(&json!({ "id": "x" })), SignatureState::Absent);
+    }
+
+    #[tokio::test]
+    async fn present_and_valid() {
+        let wallet = wallet(KEY_A);
+        let mut snap = unsigned();
+        signing::sign_snapshot(&mut snap, &wallet).await.unwrap();
+        assert_eq!(detect(&snap), SignatureState::Valid(wallet.address()));
+    }
+
+    #[tokio::test]
+    async fn present_and_invalid() {
+        let wallet = wallet(KEY_A);
+        let mut snap = unsigned();
+        signing::sign_snapshot(&mut snap, &wallet).await.unwrap();
+        snap["body"]["compute_seconds"] = json!(9000);
+        assert!(
+            matches!(detect(&snap), SignatureState::Invalid(e) if e.contains("payload_hash mismatch"))
+        );
+    }
+
+    #[tokio::test]
+    async fn signs_absent_snapshot_with_yes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("snap.json");
+        let key = dir.path().join("key.hex");
+        std::fs::write(&file, unsigned().to_string()).unwrap();
+        std::fs::write(&key, KEY_A).unwrap();
+
+        run(AutoCmd {
+            file: file.to_string_lossy().to_string(),
+            key_file: Some(key.to_string_lossy().to_string()),
+            yes: true,
+        })
+        .await
+        .unwrap();
+
+        let signed = read_snapshot(&file.to_string_lossy()).unwrap();
+        let addr = address(KEY_A);
+        assert_eq!(detect(&signed), SignatureState::Valid(addr));
+    }
+}
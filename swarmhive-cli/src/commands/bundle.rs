@@ -0,0 +1,98 @@
+//! swarmhive bundle - Package a signed snapshot for independent verification
+
+use crate::canonical::CanonicalProfile;
+use crate::cli::BundleCmd;
+use crate::commands::read_snapshot;
+use crate::signing::{self, hash_str, signature_str};
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ethers::core::types::Address;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Everything an auditor needs to check a signature with standard tools:
+/// keccak256(base64-decode(canonical_bytes_b64)) is payload_hash, and the
+/// signature over it (per `scheme`, eip191 being personal_sign) recovers
+/// to `signer`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bundle {
+    /// Standard, padded base64 of the exact bytes that were hashed
+    pub canonical_bytes_b64: String,
+    pub payload_hash: String,
+    pub signature: String,
+    pub signer: Address,
+    pub scheme: String,
+}
+
+pub async fn run(cmd: BundleCmd) -> Result<()> {
+    let snapshot = read_snapshot(&cmd.file)?;
+    let bundle = build_bundle(&snapshot).with_context(|| cmd.file.clone())?;
+    let json = serde_json::to_string_pretty(&bundle)?;
+
+    match &cmd.out {
+        Some(path) => {
+            std::fs::write(path, json).with_context(|| format!("Failed to write {path}"))?;
+            println!("Signer: {:?}", bundle.signer);
+            println!("Bundle: {path}");
+        }
+        None => println!("{json}"),
+    }
+    Ok(())
+}
+
+/// Build the bundle for a signed snapshot. Fails if the snapshot's content
+/// no longer matches its signature.
+pub fn build_bundle(snapshot: &Value) -> Result<Bundle> {
+    let signer = signing::recover_signer(snapshot)?;
+    let (hash, signature) = signing::signed_parts(snapshot)?;
+
+    let preimage = signing::payload_preimage(snapshot, &CanonicalProfile::default())?;
+    let hashed = signing::snapshot_payload_encoding(snapshot)?.encode(preimage)?;
+
+    Ok(Bundle {
+        canonical_bytes_b64: STANDARD.encode(hashed),
+        payload_hash: hash_str(hash),
+        signature: signature_str(&signature),
+        signer,
+        scheme: signing::snapshot_scheme(snapshot)?.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{address, signed, KEY_A};
+    use ethers::core::types::Signature;
+    use ethers::utils::keccak256;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn bundle_is_internally_consistent() {
+        let snap = signed(json!({ "id": "snap-1", "body": { "b": 2, "a": 1 }, "signing": { "scheme": "eip191" } }), KEY_A).await;
+
+        let bundle = build_bundle(&snap).unwrap();
+        assert_eq!(bundle.signer, address(KEY_A));
+        assert_eq!(bundle.scheme, "eip191");
+
+        // Recompute with nothing but the bundle: keccak, then personal_sign recovery
+        let bytes = STANDARD.decode(&bundle.canonical_bytes_b64).unwrap();
+        let hash = keccak256(&bytes);
+        assert_eq!(hash_str(hash), bundle.payload_hash);
+
+        let hex_sig = bundle.signature.strip_prefix("eip191:0x").unwrap();
+        let sig = Signature::try_from(hex::decode(hex_sig).unwrap().as_slice()).unwrap();
+        assert_eq!(sig.recover(hash.as_slice()).unwrap(), bundle.signer);
+    }
+
+    #[tokio::test]
+    async fn tampered_snapshot_is_not_bundled() {
+        let mut snap = signed(
+            json!({ "id": "snap-1", "body": { "a": 1 }, "signing": { "scheme": "eip191" } }),
+            KEY_A,
+        )
+        .await;
+        snap["body"]["a"] = json!(2);
+        assert!(build_bundle(&snap).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+//! swarmhive canon-diff - Compare a snapshot's v1 and v2 canonical bytes
+
+use crate::canonical::CanonicalProfile;
+use crate::cli::CanonDiffCmd;
+use crate::commands::read_snapshot;
+use crate::jcs;
+use crate::signing::{
+    self, canonical_json_bytes_with, hash_str, CanonicalVersion, UNHASHED_SIGNING_KEYS,
+};
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+pub async fn run(cmd: CanonDiffCmd) -> Result<()> {
+    let snapshot = read_snapshot(&cmd.file)?;
+    for line in render(&canon_diff(&snapshot)?) {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+/// The snapshot's payload preimage and hash under each canonical version,
+/// and each place the two forms disagree
+#[derive(Debug)]
+pub struct CanonDiff {
+    pub v1: Vec<u8>,
+    pub v2: Vec<u8>,
+    pub hash_v1: [u8; 32],
+    pub hash_v2: [u8; 32],
+    pub differences: Vec<Difference>,
+}
+
+/// One disagreement between the forms, at a JSON Pointer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// A scalar is written differently (number formatting, string escapes)
+    Value {
+        pointer: String,
+        v1: String,
+        v2: String,
+    },
+    /// An object's members are written in a different order
+    KeyOrder {
+        pointer: String,
+        v1: Vec<String>,
+        v2: Vec<String>,
+    },
+}
+
+/// Canonicalize `snapshot` as if signed under each version: the
+/// signing.canonical_version marker is set accordingly (absent for v1),
+/// so the hashes are the ones a signature would cover. The marker itself
+/// is not reported as a difference.
+pub fn canon_diff(snapshot: &Value) -> Result<CanonDiff> {
+    let (v1, hash_v1) = under(snapshot, CanonicalVersion::V1)?;
+    let (v2, hash_v2) = under(snapshot, CanonicalVersion::V2)?;
+    let mut differences = Vec::new();
+    let mut content = snapshot.clone();
+    if let Some(signing) = content.get_mut("signing").and_then(|v| v.as_object_mut()) {
+        for key in UNHASHED_SIGNING_KEYS.iter().chain(&["canonical_version"]) {
+            signing.remove(*key);
+        }
+    }
+    compare(&content, "", &mut differences)?;
+    Ok(CanonDiff {
+        v1,
+        v2,
+        hash_v1,
+        hash_v2,
+        differences,
+    })
+}
+
+fn under(snapshot: &Value, version: CanonicalVersion) -> Result<(Vec<u8>, [u8; 32])> {
+    let mut copy = snapshot.clone();
+    if let Some(signing) = copy.get_mut("signing").and_then(|v| v.as_object_mut()) {
+        match version {
+            CanonicalVersion::V1 => signing.remove("canonical_version"),
+            CanonicalVersion::V2 => signing.insert(
+                "canonical_version".to_string(),
+                Value::from(version.as_u64()),
+            ),
+        };
+    } else if version != CanonicalVersion::V1 {
+        return Err(anyhow!("snapshot missing signing object"));
+    }
+    let preimage = signing::payload_preimage(&copy, &CanonicalProfile::default())?;
+    Ok((preimage, signing::payload_hash_keccak(&copy)?))
+}
+
+fn compare(v: &Value, pointer: &str, out: &mut Vec<Difference>) -> Result<()> {
+    match v {
+        Value::Object(map) => {
+            let mut v1: Vec<String> = map.keys().cloned().collect();
+            v1.sort();
+            let mut v2 = v1.clone();
+            v2.sort_by(|a, b| jcs::key_order(a, b));
+            if v1 != v2 {
+                out.push(Difference::KeyOrder {
+                    pointer: display_pointer(pointer),
+                    v1,
+                    v2,
+                });
+            }
+            for (k, child) in map {
+                compare(
+                    child,
+                    &format!("{pointer}/{}", k.replace('~', "~0").replace('/', "~1")),
+                    out,
+                )?;
+            }
+        }
+        Value::Array(arr) => {
+            for (i, child) in arr.iter().enumerate() {
+                compare(child, &format!("{pointer}/{i}"), out)?;
+            }
+        }
+        _ => {
+            let v1 = canonical_json_bytes_with(v, CanonicalVersion::V1)?;
+            let v2 = canonical_json_bytes_with(v, CanonicalVersion::V2)?;
+            if v1 != v2 {
+                out.push(Difference::Value {
+                    pointer: display_pointer(pointer),
+                    v1: String::from_utf8_lossy(&v1).into_owned(),
+                    v2: String::from_utf8_lossy(&v2).into_owned(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn display_pointer(pointer: &str) -> String {
+    if pointer.is_empty() {
+        "/".to_string()
+    } else {
+        pointer.to_string()
+    }
+}
+
+/// Both forms, their hashes, and one line per difference
+pub fn render(diff: &CanonDiff) -> Vec<String> {
+    let text = |b: &[u8]| String::from_utf8_lossy(b).into_owned();
+    let mut lines = vec![
+        format!("v1: {}", text(&diff.v1)),
+        format!("v2: {}", text(&diff.v2)),
+        format!("v1 hash: {}", hash_str(diff.hash_v1)),
+        format!("v2 hash: {}", hash_str(diff.hash_v2)),
+    ];
+    if diff.differences.is_empty() {
+        lines.push(
+            "Content is canonicalized identically (hashes differ only by the version marker)"
+                .to_string(),
+        );
+    }
+    for difference in &diff.differences {
+        lines.push(match difference {
+            Difference::Value { pointer, v1, v2 } => format!("{pointer}: v1 {v1} | v2 {v2}"),
+            Difference::KeyOrder { pointer, v1, v2 } => {
+                format!("{pointer}: key order v1 {v1:?} | v2 {v2:?}")
+            }
+        });
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn number_formatting_difference_is_surfaced() {
+        let snap: Value = serde_json::from_str(
+            r#"{"id":"snap-1","body":{"ratio":1.0,"n":2},"signing":{"scheme":"eip191"}}"#,
+        )
+        .unwrap();
+        let diff = canon_diff(&snap).unwrap();
+        assert_ne!(diff.hash_v1, diff.hash_v2);
+        assert_eq!(
+            diff.differences,
+            vec![Difference::Value {
+                pointer: "/body/ratio".to_string(),
+                v1: "1.0".to_string(),
+                v2: "1".to_string()
+            }]
+        );
+
+        let lines = render(&diff);
+        assert!(lines[0].contains("\"ratio\":1.0"), "{lines:?}");
+        assert!(lines[1].contains("\"ratio\":1}"), "{lines:?}");
+        assert!(
+            lines.contains(&"/body/ratio: v1 1.0 | v2 1".to_string()),
+            "{lines:?}"
+        );
+    }
+
+    #[test]
+    fn key_order_difference_is_surfaced() {
+        // U+E000 sorts before U+1F600 by UTF-8 bytes, after it by UTF-16 units
+        let snap =
+            json!({ "body": { "\u{e000}": 1, "\u{1f600}": 2 }, "signing": { "scheme": "eip191" } });
+        let diff = canon_diff(&snap).unwrap();
+        assert_eq!(
+            diff.differences,
+            vec![Difference::KeyOrder {
+                pointer: "/body".to_string(),
+                v1: vec!["\u{e000}".to_string(), "\u{1f600}".to_string()],
+                v2: vec!["\u{1f600}".to_string(), "\u{e000}".to_string()],
+            }]
+        );
+
+        let plain = canon_diff(&json!({ "body": { "n": 1 }, "signing": {} })).unwrap();
+        assert!(plain.differences.is_empty());
+        assert_ne!(plain.hash_v1, plain.hash_v2);
+    }
+}
@@ -0,0 +1,36 @@
+//! swarmhive check-anchor - Compare a snapshot with its on-chain anchor
+
+use crate::anchor::{self, AnchorStatus};
+use crate::cli::CheckAnchorCmd;
+use crate::commands::read_snapshot;
+use crate::retry::{RetryPolicy, Retrying};
+use anyhow::{Context, Result};
+use ethers::core::types::Address;
+use ethers::providers::{Http, Provider};
+
+pub async fn run(cmd: CheckAnchorCmd) -> Result<()> {
+    let snapshot = read_snapshot(&cmd.file)?;
+    let contract: Address = cmd
+        .contract
+        .parse()
+        .with_context(|| format!("bad --contract {}", cmd.contract))?;
+    let provider = Retrying::new(
+        Provider::<Http>::try_from(cmd.rpc_url.as_str())
+            .with_context(|| format!("bad --rpc-url {}", cmd.rpc_url))?,
+        RetryPolicy {
+            max_attempts: cmd.rpc_attempts,
+            ..Default::default()
+        },
+    );
+    let key = anchor::anchor_key(&cmd.key);
+
+    println!("Checking: {}", cmd.file);
+    println!("Anchor: {contract:?} key 0x{}", hex::encode(key));
+    let status = anchor::check_anchor(&snapshot, contract, key, &provider).await?;
+    println!("{status}");
+    match status {
+        AnchorStatus::Matches(_) => Ok(()),
+        AnchorStatus::Mismatch { .. } => anyhow::bail!("anchor mismatch"),
+        AnchorStatus::Unanchored { .. } => anyhow::bail!("key {} is not anchored", cmd.key),
+    }
+}
@@ -0,0 +1,52 @@
+//! swarmhive extract - Forward a snapshot's content only if it verifies
+//!
+//! A gate for shell pipelines: on success the snapshot is the only thing
+//! written to stdout; on failure nothing is, and the exit status is
+//! nonzero.
+
+use crate::cli::ExtractCmd;
+use crate::commands::read_snapshot;
+use crate::verify::{self, VerifyOptions};
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use serde_json::Value;
+
+pub async fn run(cmd: ExtractCmd) -> Result<()> {
+    let snapshot = read_snapshot(&cmd.file)?;
+    let opts = VerifyOptions {
+        expected_address: cmd
+            .address
+            .as_deref()
+            .map(|a| a.parse().with_context(|| format!("bad --address {a}")))
+            .transpose()?,
+        ..Default::default()
+    };
+    let content = extract(snapshot, &opts, Utc::now().timestamp(), cmd.strip_signing)
+        .with_context(|| format!("{}: not extracted", cmd.file))?;
+    println!("{}", serde_json::to_string_pretty(&content)?);
+    Ok(())
+}
+
+/// `snapshot`, minus its signing object with `strip_signing`, if it passes
+/// every check in `opts` at `now`
+pub fn extract(
+    mut snapshot: Value,
+    opts: &VerifyOptions,
+    now: i64,
+    strip_signing: bool,
+) -> Result<Value> {
+    let report = verify::verify_report(&snapshot, opts, now);
+    if !report.is_valid() {
+        return Err(anyhow!(
+            "verification failed ({}): {}",
+            report.failed_checks().join(", "),
+            report.errors.join("; ")
+        ));
+    }
+    if strip_signing {
+        if let Some(map) = snapshot.as_object_mut() {
+            map.remove("signing");
+        }
+    }
+    Ok(snapshot)
+}
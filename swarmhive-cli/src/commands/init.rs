@@ -5,6 +5,7 @@ use crate::config::{self, Config, IdentityConfig, IpfsConfig, PoolConfig};
 use crate::signing;
 use anyhow::Result;
 use chrono::Utc;
+use ethers::signers::Signer;
 use serde_json::json;
 
 pub async fn run(cmd: InitCmd) -> Result<()> {
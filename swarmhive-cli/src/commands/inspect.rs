@@ -0,0 +1,150 @@
+//! swarmhive inspect - Show a snapshot's signing metadata
+
+use crate::cli::InspectCmd;
+use crate::commands::{read_snapshot, signer_display};
+use crate::provenance;
+use crate::signing;
+use anyhow::Result;
+use serde_json::Value;
+
+pub async fn run(cmd: InspectCmd) -> Result<()> {
+    let snapshot = read_snapshot(&cmd.file)?;
+    let signer = match signing::recover_signer(&snapshot) {
+        Ok(signer) => Some(signer_display(signer, cmd.rpc_url.as_deref()).await?),
+        Err(_) => None,
+    };
+    println!("Inspecting: {}", cmd.file);
+    for line in describe(&snapshot, signer.as_deref()) {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+/// One line per field worth showing: identity, scheme, timing, reason,
+/// provenance and whether the signature recovers. A recovered signer is shown as
+/// `signer_label` when given.
+pub fn describe(snapshot: &Value, signer_label: Option<&str>) -> Vec<String> {
+    let mut lines = Vec::new();
+    for key in ["type", "id"] {
+        if let Some(v) = snapshot.get(key).and_then(|v| v.as_str()) {
+            lines.push(format!("{}: {v}", capitalize(key)));
+        }
+    }
+    match signing::snapshot_scheme(snapshot) {
+        Ok(scheme) => lines.push(format!("Scheme: {scheme}")),
+        Err(e) => lines.push(format!("Scheme: {e}")),
+    }
+    if let Ok(signing) = signing::signing_object(snapshot) {
+        for (key, label) in [("issued_at", "Issued at"), ("valid_until", "Valid until")] {
+            if let Some(v) = signing.get(key) {
+                lines.push(format!("{label}: {v}"));
+            }
+        }
+    }
+    match signing::snapshot_reason(snapshot) {
+        Ok(Some(reason)) => lines.push(format!("Reason: {reason}")),
+        Ok(None) => {}
+        Err(e) => lines.push(format!("Reason: {e}")),
+    }
+    match provenance::snapshot_provenance(snapshot) {
+        Ok(Some(provenance)) => lines.push(format!("Provenance: {}", provenance.summary())),
+        Ok(None) => {}
+        Err(e) => lines.push(format!("Provenance: {e}")),
+    }
+    match signing::recover_signer(snapshot) {
+        Ok(signer) => match signer_label {
+            Some(label) => lines.push(format!("Signer: {label}")),
+            None => lines.push(format!("Signer: {signer:?}")),
+        },
+        Err(e) => lines.push(format!("Signature: INVALID: {e}")),
+    }
+    lines
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .map(|c| c.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{InputFormat, SignCmd};
+    use crate::commands::sign;
+    use crate::test_support::{address, KEY_A};
+    use crate::verify::{verify_report, VerifyOptions};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn reason_is_hashed_and_shown() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("snap.json").to_string_lossy().to_string();
+        let key = dir.path().join("key.hex").to_string_lossy().to_string();
+        std::fs::write(
+            &file,
+            json!({ "id": "snap-1", "body": { "n": 1 }, "signing": { "scheme": "eip191" } })
+                .to_string(),
+        )
+        .unwrap();
+        std::fs::write(&key, KEY_A).unwrap();
+
+        sign::run(SignCmd {
+            files: vec![file.clone()],
+            key_file: Some(key),
+            keystore: None,
+            password_file: None,
+            mnemonic: None,
+            index: 0,
+            remote_signer: None,
+            remote_address: None,
+            remote_header: vec![],
+            pkcs11_module: None,
+            pkcs11_slot: None,
+            pkcs11_label: None,
+            pkcs11_pin_file: None,
+            out: None,
+            canonical_out: None,
+            resign: false,
+            cosign: false,
+            reason: Some("approved maintenance window".to_string()),
+            provenance: false,
+            max_concurrency: None,
+            min_interval: None,
+            attach_file: vec![],
+            input_format: InputFormat::Json,
+            prefer_scheme: vec![],
+        })
+        .await
+        .unwrap();
+
+        let snap = read_snapshot(&file).unwrap();
+        let signer = address(KEY_A);
+        let lines = describe(&snap, None);
+        assert!(
+            lines.contains(&"Reason: approved maintenance window".to_string()),
+            "{lines:?}"
+        );
+        assert!(lines.contains(&format!("Signer: {signer:?}")), "{lines:?}");
+        assert!(describe(&snap, Some("alice.eth")).contains(&"Signer: alice.eth".to_string()));
+        let report = verify_report(&snap, &VerifyOptions::default(), 0);
+        assert_eq!(
+            report.reason.as_deref(),
+            Some("approved maintenance window")
+        );
+
+        // The reason is part of the payload hash
+        let mut edited = snap.clone();
+        edited["signing"]["reason"] = json!("routine");
+        assert_ne!(
+            signing::payload_hash_keccak(&edited).unwrap(),
+            signing::payload_hash_keccak(&snap).unwrap()
+        );
+        assert!(signing::verify_signature(&edited, signer).is_err());
+        let report = verify_report(&edited, &VerifyOptions::default(), 0);
+        assert!(!report.is_valid());
+        assert_eq!(report.reason, None);
+    }
+}
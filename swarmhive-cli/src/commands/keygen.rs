@@ -0,0 +1,78 @@
+//! swarmhive keygen - Generate a signing key into an encrypted keystore
+
+use crate::cli::KeygenCmd;
+use crate::commands::read_password_file;
+use anyhow::{anyhow, Result};
+use ethers::core::rand::thread_rng;
+use ethers::core::types::Address;
+use ethers::signers::{LocalWallet, Signer};
+use std::path::Path;
+
+pub async fn run(cmd: KeygenCmd) -> Result<()> {
+    let password = match &cmd.password_file {
+        Some(path) => read_password_file(path)?,
+        None => {
+            let password = rpassword::prompt_password("Keystore password: ")?;
+            if rpassword::prompt_password("Repeat password: ")? != password {
+                anyhow::bail!("passwords do not match");
+            }
+            password
+        }
+    };
+
+    let address = write_keystore(Path::new(&cmd.out), &password, cmd.force)?;
+    println!("Address: {:?}", address);
+    println!("Keystore: {}", cmd.out);
+    Ok(())
+}
+
+/// Generate a key from the OS-seeded CSPRNG and write it to `path` as an
+/// encrypted (scrypt) keystore. The private key never leaves this function.
+pub fn write_keystore(path: &Path, password: &str, force: bool) -> Result<Address> {
+    if password.is_empty() {
+        return Err(anyhow!(
+            "refusing to write a keystore with an empty password"
+        ));
+    }
+    if path.exists() && !force {
+        return Err(anyhow!(
+            "{} already exists; use --force to overwrite",
+            path.display()
+        ));
+    }
+
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| anyhow!("keystore path {} has no file name", path.display()))?;
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+
+    let (wallet, _) = LocalWallet::new_keystore(dir, &mut thread_rng(), password, Some(name))
+        .map_err(|e| anyhow!("failed to write keystore: {e}"))?;
+    Ok(wallet.address())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keystore_decrypts_to_printed_address() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ks.json");
+
+        let address = write_keystore(&path, "correct horse", false).unwrap();
+        let wallet = LocalWallet::decrypt_keystore(&path, "correct horse").unwrap();
+        assert_eq!(wallet.address(), address);
+        assert!(LocalWallet::decrypt_keystore(&path, "wrong").is_err());
+
+        // No clobbering without --force
+        let err = write_keystore(&path, "correct horse", false).unwrap_err();
+        assert!(err.to_string().contains("--force"), "{err}");
+        let replaced = write_keystore(&path, "correct horse", true).unwrap();
+        assert_ne!(replaced, address);
+    }
+}
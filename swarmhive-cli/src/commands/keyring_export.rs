@@ -0,0 +1,23 @@
+//! swarmhive keyring-export - Sign a list of authorized signers for distribution
+
+use crate::cli::KeyringExportCmd;
+use crate::commands::load_key_file;
+use crate::keyring;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use ethers::signers::Signer;
+
+pub async fn run(cmd: KeyringExportCmd) -> Result<()> {
+    let coordinator = load_key_file(&cmd.key_file)?;
+    let signers = keyring::load_allowlist(&cmd.signers)?;
+    let doc = keyring::export_keyring(&signers, &coordinator, Utc::now().timestamp()).await?;
+    std::fs::write(&cmd.out, serde_json::to_string_pretty(&doc)?)
+        .with_context(|| format!("Failed to write {}", cmd.out))?;
+    println!(
+        "Signers: {}",
+        doc["body"]["signers"].as_array().map_or(0, Vec::len)
+    );
+    println!("Coordinator: {:?}", coordinator.address());
+    println!("Keyring: {}", cmd.out);
+    Ok(())
+}
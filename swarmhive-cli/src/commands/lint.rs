@@ -0,0 +1,86 @@
+//! swarmhive lint - Check snapshots are stored in canonical form
+
+use crate::cli::LintCmd;
+use crate::commands::snapshot_files;
+use crate::signing;
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+
+pub async fn run(cmd: LintCmd) -> Result<()> {
+    let findings = lint_files(&snapshot_files(Path::new(&cmd.dir))?);
+    let mut failing = 0;
+    for (path, finding) in &findings {
+        match finding {
+            Ok(()) => println!("{}: canonical", path.display()),
+            Err(e) => {
+                println!("{}: {e}", path.display());
+                failing += 1;
+            }
+        }
+    }
+    println!("{} files, {failing} not canonical", findings.len());
+    if failing > 0 {
+        return Err(anyhow!("{failing} file(s) are not canonical"));
+    }
+    Ok(())
+}
+
+/// Check each file's bytes with `assert_canonical`, without modifying it
+pub fn lint_files(files: &[PathBuf]) -> Vec<(PathBuf, Result<()>)> {
+    files
+        .iter()
+        .map(|path| {
+            let finding = std::fs::read(path)
+                .with_context(|| format!("Failed to read {}", path.display()))
+                .and_then(|bytes| signing::assert_canonical(&bytes));
+            (path.clone(), finding)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reports_each_file_and_fails_on_non_canonical() {
+        let dir = tempfile::tempdir().unwrap();
+        let canonical = dir.path().join("a.json");
+        let unsorted = dir.path().join("b.json");
+        std::fs::write(
+            &canonical,
+            "{\"body\":{\"a\":1,\"b\":[1,2]},\"id\":\"snap-1\"}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &unsorted,
+            "{\"id\":\"snap-1\",\"body\":{\"a\":1,\"b\":[1,2]}}",
+        )
+        .unwrap();
+        let before = std::fs::read(&unsorted).unwrap();
+
+        let findings = lint_files(&[canonical.clone(), unsorted.clone()]);
+        assert!(findings[0].1.is_ok());
+        let err = findings[1].1.as_ref().unwrap_err().to_string();
+        assert!(err.contains("first difference at byte 2"), "{err}");
+
+        let dir_str = dir.path().to_str().unwrap().to_string();
+        let err = run(LintCmd {
+            dir: dir_str.clone(),
+        })
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("1 file(s)"), "{err}");
+        assert_eq!(std::fs::read(&unsorted).unwrap(), before);
+
+        std::fs::remove_file(&unsorted).unwrap();
+        run(LintCmd { dir: dir_str }).await.unwrap();
+    }
+
+    #[test]
+    fn whitespace_and_pretty_printing_are_not_canonical() {
+        assert!(signing::assert_canonical(b"{\"a\": 1}").is_err());
+        assert!(signing::assert_canonical(b"{\n  \"a\":1\n}").is_err());
+        assert!(signing::assert_canonical(b"{\"a\":1}").is_ok());
+    }
+}
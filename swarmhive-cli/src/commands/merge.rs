@@ -0,0 +1,35 @@
+//! swarmhive merge - Combine co-signatures from copies of one snapshot
+
+use crate::cli::MergeCmd;
+use crate::commands::read_snapshot;
+use crate::cosign;
+use anyhow::Result;
+
+pub async fn run(cmd: MergeCmd) -> Result<()> {
+    println!("Merging {} copies", cmd.files.len());
+
+    let copies = cmd
+        .files
+        .iter()
+        .map(|f| read_snapshot(f))
+        .collect::<Result<Vec<_>>>()?;
+
+    let merged = cosign::merge_cosigned(&copies)?;
+    let signers = cosign::cosignatures(&merged)?;
+
+    std::fs::write(&cmd.out, serde_json::to_string_pretty(&merged)?)?;
+
+    println!(
+        "Payload hash: {}",
+        merged["signing"]["payload_hash"]
+            .as_str()
+            .unwrap_or_default()
+    );
+    println!("Signers ({}):", signers.len());
+    for cosig in &signers {
+        println!("  {:?}", cosig.signer);
+    }
+    println!("Merged: {}", cmd.out);
+
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! swarmhive migrate-canonical - Re-sign v1 snapshots under canonical_version 2
+
+use crate::cli::MigrateCanonicalCmd;
+use crate::commands::{load_key_file, read_snapshot, snapshot_files};
+use crate::history::{self, HistoryAction};
+use crate::signing::{self, CanonicalVersion};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use ethers::core::types::Address;
+use ethers::signers::{LocalWallet, Signer};
+use serde_json::Value;
+use std::path::Path;
+
+pub async fn run(cmd: MigrateCanonicalCmd) -> Result<()> {
+    let wallet = load_key_file(&cmd.key_file)?;
+    let (mut migrated, mut skipped, mut refused) = (0, 0, 0);
+    for path in snapshot_files(Path::new(&cmd.dir))? {
+        let display = path.display().to_string();
+        let mut snapshot = read_snapshot(&display)?;
+        match migrate_snapshot(&mut snapshot, &wallet, Utc::now().timestamp()).await {
+            Ok(Some(previous)) => {
+                std::fs::write(&path, serde_json::to_string_pretty(&snapshot)?)?;
+                println!("{display}: migrated (v1 signer {previous:?})");
+                migrated += 1;
+            }
+            Ok(None) => skipped += 1,
+            Err(e) => {
+                println!("{display}: refused: {e}");
+                refused += 1;
+            }
+        }
+    }
+    println!("{migrated} migrated, {skipped} already v2, {refused} refused");
+    if refused > 0 {
+        return Err(anyhow!("{refused} snapshot(s) were not migrated"));
+    }
+    Ok(())
+}
+
+/// Verify the snapshot's v1 signature, then set signing.canonical_version
+/// to 2 and re-sign with `wallet`. Returns the v1 signer, or None if the
+/// snapshot is already v2. A snapshot whose v1 signature does not verify
+/// is left untouched, so corruption is never re-signed. Co-signatures are
+/// dropped: they cover the v1 payload hash. The re-signing is appended to
+/// signing.history at `now`.
+pub async fn migrate_snapshot(
+    snapshot: &mut Value,
+    wallet: &LocalWallet,
+    now: i64,
+) -> Result<Option<Address>> {
+    match signing::snapshot_canonical_version(snapshot)? {
+        CanonicalVersion::V2 => return Ok(None),
+        CanonicalVersion::V1 => {}
+    }
+    let previous = signing::recover_signer(snapshot)
+        .map_err(|e| anyhow!("v1 signature does not verify: {e}"))?;
+
+    let mut updated = snapshot.clone();
+    let signing = updated
+        .get_mut("signing")
+        .and_then(|v| v.as_object_mut())
+        .ok_or_else(|| anyhow!("snapshot missing signing object"))?;
+    signing.insert(
+        "canonical_version".to_string(),
+        Value::from(CanonicalVersion::V2.as_u64()),
+    );
+    signing.remove("signatures");
+    signing::sign_snapshot(&mut updated, wallet).await?;
+    let action = if previous == wallet.address() {
+        HistoryAction::Resign
+    } else {
+        HistoryAction::Rotate
+    };
+    history::record_signing(&mut updated, action, now)?;
+
+    *snapshot = updated;
+    Ok(Some(previous))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{signed, wallet, KEY_A};
+    use crate::verify::{verify_report, VerifyOptions};
+    use ethers::signers::Signer;
+    use serde_json::json;
+
+    async fn v1_signed() -> Value {
+        // Keys whose v1 (byte) and v2 (UTF-16) orders differ
+        signed(json!({ "id": "snap-1", "body": { "\u{e000}": 1, "\u{1f600}": 2 }, "signing": { "scheme": "eip191" } }), KEY_A).await
+    }
+
+    #[tokio::test]
+    async fn migrated_snapshot_verifies_under_v2() {
+        let wallet = wallet(KEY_A);
+        let mut snap = v1_signed().await;
+        let v1_hash = snap["signing"]["payload_hash"].clone();
+
+        history::record_signing(&mut snap, HistoryAction::Sign, 0).unwrap();
+        assert_eq!(
+            migrate_snapshot(&mut snap, &wallet, 60).await.unwrap(),
+            Some(wallet.address())
+        );
+        assert_eq!(
+            signing::snapshot_canonical_version(&snap).unwrap(),
+            CanonicalVersion::V2
+        );
+        assert_ne!(snap["signing"]["payload_hash"], v1_hash);
+        signing::verify_signature(&snap, wallet.address()).unwrap();
+        let opts = VerifyOptions {
+            canonical_version: Some(CanonicalVersion::V2),
+            ..Default::default()
+        };
+        assert!(verify_report(&snap, &opts, 0).is_valid());
+        let actions: Vec<HistoryAction> = history::verify_history(&snap)
+            .unwrap()
+            .iter()
+            .map(|e| e.action)
+            .collect();
+        assert_eq!(actions, vec![HistoryAction::Sign, HistoryAction::Resign]);
+
+        // Running again is a no-op
+        let before = snap.clone();
+        assert_eq!(
+            migrate_snapshot(&mut snap, &wallet, 120).await.unwrap(),
+            None
+        );
+        assert_eq!(snap, before);
+    }
+
+    #[tokio::test]
+    async fn invalid_v1_signature_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut snap = v1_signed().await;
+        snap["body"]["\u{e000}"] = json!(99);
+        let path = dir.path().join("a.json");
+        std::fs::write(&path, snap.to_string()).unwrap();
+        std::fs::write(dir.path().join("key.hex"), KEY_A).unwrap();
+        let before = std::fs::read(&path).unwrap();
+
+        let err = run(MigrateCanonicalCmd {
+            dir: dir.path().to_string_lossy().to_string(),
+            key_file: dir.path().join("key.hex").to_string_lossy().to_string(),
+        })
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("1 snapshot(s)"), "{err}");
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+
+        let wallet = wallet(KEY_A);
+        let err = migrate_snapshot(&mut snap, &wallet, 0).await.unwrap_err();
+        assert!(
+            err.to_string().contains("v1 signature does not verify"),
+            "{err}"
+        );
+    }
+}
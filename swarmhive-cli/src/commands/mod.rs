@@ -1,6 +1,235 @@
+use crate::{ens, signing};
+use anyhow::{anyhow, Context, Result};
+use ethers::core::types::Address;
+use ethers::providers::{Http, Provider};
+use ethers::signers::coins_bip39::English;
+use ethers::signers::{LocalWallet, MnemonicBuilder};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+pub mod audit_ages;
+pub mod auto;
+pub mod bundle;
+pub mod canon_diff;
+pub mod check_anchor;
 pub mod claim;
+pub mod extract;
 pub mod init;
+pub mod inspect;
+pub mod keygen;
+pub mod keyring_export;
+pub mod lint;
+pub mod merge;
+pub mod migrate_canonical;
 pub mod prove;
+pub mod reencode;
+pub mod same;
 pub mod seal;
+pub mod sign;
+pub mod sign_manifest;
+pub mod signers;
 pub mod submit;
+pub mod touch;
+pub mod unsign;
+pub mod verify;
+pub mod verify_archive;
+pub mod verify_batch;
+pub mod verify_stream;
 pub mod watch;
+
+/// Read and parse a snapshot JSON file
+pub fn read_snapshot(path: &str) -> Result<Value> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+    serde_json::from_str(&content).with_context(|| format!("{path} is not valid JSON"))
+}
+
+/// How to print a signer: its forward-confirmed reverse-ENS label when an
+/// RPC endpoint is given, the plain address otherwise
+pub async fn signer_display(signer: Address, rpc_url: Option<&str>) -> Result<String> {
+    match rpc_url {
+        Some(url) => {
+            let provider =
+                Provider::<Http>::try_from(url).with_context(|| format!("bad --rpc-url {url}"))?;
+            Ok(ens::signer_label(signer, &provider).await)
+        }
+        None => Ok(format!("{signer:?}")),
+    }
+}
+
+/// Every *.json file under `dir`, recursively, in sorted order
+pub fn snapshot_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = std::fs::read_dir(&current)
+            .with_context(|| format!("Failed to read directory {}", current.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "json") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Source of the signing key for a command. Loading can be expensive
+/// (keystore decryption, mnemonic derivation), so commands that sign many
+/// files call `signer` once and reuse the wallet.
+pub trait KeyProvider {
+    fn signer(&self) -> Result<LocalWallet>;
+}
+
+/// Key read from a file by `load_key_file`
+pub struct KeyFile(pub String);
+
+impl KeyProvider for KeyFile {
+    fn signer(&self) -> Result<LocalWallet> {
+        load_key_file(&self.0)
+    }
+}
+
+/// Encrypted JSON keystore (as written by `keygen`)
+pub struct KeystoreFile {
+    pub path: String,
+    /// Read the password from this file instead of prompting
+    pub password_file: Option<String>,
+}
+
+impl KeyProvider for KeystoreFile {
+    fn signer(&self) -> Result<LocalWallet> {
+        let password = match &self.password_file {
+            Some(path) => read_password_file(path)?,
+            None => rpassword::prompt_password(format!("Password for {}: ", self.path))?,
+        };
+        LocalWallet::decrypt_keystore(&self.path, password)
+            .map_err(|e| anyhow!("failed to decrypt keystore {}: {e}", self.path))
+    }
+}
+
+/// BIP-39 phrase in a file, derived at m/44'/60'/0'/0/{index}
+pub struct MnemonicFile {
+    pub path: String,
+    pub index: u32,
+}
+
+impl KeyProvider for MnemonicFile {
+    fn signer(&self) -> Result<LocalWallet> {
+        let phrase = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read mnemonic file {}", self.path))?;
+        MnemonicBuilder::<English>::default()
+            .phrase(phrase.trim())
+            .index(self.index)?
+            .build()
+            .map_err(|e| anyhow!("bad mnemonic in {}: {e}", self.path))
+    }
+}
+
+/// Read a password file, dropping the trailing newline
+pub fn read_password_file(path: &str) -> Result<String> {
+    let raw = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read password file {path}"))?;
+    Ok(raw.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Load a signing key from a file holding a hex private key. Keystores and
+/// mnemonics passed here by mistake get an error naming the right flag.
+pub fn load_key_file(path: &str) -> Result<LocalWallet> {
+    let key =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read key file {path}"))?;
+    signing::wallet_from_private_key_hex(&key).map_err(|e| match misplaced_key_hint(&key) {
+        Some(hint) => anyhow!("{path} {hint}"),
+        None => e,
+    })
+}
+
+/// What a key file that is not a hex key seems to hold instead
+fn misplaced_key_hint(content: &str) -> Option<&'static str> {
+    let content = content.trim();
+    if content.starts_with('{') {
+        let keystore = serde_json::from_str::<Value>(content)
+            .is_ok_and(|v| v.get("crypto").or_else(|| v.get("Crypto")).is_some());
+        return Some(if keystore {
+            "looks like an encrypted JSON keystore, not a hex private key; use --keystore <file> instead of --key-file"
+        } else {
+            "looks like JSON, not a hex private key; if it is a keystore, use --keystore <file> instead of --key-file"
+        });
+    }
+    let words: Vec<&str> = content.split_whitespace().collect();
+    if words.len() >= 12
+        && words
+            .iter()
+            .all(|w| w.chars().all(|c| c.is_ascii_lowercase()))
+    {
+        return Some("looks like a mnemonic phrase, not a hex private key; use --mnemonic <file> instead of --key-file");
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::signers::Signer;
+
+    const PHRASE: &str = "test test test test test test test test test test test junk";
+
+    fn key_file_error(content: &str) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key.hex");
+        std::fs::write(&path, content).unwrap();
+        load_key_file(path.to_str().unwrap())
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn mnemonic_in_key_file_suggests_mnemonic_flag() {
+        let err = key_file_error(&format!("{PHRASE}\n"));
+        assert!(err.contains("looks like a mnemonic phrase"), "{err}");
+        assert!(err.contains("--mnemonic"), "{err}");
+    }
+
+    #[test]
+    fn keystore_in_key_file_suggests_keystore_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        let address = keygen::write_keystore(&dir.path().join("ks.json"), "pw", false).unwrap();
+        let keystore = std::fs::read_to_string(dir.path().join("ks.json")).unwrap();
+
+        let err = key_file_error(&keystore);
+        assert!(err.contains("encrypted JSON keystore"), "{err}");
+        assert!(err.contains("--keystore"), "{err}");
+
+        // And the suggested flag's provider loads it
+        std::fs::write(dir.path().join("pw.txt"), "pw\n").unwrap();
+        let provider = KeystoreFile {
+            path: dir.path().join("ks.json").to_string_lossy().to_string(),
+            password_file: Some(dir.path().join("pw.txt").to_string_lossy().to_string()),
+        };
+        assert_eq!(provider.signer().unwrap().address(), address);
+    }
+
+    #[test]
+    fn plain_bad_hex_keeps_original_error() {
+        assert!(key_file_error("0xnothex").contains("bad hex private key"));
+    }
+
+    #[test]
+    fn mnemonic_provider_derives_first_account() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("phrase.txt");
+        std::fs::write(&path, PHRASE).unwrap();
+        let provider = MnemonicFile {
+            path: path.to_string_lossy().to_string(),
+            index: 0,
+        };
+        // Well-known first account of the test phrase
+        let expected: ethers::core::types::Address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+            .parse()
+            .unwrap();
+        assert_eq!(provider.signer().unwrap().address(), expected);
+    }
+}
@@ -0,0 +1,125 @@
+//! swarmhive reencode - Rewrite signatures to another encoding
+
+use crate::cli::ReencodeCmd;
+use crate::commands::{read_snapshot, snapshot_files};
+use crate::signing::{self, SignatureEncoding};
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::path::Path;
+
+pub async fn run(cmd: ReencodeCmd) -> Result<()> {
+    let mut rewritten = 0;
+    let mut skipped = 0;
+    for path in snapshot_files(Path::new(&cmd.dir))? {
+        let display = path.display().to_string();
+        let mut snapshot = read_snapshot(&display)?;
+        match reencode_snapshot(&mut snapshot, cmd.to).with_context(|| display.clone())? {
+            Some(from) => {
+                std::fs::write(&path, serde_json::to_string_pretty(&snapshot)?)?;
+                println!("{display}: {from} -> {}", cmd.to);
+                rewritten += 1;
+            }
+            None => skipped += 1,
+        }
+    }
+    println!("{rewritten} rewritten, {skipped} unchanged or unsigned");
+    Ok(())
+}
+
+/// Rewrite signing.signature in `encoding`, leaving the signature itself
+/// untouched. Returns the previous encoding, or None when the snapshot is
+/// unsigned or already in `encoding`. Fails, without modifying `snapshot`,
+/// if it does not recover to the same signer afterwards.
+pub fn reencode_snapshot(
+    snapshot: &mut Value,
+    encoding: SignatureEncoding,
+) -> Result<Option<SignatureEncoding>> {
+    let sig_str = match snapshot
+        .get("signing")
+        .and_then(|s| s.get("signature"))
+        .and_then(|v| v.as_str())
+    {
+        Some(s) if !s.is_empty() => s.to_string(),
+        _ => return Ok(None),
+    };
+    let from = SignatureEncoding::detect(&sig_str)?;
+    if from == encoding {
+        return Ok(None);
+    }
+
+    let before = signing::recover_signer(snapshot)?;
+    let sig = signing::parse_signature(&sig_str)?;
+    let mut updated = snapshot.clone();
+    updated["signing"]["signature"] = Value::String(signing::encode_signature(&sig, encoding));
+
+    let after = signing::recover_signer(&updated)?;
+    if after != before {
+        return Err(anyhow!(
+            "re-encoded signature recovers to {after:?}, not {before:?}"
+        ));
+    }
+    *snapshot = updated;
+    Ok(Some(from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::ReencodeCmd;
+    use crate::test_support::{signed, wallet, KEY_A};
+    use ethers::signers::Signer;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn compact_round_trip_is_byte_identical() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snap.json");
+        let wallet = wallet(KEY_A);
+
+        let mut snap = json!({ "id": "snap-1", "body": { "k": 1 } });
+        signing::sign_snapshot(&mut snap, &wallet).await.unwrap();
+        let original = serde_json::to_string_pretty(&snap).unwrap();
+        std::fs::write(&path, &original).unwrap();
+
+        let reencode = |to| ReencodeCmd {
+            dir: dir.path().to_string_lossy().to_string(),
+            to,
+        };
+
+        run(reencode(SignatureEncoding::Compact)).await.unwrap();
+        let compact = read_snapshot(&path.to_string_lossy()).unwrap();
+        assert!(compact["signing"]["signature"]
+            .as_str()
+            .unwrap()
+            .starts_with("eip2098:0x"));
+        signing::verify_signature(&compact, wallet.address()).unwrap();
+
+        run(reencode(SignatureEncoding::Hex)).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+        signing::verify_signature(
+            &read_snapshot(&path.to_string_lossy()).unwrap(),
+            wallet.address(),
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn every_encoding_parses_to_the_same_signature() {
+        let snap = signed(json!({ "id": "snap-1" }), KEY_A).await;
+        let sig = signing::parse_signature(snap["signing"]["signature"].as_str().unwrap()).unwrap();
+
+        for encoding in [
+            SignatureEncoding::Compact,
+            SignatureEncoding::Rsv,
+            SignatureEncoding::B64url,
+        ] {
+            let encoded = signing::encode_signature(&sig, encoding);
+            assert_eq!(SignatureEncoding::detect(&encoded).unwrap(), encoding);
+            assert_eq!(
+                signing::parse_signature(&encoded).unwrap(),
+                sig,
+                "{encoding}"
+            );
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! swarmhive same - Compare the signed content of two snapshots across encodings
+
+use crate::cli::SameCmd;
+use crate::commands::read_snapshot;
+use crate::signing::{self, UNHASHED_SIGNING_KEYS};
+use anyhow::{anyhow, Context, Result};
+use ethers::core::types::Address;
+use serde_json::Value;
+use std::path::Path;
+
+/// Outcome of comparing two snapshots
+#[derive(Debug)]
+pub struct Sameness {
+    /// Equal once signatures and the encoding marker are set aside
+    pub content_equal: bool,
+    pub signer_a: Result<Address>,
+    pub signer_b: Result<Address>,
+}
+
+impl Sameness {
+    /// Both signatures verify, to the same address
+    pub fn same_signer(&self) -> bool {
+        matches!((&self.signer_a, &self.signer_b), (Ok(a), Ok(b)) if a == b)
+    }
+}
+
+pub async fn run(cmd: SameCmd) -> Result<()> {
+    let a = load_snapshot(&cmd.a)?;
+    let b = load_snapshot(&cmd.b)?;
+    let result = compare(&a, &b);
+
+    println!(
+        "Content: {}",
+        if result.content_equal {
+            "equal"
+        } else {
+            "DIFFERENT"
+        }
+    );
+    for (file, signer) in [(&cmd.a, &result.signer_a), (&cmd.b, &result.signer_b)] {
+        match signer {
+            Ok(addr) => println!("{file}: signed by {addr:?}"),
+            Err(e) => println!("{file}: signature INVALID: {e}"),
+        }
+    }
+    println!(
+        "Signer: {}",
+        if result.same_signer() {
+            "same"
+        } else {
+            "DIFFERENT"
+        }
+    );
+
+    if !(result.content_equal && result.same_signer()) {
+        anyhow::bail!("snapshots are not the same signed content");
+    }
+    Ok(())
+}
+
+/// Read a snapshot, decoding by file extension: `.cbor` is a CBOR map,
+/// anything else JSON. The payload-hash encoding (signing.encoding) is
+/// separate and handled by hashing as usual.
+pub fn load_snapshot(path: &str) -> Result<Value> {
+    match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("cbor") => {
+            let bytes = std::fs::read(path).with_context(|| format!("Failed to read {path}"))?;
+            ciborium::from_reader(bytes.as_slice())
+                .map_err(|e| anyhow!("{path} is not valid CBOR: {e}"))
+        }
+        _ => read_snapshot(path),
+    }
+}
+
+/// Compare logical content and recovered signers. Payload hashes are not
+/// compared: they legitimately differ between encodings.
+pub fn compare(a: &Value, b: &Value) -> Sameness {
+    Sameness {
+        content_equal: logical_content(a) == logical_content(b),
+        signer_a: signing::recover_signer(a),
+        signer_b: signing::recover_signer(b),
+    }
+}
+
+/// The snapshot without its signatures, payload hash and signing.encoding
+fn logical_content(snapshot: &Value) -> Value {
+    let mut content = snapshot.clone();
+    if let Some(signing) = content.get_mut("signing").and_then(|v| v.as_object_mut()) {
+        for key in UNHASHED_SIGNING_KEYS.iter().chain(&["encoding"]) {
+            signing.remove(*key);
+        }
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{address, signed, KEY_A, KEY_B};
+    use serde_json::json;
+
+    async fn signed_by(key: &str, encoding: Option<&str>, n: u64) -> Value {
+        let mut snap = json!({
+            "id": "snap-1",
+            "body": { "n": n, "ratio": 0.25, "tags": ["a", "b"] },
+            "signing": { "scheme": "eip191" }
+        });
+        if let Some(encoding) = encoding {
+            snap["signing"]["encoding"] = json!(encoding);
+        }
+        signed(snap, key).await
+    }
+
+    fn write_cbor(path: &Path, v: &Value) {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(v, &mut bytes).unwrap();
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[tokio::test]
+    async fn json_and_cbor_of_same_content_match() {
+        let dir = tempfile::tempdir().unwrap();
+        let json_path = dir.path().join("a.json");
+        let cbor_path = dir.path().join("b.cbor");
+
+        let plain = signed_by(KEY_A, None, 1).await;
+        let gzipped = signed_by(KEY_A, Some("gzip+canonical-json"), 1).await;
+        assert_ne!(
+            plain["signing"]["payload_hash"],
+            gzipped["signing"]["payload_hash"]
+        );
+        std::fs::write(&json_path, plain.to_string()).unwrap();
+        write_cbor(&cbor_path, &gzipped);
+
+        let a = load_snapshot(json_path.to_str().unwrap()).unwrap();
+        let b = load_snapshot(cbor_path.to_str().unwrap()).unwrap();
+        assert_eq!(b, gzipped);
+
+        let result = compare(&a, &b);
+        assert!(result.content_equal);
+        assert!(result.same_signer());
+        assert_eq!(*result.signer_b.as_ref().unwrap(), address(KEY_A));
+
+        run(SameCmd {
+            a: json_path.to_string_lossy().to_string(),
+            b: cbor_path.to_string_lossy().to_string(),
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn different_content_or_signer_is_reported() {
+        let a = signed_by(KEY_A, None, 1).await;
+
+        let other_content = compare(&a, &signed_by(KEY_A, None, 2).await);
+        assert!(!other_content.content_equal);
+        assert!(other_content.same_signer());
+
+        let other_signer = compare(&a, &signed_by(KEY_B, None, 1).await);
+        assert!(other_signer.content_equal);
+        assert!(!other_signer.same_signer());
+    }
+}
@@ -0,0 +1,547 @@
+//! swarmhive sign - Sign a snapshot with a local key
+
+use crate::artifacts;
+use crate::canonical::CanonicalProfile;
+use crate::cli::{InputFormat, SignCmd};
+use crate::commands::{read_snapshot, KeyFile, KeyProvider, KeystoreFile, MnemonicFile};
+use crate::cosign;
+use crate::history::{self, HistoryAction};
+use crate::provenance::Provenance;
+use crate::signing::{self, BoxedSigner, HashSigner};
+use crate::throttle::Throttle;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+pub async fn run(cmd: SignCmd) -> Result<()> {
+    check_outputs(&cmd)?;
+    if let Some(url) = &cmd.remote_signer {
+        return sign_files_with(&cmd, &*remote_signer(&cmd, url)?).await;
+    }
+    if let Some(module) = &cmd.pkcs11_module {
+        return sign_files_with(&cmd, &*pkcs11_signer(&cmd, module)?).await;
+    }
+    sign_files(&cmd, &*key_provider(&cmd)?).await
+}
+
+/// The local key source the flags in `cmd` name
+fn key_provider(cmd: &SignCmd) -> Result<Box<dyn KeyProvider>> {
+    Ok(match (&cmd.key_file, &cmd.keystore, &cmd.mnemonic) {
+        (Some(path), _, _) => Box::new(KeyFile(path.clone())),
+        (_, Some(path), _) => Box::new(KeystoreFile {
+            path: path.clone(),
+            password_file: cmd.password_file.clone(),
+        }),
+        (_, _, Some(path)) => Box::new(MnemonicFile {
+            path: path.clone(),
+            index: cmd.index,
+        }),
+        _ => anyhow::bail!("one of --key-file, --keystore, --mnemonic, --remote-signer or --pkcs11-module is required"),
+    })
+}
+
+/// Sign every file in `cmd`, loading the key from `keys` once up front
+async fn sign_files(cmd: &SignCmd, keys: &dyn KeyProvider) -> Result<()> {
+    sign_files_with(cmd, &keys.signer()?).await
+}
+
+#[cfg(feature = "http-signer")]
+fn remote_signer(cmd: &SignCmd, url: &str) -> Result<BoxedSigner> {
+    let address = cmd
+        .remote_address
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("--remote-signer needs --remote-address"))?;
+    let address = address
+        .parse()
+        .with_context(|| format!("bad --remote-address {address}"))?;
+    let mut signer = crate::remote_signer::RemoteHttpSigner::new(url, address);
+    for line in &cmd.remote_header {
+        signer = signer.with_header_line(line)?;
+    }
+    Ok(Box::new(signer))
+}
+
+#[cfg(not(feature = "http-signer"))]
+fn remote_signer(_cmd: &SignCmd, _url: &str) -> Result<BoxedSigner> {
+    anyhow::bail!("--remote-signer needs a build with the http-signer feature")
+}
+
+#[cfg(feature = "pkcs11")]
+fn pkcs11_signer(cmd: &SignCmd, module: &str) -> Result<BoxedSigner> {
+    use crate::pkcs11::{Pkcs11Config, Pkcs11Signer};
+    let pin = match &cmd.pkcs11_pin_file {
+        Some(path) => crate::commands::read_password_file(path)?,
+        None => rpassword::prompt_password(format!(
+            "PIN for PKCS#11 slot {}: ",
+            cmd.pkcs11_slot.unwrap_or_default()
+        ))?,
+    };
+    let config = Pkcs11Config {
+        module: module.into(),
+        slot: cmd
+            .pkcs11_slot
+            .ok_or_else(|| anyhow::anyhow!("--pkcs11-module needs --pkcs11-slot"))?,
+        label: cmd
+            .pkcs11_label
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("--pkcs11-module needs --pkcs11-label"))?,
+        pin: pin.into(),
+    };
+    Ok(Box::new(Pkcs11Signer::open(&config)?))
+}
+
+#[cfg(not(feature = "pkcs11"))]
+fn pkcs11_signer(_cmd: &SignCmd, _module: &str) -> Result<BoxedSigner> {
+    anyhow::bail!("--pkcs11-module needs a build with the pkcs11 feature")
+}
+
+/// Sign every file in `cmd` with `signer`. Files are signed concurrently,
+/// with signer calls limited by --max-concurrency and --min-interval.
+async fn sign_files_with(cmd: &SignCmd, signer: &dyn HashSigner) -> Result<()> {
+    let throttle = Throttle::new(cmd.max_concurrency, cmd.min_interval);
+    futures::future::try_join_all(
+        cmd.files
+            .iter()
+            .map(|file| sign_file(cmd, file, signer, &throttle)),
+    )
+    .await?;
+    Ok(())
+}
+
+fn signing_mut(snapshot: &mut Value) -> Result<&mut Map<String, Value>> {
+    snapshot
+        .get_mut("signing")
+        .and_then(|v| v.as_object_mut())
+        .ok_or_else(|| anyhow::anyhow!("snapshot missing signing object"))
+}
+
+/// Checked before any key is loaded
+fn check_outputs(cmd: &SignCmd) -> Result<()> {
+    if cmd.files.len() > 1 && (cmd.out.is_some() || cmd.canonical_out.is_some()) {
+        anyhow::bail!("--out and --canonical-out take a single input file");
+    }
+    Ok(())
+}
+
+async fn sign_file(
+    cmd: &SignCmd,
+    file: &str,
+    wallet: &dyn HashSigner,
+    throttle: &Throttle,
+) -> Result<()> {
+    println!("Signing: {}", file);
+
+    let mut snapshot = read_input(file, cmd.input_format)?;
+
+    // Never silently clobber someone else's valid signature
+    let existing = signing::recover_signer(&snapshot).ok();
+    match existing {
+        Some(signer) if cmd.cosign => {
+            let addr = throttle
+                .run(cosign::add_cosignature(&mut snapshot, wallet))
+                .await?;
+            println!("Co-signer: {:?} (primary {:?})", addr, signer);
+        }
+        Some(signer) if !cmd.resign => {
+            anyhow::bail!("snapshot already signed by {signer:?}; use --resign or --cosign");
+        }
+        _ => {
+            if cmd.cosign {
+                anyhow::bail!("--cosign requires a snapshot with a valid existing signature");
+            }
+            if let Some(reason) = &cmd.reason {
+                signing_mut(&mut snapshot)?.insert("reason".to_string(), reason.clone().into());
+            }
+            if cmd.provenance {
+                let provenance = Provenance::from_env();
+                println!("Provenance: {}", provenance.summary());
+                signing_mut(&mut snapshot)?
+                    .insert("provenance".to_string(), serde_json::to_value(provenance)?);
+            }
+            for artifact in &cmd.attach_file {
+                let hash = artifacts::attach_artifact(&mut snapshot, Path::new(artifact))?;
+                println!("Artifact: {artifact} {}", signing::hash_str(hash));
+            }
+            if !cmd.prefer_scheme.is_empty() {
+                let scheme = signing::negotiate_scheme_for(&cmd.prefer_scheme, wallet);
+                signing_mut(&mut snapshot)?.insert("scheme".to_string(), scheme.as_str().into());
+                println!("Scheme: {scheme}");
+            }
+            let (hash, addr) = throttle
+                .run(signing::sign_snapshot(&mut snapshot, wallet))
+                .await?;
+            let action = match existing {
+                None => HistoryAction::Sign,
+                Some(previous) if previous == addr => HistoryAction::Resign,
+                Some(_) => HistoryAction::Rotate,
+            };
+            history::record_signing(&mut snapshot, action, Utc::now().timestamp())?;
+            println!("Signer: {:?}", addr);
+            println!("Payload hash: {}", signing::hash_str(hash));
+        }
+    }
+
+    if let Some(path) = &cmd.canonical_out {
+        // Pre-image of the payload hash: keccak256 of this file == payload_hash
+        let preimage = signing::payload_preimage(&snapshot, &CanonicalProfile::default())?;
+        let preimage = signing::snapshot_payload_encoding(&snapshot)?.encode(preimage)?;
+        std::fs::write(path, preimage).with_context(|| format!("Failed to write {path}"))?;
+        println!("Canonical bytes: {}", path);
+    }
+
+    let out = cmd.out.as_deref().unwrap_or(file);
+    std::fs::write(out, serde_json::to_string_pretty(&snapshot)?)?;
+    println!("Signed: {}", out);
+
+    Ok(())
+}
+
+/// Read a snapshot written in `format`. JSON5 comments are dropped at
+/// parse time, so they are never part of the hash.
+fn read_input(file: &str, format: InputFormat) -> Result<Value> {
+    match format {
+        InputFormat::Json => read_snapshot(file),
+        InputFormat::Json5 => {
+            let content =
+                std::fs::read_to_string(file).with_context(|| format!("Failed to read {file}"))?;
+            json5::from_str(&content).with_context(|| format!("{file} is not valid JSON5"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::SigningScheme;
+    use crate::test_support::{address, KEY_A, KEY_B};
+    use ethers::signers::LocalWallet;
+    use ethers::utils::keccak256;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::TempDir;
+
+    struct Fixture {
+        dir: TempDir,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let fx = Self {
+                dir: tempfile::tempdir().unwrap(),
+            };
+            let snapshot = json!({
+                "type": "PROOF",
+                "id": "proof-job-1",
+                "body": { "job_id": "job-1", "compute_seconds": 42 },
+                "signing": { "scheme": "eip191", "payload_hash": "", "signature": "" }
+            });
+            std::fs::write(fx.path("snap.json"), snapshot.to_string()).unwrap();
+            std::fs::write(fx.path("a.hex"), KEY_A).unwrap();
+            std::fs::write(fx.path("b.hex"), KEY_B).unwrap();
+            fx
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.dir.path().join(name).to_string_lossy().to_string()
+        }
+
+        fn cmd(&self, key: &str) -> SignCmd {
+            SignCmd {
+                files: vec![self.path("snap.json")],
+                key_file: Some(self.path(key)),
+                keystore: None,
+                password_file: None,
+                mnemonic: None,
+                index: 0,
+                remote_signer: None,
+                remote_address: None,
+                remote_header: vec![],
+                pkcs11_module: None,
+                pkcs11_slot: None,
+                pkcs11_label: None,
+                pkcs11_pin_file: None,
+                out: None,
+                canonical_out: None,
+                resign: false,
+                cosign: false,
+                reason: None,
+                provenance: false,
+                max_concurrency: None,
+                min_interval: None,
+                attach_file: vec![],
+                input_format: InputFormat::Json,
+                prefer_scheme: vec![],
+            }
+        }
+
+        fn snapshot(&self) -> serde_json::Value {
+            read_snapshot(&self.path("snap.json")).unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn canonical_out_hashes_to_payload_hash() {
+        let fx = Fixture::new();
+        run(SignCmd {
+            out: Some(fx.path("signed.json")),
+            canonical_out: Some(fx.path("signed.canonical")),
+            ..fx.cmd("a.hex")
+        })
+        .await
+        .unwrap();
+
+        let signed = read_snapshot(&fx.path("signed.json")).unwrap();
+        let canonical = std::fs::read(fx.path("signed.canonical")).unwrap();
+        assert_eq!(
+            signing::hash_str(keccak256(&canonical)),
+            signed["signing"]["payload_hash"].as_str().unwrap()
+        );
+        signing::verify_signature(&signed, address(KEY_A)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn provenance_is_hashed_and_inspected() {
+        let fx = Fixture::new();
+        run(SignCmd {
+            provenance: true,
+            ..fx.cmd("a.hex")
+        })
+        .await
+        .unwrap();
+
+        let snap = fx.snapshot();
+        let recorded = crate::provenance::snapshot_provenance(&snap)
+            .unwrap()
+            .unwrap();
+        assert_eq!(recorded, Provenance::from_env());
+        signing::verify_signature(&snap, address(KEY_A)).unwrap();
+        let lines = crate::commands::inspect::describe(&snap, None);
+        assert!(
+            lines.contains(&format!("Provenance: {}", recorded.summary())),
+            "{lines:?}"
+        );
+
+        // Claiming another commit after signing breaks the signature
+        let mut forged = snap.clone();
+        forged["signing"]["provenance"]["git_commit"] =
+            json!("0000000000000000000000000000000000000000");
+        assert!(signing::verify_signature(&forged, address(KEY_A)).is_err());
+    }
+
+    #[tokio::test]
+    async fn refuses_to_overwrite_valid_signature() {
+        let fx = Fixture::new();
+        run(fx.cmd("a.hex")).await.unwrap();
+        let before = fx.snapshot();
+
+        let err = run(fx.cmd("b.hex")).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "snapshot already signed by {:?}; use --resign or --cosign",
+                address(KEY_A)
+            )
+        );
+        assert_eq!(fx.snapshot(), before);
+    }
+
+    #[tokio::test]
+    async fn resign_overwrites() {
+        let fx = Fixture::new();
+        run(fx.cmd("a.hex")).await.unwrap();
+        run(SignCmd {
+            resign: true,
+            ..fx.cmd("b.hex")
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            signing::recover_signer(&fx.snapshot()).unwrap(),
+            address(KEY_B)
+        );
+        let actions: Vec<HistoryAction> = history::verify_history(&fx.snapshot())
+            .unwrap()
+            .iter()
+            .map(|e| e.action)
+            .collect();
+        assert_eq!(actions, vec![HistoryAction::Sign, HistoryAction::Rotate]);
+    }
+
+    #[tokio::test]
+    async fn preferred_scheme_is_recorded_and_verifies() {
+        let fx = Fixture::new();
+        run(SignCmd {
+            prefer_scheme: vec![SigningScheme::Trezor, SigningScheme::Eip191],
+            ..fx.cmd("a.hex")
+        })
+        .await
+        .unwrap();
+
+        let snap = fx.snapshot();
+        assert_eq!(snap["signing"]["scheme"], "trezor");
+        assert_eq!(signing::recover_signer(&snap).unwrap(), address(KEY_A));
+    }
+
+    #[tokio::test]
+    async fn cosign_appends() {
+        let fx = Fixture::new();
+        run(fx.cmd("a.hex")).await.unwrap();
+        run(SignCmd {
+            cosign: true,
+            ..fx.cmd("b.hex")
+        })
+        .await
+        .unwrap();
+
+        let snap = fx.snapshot();
+        assert_eq!(signing::recover_signer(&snap).unwrap(), address(KEY_A));
+        let signers: Vec<_> = cosign::cosignatures(&snap)
+            .unwrap()
+            .iter()
+            .map(|c| c.signer)
+            .collect();
+        assert_eq!(signers, vec![address(KEY_A), address(KEY_B)]);
+
+        // Co-signing twice with the same key is refused
+        let err = run(SignCmd {
+            cosign: true,
+            ..fx.cmd("b.hex")
+        })
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("already signed by"), "{err}");
+    }
+
+    struct CountingProvider {
+        key: &'static str,
+        built: AtomicUsize,
+    }
+
+    impl KeyProvider for CountingProvider {
+        fn signer(&self) -> Result<LocalWallet> {
+            self.built.fetch_add(1, Ordering::SeqCst);
+            signing::wallet_from_private_key_hex(self.key)
+        }
+    }
+
+    #[tokio::test]
+    async fn multi_file_sign_builds_signer_once() {
+        let fx = Fixture::new();
+        let snapshot = fx.snapshot();
+        let files: Vec<String> = ["one.json", "two.json", "three.json"]
+            .iter()
+            .map(|n| fx.path(n))
+            .collect();
+        for file in &files {
+            std::fs::write(file, snapshot.to_string()).unwrap();
+        }
+
+        let keys = CountingProvider {
+            key: KEY_A,
+            built: AtomicUsize::new(0),
+        };
+        let cmd = || SignCmd {
+            files: files.clone(),
+            ..fx.cmd("a.hex")
+        };
+        sign_files(&cmd(), &keys).await.unwrap();
+
+        assert_eq!(keys.built.load(Ordering::SeqCst), 1);
+        for file in &files {
+            signing::verify_signature(&read_snapshot(file).unwrap(), address(KEY_A)).unwrap();
+        }
+
+        // Refused before the key file is read
+        let err = run(SignCmd {
+            out: Some(fx.path("out.json")),
+            key_file: Some(fx.path("missing.hex")),
+            ..cmd()
+        })
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("single input file"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn json5_input_signs_as_plain_json() {
+        let fx = Fixture::new();
+        let source = |comment: &str| {
+            format!(
+                "{{\n  // {comment}\n  id: 'snap-1',\n  body: {{ n: 1, tags: ['a', 'b',], }},\n  signing: {{ scheme: \"eip191\" }}, /* trailing */\n}}\n"
+            )
+        };
+        let sign = |name: &str, comment: &str| {
+            std::fs::write(fx.path(name), source(comment)).unwrap();
+            run(SignCmd {
+                files: vec![fx.path(name)],
+                out: Some(fx.path(&format!("{name}.json"))),
+                input_format: InputFormat::Json5,
+                ..fx.cmd("a.hex")
+            })
+        };
+        sign("one.json5", "first draft").await.unwrap();
+        sign("two.json5", "comments are not hashed").await.unwrap();
+
+        let one = read_snapshot(&fx.path("one.json5.json")).unwrap();
+        let two = read_snapshot(&fx.path("two.json5.json")).unwrap();
+        signing::verify_signature(&one, address(KEY_A)).unwrap();
+        assert_eq!(one["body"], json!({ "n": 1, "tags": ["a", "b"] }));
+        assert_eq!(
+            one["signing"]["payload_hash"],
+            two["signing"]["payload_hash"]
+        );
+
+        // Plain JSON parsing rejects the same file
+        let err = run(SignCmd {
+            files: vec![fx.path("one.json5")],
+            out: Some(fx.path("x.json")),
+            ..fx.cmd("a.hex")
+        })
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("not valid JSON"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn attached_file_is_checked_on_verify() {
+        use crate::cli::{ReportFormat, VerifyCmd};
+
+        let fx = Fixture::new();
+        std::fs::write(fx.path("weights.bin"), b"model weights v1").unwrap();
+        run(SignCmd {
+            attach_file: vec![fx.path("weights.bin")],
+            ..fx.cmd("a.hex")
+        })
+        .await
+        .unwrap();
+        let snap = fx.snapshot();
+        assert_eq!(snap["artifacts"][0]["name"], "weights.bin");
+        signing::verify_signature(&snap, address(KEY_A)).unwrap();
+
+        let verify = || {
+            crate::commands::verify::run(VerifyCmd {
+                file: fx.path("snap.json"),
+                address: None,
+                max_age: None,
+                clock_skew: None,
+                chain_id: None,
+                content_type: None,
+                require_canonical_version: None,
+                nonce_store: None,
+                check_file: vec![fx.path("weights.bin")],
+                ens: None,
+                min_balance: None,
+                require_activity: false,
+                rpc_url: None,
+                rpc_attempts: 3,
+                report: ReportFormat::Json,
+            })
+        };
+        verify().await.unwrap();
+
+        std::fs::write(fx.path("weights.bin"), b"model weights v2").unwrap();
+        assert!(verify().await.is_err());
+    }
+}
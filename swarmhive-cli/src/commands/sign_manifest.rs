@@ -0,0 +1,163 @@
+//! swarmhive sign-manifest - Sign a directory of snapshots and a manifest over them
+
+use crate::cli::SignManifestCmd;
+use crate::commands::{load_key_file, read_snapshot, snapshot_files};
+use crate::signing::{self, hash_str, parse_payload_hash};
+use anyhow::{anyhow, Context, Result};
+use ethers::core::types::Address;
+use ethers::signers::LocalWallet;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// `type` of a manifest snapshot
+pub const MANIFEST_TYPE: &str = "MANIFEST";
+
+pub async fn run(cmd: SignManifestCmd) -> Result<()> {
+    let wallet = load_key_file(&cmd.key_file)?;
+    let dir = Path::new(&cmd.dir);
+    let out = Path::new(&cmd.out);
+    let files: Vec<PathBuf> = snapshot_files(dir)?
+        .into_iter()
+        .filter(|f| !same_file(f, out))
+        .collect();
+
+    let (manifest, signer) = sign_manifest(&files, dir, &wallet).await?;
+    std::fs::write(out, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("Failed to write {}", out.display()))?;
+    println!("Signed {} files", files.len());
+    println!("Signer: {:?}", signer);
+    println!("Manifest: {}", out.display());
+    Ok(())
+}
+
+/// Sign each file in place, then build and sign a manifest listing each
+/// file (relative to `base`) with its payload hash. The manifest's own
+/// payload hash covers every listed hash, so its one signature attests to
+/// the whole set.
+pub async fn sign_manifest(
+    files: &[PathBuf],
+    base: &Path,
+    wallet: &LocalWallet,
+) -> Result<(Value, Address)> {
+    let mut entries = Vec::with_capacity(files.len());
+    for file in files {
+        let path = file.to_string_lossy();
+        let mut snapshot = read_snapshot(&path)?;
+        let (hash, _) = signing::sign_snapshot(&mut snapshot, wallet).await?;
+        std::fs::write(file, serde_json::to_string_pretty(&snapshot)?)?;
+        entries.push(json!({ "file": relative_name(file, base), "payload_hash": hash_str(hash) }));
+    }
+
+    let mut manifest = json!({
+        "type": MANIFEST_TYPE,
+        "body": { "entries": entries },
+        "signing": { "scheme": "eip191" }
+    });
+    let (_, signer) = signing::sign_snapshot(&mut manifest, wallet).await?;
+    Ok((manifest, signer))
+}
+
+/// Each `(file, payload hash)` listed in a manifest
+pub fn manifest_entries(manifest: &Value) -> Result<Vec<(String, [u8; 32])>> {
+    manifest["body"]["entries"]
+        .as_array()
+        .ok_or_else(|| anyhow!("manifest missing body.entries"))?
+        .iter()
+        .map(|entry| {
+            let file = entry["file"]
+                .as_str()
+                .ok_or_else(|| anyhow!("manifest entry missing file"))?;
+            let hash = entry["payload_hash"]
+                .as_str()
+                .ok_or_else(|| anyhow!("manifest entry {file} missing payload_hash"))?;
+            Ok((file.to_string(), parse_payload_hash(hash)?))
+        })
+        .collect()
+}
+
+/// Check that `file` (as named in the manifest, under `base`) still has the
+/// payload hash the manifest lists for it. Verify the manifest's own
+/// signature first; this only ties the file to the manifest.
+pub fn spot_check(manifest: &Value, base: &Path, file: &str) -> Result<()> {
+    let (_, listed) = manifest_entries(manifest)?
+        .into_iter()
+        .find(|(name, _)| name == file)
+        .ok_or_else(|| anyhow!("{file} is not listed in the manifest"))?;
+    let snapshot = read_snapshot(&base.join(file).to_string_lossy())?;
+    let actual = signing::payload_hash_keccak(&snapshot)?;
+    if actual != listed {
+        return Err(anyhow!(
+            "{file}: payload hash {} does not match manifest entry {}",
+            hash_str(actual),
+            hash_str(listed)
+        ));
+    }
+    Ok(())
+}
+
+/// `file` relative to `base`, with `/` separators so manifests are portable
+fn relative_name(file: &Path, base: &Path) -> String {
+    let rel = file.strip_prefix(base).unwrap_or(file);
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{address, KEY_A};
+
+    #[tokio::test]
+    async fn manifest_attests_to_every_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        for (i, name) in ["a.json", "b.json", "sub/c.json"].iter().enumerate() {
+            let snap = json!({ "id": format!("snap-{i}"), "body": { "n": i }, "signing": { "scheme": "eip191" } });
+            std::fs::write(dir.path().join(name), snap.to_string()).unwrap();
+        }
+        std::fs::write(dir.path().join("key.hex"), KEY_A).unwrap();
+        let out = dir.path().join("manifest.json");
+
+        run(SignManifestCmd {
+            dir: dir.path().to_string_lossy().to_string(),
+            key_file: dir.path().join("key.hex").to_string_lossy().to_string(),
+            out: out.to_string_lossy().to_string(),
+        })
+        .await
+        .unwrap();
+
+        let manifest = read_snapshot(&out.to_string_lossy()).unwrap();
+        let signer = address(KEY_A);
+        signing::verify_signature(&manifest, signer).unwrap();
+
+        let entries = manifest_entries(&manifest).unwrap();
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["a.json", "b.json", "sub/c.json"]);
+        for (name, hash) in &entries {
+            let snap = read_snapshot(&dir.path().join(name).to_string_lossy()).unwrap();
+            assert_eq!(signing::payload_hash_keccak(&snap).unwrap(), *hash);
+            signing::verify_signature(&snap, signer).unwrap();
+            spot_check(&manifest, dir.path(), name).unwrap();
+        }
+
+        // An edited file no longer matches its entry
+        let b = dir.path().join("b.json");
+        let mut snap = read_snapshot(&b.to_string_lossy()).unwrap();
+        snap["body"]["n"] = json!(99);
+        std::fs::write(&b, snap.to_string()).unwrap();
+        let err = spot_check(&manifest, dir.path(), "b.json").unwrap_err();
+        assert!(
+            err.to_string().contains("does not match manifest entry"),
+            "{err}"
+        );
+    }
+}
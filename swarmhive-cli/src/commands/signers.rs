@@ -0,0 +1,108 @@
+//! swarmhive signers - Signer frequency report for a snapshot archive
+
+use crate::cli::SignersCmd;
+use crate::commands::{read_snapshot, signer_display, snapshot_files};
+use crate::signing;
+use anyhow::Result;
+use ethers::core::types::Address;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Who signed what under a directory
+#[derive(Debug, Default)]
+pub struct SignerCounts {
+    /// (signer, snapshots signed), most frequent first, ties by address
+    pub signers: Vec<(Address, usize)>,
+    /// Snapshots without a signing.signature
+    pub unsigned: usize,
+    /// (file, error) for each file that could not be read or recovered
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+pub async fn run(cmd: SignersCmd) -> Result<()> {
+    let counts = count_signers(Path::new(&cmd.dir))?;
+
+    println!("{:>6}  SIGNER", "COUNT");
+    for (signer, count) in &counts.signers {
+        println!(
+            "{count:>6}  {}",
+            signer_display(*signer, cmd.rpc_url.as_deref()).await?
+        );
+    }
+
+    let signed: usize = counts.signers.iter().map(|(_, n)| n).sum();
+    println!();
+    println!(
+        "{signed} signed by {} signers, {} unsigned, {} failed",
+        counts.signers.len(),
+        counts.unsigned,
+        counts.failed.len()
+    );
+    for (path, error) in &counts.failed {
+        println!("  {}: {error}", path.display());
+    }
+    Ok(())
+}
+
+/// Recover the signer of every snapshot under `dir`
+pub fn count_signers(dir: &Path) -> Result<SignerCounts> {
+    let mut counts = SignerCounts::default();
+    let mut by_signer: HashMap<Address, usize> = HashMap::new();
+    for path in snapshot_files(dir)? {
+        let recovered = read_snapshot(&path.to_string_lossy()).and_then(|snapshot| {
+            let signed = snapshot
+                .get("signing")
+                .and_then(|s| s.get("signature"))
+                .is_some();
+            signed
+                .then(|| signing::recover_signer(&snapshot))
+                .transpose()
+        });
+        match recovered {
+            Ok(Some(signer)) => *by_signer.entry(signer).or_default() += 1,
+            Ok(None) => counts.unsigned += 1,
+            Err(e) => counts.failed.push((path, e.to_string())),
+        }
+    }
+    counts.signers = by_signer.into_iter().collect();
+    counts
+        .signers
+        .sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{wallet, KEY_A, KEY_B};
+    use ethers::signers::Signer;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn counts_each_signer() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = wallet(KEY_A);
+        let b = wallet(KEY_B);
+
+        for (i, wallet) in [&a, &b, &b, &a, &b].into_iter().enumerate() {
+            let mut snap = json!({ "id": format!("snap-{i}"), "signing": { "scheme": "eip191" } });
+            signing::sign_snapshot(&mut snap, wallet).await.unwrap();
+            std::fs::write(dir.path().join(format!("{i}.json")), snap.to_string()).unwrap();
+        }
+        std::fs::write(
+            dir.path().join("unsigned.json"),
+            json!({ "id": "u" }).to_string(),
+        )
+        .unwrap();
+        let mut tampered = json!({ "id": "t", "signing": { "scheme": "eip191" } });
+        signing::sign_snapshot(&mut tampered, &a).await.unwrap();
+        tampered["id"] = json!("changed");
+        std::fs::write(dir.path().join("tampered.json"), tampered.to_string()).unwrap();
+
+        let counts = count_signers(dir.path()).unwrap();
+        assert_eq!(counts.signers, vec![(b.address(), 3), (a.address(), 2)]);
+        assert_eq!(counts.unsigned, 1);
+        assert_eq!(counts.failed.len(), 1);
+        assert!(counts.failed[0].0.ends_with("tampered.json"));
+    }
+}
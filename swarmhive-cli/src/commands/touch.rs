@@ -0,0 +1,150 @@
+//! swarmhive touch - Refresh issued_at and re-sign a liveness snapshot
+
+use crate::cli::TouchCmd;
+use crate::commands::{load_key_file, read_snapshot};
+use crate::history::{self, HistoryAction};
+use crate::signing;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use ethers::core::types::Address;
+use ethers::signers::{LocalWallet, Signer};
+use serde_json::Value;
+use std::time::Duration;
+
+pub async fn run(cmd: TouchCmd) -> Result<()> {
+    let mut snapshot = read_snapshot(&cmd.file)?;
+    let wallet = load_key_file(&cmd.key_file)?;
+
+    let now = Utc::now().timestamp();
+    let signer = touch_snapshot(&mut snapshot, &wallet, now, cmd.extend, cmd.force).await?;
+
+    std::fs::write(&cmd.file, serde_json::to_string_pretty(&snapshot)?)?;
+    println!("Touched: {}", cmd.file);
+    println!("Signer: {:?}", signer);
+    println!("Issued at: {now}");
+    if let Some(valid_until) = snapshot["signing"].get("valid_until") {
+        println!("Valid until: {valid_until}");
+    }
+    Ok(())
+}
+
+/// Set signing.issued_at to `now` (and signing.valid_until to `now +
+/// extend` when given) and re-sign with `wallet`. The snapshot must carry
+/// a valid signature from `wallet` already unless `force` is set.
+/// Co-signatures are dropped: they cover the old payload hash. The touch
+/// is appended to signing.history, as a rotation if `wallet` took the
+/// snapshot over from another signer.
+pub async fn touch_snapshot(
+    snapshot: &mut Value,
+    wallet: &LocalWallet,
+    now: i64,
+    extend: Option<Duration>,
+    force: bool,
+) -> Result<Address> {
+    let previous = signing::recover_signer(snapshot);
+    if !force {
+        let previous = previous.as_ref().map_err(|e| {
+            anyhow!("current signature does not verify ({e}); use --force to re-sign anyway")
+        })?;
+        if *previous != wallet.address() {
+            return Err(anyhow!(
+                "snapshot is signed by {previous:?}, not {:?}; use --force to take it over",
+                wallet.address()
+            ));
+        }
+    }
+
+    let signing = snapshot
+        .get_mut("signing")
+        .and_then(|v| v.as_object_mut())
+        .ok_or_else(|| anyhow!("snapshot missing signing object"))?;
+    signing.insert("issued_at".to_string(), Value::from(now));
+    if let Some(extend) = extend {
+        signing.insert(
+            "valid_until".to_string(),
+            Value::from(now.saturating_add(i64::try_from(extend.as_secs()).unwrap_or(i64::MAX))),
+        );
+    }
+    signing.remove("signatures");
+
+    let (_, signer) = signing::sign_snapshot(snapshot, wallet).await?;
+    let action = match previous {
+        Ok(previous) if previous == signer => HistoryAction::Touch,
+        _ => HistoryAction::Rotate,
+    };
+    history::record_signing(snapshot, action, now)?;
+    Ok(signer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{signed, wallet, KEY_A, KEY_B};
+    use crate::verify::{verify_freshness, DEFAULT_CLOCK_SKEW};
+    use serde_json::json;
+
+    const NOW: i64 = 1_700_000_000;
+
+    async fn signed_by(key: &str, issued_at: i64) -> Value {
+        let snap = json!({
+            "id": "heartbeat-1",
+            "body": { "alive": true },
+            "signing": { "scheme": "eip191", "issued_at": issued_at, "valid_until": issued_at + 60 }
+        });
+        signed(snap, key).await
+    }
+
+    #[tokio::test]
+    async fn touch_refreshes_and_resigns() {
+        let wallet = wallet(KEY_A);
+        let mut snap = signed_by(KEY_A, NOW - 3600).await;
+        assert!(verify_freshness(
+            &snap,
+            Some(Duration::from_secs(300)),
+            DEFAULT_CLOCK_SKEW,
+            NOW
+        )
+        .is_err());
+
+        let signer = touch_snapshot(
+            &mut snap,
+            &wallet,
+            NOW,
+            Some(Duration::from_secs(900)),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(signer, wallet.address());
+        assert_eq!(snap["signing"]["issued_at"], json!(NOW));
+        assert_eq!(snap["signing"]["valid_until"], json!(NOW + 900));
+        signing::verify_signature(&snap, wallet.address()).unwrap();
+        verify_freshness(
+            &snap,
+            Some(Duration::from_secs(300)),
+            DEFAULT_CLOCK_SKEW,
+            NOW,
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn refuses_other_signer_without_force() {
+        let other = wallet(KEY_B);
+        let mut snap = signed_by(KEY_A, NOW - 3600).await;
+        let before = snap.clone();
+
+        let err = touch_snapshot(&mut snap, &other, NOW, None, false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("use --force"), "{err}");
+        assert_eq!(snap, before);
+
+        touch_snapshot(&mut snap, &other, NOW, None, true)
+            .await
+            .unwrap();
+        assert_eq!(signing::recover_signer(&snap).unwrap(), other.address());
+        let history = history::verify_history(&snap).unwrap();
+        assert_eq!(history.last().unwrap().action, HistoryAction::Rotate);
+    }
+}
@@ -0,0 +1,80 @@
+//! swarmhive unsign - Strip signatures for review, keeping the payload hash
+
+use crate::cli::UnsignCmd;
+use crate::commands::read_snapshot;
+use crate::signing::{self, hash_str, parse_payload_hash, payload_hash_keccak};
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+pub async fn run(cmd: UnsignCmd) -> Result<()> {
+    let mut snapshot = read_snapshot(&cmd.file)?;
+    let hash = unsign_snapshot(&mut snapshot)?;
+
+    let out = cmd.out.as_deref().unwrap_or(&cmd.file);
+    std::fs::write(out, serde_json::to_string_pretty(&snapshot)?)?;
+    println!("Unsigned: {out}");
+    println!("Payload hash: {} (matches content)", hash_str(hash));
+    Ok(())
+}
+
+/// Remove signing.signature and signing.signatures, keeping
+/// signing.payload_hash, and check the remaining content still hashes to
+/// it. Returns the payload hash. On a mismatch the snapshot is left as is.
+pub fn unsign_snapshot(snapshot: &mut Value) -> Result<[u8; 32]> {
+    let signing_obj = signing::signing_object(snapshot)?;
+    let stored = signing::string_field(signing_obj, "payload_hash")?
+        .ok_or_else(|| anyhow!("missing signing.payload_hash; nothing anchors the content"))?;
+    let stored = parse_payload_hash(stored)?;
+    if payload_hash_keccak(snapshot)? != stored {
+        return Err(anyhow!(
+            "payload_hash mismatch: snapshot content changed since it was signed"
+        ));
+    }
+
+    if let Some(signing) = snapshot.get_mut("signing").and_then(|v| v.as_object_mut()) {
+        signing.remove("signature");
+        signing.remove("signatures");
+    }
+    Ok(stored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{signed, wallet, KEY_A, KEY_B};
+    use serde_json::json;
+
+    async fn cosigned() -> Value {
+        let mut snap = signed(
+            json!({ "id": "snap-1", "body": { "n": 1 }, "signing": { "scheme": "eip191" } }),
+            KEY_A,
+        )
+        .await;
+        crate::cosign::add_cosignature(&mut snap, &wallet(KEY_B))
+            .await
+            .unwrap();
+        snap
+    }
+
+    #[tokio::test]
+    async fn unsigned_content_keeps_matching_hash() {
+        let mut snap = cosigned().await;
+        let hash = unsign_snapshot(&mut snap).unwrap();
+
+        let signing = snap["signing"].as_object().unwrap();
+        assert!(!signing.contains_key("signature") && !signing.contains_key("signatures"));
+        assert_eq!(signing["payload_hash"], json!(hash_str(hash)));
+        assert_eq!(payload_hash_keccak(&snap).unwrap(), hash);
+    }
+
+    #[tokio::test]
+    async fn tampered_content_is_refused() {
+        let mut snap = cosigned().await;
+        snap["body"]["n"] = json!(2);
+        let before = snap.clone();
+
+        let err = unsign_snapshot(&mut snap).unwrap_err();
+        assert!(err.to_string().contains("payload_hash mismatch"), "{err}");
+        assert_eq!(snap, before);
+    }
+}
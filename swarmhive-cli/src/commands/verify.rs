@@ -0,0 +1,97 @@
+//! swarmhive verify - Verify a signed snapshot
+
+use crate::artifacts;
+use crate::cli::{ReportFormat, VerifyCmd};
+use crate::commands::{read_snapshot, signer_display};
+use crate::nonce::FileNonceStore;
+use crate::retry::{RetryPolicy, Retrying};
+use crate::verify::{self, MaybeProvider, VerificationReport, VerifyOptions};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use ethers::providers::{Http, Provider};
+use std::path::Path;
+
+pub async fn run(cmd: VerifyCmd) -> Result<()> {
+    let snapshot = read_snapshot(&cmd.file)?;
+    let opts = VerifyOptions {
+        expected_address: cmd
+            .address
+            .as_deref()
+            .map(|a| a.parse().with_context(|| format!("bad --address {a}")))
+            .transpose()?,
+        max_age: cmd.max_age,
+        clock_skew: cmd.clock_skew,
+        chain_id: cmd.chain_id,
+        content_type: cmd.content_type,
+        canonical_version: cmd.require_canonical_version,
+        ens_name: cmd.ens,
+        min_balance: cmd.min_balance,
+        require_activity: cmd.require_activity,
+    };
+    let provider = match &cmd.rpc_url {
+        Some(url) => MaybeProvider::Online(Retrying::new(
+            Provider::<Http>::try_from(url.as_str())
+                .with_context(|| format!("bad --rpc-url {url}"))?,
+            RetryPolicy {
+                max_attempts: cmd.rpc_attempts,
+                ..Default::default()
+            },
+        )),
+        None => MaybeProvider::Offline,
+    };
+
+    let now = Utc::now().timestamp();
+    let mut report = verify::verify_report(&snapshot, &opts, now);
+    verify::run_online_checks(&mut report, &opts, &provider).await;
+    if !cmd.check_file.is_empty() {
+        let files = cmd
+            .check_file
+            .iter()
+            .try_for_each(|file| artifacts::check_artifact(&snapshot, Path::new(file)));
+        report.artifacts_ok = report.record(files);
+    }
+    // Last, so a snapshot failing any other check keeps its nonce unused
+    if let Some(path) = &cmd.nonce_store {
+        verify::consume_nonce(&mut report, &snapshot, &FileNonceStore::open(path)?);
+    }
+
+    match cmd.report {
+        ReportFormat::Json => println!("{}", serde_json::to_string(&report)?),
+        ReportFormat::Text => {
+            let signer = match report.signer {
+                Some(signer) => Some(signer_display(signer, cmd.rpc_url.as_deref()).await?),
+                None => None,
+            };
+            print_text(&cmd.file, &report, signer.as_deref())
+        }
+    }
+
+    if !report.is_valid() {
+        anyhow::bail!("verification failed");
+    }
+    Ok(())
+}
+
+fn print_text(file: &str, report: &VerificationReport, signer: Option<&str>) {
+    println!("Verifying: {}", file);
+    if let Some(signer) = signer {
+        println!("Signer: {signer}");
+    }
+    if let Some(reason) = &report.reason {
+        println!("Reason: {reason}");
+    }
+    for (name, outcome) in report.checks() {
+        let status = match outcome {
+            Some(true) => "ok",
+            Some(false) => "FAILED",
+            None => "skipped",
+        };
+        println!("  {name}: {status}");
+    }
+    for check in &report.skipped {
+        println!("  {check}: not checked offline (pass --rpc-url)");
+    }
+    for error in &report.errors {
+        println!("  error: {error}");
+    }
+}
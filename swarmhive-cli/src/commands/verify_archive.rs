@@ -0,0 +1,202 @@
+//! swarmhive verify-archive - Verify the snapshots in a tarball in place
+//!
+//! Entries are read straight from the archive stream; nothing is
+//! extracted to disk. Only regular files named `*.json` are treated as
+//! snapshots; anything else is listed as skipped.
+
+use crate::cli::VerifyArchiveCmd;
+use crate::verify::{self, VerifyOptions};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use ethers::core::types::Address;
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use std::fs::File;
+use std::io::Read;
+
+/// What became of one archive entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryOutcome {
+    /// Every check passed; the recovered signer
+    Valid(Address),
+    /// Parsed, but failed these checks with these errors
+    Invalid {
+        failed: Vec<&'static str>,
+        errors: Vec<String>,
+    },
+    /// Named `*.json` but not readable as JSON
+    Unreadable(String),
+    /// Not a `*.json` regular file
+    Skipped,
+}
+
+/// One archive entry's path and outcome
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub path: String,
+    pub outcome: EntryOutcome,
+}
+
+pub async fn run(cmd: VerifyArchiveCmd) -> Result<()> {
+    let opts = VerifyOptions {
+        expected_address: cmd
+            .address
+            .as_deref()
+            .map(|a| a.parse().with_context(|| format!("bad --address {a}")))
+            .transpose()?,
+        ..Default::default()
+    };
+    let file = File::open(&cmd.file).with_context(|| format!("Failed to open {}", cmd.file))?;
+    let reader: Box<dyn Read> = if cmd.file.ends_with(".gz") || cmd.file.ends_with(".tgz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    let entries =
+        verify_archive(reader, &opts, Utc::now().timestamp()).with_context(|| cmd.file.clone())?;
+
+    let mut failures = 0;
+    for entry in &entries {
+        match &entry.outcome {
+            EntryOutcome::Valid(signer) => println!("ok       {}  {signer:?}", entry.path),
+            EntryOutcome::Invalid { failed, errors } => {
+                failures += 1;
+                println!(
+                    "INVALID  {}  {}: {}",
+                    entry.path,
+                    failed.join(", "),
+                    errors.join("; ")
+                );
+            }
+            EntryOutcome::Unreadable(e) => {
+                failures += 1;
+                println!("ERROR    {}  {e}", entry.path);
+            }
+            EntryOutcome::Skipped => println!("skipped  {}", entry.path),
+        }
+    }
+    let checked = entries
+        .iter()
+        .filter(|e| e.outcome != EntryOutcome::Skipped)
+        .count();
+    println!();
+    println!("{checked} snapshots checked, {failures} failed");
+
+    if failures > 0 {
+        anyhow::bail!("verification failed");
+    }
+    Ok(())
+}
+
+/// Verify every `*.json` entry of the tar stream `reader` against `opts` at
+/// `now`, in archive order. Only a broken archive is an error; bad
+/// snapshots are per-entry outcomes.
+pub fn verify_archive(
+    reader: impl Read,
+    opts: &VerifyOptions,
+    now: i64,
+) -> Result<Vec<ArchiveEntry>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut results = Vec::new();
+    for entry in archive.entries().context("not a tar archive")? {
+        let mut entry = entry.context("corrupt tar entry")?;
+        let path = entry
+            .path()
+            .context("bad tar entry path")?
+            .to_string_lossy()
+            .to_string();
+        if !entry.header().entry_type().is_file() || !path.ends_with(".json") {
+            results.push(ArchiveEntry {
+                path,
+                outcome: EntryOutcome::Skipped,
+            });
+            continue;
+        }
+
+        let mut bytes = Vec::new();
+        entry
+            .read_to_end(&mut bytes)
+            .with_context(|| format!("reading {path}"))?;
+        let outcome = match serde_json::from_slice::<Value>(&bytes) {
+            Err(e) => EntryOutcome::Unreadable(e.to_string()),
+            Ok(snapshot) => {
+                let report = verify::verify_report(&snapshot, opts, now);
+                match (report.is_valid(), report.signer) {
+                    (true, Some(signer)) => EntryOutcome::Valid(signer),
+                    _ => EntryOutcome::Invalid {
+                        failed: report.failed_checks(),
+                        errors: report.errors,
+                    },
+                }
+            }
+        };
+        results.push(ArchiveEntry { path, outcome });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing;
+    use crate::test_support::{wallet, KEY_A};
+    use ethers::signers::Signer;
+    use serde_json::json;
+
+    fn append(builder: &mut tar::Builder<Vec<u8>>, path: &str, body: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(body.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, body).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reports_each_entry() {
+        let wallet = wallet(KEY_A);
+        let mut signed = Vec::new();
+        for i in 0..3 {
+            let mut snap = json!({ "id": format!("snap-{i}"), "signing": { "scheme": "eip191" } });
+            signing::sign_snapshot(&mut snap, &wallet).await.unwrap();
+            signed.push(snap);
+        }
+        signed[2]["id"] = json!("tampered");
+
+        let mut builder = tar::Builder::new(Vec::new());
+        append(
+            &mut builder,
+            "state/a.json",
+            signed[0].to_string().as_bytes(),
+        );
+        append(&mut builder, "README", b"not a snapshot");
+        append(
+            &mut builder,
+            "state/b.json",
+            signed[1].to_string().as_bytes(),
+        );
+        append(
+            &mut builder,
+            "state/c.json",
+            signed[2].to_string().as_bytes(),
+        );
+        let tarball = builder.into_inner().unwrap();
+
+        let opts = VerifyOptions {
+            expected_address: Some(wallet.address()),
+            ..Default::default()
+        };
+        let entries = verify_archive(tarball.as_slice(), &opts, 0).unwrap();
+        let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            ["state/a.json", "README", "state/b.json", "state/c.json"]
+        );
+        assert_eq!(entries[0].outcome, EntryOutcome::Valid(wallet.address()));
+        assert_eq!(entries[1].outcome, EntryOutcome::Skipped);
+        assert_eq!(entries[2].outcome, EntryOutcome::Valid(wallet.address()));
+        match &entries[3].outcome {
+            EntryOutcome::Invalid { failed, .. } => assert_eq!(failed, &["Content hash"]),
+            other => panic!("tampered entry: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! swarmhive verify-batch - Verify a directory of snapshots in parallel
+
+use crate::cli::VerifyBatchCmd;
+use crate::commands::{read_snapshot, snapshot_files};
+use crate::verify::{self, VerifyOptions};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Tally of one batch run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub valid: usize,
+    pub invalid: usize,
+    /// Files that could not be read or parsed
+    pub errored: usize,
+    /// Failed check name -> number of snapshots failing it
+    pub reasons: BTreeMap<&'static str, usize>,
+    /// (file, error) for each errored file
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+pub async fn run(cmd: VerifyBatchCmd) -> Result<()> {
+    let opts = VerifyOptions {
+        expected_address: cmd
+            .address
+            .as_deref()
+            .map(|a| a.parse().with_context(|| format!("bad --address {a}")))
+            .transpose()?,
+        max_age: cmd.max_age,
+        clock_skew: cmd.clock_skew,
+        chain_id: cmd.chain_id,
+        content_type: cmd.content_type,
+        canonical_version: None,
+        ens_name: None,
+        min_balance: None,
+        require_activity: false,
+    };
+    let files = snapshot_files(Path::new(&cmd.dir))?;
+
+    let progress = if cmd.no_progress {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(files.len() as u64).with_style(
+            ProgressStyle::with_template("{bar:40} {pos}/{len} {elapsed_precise}")
+                .expect("static progress template"),
+        )
+    };
+    let summary = verify_files(&files, &opts, Utc::now().timestamp(), &progress);
+    progress.finish_and_clear();
+
+    println!(
+        "{} snapshots: {} valid, {} invalid, {} errored",
+        summary.total, summary.valid, summary.invalid, summary.errored
+    );
+    if !summary.reasons.is_empty() {
+        println!("Failures by check:");
+        for (reason, count) in &summary.reasons {
+            println!("  {reason}: {count}");
+        }
+    }
+    for (path, error) in &summary.errors {
+        println!("  error: {}: {error}", path.display());
+    }
+
+    if summary.invalid + summary.errored > 0 {
+        anyhow::bail!("verification failed");
+    }
+    Ok(())
+}
+
+/// Read and verify `files` in parallel, advancing `progress` once per file.
+/// ProgressBar is internally synchronized, so the workers share it directly.
+pub fn verify_files(
+    files: &[PathBuf],
+    opts: &VerifyOptions,
+    now: i64,
+    progress: &ProgressBar,
+) -> BatchSummary {
+    let loaded: Vec<_> = files
+        .par_iter()
+        .map(|path| (path, read_snapshot(&path.to_string_lossy())))
+        .collect();
+
+    let mut summary = BatchSummary {
+        total: files.len(),
+        ..Default::default()
+    };
+    let mut snapshots = Vec::new();
+    for (path, result) in loaded {
+        match result {
+            Ok(snapshot) => snapshots.push(snapshot),
+            Err(e) => {
+                summary.errored += 1;
+                summary.errors.push((path.clone(), format!("{e:#}")));
+                progress.inc(1);
+            }
+        }
+    }
+
+    let reports = verify::verify_batch_with(&snapshots, opts, now, |_| progress.inc(1));
+    for report in reports {
+        if report.is_valid() {
+            summary.valid += 1;
+        } else {
+            summary.invalid += 1;
+            for check in report.failed_checks() {
+                *summary.reasons.entry(check).or_default() += 1;
+            }
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing;
+    use crate::test_support::{wallet, KEY_A, KEY_B};
+    use ethers::signers::Signer;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn summary_counts_mixed_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let write =
+            |name: &str, contents: String| std::fs::write(dir.path().join(name), contents).unwrap();
+        let wallet_a = wallet(KEY_A);
+        let wallet_b = wallet(KEY_B);
+
+        for i in 0..3 {
+            let mut snap = json!({ "id": format!("ok-{i}") });
+            signing::sign_snapshot(&mut snap, &wallet_a).await.unwrap();
+            write(&format!("ok-{i}.json"), snap.to_string());
+        }
+        let mut tampered = json!({ "id": "tampered", "body": { "n": 1 } });
+        signing::sign_snapshot(&mut tampered, &wallet_a)
+            .await
+            .unwrap();
+        tampered["body"]["n"] = json!(2);
+        write("tampered.json", tampered.to_string());
+
+        let mut other = json!({ "id": "other" });
+        signing::sign_snapshot(&mut other, &wallet_b).await.unwrap();
+        write("other.json", other.to_string());
+
+        write("broken.json", "{ not json".to_string());
+
+        let opts = VerifyOptions {
+            expected_address: Some(wallet_a.address()),
+            ..Default::default()
+        };
+        let files = snapshot_files(dir.path()).unwrap();
+        let summary = verify_files(&files, &opts, 0, &ProgressBar::hidden());
+
+        assert_eq!(summary.total, 6);
+        assert_eq!(summary.valid, 3);
+        assert_eq!(summary.invalid, 2);
+        assert_eq!(summary.errored, 1);
+        assert_eq!(
+            summary.reasons,
+            BTreeMap::from([("Address", 1), ("Content hash", 1)])
+        );
+        assert!(summary.errors[0].0.ends_with("broken.json"));
+    }
+}
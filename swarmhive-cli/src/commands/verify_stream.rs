@@ -0,0 +1,153 @@
+//! swarmhive verify-stream - Verify newline-delimited snapshots from stdin
+//!
+//! Each line is verified independently (signature recovery + content hash)
+//! and reported as it is read, so arbitrarily large logs are never held in
+//! memory. Bad lines are reported by number and do not stop the stream.
+
+use crate::cli::VerifyStreamCmd;
+use crate::signing;
+use anyhow::{anyhow, Context, Result};
+use ethers::core::types::Address;
+use serde_json::Value;
+use std::io::{BufRead, Write};
+
+/// Totals for one stream
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct StreamSummary {
+    pub valid: usize,
+    pub invalid: usize,
+}
+
+pub async fn run(cmd: VerifyStreamCmd) -> Result<()> {
+    let expected = cmd
+        .address
+        .as_deref()
+        .map(|a| {
+            a.parse::<Address>()
+                .with_context(|| format!("bad --address {a}"))
+        })
+        .transpose()?;
+
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    let summary = verify_lines(stdin.lock(), stdout.lock(), expected)?;
+
+    if summary.invalid > 0 {
+        anyhow::bail!("{} invalid snapshot(s)", summary.invalid);
+    }
+    Ok(())
+}
+
+/// Verify every non-blank line of `input`, writing one result per line and
+/// a final summary to `out`.
+pub fn verify_lines<R: BufRead, W: Write>(
+    input: R,
+    mut out: W,
+    expected: Option<Address>,
+) -> Result<StreamSummary> {
+    let mut summary = StreamSummary::default();
+
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_no = i + 1;
+
+        match verify_line(&line, expected) {
+            Ok(signer) => {
+                summary.valid += 1;
+                writeln!(out, "line {line_no}: ok {signer:?}")?;
+            }
+            Err(e) => {
+                summary.invalid += 1;
+                writeln!(out, "line {line_no}: INVALID {e}")?;
+            }
+        }
+    }
+
+    writeln!(
+        out,
+        "{} snapshots: {} valid, {} invalid",
+        summary.valid + summary.invalid,
+        summary.valid,
+        summary.invalid
+    )?;
+    Ok(summary)
+}
+
+fn verify_line(line: &str, expected: Option<Address>) -> Result<Address> {
+    let snapshot: Value = serde_json::from_str(line).map_err(|e| anyhow!("not JSON: {e}"))?;
+    let signer = signing::recover_signer(&snapshot)?;
+    if let Some(expected) = expected {
+        if signer != expected {
+            return Err(anyhow!("signed by {signer:?}, expected {expected:?}"));
+        }
+    }
+    Ok(signer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{signed, KEY_A};
+    use serde_json::json;
+
+    async fn signed_line(id: &str) -> Value {
+        signed(
+            json!({ "id": id, "body": { "n": 1 }, "signing": { "scheme": "eip191" } }),
+            KEY_A,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn reports_each_line_and_summary() {
+        let a = signed_line("a").await;
+        let mut tampered = signed_line("b").await;
+        tampered["body"]["n"] = json!(2);
+        let c = signed_line("c").await;
+
+        let input = format!("{a}\n{tampered}\n\n{c}\n");
+        let mut out = Vec::new();
+        let summary = verify_lines(input.as_bytes(), &mut out, None).unwrap();
+
+        assert_eq!(
+            summary,
+            StreamSummary {
+                valid: 2,
+                invalid: 1
+            }
+        );
+
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 4, "{out}");
+        assert!(lines[0].starts_with("line 1: ok 0x"), "{out}");
+        assert!(
+            lines[1].starts_with("line 2: INVALID payload_hash mismatch"),
+            "{out}"
+        );
+        assert!(lines[2].starts_with("line 4: ok 0x"), "{out}");
+        assert_eq!(lines[3], "3 snapshots: 2 valid, 1 invalid");
+    }
+
+    #[tokio::test]
+    async fn enforces_expected_address_and_survives_garbage() {
+        let a = signed_line("a").await;
+        let input = format!("{a}\nnot json\n");
+        let mut out = Vec::new();
+        let summary = verify_lines(input.as_bytes(), &mut out, Some(Address::zero())).unwrap();
+
+        assert_eq!(
+            summary,
+            StreamSummary {
+                valid: 0,
+                invalid: 2
+            }
+        );
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("line 1: INVALID signed by"), "{out}");
+        assert!(out.contains("line 2: INVALID not JSON"), "{out}");
+    }
+}
@@ -1,12 +1,20 @@
-//! swarmhive watch - Observe the memepool
+//! swarmhive watch - Observe the memepool, or a live snapshot URL
 
 use crate::cli::WatchCmd;
-use anyhow::Result;
+use crate::verify::{self, VerifyOptions};
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use ethers::core::types::Address;
+use serde_json::Value;
 use std::time::Duration;
 
 pub async fn run(cmd: WatchCmd) -> Result<()> {
+    if let Some(url) = &cmd.url {
+        return watch_url(url, &cmd).await;
+    }
+
     println!("Watching pool: {}", cmd.pool);
-    println!("Interval: {}s", cmd.interval);
+    println!("Interval: {:?}", cmd.interval);
     println!();
     println!("Press Ctrl+C to exit.");
     println!();
@@ -20,6 +28,212 @@ pub async fn run(cmd: WatchCmd) -> Result<()> {
 
         println!("[{}] Syncing... (stub)", chrono::Utc::now().format("%H:%M:%S"));
 
-        tokio::time::sleep(Duration::from_secs(cmd.interval)).await;
+        tokio::time::sleep(cmd.interval).await;
+    }
+}
+
+/// Where a watched snapshot stands after one poll
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchStatus {
+    /// Signature, content hash and every requested check passed
+    Valid(Address),
+    /// Everything passed except freshness
+    Stale(String),
+    /// Fetched, but the snapshot does not verify
+    Invalid(String),
+    /// The fetch failed or the body was not JSON
+    Unreachable(String),
+}
+
+impl WatchStatus {
+    fn label(&self) -> &'static str {
+        match self {
+            WatchStatus::Valid(_) => "valid",
+            WatchStatus::Stale(_) => "stale",
+            WatchStatus::Invalid(_) => "invalid",
+            WatchStatus::Unreachable(_) => "unreachable",
+        }
+    }
+
+    fn detail(&self) -> String {
+        match self {
+            WatchStatus::Valid(signer) => format!("signed by {signer:?}"),
+            WatchStatus::Stale(why) | WatchStatus::Invalid(why) | WatchStatus::Unreachable(why) => {
+                why.clone()
+            }
+        }
+    }
+}
+
+/// Remembers the last status so only changes are reported
+#[derive(Debug, Default)]
+pub struct StatusTracker {
+    last: Option<WatchStatus>,
+}
+
+impl StatusTracker {
+    /// A line describing the change, when `status` differs in kind from
+    /// the previous poll's (the first poll always reports)
+    pub fn observe(&mut self, status: WatchStatus) -> Option<String> {
+        let line = match &self.last {
+            Some(last) if last.label() == status.label() => None,
+            Some(last) => Some(format!(
+                "{} -> {}: {}",
+                last.label(),
+                status.label(),
+                status.detail()
+            )),
+            None => Some(format!("{}: {}", status.label(), status.detail())),
+        };
+        self.last = Some(status);
+        line
+    }
+}
+
+async fn watch_url(url: &str, cmd: &WatchCmd) -> Result<()> {
+    let opts = VerifyOptions {
+        expected_address: cmd
+            .address
+            .as_deref()
+            .map(|a| a.parse().with_context(|| format!("bad --address {a}")))
+            .transpose()?,
+        max_age: cmd.max_age,
+        ..Default::default()
+    };
+    let client = reqwest::Client::builder()
+        .timeout(cmd.interval.max(Duration::from_secs(5)))
+        .build()?;
+    println!("Watching: {url} every {:?}", cmd.interval);
+
+    let mut tracker = StatusTracker::default();
+    loop {
+        let status = poll(&client, url, &opts, Utc::now().timestamp()).await;
+        if let Some(line) = tracker.observe(status) {
+            println!("[{}] {line}", Utc::now().format("%H:%M:%S"));
+        }
+        tokio::time::sleep(cmd.interval).await;
+    }
+}
+
+/// Fetch the snapshot at `url` and verify it at time `now`. Network and
+/// parse errors become `Unreachable` rather than errors, so a watch loop
+/// rides out transient failures.
+pub async fn poll(
+    client: &reqwest::Client,
+    url: &str,
+    opts: &VerifyOptions,
+    now: i64,
+) -> WatchStatus {
+    let snapshot = match fetch(client, url).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => return WatchStatus::Unreachable(format!("{e:#}")),
+    };
+    let report = verify::verify_report(&snapshot, opts, now);
+    match (report.is_valid(), report.signer) {
+        (true, Some(signer)) => WatchStatus::Valid(signer),
+        _ if report.failed_checks() == ["Freshness"] => {
+            WatchStatus::Stale(report.errors.join("; "))
+        }
+        _ => WatchStatus::Invalid(report.errors.join("; ")),
+    }
+}
+
+async fn fetch(client: &reqwest::Client, url: &str) -> Result<Value> {
+    let response = client.get(url).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(anyhow!("{url} answered {status}"));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| anyhow!("{url} did not serve JSON: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{address, signed, KEY_A};
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const NOW: i64 = 1_700_000_000;
+
+    /// HTTP server answering every request with the current `body`
+    async fn mock_node(body: Arc<Mutex<String>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/snapshot.json", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut conn, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 1024];
+                let _ = conn.read(&mut buf).await.unwrap();
+                let body = body.lock().unwrap().clone();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                conn.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn reports_transition_to_invalid() {
+        let mut snap = signed(json!({ "id": "node-1", "body": { "up": true }, "signing": { "scheme": "eip191", "issued_at": NOW } }), KEY_A).await;
+
+        let served = Arc::new(Mutex::new(snap.to_string()));
+        let url = mock_node(served.clone()).await;
+        let client = reqwest::Client::new();
+        let opts = VerifyOptions {
+            expected_address: Some(address(KEY_A)),
+            max_age: Some(Duration::from_secs(300)),
+            ..Default::default()
+        };
+        let mut tracker = StatusTracker::default();
+
+        let status = poll(&client, &url, &opts, NOW).await;
+        assert_eq!(status, WatchStatus::Valid(address(KEY_A)));
+        assert!(tracker
+            .observe(status)
+            .unwrap()
+            .starts_with("valid: signed by"));
+        // No change, nothing reported
+        assert_eq!(tracker.observe(poll(&client, &url, &opts, NOW).await), None);
+
+        snap["body"]["up"] = json!(false);
+        *served.lock().unwrap() = snap.to_string();
+        let status = poll(&client, &url, &opts, NOW).await;
+        assert!(matches!(status, WatchStatus::Invalid(_)), "{status:?}");
+        let line = tracker.observe(status).unwrap();
+        assert!(
+            line.starts_with("valid -> invalid: payload_hash mismatch"),
+            "{line}"
+        );
+    }
+
+    #[tokio::test]
+    async fn stale_and_unreachable_are_distinguished() {
+        let snap = signed(
+            json!({ "id": "node-1", "signing": { "scheme": "eip191", "issued_at": NOW } }),
+            KEY_A,
+        )
+        .await;
+        let url = mock_node(Arc::new(Mutex::new(snap.to_string()))).await;
+        let client = reqwest::Client::new();
+        let opts = VerifyOptions {
+            max_age: Some(Duration::from_secs(300)),
+            ..Default::default()
+        };
+
+        let status = poll(&client, &url, &opts, NOW + 3600).await;
+        assert!(matches!(status, WatchStatus::Stale(_)), "{status:?}");
+
+        // Nothing listens on the discard port
+        let status = poll(&client, "http://127.0.0.1:9/snapshot.json", &opts, NOW).await;
+        assert!(matches!(status, WatchStatus::Unreachable(_)), "{status:?}");
     }
 }
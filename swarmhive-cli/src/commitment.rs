@@ -0,0 +1,112 @@
+//! Commit-reveal attestations
+//!
+//! `publish_commitment` signs a snapshot's payload hash and returns only
+//! the hash, signature and signer, so the attestation can be published
+//! while the content stays private. `reveal_and_verify` later checks that
+//! disclosed content is exactly what was committed to.
+
+use crate::signing::{
+    self, hash_str, parse_payload_hash, parse_signature, payload_hash_keccak, signature_str,
+};
+use anyhow::{anyhow, Result};
+use ethers::core::types::Address;
+use ethers::signers::LocalWallet;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The public part of a signed snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Commitment {
+    /// "keccak256:<hex>"
+    pub payload_hash: String,
+    /// "eip191:0x<r||s||v>"
+    pub signature: String,
+    pub signer: Address,
+}
+
+/// Sign `snapshot`'s payload hash with `wallet` without modifying it.
+/// The snapshot's signing.scheme (eip191 when absent) is used and must be
+/// kept when the content is revealed, since it is part of the hash.
+pub async fn publish_commitment(snapshot: &Value, wallet: &LocalWallet) -> Result<Commitment> {
+    let scheme = signing::snapshot_scheme(snapshot)?;
+    let hash = payload_hash_keccak(snapshot)?;
+    let (signature, signer) = signing::sign_hash(wallet, hash, scheme).await?;
+    Ok(Commitment {
+        payload_hash: hash_str(hash),
+        signature: signature_str(&signature),
+        signer,
+    })
+}
+
+/// Check revealed `snapshot` hashes to the committed payload hash and that
+/// the commitment's signature recovers to its signer. Returns the signer.
+pub fn reveal_and_verify(commitment: &Commitment, snapshot: &Value) -> Result<Address> {
+    let committed = parse_payload_hash(&commitment.payload_hash)?;
+    let revealed = payload_hash_keccak(snapshot)?;
+    if revealed != committed {
+        return Err(anyhow!(
+            "revealed content hashes to {}, not the committed {}",
+            hash_str(revealed),
+            commitment.payload_hash
+        ));
+    }
+
+    let signature = parse_signature(&commitment.signature)?;
+    let signer =
+        signing::recover_hash_signer(committed, &signature, signing::snapshot_scheme(snapshot)?)?;
+    if signer != commitment.signer {
+        return Err(anyhow!(
+            "commitment signature recovers to {signer:?}, not {:?}",
+            commitment.signer
+        ));
+    }
+    Ok(signer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{wallet, KEY_A};
+    use ethers::signers::Signer;
+    use serde_json::json;
+
+    fn snapshot() -> Value {
+        json!({ "id": "snap-1", "body": { "secret": "model weights v3" }, "signing": { "scheme": "eip191" } })
+    }
+
+    #[tokio::test]
+    async fn commit_then_reveal() {
+        let wallet = wallet(KEY_A);
+        let snap = snapshot();
+        let commitment = publish_commitment(&snap, &wallet).await.unwrap();
+
+        // Only the commitment is published
+        let public = serde_json::to_value(&commitment).unwrap();
+        assert!(!public.to_string().contains("model weights"));
+        assert_eq!(snap, snapshot());
+
+        let published: Commitment = serde_json::from_value(public).unwrap();
+        assert_eq!(
+            reveal_and_verify(&published, &snap).unwrap(),
+            wallet.address()
+        );
+    }
+
+    #[tokio::test]
+    async fn altered_reveal_fails() {
+        let wallet = wallet(KEY_A);
+        let commitment = publish_commitment(&snapshot(), &wallet).await.unwrap();
+
+        let mut altered = snapshot();
+        altered["body"]["secret"] = json!("model weights v4");
+        let err = reveal_and_verify(&commitment, &altered).unwrap_err();
+        assert!(err.to_string().contains("not the committed"), "{err}");
+
+        // A commitment claiming another signer is rejected too
+        let forged = Commitment {
+            signer: Address::repeat_byte(0x11),
+            ..commitment
+        };
+        assert!(reveal_and_verify(&forged, &snapshot()).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+//! Payload hashing on `core` + `alloc` only
+//!
+//! For embedded nodes that cannot link std: the v1 canonical form and its
+//! keccak256 payload hash, byte-identical to `hash::payload_hash_keccak`
+//! for snapshots using the default canonical version, encoding and
+//! profile. Everything else (JCS, gzip, protobuf, profiles, signing) stays
+//! in the std modules; snapshots needing it are refused rather than
+//! hashed differently.
+
+#![deny(clippy::std_instead_of_core, clippy::std_instead_of_alloc)]
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use serde_json::{Map, Value};
+use tiny_keccak::{Hasher, Keccak};
+
+/// Members of the top-level signing object that are never hashed; kept in
+/// step with `hash::UNHASHED_SIGNING_KEYS`
+pub const UNHASHED_SIGNING_KEYS: [&str; 6] = [
+    "signature",
+    "signatures",
+    "payload_hash",
+    "payload_hashes",
+    "sections",
+    "history",
+];
+
+/// Why a snapshot cannot be hashed by the core
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoreHashError {
+    /// signing.<field> is set to something only the std path implements
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for CoreHashError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoreHashError::Unsupported(field) => {
+                write!(f, "signing.{field} is not supported without std")
+            }
+        }
+    }
+}
+
+/// Keccak-256 (the Ethereum variant) of `bytes`
+pub fn keccak256(bytes: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    hasher.update(bytes);
+    let mut out = [0u8; 32];
+    hasher.finalize(&mut out);
+    out
+}
+
+/// v1 canonical bytes of `v`: serde_json output, keys sorted by byte value
+pub fn canonical_bytes(v: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_value(v, &mut out, false);
+    out
+}
+
+/// The bytes the payload hash is taken over: the canonical snapshot minus
+/// the unhashed signing members
+pub fn payload_preimage(snapshot: &Value) -> Result<Vec<u8>, CoreHashError> {
+    if let Some(signing) = snapshot.get("signing") {
+        if signing
+            .get("canonical_version")
+            .is_some_and(|v| v.as_u64() != Some(1))
+        {
+            return Err(CoreHashError::Unsupported("canonical_version"));
+        }
+        if signing
+            .get("encoding")
+            .is_some_and(|v| v.as_str() != Some("canonical-json"))
+        {
+            return Err(CoreHashError::Unsupported("encoding"));
+        }
+        if signing.get("canonical_profile").is_some() {
+            return Err(CoreHashError::Unsupported("canonical_profile"));
+        }
+    }
+    let mut out = Vec::new();
+    write_value(snapshot, &mut out, true);
+    Ok(out)
+}
+
+/// keccak256 of `payload_preimage`
+pub fn payload_hash_keccak(snapshot: &Value) -> Result<[u8; 32], CoreHashError> {
+    Ok(keccak256(&payload_preimage(snapshot)?))
+}
+
+fn write_value(v: &Value, out: &mut Vec<u8>, strip_signing: bool) {
+    match v {
+        Value::Object(map) => write_object(
+            map,
+            out,
+            |_| true,
+            |k, child, out| match child {
+                Value::Object(signing) if strip_signing && k == "signing" => write_object(
+                    signing,
+                    out,
+                    |k| !UNHASHED_SIGNING_KEYS.contains(&k),
+                    |_, v, out| write_value(v, out, false),
+                ),
+                child => write_value(child, out, false),
+            },
+        ),
+        Value::Array(arr) => {
+            out.push(b'[');
+            for (i, child) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_value(child, out, false);
+            }
+            out.push(b']');
+        }
+        Value::String(s) => write_str(s, out),
+        Value::Number(n) => out.extend_from_slice(alloc::format!("{n}").as_bytes()),
+        Value::Bool(b) => out.extend_from_slice(if *b { b"true" } else { b"false" }),
+        Value::Null => out.extend_from_slice(b"null"),
+    }
+}
+
+/// An object with the members `keep` accepts, in byte order of their keys
+fn write_object(
+    map: &Map<String, Value>,
+    out: &mut Vec<u8>,
+    keep: impl Fn(&str) -> bool,
+    write_child: impl Fn(&str, &Value, &mut Vec<u8>),
+) {
+    let mut keys: Vec<&String> = map.keys().filter(|k| keep(k)).collect();
+    keys.sort();
+    out.push(b'{');
+    for (i, k) in keys.into_iter().enumerate() {
+        if i > 0 {
+            out.push(b',');
+        }
+        write_str(k, out);
+        out.push(b':');
+        write_child(k, &map[k.as_str()], out);
+    }
+    out.push(b'}');
+}
+
+/// JSON string escaping exactly as serde_json writes it: `"` and `\`,
+/// the short escapes for \b \t \n \f \r, `\u00XX` for other control
+/// characters, everything else verbatim
+fn write_str(s: &str, out: &mut Vec<u8>) {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    out.push(b'"');
+    for &b in s.as_bytes() {
+        match b {
+            b'"' => out.extend_from_slice(b"\\\""),
+            b'\\' => out.extend_from_slice(b"\\\\"),
+            0x08 => out.extend_from_slice(b"\\b"),
+            b'\t' => out.extend_from_slice(b"\\t"),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            0x0c => out.extend_from_slice(b"\\f"),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            0x00..=0x1f => out.extend_from_slice(&[
+                b'\\',
+                b'u',
+                b'0',
+                b'0',
+                HEX[(b >> 4) as usize],
+                HEX[(b & 0xf) as usize],
+            ]),
+            _ => out.push(b),
+        }
+    }
+    out.push(b'"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash;
+    use serde_json::json;
+
+    fn inputs() -> Vec<Value> {
+        vec![
+            json!({ "id": "snap-1", "body": { "n": 1 }, "signing": { "scheme": "eip191" } }),
+            json!({
+                "z": [1, -2, 2.5, 1e300, 0.1, -0.0, u64::MAX, i64::MIN, null, true, false],
+                "a": { "nested": { "é": "ünïcödé ✓", "ctl": "\u{0}\u{7}\u{8}\t\n\u{b}\u{c}\r\u{1f}\u{7f}" } },
+                "quote\"back\\": "\"\\/",
+                "signing": {
+                    "scheme": "eip191",
+                    "canonical_version": 1,
+                    "signature": "eip191:0xdead",
+                    "signatures": [],
+                    "payload_hash": "keccak256:00",
+                    "payload_hashes": ["sha256:00"],
+                    "sections": {},
+                    "issued_at": 1_700_000_000
+                }
+            }),
+            json!({ "body": { "signing": { "signature": "nested, so hashed" } }, "signing": "not an object" }),
+            json!([{ "b": 1, "a": 2 }, "top-level array"]),
+        ]
+    }
+
+    #[test]
+    fn matches_std_path_byte_for_byte() {
+        for input in inputs() {
+            assert_eq!(
+                canonical_bytes(&input),
+                hash::canonical_json_bytes(&input).unwrap(),
+                "{input}"
+            );
+            if input.is_object() {
+                let std_preimage = hash::payload_preimage(&input, &Default::default()).unwrap();
+                assert_eq!(payload_preimage(&input).unwrap(), std_preimage, "{input}");
+                assert_eq!(
+                    payload_hash_keccak(&input).unwrap(),
+                    hash::payload_hash_keccak(&input).unwrap()
+                );
+            }
+        }
+        assert_eq!(UNHASHED_SIGNING_KEYS, hash::UNHASHED_SIGNING_KEYS);
+    }
+
+    #[test]
+    fn std_only_forms_are_refused() {
+        for (field, value) in [
+            ("canonical_version", json!(2)),
+            ("encoding", json!("gzip+canonical-json")),
+        ] {
+            let mut snap = json!({ "body": {}, "signing": {} });
+            snap["signing"][field] = value;
+            assert_eq!(
+                payload_preimage(&snap),
+                Err(CoreHashError::Unsupported(field))
+            );
+        }
+    }
+}
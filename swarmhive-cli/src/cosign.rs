@@ -0,0 +1,687 @@
+//! Co-signatures
+//!
+//! Several operators can sign the same snapshot content. Extra signatures
+//! live in `signing.signatures` as `{signer, signature}` entries next to the
+//! primary `signing.signature`. Neither is part of the payload hash, so each
+//! operator's copy hashes identically and the copies can be merged.
+//!
+//! An entry with a `scope` (a JSON Pointer) is a scoped co-signature: it
+//! signs only that subtree, via `sections::section_hash`, and counts only
+//! towards `verify_scoped_cosignatures`. Whole-document checks skip it.
+
+use crate::sections::section_hash;
+use crate::signing::{
+    self, hash_str, parse_signature, payload_hash_keccak, signature_str, HashSigner, SigningScheme,
+};
+use anyhow::{anyhow, Result};
+use ethers::core::types::{Address, Signature};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+
+/// One signer's signature over a snapshot's payload hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoSignature {
+    pub signer: Address,
+    pub signature: Signature,
+}
+
+impl CoSignature {
+    /// `signing.signatures` entry form
+    pub fn to_json(&self) -> Value {
+        json!({
+            "signer": format!("{:?}", self.signer),
+            "signature": signature_str(&self.signature),
+        })
+    }
+}
+
+/// Every valid signature on `snapshot`: the primary signing.signature (if
+/// set) followed by the signing.signatures entries, deduplicated by signer.
+/// Each must recover over the snapshot's recomputed payload hash.
+pub fn cosignatures(snapshot: &Value) -> Result<Vec<CoSignature>> {
+    let mut found = Vec::new();
+    walk_signatures(snapshot, |cosig| {
+        found.push(cosig);
+        ControlFlow::Continue(())
+    })?;
+    Ok(found)
+}
+
+/// Recover the snapshot's signatures in order, handing each new signer to
+/// `visit` until it breaks. Returns how many signatures were recovered.
+fn walk_signatures(
+    snapshot: &Value,
+    mut visit: impl FnMut(CoSignature) -> ControlFlow<()>,
+) -> Result<usize> {
+    let signing = signing::signing_object(snapshot)?;
+    let scheme = signing::snapshot_scheme(snapshot)?;
+    let hash = payload_hash_keccak(snapshot)?;
+
+    if let Some(stored) = signing::string_field(signing, "payload_hash")? {
+        if !stored.is_empty() && signing::parse_payload_hash(stored)? != hash {
+            return Err(anyhow!("payload_hash mismatch: snapshot content changed"));
+        }
+    }
+
+    let mut seen: Vec<Address> = Vec::new();
+    let mut recovered = 0;
+    let mut push = |cosig: CoSignature| {
+        recovered += 1;
+        if seen.contains(&cosig.signer) {
+            return ControlFlow::Continue(());
+        }
+        seen.push(cosig.signer);
+        visit(cosig)
+    };
+
+    if let Some(sig) = signing.get("signature") {
+        if signing::signature_present(sig) {
+            let signature = signing::parse_signature_value(sig)?;
+            let signer = signing::recover_hash_signer(hash, &signature, scheme)?;
+            if push(CoSignature { signer, signature }).is_break() {
+                return Ok(recovered);
+            }
+        }
+    }
+
+    if let Some(entries) = signing.get("signatures") {
+        let entries = entries
+            .as_array()
+            .ok_or_else(|| anyhow!("signing.signatures must be an array"))?;
+        for (i, entry) in entries.iter().enumerate() {
+            if entry.get("scope").is_some() {
+                continue;
+            }
+            let sig_str = entry
+                .get("signature")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow!("signing.signatures[{i}] missing signature"))?;
+            let signature = parse_signature(sig_str)?;
+            let signer = signing::recover_hash_signer(hash, &signature, scheme)?;
+
+            if let Some(claimed) = entry.get("signer").and_then(|v| v.as_str()) {
+                let claimed: Address = claimed
+                    .parse()
+                    .map_err(|e| anyhow!("signing.signatures[{i}] bad signer: {e}"))?;
+                if claimed != signer {
+                    return Err(anyhow!(
+                        "signing.signatures[{i}] recovers to {signer:?}, not the listed {claimed:?}"
+                    ));
+                }
+            }
+            if push(CoSignature { signer, signature }).is_break() {
+                return Ok(recovered);
+            }
+        }
+    }
+
+    Ok(recovered)
+}
+
+/// How much of a snapshot `verify_threshold_with` validates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThresholdMode {
+    /// Stop once `threshold` owners have signed. Fastest, but signatures
+    /// past that point are never parsed or recovered, so a malformed or
+    /// mislabelled extra entry goes unnoticed and the result depends on
+    /// entry order.
+    EarlyExit,
+    /// Recover every signature, failing on any bad entry. Use when the
+    /// snapshot is being audited rather than just accepted.
+    #[default]
+    VerifyAll,
+}
+
+/// Result of a threshold check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThresholdOutcome {
+    /// Owners that signed, in signature order (exactly `threshold` of them
+    /// under early exit)
+    pub signers: Vec<Address>,
+    /// Signatures recovered to reach the decision
+    pub recovered: usize,
+}
+
+/// Require at least `threshold` distinct `owners` among the snapshot's
+/// valid signatures. Signatures from non-owners are ignored. Returns the
+/// owners that signed, in signature order.
+pub fn verify_threshold(
+    snapshot: &Value,
+    owners: &[Address],
+    threshold: usize,
+) -> Result<Vec<Address>> {
+    Ok(verify_threshold_with(snapshot, owners, threshold, ThresholdMode::VerifyAll)?.signers)
+}
+
+/// `verify_threshold` with a choice of how far to go; see `ThresholdMode`
+pub fn verify_threshold_with(
+    snapshot: &Value,
+    owners: &[Address],
+    threshold: usize,
+    mode: ThresholdMode,
+) -> Result<ThresholdOutcome> {
+    if threshold == 0 {
+        return Err(anyhow!("threshold must be at least 1"));
+    }
+    let mut signers = Vec::new();
+    let recovered = walk_signatures(snapshot, |cosig| {
+        if owners.contains(&cosig.signer) {
+            signers.push(cosig.signer);
+        }
+        if mode == ThresholdMode::EarlyExit && signers.len() >= threshold {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })?;
+    if signers.len() < threshold {
+        return Err(anyhow!(
+            "only {} of the required {threshold} owner signatures present",
+            signers.len()
+        ));
+    }
+    Ok(ThresholdOutcome { signers, recovered })
+}
+
+/// Require the distinct valid signers of `snapshot` to carry at least
+/// `threshold` voting weight between them. Signers missing from `weights`
+/// count for nothing. Returns the total weight that signed.
+pub fn verify_weighted(
+    snapshot: &Value,
+    weights: &HashMap<Address, u64>,
+    threshold: u64,
+) -> Result<u64> {
+    if threshold == 0 {
+        return Err(anyhow!("threshold must be at least 1"));
+    }
+    let mut total: u64 = 0;
+    walk_signatures(snapshot, |cosig| {
+        total = total.saturating_add(weights.get(&cosig.signer).copied().unwrap_or(0));
+        ControlFlow::Continue(())
+    })?;
+    if total < threshold {
+        return Err(anyhow!(
+            "signed weight {total} is below the required {threshold}"
+        ));
+    }
+    Ok(total)
+}
+
+/// Sign an already-signed snapshot's payload hash with `wallet` and append
+/// the signature to signing.signatures. Fails if the content no longer
+/// matches the stored payload hash or `wallet` has already signed.
+pub async fn add_cosignature(
+    snapshot: &mut Value,
+    wallet: &(impl HashSigner + ?Sized),
+) -> Result<Address> {
+    let existing = cosignatures(snapshot)?;
+    if existing.iter().any(|c| c.signer == wallet.signer_address()) {
+        return Err(anyhow!(
+            "snapshot is already signed by {:?}",
+            wallet.signer_address()
+        ));
+    }
+
+    let hash = payload_hash_keccak(snapshot)?;
+    let scheme = signing::snapshot_scheme(snapshot)?;
+    let (signature, signer) = signing::sign_hash(wallet, hash, scheme).await?;
+
+    let signing = snapshot
+        .get_mut("signing")
+        .and_then(|v| v.as_object_mut())
+        .ok_or_else(|| anyhow!("snapshot missing signing object"))?;
+    let entries = signing
+        .entry("signatures")
+        .or_insert_with(|| Value::Array(Vec::new()))
+        .as_array_mut()
+        .ok_or_else(|| anyhow!("signing.signatures must be an array"))?;
+    entries.push(CoSignature { signer, signature }.to_json());
+
+    Ok(signer)
+}
+
+/// Sign the subtree at `scope` with `wallet` and append the signature to
+/// signing.signatures as a scoped entry. Fails if `wallet` has already
+/// signed that scope.
+pub async fn add_scoped_cosignature(
+    snapshot: &mut Value,
+    scope: &str,
+    wallet: &(impl HashSigner + ?Sized),
+) -> Result<Address> {
+    if scoped_cosignatures(snapshot)?
+        .valid
+        .iter()
+        .any(|(s, signer)| s == scope && *signer == wallet.signer_address())
+    {
+        return Err(anyhow!(
+            "{scope} is already signed by {:?}",
+            wallet.signer_address()
+        ));
+    }
+    let hash = section_hash(snapshot, scope)?;
+    let (signature, signer) = signing::sign_hash(wallet, hash, SigningScheme::Eip191).await?;
+
+    let signing = snapshot
+        .get_mut("signing")
+        .and_then(|v| v.as_object_mut())
+        .ok_or_else(|| anyhow!("snapshot missing signing object"))?;
+    let entries = signing
+        .entry("signatures")
+        .or_insert_with(|| Value::Array(Vec::new()))
+        .as_array_mut()
+        .ok_or_else(|| anyhow!("signing.signatures must be an array"))?;
+    let mut entry = CoSignature { signer, signature }.to_json();
+    entry["scope"] = Value::String(scope.to_string());
+    entries.push(entry);
+
+    Ok(signer)
+}
+
+/// Scoped entries in signing.signatures, checked against the current
+/// content of their scopes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScopedCosignatures {
+    /// (scope, signer) of each entry that recovers to its listed signer
+    pub valid: Vec<(String, Address)>,
+    /// Why each other entry was skipped
+    pub skipped: Vec<String>,
+}
+
+/// Every scoped entry in signing.signatures. signing.signatures is not
+/// hashed, so an entry that is malformed, stale or forged is reported in
+/// `skipped` rather than failing the others.
+pub fn scoped_cosignatures(snapshot: &Value) -> Result<ScopedCosignatures> {
+    collect_scoped(snapshot, |_| true)
+}
+
+/// The scoped entries whose scope passes `wanted`; others are not looked at
+fn collect_scoped(snapshot: &Value, wanted: impl Fn(&str) -> bool) -> Result<ScopedCosignatures> {
+    let mut found = ScopedCosignatures::default();
+    let Some(entries) = signing::signing_object(snapshot)?.get("signatures") else {
+        return Ok(found);
+    };
+    let entries = entries
+        .as_array()
+        .ok_or_else(|| anyhow!("signing.signatures must be an array"))?;
+    for (i, entry) in entries.iter().enumerate() {
+        let Some(scope) = entry.get("scope") else {
+            continue;
+        };
+        let Some(scope) = scope.as_str() else {
+            found.skipped.push(format!(
+                "signing.signatures[{i}].scope must be a JSON Pointer string"
+            ));
+            continue;
+        };
+        if !wanted(scope) {
+            continue;
+        }
+        match recover_scoped(snapshot, entry, scope) {
+            Ok(signer) => found.valid.push((scope.to_string(), signer)),
+            Err(e) => found
+                .skipped
+                .push(format!("signing.signatures[{i}] ({scope}): {e}")),
+        }
+    }
+    Ok(found)
+}
+
+/// The listed signer of a scoped entry, if its signature recovers to it
+fn recover_scoped(snapshot: &Value, entry: &Value, scope: &str) -> Result<Address> {
+    let field = |key: &str| {
+        entry
+            .get(key)
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("missing {key}"))
+    };
+    let claimed: Address = field("signer")?
+        .parse()
+        .map_err(|e| anyhow!("bad signer: {e}"))?;
+    let signature = parse_signature(field("signature")?)?;
+    let hash = section_hash(snapshot, scope)?;
+    let signer = signing::recover_hash_signer(hash, &signature, SigningScheme::Eip191)?;
+    if signer != claimed {
+        return Err(anyhow!(
+            "recovers to {signer:?}, not the listed {claimed:?}; the section changed or the entry is forged"
+        ));
+    }
+    Ok(signer)
+}
+
+/// A subtree that must be signed, and who may sign it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeRequirement {
+    /// JSON Pointer of the subtree
+    pub scope: String,
+    /// Signers authorized for it; any one of them suffices
+    pub signers: Vec<Address>,
+}
+
+/// Require each scope in `requirements` to carry a valid scoped
+/// co-signature from one of its authorized signers. Returns the
+/// (scope, signer) that satisfied each requirement, in requirement order.
+pub fn verify_scoped_cosignatures(
+    snapshot: &Value,
+    requirements: &[ScopeRequirement],
+) -> Result<Vec<(String, Address)>> {
+    let found = collect_scoped(snapshot, |scope| {
+        requirements.iter().any(|r| r.scope == scope)
+    })?;
+    requirements
+        .iter()
+        .map(|required| {
+            found
+                .valid
+                .iter()
+                .find(|(scope, signer)| {
+                    *scope == required.scope && required.signers.contains(signer)
+                })
+                .cloned()
+                .ok_or_else(|| {
+                    let tag = format!("] ({}): ", required.scope);
+                    let skipped: Vec<&str> = found
+                        .skipped
+                        .iter()
+                        .filter(|e| e.contains(&tag))
+                        .map(String::as_str)
+                        .collect();
+                    let mut err = format!(
+                        "no signature from an authorized signer for {}",
+                        required.scope
+                    );
+                    if !skipped.is_empty() {
+                        err.push_str(&format!(" (skipped {})", skipped.join("; ")));
+                    }
+                    anyhow!(err)
+                })
+        })
+        .collect()
+}
+
+/// Merge co-signed copies of the same snapshot into one.
+///
+/// All copies must have the same payload hash. The first copy is the base:
+/// its primary signature is kept, and every other distinct signer across the
+/// copies is written to signing.signatures.
+pub fn merge_cosigned(copies: &[Value]) -> Result<Value> {
+    let first = copies.first().ok_or_else(|| anyhow!("nothing to merge"))?;
+    let expected = payload_hash_keccak(first)?;
+    for (i, copy) in copies.iter().enumerate().skip(1) {
+        let hash = payload_hash_keccak(copy)?;
+        if hash != expected {
+            return Err(anyhow!(
+                "input #{} has different content: payload hash {} != {} (input #1)",
+                i + 1,
+                hash_str(hash),
+                hash_str(expected)
+            ));
+        }
+    }
+
+    let mut all: Vec<CoSignature> = Vec::new();
+    for (i, copy) in copies.iter().enumerate() {
+        let found = cosignatures(copy).map_err(|e| anyhow!("input #{}: {e}", i + 1))?;
+        for cosig in found {
+            if !all.iter().any(|c| c.signer == cosig.signer) {
+                all.push(cosig);
+            }
+        }
+    }
+
+    let mut merged = first.clone();
+    let signing = merged
+        .get_mut("signing")
+        .and_then(|v| v.as_object_mut())
+        .ok_or_else(|| anyhow!("snapshot missing signing object"))?;
+
+    let has_primary = signing
+        .get("signature")
+        .is_some_and(signing::signature_present);
+    let extra = if has_primary { &all[1..] } else { &all[..] };
+
+    signing.insert(
+        "payload_hash".to_string(),
+        Value::String(hash_str(expected)),
+    );
+    let mut entries: Vec<Value> = extra.iter().map(CoSignature::to_json).collect();
+    // Scoped entries are carried over as they are, once each
+    for copy in copies {
+        let scoped = copy["signing"]["signatures"]
+            .as_array()
+            .into_iter()
+            .flatten();
+        for entry in scoped.filter(|e| e.get("scope").is_some()) {
+            if !entries.contains(entry) {
+                entries.push(entry.clone());
+            }
+        }
+    }
+    signing.insert("signatures".to_string(), Value::Array(entries));
+
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::{attach_signature, sign_eip191_hash};
+    use crate::test_support::{address, wallet, KEY_A, KEY_B, KEY_C};
+    use ethers::signers::{LocalWallet, Signer};
+
+    async fn copy_signed_by(key: &str, body: Value) -> Value {
+        let wallet = wallet(key);
+        let mut snap = json!({
+            "id": "epoch-7-seal",
+            "body": body,
+            "signing": { "scheme": "eip191" }
+        });
+        let hash = payload_hash_keccak(&snap).unwrap();
+        let (sig, _) = sign_eip191_hash(&wallet, hash).await.unwrap();
+        attach_signature(&mut snap, hash, &sig).unwrap();
+        snap
+    }
+
+    #[tokio::test]
+    async fn merges_matching_copies() {
+        let a = copy_signed_by(KEY_A, json!({ "epoch": 7 })).await;
+        let b = copy_signed_by(KEY_B, json!({ "epoch": 7 })).await;
+
+        // Duplicate copy of A must not add a second entry
+        let merged = merge_cosigned(&[a.clone(), b, a]).unwrap();
+        let signers: Vec<Address> = cosignatures(&merged)
+            .unwrap()
+            .iter()
+            .map(|c| c.signer)
+            .collect();
+
+        let addr_a = address(KEY_A);
+        let addr_b = address(KEY_B);
+        assert_eq!(signers, vec![addr_a, addr_b]);
+        assert_eq!(merged["signing"]["signatures"].as_array().unwrap().len(), 1);
+
+        // The merged snapshot still verifies for the primary signer
+        signing::verify_signature(&merged, addr_a).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_content() {
+        let a = copy_signed_by(KEY_A, json!({ "epoch": 7 })).await;
+        let b = copy_signed_by(KEY_B, json!({ "epoch": 8 })).await;
+
+        let err = merge_cosigned(&[a, b]).unwrap_err();
+        assert!(
+            err.to_string().contains("input #2 has different content"),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_entry_with_wrong_signer() {
+        let mut a = copy_signed_by(KEY_A, json!({ "epoch": 7 })).await;
+        let b = copy_signed_by(KEY_B, json!({ "epoch": 7 })).await;
+        a["signing"]["signatures"] = json!([{
+            "signer": format!("{:?}", Address::repeat_byte(0x11)),
+            "signature": b["signing"]["signature"],
+        }]);
+
+        let err = cosignatures(&a).unwrap_err();
+        assert!(err.to_string().contains("not the listed"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn wrongly_typed_payload_hash_is_not_skipped() {
+        let a = copy_signed_by(KEY_A, json!({ "epoch": 7 })).await;
+        let mut b = copy_signed_by(KEY_B, json!({ "epoch": 7 })).await;
+        b["signing"]["payload_hash"] = json!({ "h": "0x00" });
+
+        let err = merge_cosigned(&[a, b]).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("signing.payload_hash must be a string, found object"),
+            "{err:#}"
+        );
+    }
+
+    #[tokio::test]
+    async fn early_exit_stops_at_threshold() {
+        let mut snap = copy_signed_by(KEY_A, json!({ "epoch": 7 })).await;
+        for key in [KEY_B, KEY_C] {
+            add_cosignature(&mut snap, &wallet(key)).await.unwrap();
+        }
+        let owners: Vec<Address> = [KEY_A, KEY_B, KEY_C].map(address).to_vec();
+
+        let early = verify_threshold_with(&snap, &owners, 2, ThresholdMode::EarlyExit).unwrap();
+        assert_eq!(
+            early,
+            ThresholdOutcome {
+                signers: owners[..2].to_vec(),
+                recovered: 2
+            }
+        );
+        let all = verify_threshold_with(&snap, &owners, 2, ThresholdMode::VerifyAll).unwrap();
+        assert_eq!(
+            all,
+            ThresholdOutcome {
+                signers: owners.clone(),
+                recovered: 3
+            }
+        );
+
+        // A broken entry past the threshold is only caught when verifying all
+        snap["signing"]["signatures"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({ "signature": "0xdead" }));
+        assert_eq!(
+            verify_threshold_with(&snap, &owners, 2, ThresholdMode::EarlyExit)
+                .unwrap()
+                .recovered,
+            2
+        );
+        assert!(verify_threshold_with(&snap, &owners, 2, ThresholdMode::VerifyAll).is_err());
+        assert!(verify_threshold(&snap, &owners, 2).is_err());
+    }
+
+    #[tokio::test]
+    async fn weighted_signers_must_reach_threshold() {
+        let mut snap = copy_signed_by(KEY_A, json!({ "epoch": 7 })).await;
+        add_cosignature(&mut snap, &wallet(KEY_B)).await.unwrap();
+        let [a, b, c] = [KEY_A, KEY_B, KEY_C].map(address);
+        let weights = HashMap::from([(a, 3), (b, 2), (c, 10)]);
+
+        assert_eq!(verify_weighted(&snap, &weights, 5).unwrap(), 5);
+        assert_eq!(verify_weighted(&snap, &weights, 4).unwrap(), 5);
+        let err = verify_weighted(&snap, &weights, 6).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("signed weight 5 is below the required 6"),
+            "{err}"
+        );
+
+        // A repeated signature from A still counts once
+        let again =
+            json!({ "signer": format!("{a:?}"), "signature": snap["signing"]["signature"] });
+        snap["signing"]["signatures"]
+            .as_array_mut()
+            .unwrap()
+            .push(again);
+        assert_eq!(verify_weighted(&snap, &weights, 5).unwrap(), 5);
+        assert!(verify_weighted(&snap, &weights, 8).is_err());
+
+        // Signers without a weight contribute nothing
+        assert!(verify_weighted(&snap, &HashMap::from([(c, 10)]), 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn scoped_cosignatures_cover_their_sections() {
+        let mut snap = json!({
+            "id": "epoch-7-report",
+            "metadata": { "operator": "alpha", "region": "eu" },
+            "results": { "score": 0.93 },
+            "signing": { "scheme": "eip191" }
+        });
+        let a = wallet(KEY_A);
+        let b = wallet(KEY_B);
+        add_scoped_cosignature(&mut snap, "/metadata", &a)
+            .await
+            .unwrap();
+        add_scoped_cosignature(&mut snap, "/results", &b)
+            .await
+            .unwrap();
+        assert!(add_scoped_cosignature(&mut snap, "/results", &b)
+            .await
+            .is_err());
+        assert_eq!(
+            merge_cosigned(&[snap.clone(), snap.clone()]).unwrap()["signing"]["signatures"],
+            snap["signing"]["signatures"]
+        );
+
+        let require = |scope: &str, signer: &LocalWallet| ScopeRequirement {
+            scope: scope.to_string(),
+            signers: vec![signer.address()],
+        };
+        let covered =
+            verify_scoped_cosignatures(&snap, &[require("/metadata", &a), require("/results", &b)])
+                .unwrap();
+        assert_eq!(
+            covered,
+            vec![
+                ("/metadata".to_string(), a.address()),
+                ("/results".to_string(), b.address())
+            ]
+        );
+        // Scoped entries are not whole-document signatures
+        assert!(cosignatures(&snap).unwrap().is_empty());
+
+        // Authority is per scope: A signed /metadata, not /results
+        let err = verify_scoped_cosignatures(&snap, &[require("/results", &a)]).unwrap_err();
+        assert!(err.to_string().contains("/results"), "{err}");
+        let err = verify_scoped_cosignatures(&snap, &[require("/audit", &a)]).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("no signature from an authorized signer for /audit"),
+            "{err}"
+        );
+
+        // Editing a section breaks only its own signature
+        snap["results"]["score"] = json!(0.99);
+        assert_eq!(
+            verify_scoped_cosignatures(&snap, &[require("/metadata", &a)]).unwrap(),
+            vec![("/metadata".to_string(), a.address())]
+        );
+        let err = verify_scoped_cosignatures(&snap, &[require("/results", &b)]).unwrap_err();
+        assert!(err.to_string().contains("(/results): recovers to"), "{err}");
+
+        // Appended junk for one scope does not block the others
+        let entries = snap["signing"]["signatures"].as_array_mut().unwrap();
+        entries.push(json!({
+            "scope": "/metadata",
+            "signer": format!("{:?}", b.address()),
+            "signature": format!("0x{}", "11".repeat(65))
+        }));
+        entries.push(json!({ "scope": 7 }));
+        verify_scoped_cosignatures(&snap, &[require("/metadata", &a)]).unwrap();
+        assert_eq!(scoped_cosignatures(&snap).unwrap().skipped.len(), 3);
+    }
+}
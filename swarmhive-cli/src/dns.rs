@@ -0,0 +1,222 @@
+//! DNS TXT identity anchor
+//!
+//! Operators without ENS can publish their signing address as a TXT record:
+//!
+//! ```text
+//! _swarmhive.example.com.  TXT  "swarmhive-signer=0xabc..."
+//! ```
+//!
+//! `verify_dns` looks the record up and checks the snapshot recovers to one
+//! of the published addresses. The lookup is behind `TxtResolver` so the
+//! system resolver (feature `dns`) can be swapped for a mock.
+
+use crate::signing;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::core::types::Address;
+use serde_json::Value;
+
+/// Label prepended to the operator's domain for the lookup
+pub const TXT_LABEL: &str = "_swarmhive";
+
+/// Prefix of the TXT value carrying the signer address
+pub const SIGNER_PREFIX: &str = "swarmhive-signer=";
+
+/// Source of TXT records
+#[async_trait]
+pub trait TxtResolver {
+    /// Return every TXT string published at `name` (empty if none)
+    async fn lookup_txt(&self, name: &str) -> Result<Vec<String>>;
+}
+
+/// Parse the signer addresses out of a set of TXT strings.
+/// Unrelated TXT values are ignored, and so are signer values that do not
+/// parse, unless no usable address remains.
+pub fn parse_signer_records(records: &[String]) -> Result<Vec<Address>> {
+    let mut signers = Vec::new();
+    let mut bad = Vec::new();
+    for addr in records
+        .iter()
+        .filter_map(|r| r.trim().strip_prefix(SIGNER_PREFIX))
+    {
+        match addr.trim().parse::<Address>() {
+            Ok(signer) => signers.push(signer),
+            Err(e) => bad.push(format!("{addr:?}: {e}")),
+        }
+    }
+    if signers.is_empty() && !bad.is_empty() {
+        return Err(anyhow!("bad {SIGNER_PREFIX} value {}", bad.join(", ")));
+    }
+    Ok(signers)
+}
+
+/// Verify `snapshot` against the signer published for `domain`.
+/// Each published address is tried in turn; returns the one that matched.
+pub async fn verify_dns<R: TxtResolver + ?Sized>(
+    snapshot: &Value,
+    domain: &str,
+    resolver: &R,
+) -> Result<Address> {
+    let name = format!("{TXT_LABEL}.{}", domain.trim_end_matches('.'));
+    let records = resolver.lookup_txt(&name).await?;
+    let signers = parse_signer_records(&records)?;
+    if signers.is_empty() {
+        return Err(anyhow!(
+            "no signer TXT record ({SIGNER_PREFIX}0x...) at {name}"
+        ));
+    }
+
+    for signer in &signers {
+        if signing::verify_signature(snapshot, *signer).is_ok() {
+            return Ok(*signer);
+        }
+    }
+    Err(anyhow!(
+        "snapshot does not recover to any signer published at {name} ({} tried)",
+        signers.len()
+    ))
+}
+
+/// System resolver backed by hickory
+#[cfg(feature = "dns")]
+pub struct SystemResolver {
+    inner: hickory_resolver::TokioAsyncResolver,
+}
+
+#[cfg(feature = "dns")]
+impl SystemResolver {
+    /// Build from the host's resolv.conf
+    pub fn from_system_conf() -> Result<Self> {
+        let inner = hickory_resolver::TokioAsyncResolver::tokio_from_system_conf()?;
+        Ok(Self { inner })
+    }
+}
+
+#[cfg(feature = "dns")]
+#[async_trait]
+impl TxtResolver for SystemResolver {
+    async fn lookup_txt(&self, name: &str) -> Result<Vec<String>> {
+        use hickory_resolver::error::ResolveErrorKind;
+
+        match self.inner.txt_lookup(name).await {
+            Ok(lookup) => Ok(lookup
+                .iter()
+                .map(|txt| {
+                    // A TXT record may be split into several character-strings
+                    txt.txt_data()
+                        .iter()
+                        .map(|chunk| String::from_utf8_lossy(chunk))
+                        .collect::<String>()
+                })
+                .collect()),
+            Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => Ok(Vec::new()),
+            Err(e) => Err(anyhow!("TXT lookup for {name} failed: {e}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::{attach_signature, payload_hash_keccak, sign_eip191_hash};
+    use crate::test_support::{address, wallet, KEY_A, KEY_B};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    struct MockResolver(HashMap<String, Vec<String>>);
+
+    #[async_trait]
+    impl TxtResolver for MockResolver {
+        async fn lookup_txt(&self, name: &str) -> Result<Vec<String>> {
+            Ok(self.0.get(name).cloned().unwrap_or_default())
+        }
+    }
+
+    async fn signed_by(key: &str) -> Value {
+        let wallet = wallet(key);
+        let mut snap = json!({
+            "id": "snap-1",
+            "body": { "k": 1 },
+            "signing": { "scheme": "eip191" }
+        });
+        let hash = payload_hash_keccak(&snap).unwrap();
+        let (sig, _) = sign_eip191_hash(&wallet, hash).await.unwrap();
+        attach_signature(&mut snap, hash, &sig).unwrap();
+        snap
+    }
+
+    fn resolver_for(records: Vec<String>) -> MockResolver {
+        MockResolver(HashMap::from([(
+            "_swarmhive.example.com".to_string(),
+            records,
+        )]))
+    }
+
+    #[tokio::test]
+    async fn passes_only_for_published_signer() {
+        let addr_a = address(KEY_A);
+        let resolver = resolver_for(vec![format!("swarmhive-signer={addr_a:?}")]);
+
+        let good = signed_by(KEY_A).await;
+        assert_eq!(
+            verify_dns(&good, "example.com", &resolver).await.unwrap(),
+            addr_a
+        );
+
+        let other = signed_by(KEY_B).await;
+        let err = verify_dns(&other, "example.com", &resolver)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("does not recover"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn tries_each_published_record() {
+        let addr_a = address(KEY_A);
+        let addr_b = address(KEY_B);
+        let resolver = resolver_for(vec![
+            "v=spf1 -all".to_string(),
+            format!("swarmhive-signer={addr_a:?}"),
+            format!("swarmhive-signer={addr_b:?}"),
+        ]);
+
+        let snap = signed_by(KEY_B).await;
+        assert_eq!(
+            verify_dns(&snap, "example.com", &resolver).await.unwrap(),
+            addr_b
+        );
+    }
+
+    #[tokio::test]
+    async fn malformed_record_does_not_hide_a_good_one() {
+        let addr_a = address(KEY_A);
+        let resolver = resolver_for(vec![
+            "swarmhive-signer=0xnot-an-address".to_string(),
+            format!("swarmhive-signer={addr_a:?}"),
+        ]);
+        let snap = signed_by(KEY_A).await;
+        assert_eq!(
+            verify_dns(&snap, "example.com", &resolver).await.unwrap(),
+            addr_a
+        );
+
+        let resolver = resolver_for(vec!["swarmhive-signer=0xnot-an-address".to_string()]);
+        let err = verify_dns(&snap, "example.com", &resolver)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("bad swarmhive-signer= value"),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_record_is_an_error() {
+        let resolver = resolver_for(vec!["v=spf1 -all".to_string()]);
+        let snap = signed_by(KEY_A).await;
+        let err = verify_dns(&snap, "example.com", &resolver)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no signer TXT record"), "{err}");
+    }
+}
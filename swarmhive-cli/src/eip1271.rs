@@ -0,0 +1,358 @@
+//! Contract signatures (EIP-1271)
+//!
+//! A snapshot signed by a smart-contract wallet names the wallet in
+//! `signing.contract` and carries whatever bytes the wallet accepts in
+//! `signing.signature`. Nothing can be recovered from those bytes, so
+//! verification asks the contract: `isValidSignature(payload hash,
+//! signature)` must return the EIP-1271 magic value. Under `safe-eip1271`
+//! the bytes are Safe owner signatures over the payload hash wrapped in the
+//! Safe's EIP-712 `SafeMessage` domain. The Safe does that wrapping itself,
+//! so it is given the bare payload hash too; `safe_message_hash` is only
+//! for recovering owners locally. The calls go through
+//! `ContractSignatureReader` so a provider can be swapped for a mock.
+
+use crate::quorum::{self, eth_call};
+use crate::signing::{self, hash_str, parse_payload_hash, payload_hash_keccak};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::abi::{self, ParamType, Token};
+use ethers::core::types::{Address, Signature, H256, U256};
+use ethers::providers::{JsonRpcClient, Provider};
+use ethers::utils::keccak256;
+use serde_json::Value;
+
+/// signing.scheme of a snapshot whose contract checks the payload hash
+pub const EIP1271_SCHEME: &str = "eip1271";
+/// signing.scheme of a snapshot whose Safe checks the SafeMessage hash of
+/// the payload hash
+pub const SAFE_EIP1271_SCHEME: &str = "safe-eip1271";
+
+/// `isValidSignature(bytes32,bytes)` selector, which is also the value a
+/// contract returns to accept a signature
+pub const EIP1271_MAGIC: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+/// `domainSeparator()` selector
+const DOMAIN_SEPARATOR: [u8; 4] = [0xf6, 0x98, 0xda, 0x25];
+/// keccak256("SafeMessage(bytes message)")
+const SAFE_MSG_TYPEHASH: [u8; 32] = [
+    0x60, 0xb3, 0xcb, 0xf8, 0xb4, 0xa2, 0x23, 0xd6, 0x8d, 0x64, 0x1b, 0x3b, 0x6d, 0xdf, 0x9a, 0x29,
+    0x8e, 0x7f, 0x33, 0x71, 0x0c, 0xf3, 0xd3, 0xa9, 0xd1, 0x14, 0x6b, 0x5a, 0x61, 0x50, 0xfb, 0xca,
+];
+
+/// The contract calls signature verification needs
+#[async_trait]
+pub trait ContractSignatureReader {
+    /// The 4 bytes `contract.isValidSignature(hash, signature)` returns
+    async fn is_valid_signature(
+        &self,
+        contract: Address,
+        hash: [u8; 32],
+        signature: &[u8],
+    ) -> Result<[u8; 4]>;
+    /// A Safe's EIP-712 domain separator
+    async fn domain_separator(&self, safe: Address) -> Result<[u8; 32]>;
+}
+
+#[async_trait]
+impl<P: JsonRpcClient> ContractSignatureReader for Provider<P> {
+    async fn is_valid_signature(
+        &self,
+        contract: Address,
+        hash: [u8; 32],
+        signature: &[u8],
+    ) -> Result<[u8; 4]> {
+        let mut data = EIP1271_MAGIC.to_vec();
+        data.extend(abi::encode(&[
+            Token::FixedBytes(hash.to_vec()),
+            Token::Bytes(signature.to_vec()),
+        ]));
+        let out = eth_call(self, contract, data).await?;
+        match abi::decode(&[ParamType::FixedBytes(4)], &out)?.pop() {
+            Some(Token::FixedBytes(b)) if b.len() == 4 => Ok([b[0], b[1], b[2], b[3]]),
+            _ => Err(anyhow!("isValidSignature returned an unexpected value")),
+        }
+    }
+
+    async fn domain_separator(&self, safe: Address) -> Result<[u8; 32]> {
+        let out = eth_call(self, safe, DOMAIN_SEPARATOR.to_vec()).await?;
+        out.as_ref()
+            .try_into()
+            .map_err(|_| anyhow!("domainSeparator returned {} bytes, expected 32", out.len()))
+    }
+}
+
+/// The hash a Safe with `domain_separator` has its owners sign for
+/// `payload_hash`: keccak256(0x19 0x01 || domain separator ||
+/// keccak256(SAFE_MSG_TYPEHASH || keccak256(payload hash)))
+pub fn safe_message_hash(domain_separator: [u8; 32], payload_hash: [u8; 32]) -> [u8; 32] {
+    let mut struct_hash = SAFE_MSG_TYPEHASH.to_vec();
+    struct_hash.extend_from_slice(&keccak256(payload_hash));
+    let mut preimage = vec![0x19, 0x01];
+    preimage.extend_from_slice(&domain_separator);
+    preimage.extend_from_slice(&keccak256(struct_hash));
+    keccak256(preimage)
+}
+
+/// The owners whose ECDSA signatures (v of 27 or 28) are among a
+/// `safe-eip1271` snapshot's signature bytes, recovered locally over the
+/// SafeMessage hash under the Safe's domain separator. Contract,
+/// approved-hash and eth_sign entries are skipped. This says who signed,
+/// not whether the Safe accepts it; that is `verify_contract_signature`.
+pub async fn safe_owner_signers<R: ContractSignatureReader + ?Sized>(
+    snapshot: &Value,
+    reader: &R,
+) -> Result<Vec<Address>> {
+    let signing = signing::signing_object(snapshot)?;
+    if signing::string_field(signing, "scheme")? != Some(SAFE_EIP1271_SCHEME) {
+        return Err(anyhow!(
+            "owner signatures are only defined for {SAFE_EIP1271_SCHEME}"
+        ));
+    }
+    let (contract, hash, signature) = contract_signature_parts(snapshot)?;
+    let message = safe_message_hash(reader.domain_separator(contract).await?, hash);
+
+    // Contract signatures (v = 0) point into a dynamic tail after the
+    // 65-byte entries; the first such offset ends the entries
+    let entries = signature.chunks_exact(65);
+    let static_len = entries
+        .clone()
+        .filter(|e| e[64] == 0)
+        .map(|e| {
+            U256::from_big_endian(&e[32..64])
+                .min(U256::from(signature.len()))
+                .as_usize()
+        })
+        .fold(signature.len(), usize::min);
+    signature[..static_len]
+        .chunks_exact(65)
+        .filter(|e| matches!(e[64], 27 | 28))
+        .map(|e| {
+            let sig =
+                Signature::try_from(e).map_err(|err| anyhow!("bad owner signature: {err}"))?;
+            Ok(sig.recover(H256::from(message))?)
+        })
+        .collect()
+}
+
+/// Set signing.payload_hash and attach a contract wallet's `signature`.
+/// signing.scheme and signing.contract must already be set, since both
+/// are part of the payload hash. Returns the payload hash.
+pub fn attach_contract_signature(snapshot: &mut Value, signature: &[u8]) -> Result<[u8; 32]> {
+    let hash = payload_hash_keccak(snapshot)?;
+    let signing = snapshot
+        .get_mut("signing")
+        .and_then(|v| v.as_object_mut())
+        .ok_or_else(|| anyhow!("missing signing object"))?;
+    signing.insert("payload_hash".to_string(), hash_str(hash).into());
+    signing.insert(
+        "signature".to_string(),
+        format!("0x{}", hex::encode(signature)).into(),
+    );
+    Ok(hash)
+}
+
+/// Verify a snapshot signed under `eip1271` or `safe-eip1271` by asking
+/// the contract in signing.contract. Returns the contract address.
+pub async fn verify_contract_signature<R: ContractSignatureReader + ?Sized>(
+    snapshot: &Value,
+    reader: &R,
+) -> Result<Address> {
+    let (contract, hash, signature) = contract_signature_parts(snapshot)?;
+    let answer = match reader.is_valid_signature(contract, hash, &signature).await {
+        Err(e) => match quorum::reverted(&e) {
+            // Safes and many wallets revert rather than return a non-magic value
+            Some(reverted) => {
+                return Err(anyhow!("{contract:?} rejected the signature ({reverted})"))
+            }
+            None => return Err(e),
+        },
+        Ok(answer) => answer,
+    };
+    match answer {
+        EIP1271_MAGIC => Ok(contract),
+        other => Err(anyhow!(
+            "{contract:?} rejected the signature (isValidSignature returned 0x{})",
+            hex::encode(other)
+        )),
+    }
+}
+
+/// signing.contract, the checked payload hash and the signature bytes of a
+/// contract-signed snapshot
+fn contract_signature_parts(snapshot: &Value) -> Result<(Address, [u8; 32], Vec<u8>)> {
+    let signing = signing::signing_object(snapshot)?;
+    let scheme = signing::string_field(signing, "scheme")?;
+    if !matches!(scheme, Some(EIP1271_SCHEME | SAFE_EIP1271_SCHEME)) {
+        return Err(anyhow!("not a contract signature scheme: {scheme:?}"));
+    }
+    let contract = signing::string_field(signing, "contract")?.ok_or_else(|| {
+        anyhow!(
+            "{} scheme requires signing.contract",
+            scheme.unwrap_or_default()
+        )
+    })?;
+    let contract: Address = contract
+        .parse()
+        .map_err(|e| anyhow!("bad signing.contract {contract:?}: {e}"))?;
+
+    let stored = signing::string_field(signing, "payload_hash")?
+        .ok_or_else(|| anyhow!("missing signing.payload_hash"))?;
+    let hash = parse_payload_hash(stored)?;
+    if payload_hash_keccak(snapshot)? != hash {
+        return Err(anyhow!("payload_hash mismatch: snapshot content changed"));
+    }
+    let signature = signing::string_field(signing, "signature")?
+        .ok_or_else(|| anyhow!("missing signing.signature"))?;
+    let signature = hex::decode(signature.trim_start_matches("0x"))
+        .map_err(|e| anyhow!("bad signing.signature: {e}"))?;
+    Ok((contract, hash, signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::HashSigner;
+    use crate::test_support::{wallet, KEY_A, KEY_B};
+    use serde_json::json;
+
+    const DOMAIN_SEPARATOR_VALUE: [u8; 32] = [0x42; 32];
+
+    /// A Safe as its fallback handler behaves: it wraps the hash it is given
+    /// in its SafeMessage domain, then requires `threshold` owner ECDSA
+    /// signatures over the result
+    struct MockSafe {
+        owners: Vec<Address>,
+        threshold: usize,
+    }
+
+    #[async_trait]
+    impl ContractSignatureReader for MockSafe {
+        async fn is_valid_signature(
+            &self,
+            _contract: Address,
+            hash: [u8; 32],
+            signature: &[u8],
+        ) -> Result<[u8; 4]> {
+            let message = H256::from(safe_message_hash(DOMAIN_SEPARATOR_VALUE, hash));
+            let approvals = signature
+                .chunks_exact(65)
+                .filter_map(|e| Signature::try_from(e).ok()?.recover(message).ok())
+                .filter(|signer| self.owners.contains(signer))
+                .count();
+            Ok(if approvals >= self.threshold {
+                EIP1271_MAGIC
+            } else {
+                [0xff; 4]
+            })
+        }
+
+        async fn domain_separator(&self, _safe: Address) -> Result<[u8; 32]> {
+            Ok(DOMAIN_SEPARATOR_VALUE)
+        }
+    }
+
+    /// A plain EIP-1271 wallet that accepts one signature over the bare hash
+    struct MockWallet {
+        accepts: ([u8; 32], Vec<u8>),
+    }
+
+    #[async_trait]
+    impl ContractSignatureReader for MockWallet {
+        async fn is_valid_signature(
+            &self,
+            _contract: Address,
+            hash: [u8; 32],
+            signature: &[u8],
+        ) -> Result<[u8; 4]> {
+            Ok(if (hash, signature.to_vec()) == self.accepts {
+                EIP1271_MAGIC
+            } else {
+                [0xff; 4]
+            })
+        }
+
+        async fn domain_separator(&self, _safe: Address) -> Result<[u8; 32]> {
+            unreachable!("a plain wallet has no SafeMessage domain")
+        }
+    }
+
+    fn unsigned(scheme: &str) -> Value {
+        json!({
+            "id": "treasury-report-1",
+            "body": { "balance": "100" },
+            "signing": { "scheme": scheme, "contract": format!("{:?}", Address::repeat_byte(0x5a)) }
+        })
+    }
+
+    /// A `safe-eip1271` snapshot with owner signatures from `keys` over its
+    /// SafeMessage hash
+    async fn safe_signed(keys: &[&str]) -> Value {
+        let mut snap = unsigned(SAFE_EIP1271_SCHEME);
+        let message = H256::from(safe_message_hash(
+            DOMAIN_SEPARATOR_VALUE,
+            payload_hash_keccak(&snap).unwrap(),
+        ));
+        let mut owner_sigs = Vec::new();
+        for key in keys {
+            let owner = wallet(key);
+            owner_sigs.extend(owner.sign_digest(message).await.unwrap().to_vec());
+        }
+        attach_contract_signature(&mut snap, &owner_sigs).unwrap();
+        snap
+    }
+
+    fn owners() -> MockSafe {
+        let owner = |key| wallet(key).signer_address();
+        MockSafe {
+            owners: vec![owner(KEY_A), owner(KEY_B)],
+            threshold: 2,
+        }
+    }
+
+    #[test]
+    fn constants_match_their_definitions() {
+        assert_eq!(
+            keccak256("isValidSignature(bytes32,bytes)")[..4],
+            EIP1271_MAGIC
+        );
+        assert_eq!(keccak256("domainSeparator()")[..4], DOMAIN_SEPARATOR);
+        assert_eq!(keccak256("SafeMessage(bytes message)"), SAFE_MSG_TYPEHASH);
+    }
+
+    #[tokio::test]
+    async fn safe_wraps_the_payload_hash_itself() {
+        let safe = owners();
+        let snap = safe_signed(&[KEY_A, KEY_B]).await;
+        assert_eq!(
+            verify_contract_signature(&snap, &safe).await.unwrap(),
+            Address::repeat_byte(0x5a)
+        );
+        assert_eq!(safe_owner_signers(&snap, &safe).await.unwrap(), safe.owners);
+
+        // Below the Safe's threshold
+        let err = verify_contract_signature(&safe_signed(&[KEY_A]).await, &safe)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("rejected the signature"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn plain_wallet_checks_the_bare_hash() {
+        let mut snap = unsigned(EIP1271_SCHEME);
+        let hash = attach_contract_signature(&mut snap, &[0xab; 65]).unwrap();
+        let wallet = MockWallet {
+            accepts: (hash, vec![0xab; 65]),
+        };
+        verify_contract_signature(&snap, &wallet).await.unwrap();
+        assert!(safe_owner_signers(&snap, &wallet).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn content_change_fails_before_any_call() {
+        let mut snap = safe_signed(&[KEY_A, KEY_B]).await;
+        snap["body"]["balance"] = json!("1000000");
+        let err = verify_contract_signature(&snap, &owners())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("payload_hash mismatch"), "{err}");
+    }
+}
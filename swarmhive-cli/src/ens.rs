@@ -0,0 +1,119 @@
+//! Reverse-ENS signer labels
+//!
+//! `signer_label` turns a recovered address into friendlier output such as
+//! `alice.eth (0xAbC...)`. A reverse record is only trusted when its name
+//! forward-resolves back to the same address; anyone can set a reverse
+//! record claiming any name. The lookups are behind `EnsResolver` so an
+//! RPC provider can be swapped for a mock.
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::core::types::Address;
+use ethers::providers::{JsonRpcClient, Middleware, Provider, ProviderError};
+use ethers::utils::to_checksum;
+
+/// Source of ENS records
+#[async_trait]
+pub trait EnsResolver {
+    /// Name from the address's reverse record, if one is set
+    async fn lookup_address(&self, addr: Address) -> Result<Option<String>>;
+    /// Address `name` resolves to, if any
+    async fn resolve_name(&self, name: &str) -> Result<Option<Address>>;
+}
+
+#[async_trait]
+impl<P: JsonRpcClient> EnsResolver for Provider<P> {
+    async fn lookup_address(&self, addr: Address) -> Result<Option<String>> {
+        // ethers forward-checks too; a name it rejects as not owned is still
+        // the reverse record, and `signer_label` does its own check
+        match Middleware::lookup_address(self, addr).await {
+            Ok(name) if name.is_empty() => Ok(None),
+            Ok(name) | Err(ProviderError::EnsNotOwned(name)) => Ok(Some(name)),
+            Err(ProviderError::EnsError(_)) => Ok(None),
+            Err(e) => Err(anyhow!("reverse ENS lookup for {addr:?} failed: {e}")),
+        }
+    }
+
+    async fn resolve_name(&self, name: &str) -> Result<Option<Address>> {
+        match Middleware::resolve_name(self, name).await {
+            Ok(addr) if addr.is_zero() => Ok(None),
+            Ok(addr) => Ok(Some(addr)),
+            Err(ProviderError::EnsError(_)) => Ok(None),
+            Err(e) => Err(anyhow!("ENS lookup for {name} failed: {e}")),
+        }
+    }
+}
+
+/// `"name.eth (0x<checksummed>)"` when `addr` has a reverse record whose
+/// name resolves back to `addr`, otherwise the checksummed address alone.
+/// Lookup failures fall back to the address too: this is for display only.
+pub async fn signer_label<R: EnsResolver + ?Sized>(addr: Address, resolver: &R) -> String {
+    let checksummed = to_checksum(&addr, None);
+    match confirmed_name(addr, resolver).await {
+        Some(name) => format!("{name} ({checksummed})"),
+        None => checksummed,
+    }
+}
+
+async fn confirmed_name<R: EnsResolver + ?Sized>(addr: Address, resolver: &R) -> Option<String> {
+    let name = resolver.lookup_address(addr).await.ok()??;
+    let forward = resolver.resolve_name(&name).await.ok()??;
+    (forward == addr).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct MockEns {
+        reverse: HashMap<Address, String>,
+        forward: HashMap<String, Address>,
+    }
+
+    #[async_trait]
+    impl EnsResolver for MockEns {
+        async fn lookup_address(&self, addr: Address) -> Result<Option<String>> {
+            Ok(self.reverse.get(&addr).cloned())
+        }
+
+        async fn resolve_name(&self, name: &str) -> Result<Option<Address>> {
+            Ok(self.forward.get(name).copied())
+        }
+    }
+
+    const ALICE: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const MALLORY: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+
+    fn ens() -> MockEns {
+        let alice: Address = ALICE.parse().unwrap();
+        let mallory: Address = MALLORY.parse().unwrap();
+        MockEns {
+            // Mallory's reverse record claims alice.eth, which resolves to Alice
+            reverse: HashMap::from([
+                (alice, "alice.eth".to_string()),
+                (mallory, "alice.eth".to_string()),
+            ]),
+            forward: HashMap::from([("alice.eth".to_string(), alice)]),
+        }
+    }
+
+    #[tokio::test]
+    async fn forward_confirmed_reverse_record_is_shown() {
+        let label = signer_label(ALICE.parse().unwrap(), &ens()).await;
+        assert_eq!(label, format!("alice.eth ({ALICE})"));
+    }
+
+    #[tokio::test]
+    async fn spoofed_reverse_record_falls_back_to_address() {
+        assert_eq!(
+            signer_label(MALLORY.parse().unwrap(), &ens()).await,
+            MALLORY
+        );
+        let unnamed = "0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC";
+        assert_eq!(
+            signer_label(unnamed.parse().unwrap(), &ens()).await,
+            unnamed
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Deterministic gzip for `signing.encoding = "gzip+canonical-json"`
+//!
+//! Some producers ship gzip(canonical bytes) and sign the hash of the
+//! compressed blob. The blob is only reproducible if every header field
+//! and the compressor settings are pinned, so `compress` always writes
+//! mtime 0, no file name or comment, OS byte 255 ("unknown") and uses
+//! level `LEVEL` of the bundled miniz_oxide deflater.
+
+#[cfg(feature = "ethers")]
+use crate::signing::{self, SigningScheme};
+use anyhow::{Context, Result};
+#[cfg(feature = "ethers")]
+use ethers::core::types::{Address, Signature};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::{Compression, GzBuilder};
+#[cfg(feature = "ethers")]
+use serde_json::Value;
+use std::io::{Read, Write};
+
+/// Fixed deflate level. Changing it changes every gzip payload hash.
+pub const LEVEL: u32 = 9;
+
+/// gzip `bytes` with the pinned header and level
+pub fn compress(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder: GzEncoder<Vec<u8>> = GzBuilder::new()
+        .mtime(0)
+        .operating_system(255)
+        .write(Vec::new(), Compression::new(LEVEL));
+    encoder.write_all(bytes)?;
+    Ok(encoder.finish()?)
+}
+
+/// Inflate a gzip blob
+pub fn decompress(blob: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    GzDecoder::new(blob)
+        .read_to_end(&mut out)
+        .context("payload is not valid gzip")?;
+    Ok(out)
+}
+
+/// Check a transmitted gzip payload and its signature: decompress the
+/// blob, recompute the snapshot's payload hash from the recovered content,
+/// require it to equal keccak256(blob), and recover the signer. Returns
+/// the decompressed snapshot (without its signature) and the signer.
+#[cfg(feature = "ethers")]
+pub fn verify_compressed(
+    blob: &[u8],
+    signature: &Signature,
+    scheme: SigningScheme,
+) -> Result<(Value, Address)> {
+    let snapshot: Value =
+        serde_json::from_slice(&decompress(blob)?).context("decompressed payload is not JSON")?;
+
+    let encoding = signing::snapshot_payload_encoding(&snapshot)?;
+    if encoding != signing::PayloadEncoding::GzipCanonicalJson {
+        return Err(anyhow::anyhow!(
+            "decompressed snapshot declares signing.encoding {:?}",
+            encoding.as_str()
+        ));
+    }
+
+    let hash = crate::hash::keccak256(blob);
+    if signing::payload_hash_keccak(&snapshot)? != hash {
+        return Err(anyhow::anyhow!(
+            "gzip payload is not the deterministic compression of its canonical content"
+        ));
+    }
+
+    let signer = signing::recover_hash_signer(hash, signature, scheme)?;
+    Ok((snapshot, signer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canonical::CanonicalProfile;
+    use crate::hash::payload_preimage;
+    #[cfg(feature = "ethers")]
+    use crate::signing::{sign_snapshot, signed_parts, verify_signature};
+    #[cfg(feature = "ethers")]
+    use crate::test_support::{wallet, KEY_A};
+    use serde_json::{json, Value};
+    #[cfg(feature = "ethers")]
+    use {crate::hash::keccak256, ethers::signers::Signer};
+
+    fn snapshot() -> Value {
+        json!({
+            "id": "snap-gz",
+            "body": { "nodes": (0..50).map(|i| json!({ "n": i })).collect::<Vec<_>>() },
+            "signing": { "scheme": "eip191", "encoding": "gzip+canonical-json" }
+        })
+    }
+
+    #[test]
+    fn compression_is_byte_stable() {
+        let preimage = payload_preimage(&snapshot(), &CanonicalProfile::default()).unwrap();
+        let first = compress(&preimage).unwrap();
+        for _ in 0..5 {
+            assert_eq!(compress(&preimage).unwrap(), first);
+        }
+        // Header: magic, deflate, no flags, mtime 0, XFL 2 (max compression), OS unknown
+        assert_eq!(&first[..10], &[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 2, 255]);
+        assert_eq!(decompress(&first).unwrap(), preimage);
+        assert!(first.len() < preimage.len());
+    }
+
+    #[cfg(feature = "ethers")]
+    #[tokio::test]
+    async fn gzip_encoded_signature_verifies() {
+        let wallet = wallet(KEY_A);
+        let mut snap = snapshot();
+        let (hash, _) = sign_snapshot(&mut snap, &wallet).await.unwrap();
+
+        let preimage = payload_preimage(&snap, &CanonicalProfile::default()).unwrap();
+        let blob = compress(&preimage).unwrap();
+        assert_eq!(hash, keccak256(&blob));
+        assert_ne!(hash, keccak256(&preimage));
+        verify_signature(&snap, wallet.address()).unwrap();
+
+        // The transmitted blob verifies on its own
+        let (_, signature) = signed_parts(&snap).unwrap();
+        let (content, signer) =
+            verify_compressed(&blob, &signature, SigningScheme::Eip191).unwrap();
+        assert_eq!(signer, wallet.address());
+        assert_eq!(content["body"], snap["body"]);
+
+        // Tampered content no longer verifies
+        snap["body"]["nodes"][0]["n"] = json!(99);
+        assert!(verify_signature(&snap, wallet.address()).is_err());
+    }
+
+    #[cfg(feature = "ethers")]
+    #[tokio::test]
+    async fn rejects_non_deterministic_blob() {
+        let wallet = wallet(KEY_A);
+        let mut snap = snapshot();
+        sign_snapshot(&mut snap, &wallet).await.unwrap();
+        let (_, signature) = signed_parts(&snap).unwrap();
+
+        // Same content compressed at another level hashes differently
+        let preimage = payload_preimage(&snap, &CanonicalProfile::default()).unwrap();
+        let mut encoder = GzBuilder::new()
+            .mtime(0)
+            .write(Vec::new(), Compression::fast());
+        encoder.write_all(&preimage).unwrap();
+        let blob = encoder.finish().unwrap();
+
+        let err = verify_compressed(&blob, &signature, SigningScheme::Eip191).unwrap_err();
+        assert!(err.to_string().contains("deterministic"), "{err}");
+    }
+}
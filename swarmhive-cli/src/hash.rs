@@ -0,0 +1,761 @@
+//! Canonical bytes and payload hashes
+//!
+//! Everything needed to hash a snapshot, and nothing needed to sign one:
+//! this module depends on neither `ethers` nor any key material, so
+//! hash-only consumers can build the crate with `default-features = false`.
+//! `signing` re-exports all of it under the names it has always had.
+
+use crate::canonical::CanonicalProfile;
+use crate::gzip;
+use crate::jcs;
+use crate::protobuf;
+use anyhow::{anyhow, Result};
+use rayon::prelude::*;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+use tiny_keccak::{Hasher, Keccak};
+
+/// Keccak-256 (the Ethereum variant, not SHA3-256) of `bytes`
+pub fn keccak256(bytes: impl AsRef<[u8]>) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    hasher.update(bytes.as_ref());
+    let mut out = [0u8; 32];
+    hasher.finalize(&mut out);
+    out
+}
+
+pub fn canonical_json_bytes(v: &Value) -> Result<Vec<u8>> {
+    canonical_json_bytes_with(v, CanonicalVersion::V1)
+}
+
+/// Canonical bytes of `v` in the given canonical form
+pub fn canonical_json_bytes_with(v: &Value, version: CanonicalVersion) -> Result<Vec<u8>> {
+    // Canonical JSON: stable key order, no whitespace.
+    let mut out = Vec::new();
+    write_canonical(v, &mut out, false, version.into(), &mut Vec::new())?;
+    #[cfg(feature = "strict-canonical")]
+    check_round_trip(v, version, &out)?;
+    Ok(out)
+}
+
+/// Self-check of the writer: `bytes`, the canonical form of `v`, must
+/// parse back to `v`, so no escaping or number formatting slip can make
+/// them ambiguous. JCS reads every number as a double and may respell it
+/// (1.0 as 1), so under v2 numbers only need to parse back to the same
+/// double.
+#[cfg(any(feature = "strict-canonical", test))]
+fn check_round_trip(v: &Value, version: CanonicalVersion, bytes: &[u8]) -> Result<()> {
+    fn same(a: &Value, b: &Value, version: CanonicalVersion) -> bool {
+        match (a, b) {
+            (Value::Number(x), Value::Number(y)) if version == CanonicalVersion::V2 => {
+                x.as_f64() == y.as_f64()
+            }
+            (Value::Array(x), Value::Array(y)) => {
+                x.len() == y.len() && x.iter().zip(y).all(|(x, y)| same(x, y, version))
+            }
+            (Value::Object(x), Value::Object(y)) => {
+                x.len() == y.len()
+                    && x.iter()
+                        .all(|(k, x)| y.get(k).is_some_and(|y| same(x, y, version)))
+            }
+            _ => a == b,
+        }
+    }
+    let reparsed: Value = serde_json::from_slice(bytes)
+        .map_err(|e| anyhow!("canonical bytes do not parse back: {e}"))?;
+    if !same(&reparsed, v, version) {
+        return Err(anyhow!("canonical bytes parse back to a different value"));
+    }
+    Ok(())
+}
+
+/// Check `bytes` are already the canonical form (under the document's own
+/// signing.canonical_version) of the JSON they hold: sorted keys, no
+/// whitespace, canonical numbers. One trailing newline is tolerated.
+pub fn assert_canonical(bytes: &[u8]) -> Result<()> {
+    let body = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+    let v: Value = serde_json::from_slice(body).map_err(|e| anyhow!("not valid JSON: {e}"))?;
+    let canonical = canonical_json_bytes_with(&v, snapshot_canonical_version(&v)?)?;
+    if canonical == body {
+        return Ok(());
+    }
+    let at = canonical
+        .iter()
+        .zip(body)
+        .position(|(a, b)| a != b)
+        .unwrap_or(canonical.len().min(body.len()));
+    let context = |b: &[u8]| String::from_utf8_lossy(&b[at..(at + 24).min(b.len())]).into_owned();
+    Err(anyhow!(
+        "not canonical: first difference at byte {at}: found {:?}, expected {:?}",
+        context(body),
+        context(&canonical)
+    ))
+}
+
+/// Canonical form recorded in `signing.canonical_version`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CanonicalVersion {
+    /// serde_json output with keys sorted by byte value (the default)
+    #[default]
+    V1,
+    /// JCS, RFC 8785 (see `jcs`)
+    V2,
+}
+
+impl CanonicalVersion {
+    pub fn as_u64(&self) -> u64 {
+        match self {
+            CanonicalVersion::V1 => 1,
+            CanonicalVersion::V2 => 2,
+        }
+    }
+
+    pub fn from_u64(v: u64) -> Result<Self> {
+        match v {
+            1 => Ok(CanonicalVersion::V1),
+            2 => Ok(CanonicalVersion::V2),
+            other => Err(anyhow!("unsupported canonical_version: {other}")),
+        }
+    }
+
+    fn sort_keys(&self, keys: &mut [&String]) {
+        match self {
+            CanonicalVersion::V1 => keys.sort(),
+            CanonicalVersion::V2 => keys.sort_by(|a, b| jcs::key_order(a, b)),
+        }
+    }
+}
+
+/// What the writer needs beyond the value: the canonical version and the
+/// profile's key-order rule
+#[derive(Clone, Copy)]
+struct Form {
+    version: CanonicalVersion,
+    numeric_keys: bool,
+}
+
+impl From<CanonicalVersion> for Form {
+    fn from(version: CanonicalVersion) -> Self {
+        Form {
+            version,
+            numeric_keys: false,
+        }
+    }
+}
+
+impl Form {
+    /// With `numeric_keys`, an object whose keys all parse as integers is
+    /// ordered by value (ties such as "1" and "01" by bytes); any other
+    /// object keeps the version's order
+    fn sort_keys(&self, keys: &mut [&String]) {
+        if self.numeric_keys {
+            let parsed: Option<Vec<i128>> = keys.iter().map(|k| k.parse().ok()).collect();
+            if let Some(parsed) = parsed {
+                let mut pairs: Vec<(i128, &String)> =
+                    parsed.into_iter().zip(keys.iter().copied()).collect();
+                pairs.sort();
+                for (slot, (_, k)) in keys.iter_mut().zip(pairs) {
+                    *slot = k;
+                }
+                return;
+            }
+        }
+        self.version.sort_keys(keys)
+    }
+}
+
+/// Canonical form named by signing.canonical_version (v1 when absent)
+pub fn snapshot_canonical_version(snapshot: &Value) -> Result<CanonicalVersion> {
+    match snapshot
+        .get("signing")
+        .and_then(|s| s.get("canonical_version"))
+    {
+        None => Ok(CanonicalVersion::V1),
+        Some(v) => CanonicalVersion::from_u64(
+            v.as_u64()
+                .ok_or_else(|| anyhow!("signing.canonical_version must be an integer"))?,
+        ),
+    }
+}
+
+/// Profile recorded in signing.canonical_profile, if any
+pub fn snapshot_canonical_profile(snapshot: &Value) -> Result<Option<CanonicalProfile>> {
+    match snapshot
+        .get("signing")
+        .and_then(|s| s.get("canonical_profile"))
+    {
+        None => Ok(None),
+        Some(v) => CanonicalProfile::from_value(v)
+            .map(Some)
+            .map_err(|e| anyhow!("signing.{e}")),
+    }
+}
+
+/// Byte form the payload hash is taken over, recorded in `signing.encoding`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PayloadEncoding {
+    /// The canonical bytes themselves (the default)
+    #[default]
+    CanonicalJson,
+    /// `gzip(canonical bytes)`, compressed deterministically (see `gzip`)
+    GzipCanonicalJson,
+    /// The deterministic protobuf form of the canonical content (see `protobuf`)
+    Protobuf,
+}
+
+impl PayloadEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayloadEncoding::CanonicalJson => "canonical-json",
+            PayloadEncoding::GzipCanonicalJson => "gzip+canonical-json",
+            PayloadEncoding::Protobuf => "protobuf",
+        }
+    }
+
+    /// The bytes that are hashed, given the canonical bytes
+    pub fn encode(&self, canonical: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            PayloadEncoding::CanonicalJson => Ok(canonical),
+            PayloadEncoding::GzipCanonicalJson => gzip::compress(&canonical),
+            PayloadEncoding::Protobuf => {
+                let content: Value = serde_json::from_slice(&canonical)?;
+                Ok(protobuf::encode(&content))
+            }
+        }
+    }
+}
+
+impl FromStr for PayloadEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "canonical-json" => Ok(PayloadEncoding::CanonicalJson),
+            "gzip+canonical-json" => Ok(PayloadEncoding::GzipCanonicalJson),
+            "protobuf" => Ok(PayloadEncoding::Protobuf),
+            other => Err(anyhow!("unsupported signing.encoding: {other}")),
+        }
+    }
+}
+
+/// Payload encoding named by signing.encoding (canonical-json when absent)
+pub fn snapshot_payload_encoding(snapshot: &Value) -> Result<PayloadEncoding> {
+    match snapshot.get("signing").and_then(|s| s.get("encoding")) {
+        None => Ok(PayloadEncoding::CanonicalJson),
+        Some(v) => v
+            .as_str()
+            .ok_or_else(|| anyhow!("signing.encoding must be a string"))?
+            .parse(),
+    }
+}
+
+/// Members of the top-level signing object that are never hashed. Section
+/// signatures are independent of the whole-document one, so adding one
+/// must not change the payload hash. signing.history is an informational
+/// audit trail that grows on every re-sign.
+pub(crate) const UNHASHED_SIGNING_KEYS: [&str; 6] = [
+    "signature",
+    "signatures",
+    "payload_hash",
+    "payload_hashes",
+    "sections",
+    "history",
+];
+
+/// Arrays at least this long have their elements canonicalized on the
+/// rayon pool, then joined in order; the bytes are the same either way
+const PARALLEL_ARRAY_MIN: usize = 1024;
+
+/// One step of the path to the value being written, for error messages
+#[derive(Clone, Copy)]
+enum PathSeg<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+fn pointer(path: &[PathSeg]) -> String {
+    if path.is_empty() {
+        return "/".to_string();
+    }
+    path.iter()
+        .map(|seg| match seg {
+            PathSeg::Key(k) => format!("/{}", k.replace('~', "~0").replace('/', "~1")),
+            PathSeg::Index(i) => format!("/{i}"),
+        })
+        .collect()
+}
+
+/// Write `v` as canonical JSON without building a sorted copy. With
+/// `strip_signing`, the top-level signing object's UNHASHED_SIGNING_KEYS are
+/// skipped as they are reached, so the input is only ever read.
+fn write_canonical<'a>(
+    v: &'a Value,
+    out: &mut Vec<u8>,
+    strip_signing: bool,
+    form: Form,
+    path: &mut Vec<PathSeg<'a>>,
+) -> Result<()> {
+    match v {
+        Value::Object(map) => {
+            // serde_json's map may preserve insertion order; sort explicitly
+            let mut keys: Vec<&String> = map.keys().collect();
+            form.sort_keys(&mut keys);
+            out.push(b'{');
+            for (i, k) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                serde_json::to_writer(&mut *out, k)?;
+                out.push(b':');
+                path.push(PathSeg::Key(k));
+                match &map[k] {
+                    Value::Object(signing) if strip_signing && k == "signing" => {
+                        write_signing(signing, out, form, path)?
+                    }
+                    child => write_canonical(child, out, false, form, path)?,
+                }
+                path.pop();
+            }
+            out.push(b'}');
+        }
+        Value::Array(arr) => write_array(arr, out, form, path, PARALLEL_ARRAY_MIN)?,
+        Value::Number(n) if form.version == CanonicalVersion::V2 => {
+            let lit = jcs::format_number(n).map_err(|e| anyhow!("{e} at {}", pointer(path)))?;
+            out.extend_from_slice(lit.as_bytes());
+        }
+        _ => serde_json::to_writer(&mut *out, v)?,
+    }
+    Ok(())
+}
+
+/// Write an array, in parallel once it has `parallel_min` elements. Each
+/// element gets its own buffer so the join is a plain in-order concat, and
+/// the first failing element (by index) is the one reported.
+fn write_array<'a>(
+    arr: &'a [Value],
+    out: &mut Vec<u8>,
+    form: Form,
+    path: &mut Vec<PathSeg<'a>>,
+    parallel_min: usize,
+) -> Result<()> {
+    out.push(b'[');
+    if arr.len() >= parallel_min {
+        let prefix: &[PathSeg<'a>] = path;
+        let parts: Vec<Result<Vec<u8>>> = arr
+            .par_iter()
+            .enumerate()
+            .map(|(i, child)| {
+                let mut path = prefix.to_vec();
+                path.push(PathSeg::Index(i));
+                let mut part = Vec::new();
+                write_canonical(child, &mut part, false, form, &mut path)?;
+                Ok(part)
+            })
+            .collect();
+        for (i, part) in parts.into_iter().enumerate() {
+            if i > 0 {
+                out.push(b',');
+            }
+            out.extend_from_slice(&part?);
+        }
+    } else {
+        for (i, child) in arr.iter().enumerate() {
+            if i > 0 {
+                out.push(b',');
+            }
+            path.push(PathSeg::Index(i));
+            write_canonical(child, out, false, form, path)?;
+            path.pop();
+        }
+    }
+    out.push(b']');
+    Ok(())
+}
+
+/// Preimage of a top-level object with `bytes`, already canonical in
+/// `version`, spliced in as the member `key`. Used by `cache`.
+pub(crate) fn spliced_preimage(
+    snapshot: &Map<String, Value>,
+    key: &str,
+    bytes: &[u8],
+    version: CanonicalVersion,
+) -> Result<Vec<u8>> {
+    let spliced = key.to_string();
+    let mut keys: Vec<&String> = snapshot.keys().chain(std::iter::once(&spliced)).collect();
+    version.sort_keys(&mut keys);
+
+    let mut out = Vec::with_capacity(bytes.len() + 256);
+    let mut path = Vec::new();
+    out.push(b'{');
+    for (i, k) in keys.into_iter().enumerate() {
+        if i > 0 {
+            out.push(b',');
+        }
+        serde_json::to_writer(&mut out, k)?;
+        out.push(b':');
+        if *k == spliced {
+            out.extend_from_slice(bytes);
+            continue;
+        }
+        path.push(PathSeg::Key(k));
+        match &snapshot[k] {
+            Value::Object(signing) if k == "signing" => {
+                write_signing(signing, &mut out, version.into(), &mut path)?
+            }
+            child => write_canonical(child, &mut out, false, version.into(), &mut path)?,
+        }
+        path.pop();
+    }
+    out.push(b'}');
+    Ok(out)
+}
+
+/// The signing object minus its unhashed members
+fn write_signing<'a>(
+    map: &'a Map<String, Value>,
+    out: &mut Vec<u8>,
+    form: Form,
+    path: &mut Vec<PathSeg<'a>>,
+) -> Result<()> {
+    let mut keys: Vec<&String> = map
+        .keys()
+        .filter(|k| !UNHASHED_SIGNING_KEYS.contains(&k.as_str()))
+        .collect();
+    form.sort_keys(&mut keys);
+    out.push(b'{');
+    for (i, k) in keys.into_iter().enumerate() {
+        if i > 0 {
+            out.push(b',');
+        }
+        serde_json::to_writer(&mut *out, k)?;
+        out.push(b':');
+        path.push(PathSeg::Key(k));
+        write_canonical(&map[k], out, false, form, path)?;
+        path.pop();
+    }
+    out.push(b'}');
+    Ok(())
+}
+
+/// Compute keccak256 over canonical JSON bytes, excluding the members of
+/// the signing object listed in `UNHASHED_SIGNING_KEYS` (signatures, the
+/// payload hash itself, section signatures, history). They are skipped
+/// whatever they hold, so a presign snapshot with an empty
+/// `"signature": ""` placeholder hashes the same as one without the key,
+/// and as the signed result.
+pub fn payload_hash_keccak(snapshot: &Value) -> Result<[u8; 32]> {
+    payload_hash_keccak_with_profile(snapshot, &CanonicalProfile::default())
+}
+
+/// Like `payload_hash_keccak`, applying a canonicalization profile first
+pub fn payload_hash_keccak_with_profile(
+    snapshot: &Value,
+    profile: &CanonicalProfile,
+) -> Result<[u8; 32]> {
+    let encoding = snapshot_payload_encoding(snapshot)?;
+    Ok(keccak256(
+        encoding.encode(payload_preimage(snapshot, profile)?)?,
+    ))
+}
+
+/// `payload_hash_keccak` together with the length of the canonical JSON
+/// preimage. Under a non-default signing.encoding that is the size before
+/// encoding, not the number of bytes hashed. For recording snapshot sizes
+/// without a second pass.
+pub fn payload_hash_keccak_with_len(snapshot: &Value) -> Result<([u8; 32], usize)> {
+    let encoding = snapshot_payload_encoding(snapshot)?;
+    let preimage = payload_preimage(snapshot, &CanonicalProfile::default())?;
+    let len = preimage.len();
+    Ok((keccak256(encoding.encode(preimage)?), len))
+}
+
+/// The exact canonical bytes the payload hash is computed over.
+/// A profile recorded in signing.canonical_profile is used in place of
+/// `profile`, which must then be the default or the same profile.
+/// Never mutates or clones `snapshot` under the default profile; other
+/// profiles rewrite a copy first.
+pub fn payload_preimage(snapshot: &Value, profile: &CanonicalProfile) -> Result<Vec<u8>> {
+    let version = snapshot_canonical_version(snapshot)?;
+    let recorded = snapshot_canonical_profile(snapshot)?;
+    let profile = match &recorded {
+        Some(recorded) if *profile == CanonicalProfile::default() || profile == recorded => {
+            recorded
+        }
+        Some(_) => {
+            return Err(anyhow!(
+                "signing.canonical_profile differs from the requested profile"
+            ))
+        }
+        None => profile,
+    };
+    let mut out = Vec::new();
+    if *profile == CanonicalProfile::default() {
+        write_canonical(snapshot, &mut out, true, version.into(), &mut Vec::new())?;
+    } else {
+        let form = Form {
+            version,
+            numeric_keys: profile.numeric_key_sort,
+        };
+        write_canonical(
+            &profile.apply(snapshot)?,
+            &mut out,
+            true,
+            form,
+            &mut Vec::new(),
+        )?;
+    }
+    Ok(out)
+}
+
+/// Payload hash as a "keccak256:<hex>" string
+pub fn payload_hash(snapshot: &Value) -> Result<String> {
+    Ok(hash_str(payload_hash_keccak(snapshot)?))
+}
+
+/// Convert [u8;32] to "keccak256:<hex>"
+pub fn hash_str(hash: [u8; 32]) -> String {
+    format!("keccak256:{}", hex::encode(hash))
+}
+
+/// Parse a "keccak256:<hex>" payload hash
+pub fn parse_payload_hash(payload_hash_str: &str) -> Result<[u8; 32]> {
+    let hash_hex = payload_hash_str
+        .strip_prefix("keccak256:")
+        .ok_or_else(|| anyhow!("payload_hash must start with keccak256:"))?;
+    let hash_bytes = hex::decode(hash_hex)?;
+    if hash_bytes.len() != 32 {
+        return Err(anyhow!("payload hash must be 32 bytes"));
+    }
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&hash_bytes);
+    Ok(hash)
+}
+
+/// Digest algorithm of a `signing.payload_hashes` entry. Each is taken
+/// over the same encoded canonical bytes as the keccak payload hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlg {
+    Keccak256,
+    Sha256,
+}
+
+impl HashAlg {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashAlg::Keccak256 => "keccak256",
+            HashAlg::Sha256 => "sha256",
+        }
+    }
+
+    pub fn digest(&self, bytes: &[u8]) -> [u8; 32] {
+        match self {
+            HashAlg::Keccak256 => keccak256(bytes),
+            HashAlg::Sha256 => Sha256::digest(bytes).into(),
+        }
+    }
+}
+
+impl FromStr for HashAlg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "keccak256" => Ok(HashAlg::Keccak256),
+            "sha256" => Ok(HashAlg::Sha256),
+            other => Err(anyhow!("unsupported hash algorithm: {other}")),
+        }
+    }
+}
+
+/// Payload hash of `snapshot` under `alg`
+pub fn payload_hash_with_alg(snapshot: &Value, alg: HashAlg) -> Result<[u8; 32]> {
+    let encoding = snapshot_payload_encoding(snapshot)?;
+    Ok(alg.digest(&encoding.encode(payload_preimage(snapshot, &CanonicalProfile::default())?)?))
+}
+
+/// "<alg>:<hex>", the `signing.payload_hashes` entry form
+pub fn alg_hash_str(alg: HashAlg, hash: [u8; 32]) -> String {
+    format!("{}:{}", alg.as_str(), hex::encode(hash))
+}
+
+/// Parse a "<alg>:<hex>" entry
+pub fn parse_alg_hash(s: &str) -> Result<(HashAlg, [u8; 32])> {
+    let (alg, hash_hex) = s
+        .split_once(':')
+        .ok_or_else(|| anyhow!("hash {s:?} must be <alg>:<hex>"))?;
+    let bytes = hex::decode(hash_hex).map_err(|e| anyhow!("hash {s:?} is not hex: {e}"))?;
+    let hash = bytes
+        .try_into()
+        .map_err(|_| anyhow!("hash {s:?} must be 32 bytes"))?;
+    Ok((alg.parse()?, hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn hash_with_len_reports_preimage_size() {
+        let snap = json!({ "id": "snap-1", "body": { "nodes": [1, 2, 3], "name": "ä" }, "signing": { "scheme": "eip191" } });
+        let (hash, len) = payload_hash_keccak_with_len(&snap).unwrap();
+        assert_eq!(hash, payload_hash_keccak(&snap).unwrap());
+        assert_eq!(len, canonical_json_bytes(&snap).unwrap().len());
+
+        // Signature fields are not part of the preimage, so not of its length
+        let mut signed = snap.clone();
+        signed["signing"]["signature"] = json!("0xabc");
+        assert_eq!(payload_hash_keccak_with_len(&signed).unwrap(), (hash, len));
+
+        // Under gzip the length is still that of the canonical JSON
+        let mut gzipped = snap.clone();
+        gzipped["signing"]["encoding"] = json!("gzip+canonical-json");
+        let preimage = payload_preimage(&gzipped, &CanonicalProfile::default()).unwrap();
+        let (hash, len) = payload_hash_keccak_with_len(&gzipped).unwrap();
+        assert_eq!(hash, payload_hash_keccak(&gzipped).unwrap());
+        assert_eq!(len, preimage.len());
+        assert_ne!(
+            len,
+            PayloadEncoding::GzipCanonicalJson
+                .encode(preimage)
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn parallel_array_bytes_match_serial() {
+        let nodes: Vec<Value> = (0..50_000)
+            .map(|i| json!({ "id": format!("node-{i}"), "gpus": [i % 8, i % 3], "score": i as f64 / 7.0 }))
+            .collect();
+        let snap = json!({ "id": "inventory", "body": { "nodes": nodes } });
+        let arr = snap["body"]["nodes"].as_array().unwrap();
+
+        for version in [CanonicalVersion::V1, CanonicalVersion::V2] {
+            let mut serial = Vec::new();
+            write_array(
+                arr,
+                &mut serial,
+                version.into(),
+                &mut Vec::new(),
+                usize::MAX,
+            )
+            .unwrap();
+            let mut parallel = Vec::new();
+            write_array(
+                arr,
+                &mut parallel,
+                version.into(),
+                &mut Vec::new(),
+                PARALLEL_ARRAY_MIN,
+            )
+            .unwrap();
+            assert_eq!(serial, parallel);
+        }
+
+        let expected = {
+            let mut out = br#"{"body":{"nodes":"#.to_vec();
+            write_array(
+                arr,
+                &mut out,
+                CanonicalVersion::V1.into(),
+                &mut Vec::new(),
+                usize::MAX,
+            )
+            .unwrap();
+            out.extend_from_slice(br#"},"id":"inventory"}"#);
+            out
+        };
+        assert_eq!(
+            payload_preimage(&snap, &CanonicalProfile::default()).unwrap(),
+            expected
+        );
+        assert_eq!(payload_hash_keccak(&snap).unwrap(), keccak256(&expected));
+    }
+
+    #[test]
+    fn parallel_array_reports_first_bad_element() {
+        let mut items: Vec<Value> = (0..PARALLEL_ARRAY_MIN * 2).map(|i| json!(i)).collect();
+        items[1500] = json!(u64::MAX);
+        items[1700] = json!(u64::MAX);
+        let snap = json!({ "items": items, "signing": { "canonical_version": 2 } });
+        let err = payload_preimage(&snap, &CanonicalProfile::default()).unwrap_err();
+        assert!(err.to_string().contains("at /items/1500"), "{err}");
+    }
+
+    #[cfg(feature = "ethers")]
+    #[test]
+    fn keccak_matches_ethers() {
+        let long = vec![0xabu8; 10_000];
+        let inputs: [&[u8]; 6] = [b"", b"a", b"abc", &[0u8; 135], &[0u8; 136], &long];
+        for input in inputs {
+            assert_eq!(
+                keccak256(input),
+                ethers::utils::keccak256(input),
+                "{} bytes",
+                input.len()
+            );
+        }
+        let snap = json!({ "id": "snap-1", "body": { "n": 1 }, "signing": { "scheme": "eip191" } });
+        let preimage = payload_preimage(&snap, &CanonicalProfile::default()).unwrap();
+        assert_eq!(
+            payload_hash_keccak(&snap).unwrap(),
+            ethers::utils::keccak256(preimage)
+        );
+    }
+
+    #[test]
+    fn empty_signature_hashes_like_absent() {
+        let empty = json!({ "id": "snap-1", "signing": { "scheme": "eip191", "signature": "" } });
+        let absent = json!({ "id": "snap-1", "signing": { "scheme": "eip191" } });
+        assert_eq!(
+            payload_hash_keccak(&empty).unwrap(),
+            payload_hash_keccak(&absent).unwrap()
+        );
+        assert_eq!(
+            payload_hash_keccak(&json!({ "signing": { "signature": "" } })).unwrap(),
+            payload_hash_keccak(&json!({ "signing": {} })).unwrap()
+        );
+    }
+
+    #[test]
+    fn canonical_bytes_round_trip() {
+        let inputs = [
+            json!({ "b": 1, "a": [true, false, null], "c": { "z": { "y": [[], {}, [{ "x": "deep" }]] } } }),
+            json!({ "é": "ünïcödé ✓ 😀", "esc": "\"\\/\u{0}\u{1f}\n\t\u{7f}\u{2028}", "": "empty key" }),
+            json!([
+                0,
+                -1,
+                1.5,
+                -0.0,
+                1e300,
+                5e-324,
+                0.1,
+                u64::MAX,
+                i64::MIN,
+                12345678901234567890u64
+            ]),
+            json!("top-level string"),
+            json!(null),
+        ];
+        for input in &inputs {
+            for version in [CanonicalVersion::V1, CanonicalVersion::V2] {
+                let Ok(bytes) = canonical_json_bytes_with(input, version) else {
+                    // JCS refuses integers it cannot represent exactly
+                    assert_eq!(version, CanonicalVersion::V2);
+                    continue;
+                };
+                check_round_trip(input, version, &bytes)
+                    .unwrap_or_else(|e| panic!("{input} ({version:?}): {e}"));
+            }
+        }
+        // A writer bug would be caught: bytes for another value fail the check
+        let bytes = canonical_json_bytes(&json!({ "a": 1 })).unwrap();
+        assert!(check_round_trip(&json!({ "a": 2 }), CanonicalVersion::V1, &bytes).is_err());
+        assert!(
+            check_round_trip(&json!({ "a": 1.5 }), CanonicalVersion::V2, b"{\"a\":1.25}").is_err()
+        );
+        assert!(check_round_trip(&json!({ "a": 1.0 }), CanonicalVersion::V2, b"{\"a\":1}").is_ok());
+    }
+}
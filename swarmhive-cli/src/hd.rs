@@ -0,0 +1,182 @@
+//! Hierarchical key helpers
+//!
+//! Operators can publish a BIP-32 extended public key (xpub) and derive one
+//! address per node from it with non-hardened child derivation. A verifier
+//! holding the xpub computes the expected address for node `index` without
+//! contacting the operator.
+//!
+//! Separately, `wallet_for_id` derives a one-off signing key per snapshot id
+//! from a master seed, so a leaked sub-key exposes a single snapshot.
+
+use crate::signing;
+use anyhow::{anyhow, Result};
+use coins_bip32::enc::{MainnetEncoder, XKeyEncoder};
+use coins_bip32::xkeys::Parent;
+use coins_bip32::BIP32_HARDEN;
+use ethers::core::k256::ecdsa::SigningKey;
+use ethers::core::types::Address;
+use ethers::signers::{LocalWallet, Signer};
+use ethers::utils::public_key_to_address;
+use hkdf::Hkdf;
+use serde_json::Value;
+use sha2::Sha256;
+
+/// HKDF salt for per-snapshot sub-keys; bump the version to change derivation
+const SUBKEY_SALT: &[u8] = b"swarmhive/snapshot-subkey/v1";
+
+/// Address of the non-hardened child `index` of `xpub`.
+pub fn address_from_xpub(xpub: &str, index: u32) -> Result<Address> {
+    if index >= BIP32_HARDEN {
+        return Err(anyhow!(
+            "index {index} is hardened; hardened children cannot be derived from an xpub"
+        ));
+    }
+    let parent =
+        MainnetEncoder::xpub_from_base58(xpub.trim()).map_err(|e| anyhow!("invalid xpub: {e}"))?;
+    let child = parent
+        .derive_child(index)
+        .map_err(|e| anyhow!("xpub derivation failed at index {index}: {e}"))?;
+    Ok(public_key_to_address(child.as_ref()))
+}
+
+/// Derive the sub-key for `snapshot_id` as HKDF-SHA256(master_seed, id).
+/// Anyone holding the master seed can recompute the address from the id.
+pub fn wallet_for_id(master_seed: &[u8], snapshot_id: &str) -> Result<(LocalWallet, Address)> {
+    if snapshot_id.is_empty() {
+        return Err(anyhow!("snapshot id must not be empty"));
+    }
+    let mut okm = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(SUBKEY_SALT), master_seed)
+        .expand(snapshot_id.as_bytes(), &mut okm)
+        .map_err(|e| anyhow!("sub-key derivation failed: {e}"))?;
+
+    // A zero or out-of-range scalar is astronomically unlikely but not valid
+    let key = SigningKey::from_bytes((&okm).into()).map_err(|_| {
+        anyhow!("derived sub-key for {snapshot_id:?} is not a valid secp256k1 scalar")
+    })?;
+    let wallet = LocalWallet::from(key);
+    let address = wallet.address();
+    Ok((wallet, address))
+}
+
+/// Sign `snapshot` with the sub-key for its `id`, recording the derived
+/// address in signing.signer (which is covered by the hash).
+pub async fn sign_with_subkey(snapshot: &mut Value, master_seed: &[u8]) -> Result<Address> {
+    let id = snapshot
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("snapshot missing id"))?
+        .to_string();
+    let (wallet, address) = wallet_for_id(master_seed, &id)?;
+
+    snapshot
+        .get_mut("signing")
+        .and_then(|v| v.as_object_mut())
+        .ok_or_else(|| anyhow!("snapshot missing signing object"))?
+        .insert("signer".to_string(), Value::String(format!("{address:?}")));
+
+    signing::sign_snapshot(snapshot, &wallet).await?;
+    Ok(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coins_bip32::primitives::Hint;
+    use coins_bip32::xkeys::XPriv;
+    use ethers::signers::coins_bip39::{English, Mnemonic};
+    use ethers::signers::MnemonicBuilder;
+    use serde_json::json;
+
+    const PHRASE: &str = "test test test test test test test test test test test junk";
+
+    /// m/44'/60'/0'/0 of PHRASE, as published by an operator
+    const KNOWN_XPUB: &str = "xpub6DyUKdwoLWmUJ4Tn9Bbsdtx7B5Ws18mEN19e5HT52ikE53FiUheSQXrZUNPovqfyKmw4579A1Mm3GXXKM39N64uooBfJ4tNAzFsEbodRTx4";
+
+    /// xpub of m/44'/60'/0'/0 for PHRASE
+    fn account_xpub() -> String {
+        let mnemonic = Mnemonic::<English>::new_from_phrase(PHRASE).unwrap();
+        let seed = mnemonic.to_seed(None).unwrap();
+        let root = XPriv::root_from_seed(&seed, Some(Hint::Legacy)).unwrap();
+        let account = root.derive_path("m/44'/60'/0'/0").unwrap();
+        MainnetEncoder::xpub_to_base58(&account.verify_key()).unwrap()
+    }
+
+    fn mnemonic_address(index: u32) -> Address {
+        MnemonicBuilder::<English>::default()
+            .phrase(PHRASE)
+            .index(index)
+            .unwrap()
+            .build()
+            .unwrap()
+            .address()
+    }
+
+    #[test]
+    fn xpub_children_match_mnemonic_addresses() {
+        let xpub = account_xpub();
+        assert_eq!(xpub, KNOWN_XPUB);
+
+        for index in [0, 1] {
+            assert_eq!(
+                address_from_xpub(&xpub, index).unwrap(),
+                mnemonic_address(index)
+            );
+        }
+        assert_eq!(
+            address_from_xpub(&xpub, 0).unwrap(),
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+                .parse::<Address>()
+                .unwrap()
+        );
+        assert_ne!(
+            address_from_xpub(&xpub, 0).unwrap(),
+            address_from_xpub(&xpub, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_hardened_index() {
+        let err = address_from_xpub(KNOWN_XPUB, BIP32_HARDEN).unwrap_err();
+        assert!(err.to_string().contains("hardened"), "{err}");
+    }
+
+    #[test]
+    fn rejects_invalid_encoding() {
+        let err = address_from_xpub("xpub-not-base58!", 0).unwrap_err();
+        assert!(err.to_string().contains("invalid xpub"), "{err}");
+
+        // Valid base58check, but an xprv rather than an xpub
+        let mnemonic = Mnemonic::<English>::new_from_phrase(PHRASE).unwrap();
+        let root =
+            XPriv::root_from_seed(&mnemonic.to_seed(None).unwrap(), Some(Hint::Legacy)).unwrap();
+        let xprv = MainnetEncoder::xpriv_to_base58(&root).unwrap();
+        assert!(address_from_xpub(&xprv, 0).is_err());
+    }
+
+    const MASTER_SEED: &[u8] = b"operator master seed: 32+ bytes of entropy here";
+
+    #[test]
+    fn subkey_is_deterministic_per_id() {
+        let (_, a1) = wallet_for_id(MASTER_SEED, "snap-1").unwrap();
+        let (_, a2) = wallet_for_id(MASTER_SEED, "snap-1").unwrap();
+        let (_, b) = wallet_for_id(MASTER_SEED, "snap-2").unwrap();
+        let (_, other_seed) = wallet_for_id(b"another seed", "snap-1").unwrap();
+
+        assert_eq!(a1, a2);
+        assert_ne!(a1, b);
+        assert_ne!(a1, other_seed);
+        assert!(wallet_for_id(MASTER_SEED, "").is_err());
+    }
+
+    #[tokio::test]
+    async fn subkey_signature_verifies_against_recomputed_address() {
+        let mut snap =
+            json!({ "id": "snap-7", "body": { "n": 1 }, "signing": { "scheme": "eip191" } });
+        let signer = sign_with_subkey(&mut snap, MASTER_SEED).await.unwrap();
+
+        assert_eq!(snap["signing"]["signer"], json!(format!("{signer:?}")));
+        let (_, expected) = wallet_for_id(MASTER_SEED, "snap-7").unwrap();
+        signing::verify_signature(&snap, expected).unwrap();
+    }
+}
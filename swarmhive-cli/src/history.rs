@@ -0,0 +1,319 @@
+//! Signing audit trail
+//!
+//! Each sign, re-sign, rotation or touch appends an entry to
+//! `signing.history`:
+//!
+//! ```json
+//! { "action": "resign", "signer": "0x..", "at": 1700000000, "scheme": "eip191",
+//!   "payload_hash": "keccak256:..", "prev_payload_hash": "keccak256:..", "signature": "eip191:0x.." }
+//! ```
+//!
+//! The array is not part of the payload hash, so appending never changes
+//! what the current signature covers. Instead each entry carries the
+//! signature made at that step, the scheme it was made under and the
+//! payload hash it replaced, so `verify_history` can check every entry
+//! against its signer, even after a scheme change, and the chain
+//! of hashes against the current one. Rewriting or reordering entries is
+//! detected; dropping the whole array is not.
+
+use crate::signing::{self, hash_str, parse_payload_hash, parse_signature, SigningScheme};
+use anyhow::{anyhow, Result};
+use ethers::core::types::Address;
+use serde_json::{json, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// What produced a history entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryAction {
+    /// First signature
+    Sign,
+    /// Same key signed changed or refreshed content
+    Resign,
+    /// A different key replaced the signature
+    Rotate,
+    /// issued_at refreshed (`swarmhive touch`)
+    Touch,
+}
+
+impl HistoryAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryAction::Sign => "sign",
+            HistoryAction::Resign => "resign",
+            HistoryAction::Rotate => "rotate",
+            HistoryAction::Touch => "touch",
+        }
+    }
+}
+
+impl fmt::Display for HistoryAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for HistoryAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sign" => Ok(HistoryAction::Sign),
+            "resign" => Ok(HistoryAction::Resign),
+            "rotate" => Ok(HistoryAction::Rotate),
+            "touch" => Ok(HistoryAction::Touch),
+            other => Err(anyhow!("unknown history action: {other}")),
+        }
+    }
+}
+
+/// One verified `signing.history` entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub action: HistoryAction,
+    pub signer: Address,
+    pub at: i64,
+    pub scheme: SigningScheme,
+    pub payload_hash: [u8; 32],
+    pub prev_payload_hash: Option<[u8; 32]>,
+}
+
+/// Append an entry for the signature just made. Call right after signing:
+/// the entry records the current payload hash, signature, scheme and
+/// signer, and links to the previous entry's payload hash.
+pub fn record_signing(snapshot: &mut Value, action: HistoryAction, at: i64) -> Result<()> {
+    let signer = signing::recover_signer(snapshot)?;
+    let scheme = signing::snapshot_scheme(snapshot)?;
+    let signing_obj = signing::signing_object(snapshot)?;
+    let payload_hash = signing::string_field(signing_obj, "payload_hash")?
+        .ok_or_else(|| anyhow!("missing signing.payload_hash"))?
+        .to_string();
+    let signature = signing::string_field(signing_obj, "signature")?
+        .ok_or_else(|| anyhow!("history needs a string signing.signature"))?
+        .to_string();
+    let prev = match signing_obj.get("history") {
+        None => Value::Null,
+        Some(history) => history
+            .as_array()
+            .ok_or_else(|| anyhow!("signing.history must be an array"))?
+            .last()
+            .map_or(Value::Null, |last| last["payload_hash"].clone()),
+    };
+
+    let history = snapshot["signing"]
+        .as_object_mut()
+        .expect("signing checked above")
+        .entry("history")
+        .or_insert_with(|| json!([]))
+        .as_array_mut()
+        .expect("history checked above");
+    history.push(json!({
+        "action": action.as_str(),
+        "signer": format!("{signer:?}"),
+        "at": at,
+        "scheme": scheme.as_str(),
+        "payload_hash": payload_hash,
+        "prev_payload_hash": prev,
+        "signature": signature,
+    }));
+    Ok(())
+}
+
+/// Check `signing.history`: every entry's signature recovers to its listed
+/// signer over its payload hash, each links to the one before, times never
+/// go backwards, and the last entry is the current signature. Returns the
+/// entries, oldest first (empty when there is no history).
+pub fn verify_history(snapshot: &Value) -> Result<Vec<HistoryEntry>> {
+    let signing_obj = signing::signing_object(snapshot)?;
+    let Some(history) = signing_obj.get("history") else {
+        return Ok(Vec::new());
+    };
+    let history = history
+        .as_array()
+        .ok_or_else(|| anyhow!("signing.history must be an array"))?;
+    let current_scheme = signing::snapshot_scheme(snapshot)?;
+
+    let mut entries: Vec<HistoryEntry> = Vec::with_capacity(history.len());
+    for (i, raw) in history.iter().enumerate() {
+        let field = |key: &str| {
+            raw.get(key)
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow!("signing.history[{i}] missing {key}"))
+        };
+        let action: HistoryAction = field("action")?.parse()?;
+        let signer: Address = field("signer")?
+            .parse()
+            .map_err(|e| anyhow!("signing.history[{i}] bad signer: {e}"))?;
+        let at = raw
+            .get("at")
+            .and_then(|v| v.as_i64())
+            .ok_or_else(|| anyhow!("signing.history[{i}] missing at"))?;
+        let payload_hash = parse_payload_hash(field("payload_hash")?)?;
+        let prev_payload_hash = match raw.get("prev_payload_hash") {
+            None | Some(Value::Null) => None,
+            Some(_) => Some(parse_payload_hash(field("prev_payload_hash")?)?),
+        };
+
+        // Entries from before schemes were recorded used the current one
+        let scheme = match raw.get("scheme") {
+            None => current_scheme,
+            Some(_) if field("scheme")? == current_scheme.as_str() => current_scheme,
+            Some(_) => field("scheme")?
+                .parse()
+                .map_err(|e| anyhow!("signing.history[{i}] bad scheme: {e}"))?,
+        };
+        let recovered = signing::recover_hash_signer(
+            payload_hash,
+            &parse_signature(field("signature")?)?,
+            scheme,
+        )?;
+        if recovered != signer {
+            return Err(anyhow!(
+                "signing.history[{i}] signature recovers to {recovered:?}, not {signer:?}"
+            ));
+        }
+        let expected_prev = entries.last().map(|e| e.payload_hash);
+        if prev_payload_hash != expected_prev {
+            return Err(anyhow!(
+                "signing.history[{i}] does not follow the entry before it"
+            ));
+        }
+        if entries.last().is_some_and(|e| e.at > at) {
+            return Err(anyhow!(
+                "signing.history[{i}] is dated before the entry before it"
+            ));
+        }
+        entries.push(HistoryEntry {
+            action,
+            signer,
+            at,
+            scheme,
+            payload_hash,
+            prev_payload_hash,
+        });
+    }
+
+    if let Some(last) = entries.last() {
+        let current = signing::recover_signer(snapshot)?;
+        let (hash, _) = signing::signed_parts(snapshot)?;
+        if last.payload_hash != hash || last.signer != current {
+            return Err(anyhow!(
+                "last history entry ({} by {:?}) is not the current signature",
+                hash_str(last.payload_hash),
+                last.signer
+            ));
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{wallet, KEY_A, KEY_B};
+    use ethers::signers::Signer;
+
+    const NOW: i64 = 1_700_000_000;
+
+    #[tokio::test]
+    async fn resign_chains_onto_sign() {
+        let a = wallet(KEY_A);
+        let b = wallet(KEY_B);
+        let mut snap =
+            json!({ "id": "snap-1", "body": { "n": 1 }, "signing": { "scheme": "eip191" } });
+
+        let (first, _) = signing::sign_snapshot(&mut snap, &a).await.unwrap();
+        record_signing(&mut snap, HistoryAction::Sign, NOW).unwrap();
+        snap["body"]["n"] = json!(2);
+        let (second, _) = signing::sign_snapshot(&mut snap, &b).await.unwrap();
+        record_signing(&mut snap, HistoryAction::Rotate, NOW + 60).unwrap();
+
+        let history = verify_history(&snap).unwrap();
+        assert_eq!(
+            history,
+            vec![
+                HistoryEntry {
+                    action: HistoryAction::Sign,
+                    signer: a.address(),
+                    at: NOW,
+                    scheme: SigningScheme::Eip191,
+                    payload_hash: first,
+                    prev_payload_hash: None,
+                },
+                HistoryEntry {
+                    action: HistoryAction::Rotate,
+                    signer: b.address(),
+                    at: NOW + 60,
+                    scheme: SigningScheme::Eip191,
+                    payload_hash: second,
+                    prev_payload_hash: Some(first),
+                },
+            ]
+        );
+        // History is outside the hash: the current signature is unaffected
+        signing::verify_signature(&snap, b.address()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn entries_keep_the_scheme_they_were_made_under() {
+        let a = wallet(KEY_A);
+        let mut snap =
+            json!({ "id": "snap-1", "body": { "n": 1 }, "signing": { "scheme": "eip191" } });
+        signing::sign_snapshot(&mut snap, &a).await.unwrap();
+        record_signing(&mut snap, HistoryAction::Sign, NOW).unwrap();
+        snap["signing"]["scheme"] = json!("eip191-prehash");
+        signing::sign_snapshot(&mut snap, &a).await.unwrap();
+        record_signing(&mut snap, HistoryAction::Resign, NOW + 60).unwrap();
+
+        let schemes: Vec<SigningScheme> = verify_history(&snap)
+            .unwrap()
+            .iter()
+            .map(|e| e.scheme)
+            .collect();
+        assert_eq!(
+            schemes,
+            vec![SigningScheme::Eip191, SigningScheme::Eip191Prehash]
+        );
+    }
+
+    #[tokio::test]
+    async fn tampered_history_is_detected() {
+        let a = wallet(KEY_A);
+        let mut snap =
+            json!({ "id": "snap-1", "body": { "n": 1 }, "signing": { "scheme": "eip191" } });
+        for (i, action) in [HistoryAction::Sign, HistoryAction::Resign]
+            .into_iter()
+            .enumerate()
+        {
+            snap["body"]["n"] = json!(i);
+            signing::sign_snapshot(&mut snap, &a).await.unwrap();
+            record_signing(&mut snap, action, NOW + i as i64).unwrap();
+        }
+        verify_history(&snap).unwrap();
+
+        let mut swapped = snap.clone();
+        swapped["signing"]["history"]
+            .as_array_mut()
+            .unwrap()
+            .swap(0, 1);
+        assert!(verify_history(&swapped).is_err());
+
+        let mut relabelled = snap.clone();
+        relabelled["signing"]["history"][0]["signer"] =
+            json!(format!("{:?}", Address::repeat_byte(1)));
+        assert!(verify_history(&relabelled)
+            .unwrap_err()
+            .to_string()
+            .contains("recovers to"));
+
+        let mut truncated = snap.clone();
+        truncated["signing"]["history"]
+            .as_array_mut()
+            .unwrap()
+            .pop();
+        assert!(verify_history(&truncated)
+            .unwrap_err()
+            .to_string()
+            .contains("not the current signature"));
+    }
+}
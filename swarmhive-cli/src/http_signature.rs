@@ -0,0 +1,259 @@
+//! HTTP message signatures (RFC 9421 style) for webhook ingestion
+//!
+//! A snapshot POSTed as an HTTP body carries its signature in headers
+//! instead of `signing.signature`:
+//!
+//! ```text
+//! Content-Digest: keccak-256=:<base64 payload hash>:
+//! Signature-Input: sig1=("content-digest");created=1700000000;keyid="0xabc...";alg="eip191-secp256k1"
+//! Signature: sig1=:<base64 65-byte signature>:
+//! ```
+//!
+//! The digest is our canonical payload hash of the body, so reformatting
+//! the JSON in transit does not break it. The signature base is built as
+//! in RFC 9421 section 2.5 and signed as an EIP-191 personal message.
+
+use crate::signing;
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use ethers::core::types::{Address, Signature};
+use ethers::signers::{LocalWallet, Signer};
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde_json::Value;
+
+/// Label of the one signature we write and read
+pub const SIGNATURE_LABEL: &str = "sig1";
+
+/// `alg` parameter naming our signing rule
+pub const ALG: &str = "eip191-secp256k1";
+
+/// Content-Digest algorithm key for the canonical payload hash
+pub const DIGEST_ALG: &str = "keccak-256";
+
+/// Headers carrying a snapshot's signature: Content-Digest,
+/// Signature-Input and Signature. `created` is the current time.
+pub async fn build_http_signature(
+    snapshot: &Value,
+    wallet: &LocalWallet,
+    key_id: &str,
+) -> Result<HeaderMap> {
+    if key_id.contains('"') || key_id.contains('\\') {
+        return Err(anyhow!("key id must not contain quotes or backslashes"));
+    }
+    let digest = content_digest(snapshot)?;
+    let params = format!(
+        "(\"content-digest\");created={};keyid=\"{key_id}\";alg=\"{ALG}\"",
+        Utc::now().timestamp()
+    );
+    let base = signature_base(&[("content-digest", digest.as_str())], &params);
+    let sig = wallet.sign_message(base.as_bytes()).await?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert("content-digest", HeaderValue::from_str(&digest)?);
+    headers.insert(
+        "signature-input",
+        HeaderValue::from_str(&format!("{SIGNATURE_LABEL}={params}"))?,
+    );
+    headers.insert(
+        "signature",
+        HeaderValue::from_str(&format!(
+            "{SIGNATURE_LABEL}=:{}:",
+            STANDARD.encode(sig.to_vec())
+        ))?,
+    );
+    Ok(headers)
+}
+
+/// Verify `headers` against the request `body` (snapshot JSON). Checks the
+/// Content-Digest against the body's canonical payload hash, rebuilds the
+/// signature base from the covered headers and recovers the signer. A
+/// keyid that is an address must be the signer. Returns the signer.
+pub fn verify_http_signature(headers: &HeaderMap, body: &[u8]) -> Result<Address> {
+    let snapshot: Value = serde_json::from_slice(body).context("body is not valid JSON")?;
+    let expected_digest = content_digest(&snapshot)?;
+    if header(headers, "content-digest")? != expected_digest {
+        return Err(anyhow!(
+            "Content-Digest does not match the body's canonical payload hash"
+        ));
+    }
+
+    let params = labelled(header(headers, "signature-input")?)?;
+    let (components, rest) = params
+        .strip_prefix('(')
+        .and_then(|p| p.split_once(')'))
+        .ok_or_else(|| anyhow!("Signature-Input must start with a component list"))?;
+    let components: Vec<&str> = components
+        .split_whitespace()
+        .map(|c| {
+            c.strip_prefix('"')
+                .and_then(|c| c.strip_suffix('"'))
+                .ok_or_else(|| anyhow!("bad component {c}"))
+        })
+        .collect::<Result<_>>()?;
+    if !components.contains(&"content-digest") {
+        return Err(anyhow!("signature does not cover content-digest"));
+    }
+    let mut covered = Vec::with_capacity(components.len());
+    for name in &components {
+        if name.starts_with('@') {
+            return Err(anyhow!("derived component {name} is not supported"));
+        }
+        covered.push((*name, header(headers, name)?));
+    }
+
+    let param = |key: &str| {
+        rest.split(';')
+            .find_map(|p| p.strip_prefix(key).and_then(|v| v.strip_prefix('=')))
+            .map(|v| v.trim_matches('"'))
+    };
+    if param("alg") != Some(ALG) {
+        return Err(anyhow!(
+            "unsupported signature alg {:?} (expected {ALG})",
+            param("alg")
+        ));
+    }
+
+    let encoded = labelled(header(headers, "signature")?)?;
+    let raw = encoded
+        .strip_prefix(':')
+        .and_then(|s| s.strip_suffix(':'))
+        .ok_or_else(|| anyhow!("Signature must be a byte sequence (:base64:)"))?;
+    let sig = Signature::try_from(
+        STANDARD
+            .decode(raw)
+            .context("Signature is not base64")?
+            .as_slice(),
+    )?;
+
+    let base = signature_base(&covered, params);
+    let signer = sig.recover(base.as_bytes())?;
+
+    if let Some(key_id) = param("keyid") {
+        if let Ok(addr) = key_id.parse::<Address>() {
+            if addr != signer {
+                return Err(anyhow!(
+                    "signature recovers to {signer:?}, not keyid {addr:?}"
+                ));
+            }
+        }
+    }
+    Ok(signer)
+}
+
+fn content_digest(snapshot: &Value) -> Result<String> {
+    let hash = signing::payload_hash_keccak(snapshot)?;
+    Ok(format!("{DIGEST_ALG}=:{}:", STANDARD.encode(hash)))
+}
+
+/// RFC 9421 signature base: one `"name": value` line per covered
+/// component, then the signature parameters
+fn signature_base(covered: &[(&str, &str)], params: &str) -> String {
+    let mut base = String::new();
+    for (name, value) in covered {
+        base.push_str(&format!("\"{name}\": {value}\n"));
+    }
+    base.push_str(&format!("\"@signature-params\": {params}"));
+    base
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str> {
+    headers
+        .get(name)
+        .ok_or_else(|| anyhow!("missing {name} header"))?
+        .to_str()
+        .map_err(|_| anyhow!("{name} header is not visible ASCII"))
+}
+
+/// The member labelled SIGNATURE_LABEL in a dictionary header
+fn labelled(value: &str) -> Result<&str> {
+    value
+        .split(',')
+        .find_map(|member| {
+            member
+                .trim()
+                .strip_prefix(SIGNATURE_LABEL)
+                .and_then(|m| m.strip_prefix('='))
+        })
+        .ok_or_else(|| anyhow!("no {SIGNATURE_LABEL} member in {value:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{wallet, KEY_A};
+    use serde_json::json;
+
+    fn snapshot() -> Value {
+        json!({ "type": "PROOF", "id": "proof-1", "body": { "job_id": "job-1", "ok": true }, "signing": { "scheme": "eip191" } })
+    }
+
+    #[tokio::test]
+    async fn headers_verify_against_body() {
+        let wallet = wallet(KEY_A);
+        let key_id = format!("{:?}", wallet.address());
+        let headers = build_http_signature(&snapshot(), &wallet, &key_id)
+            .await
+            .unwrap();
+
+        let input = headers["signature-input"].to_str().unwrap();
+        assert!(
+            input.starts_with("sig1=(\"content-digest\");created="),
+            "{input}"
+        );
+        assert!(headers["content-digest"]
+            .to_str()
+            .unwrap()
+            .starts_with("keccak-256=:"));
+
+        // Pretty-printed in transit: same canonical content, same digest
+        let body = serde_json::to_vec_pretty(&snapshot()).unwrap();
+        assert_eq!(
+            verify_http_signature(&headers, &body).unwrap(),
+            wallet.address()
+        );
+    }
+
+    #[tokio::test]
+    async fn tampering_is_rejected() {
+        let wallet = wallet(KEY_A);
+        let headers = build_http_signature(&snapshot(), &wallet, "node-1")
+            .await
+            .unwrap();
+
+        let mut tampered = snapshot();
+        tampered["body"]["ok"] = json!(false);
+        let err =
+            verify_http_signature(&headers, &serde_json::to_vec(&tampered).unwrap()).unwrap_err();
+        assert!(
+            err.to_string().contains("Content-Digest does not match"),
+            "{err}"
+        );
+
+        // Recomputing the digest for the tampered body breaks the signature
+        let mut forged = headers.clone();
+        forged.insert(
+            "content-digest",
+            HeaderValue::from_str(&content_digest(&tampered).unwrap()).unwrap(),
+        );
+        let signer = verify_http_signature(&forged, &serde_json::to_vec(&tampered).unwrap());
+        assert!(signer.map_or(true, |s| s != wallet.address()));
+
+        // A keyid naming another address is refused
+        let mut wrong_key = build_http_signature(
+            &snapshot(),
+            &wallet,
+            &format!("{:?}", Address::repeat_byte(1)),
+        )
+        .await
+        .unwrap();
+        let err = verify_http_signature(&wrong_key, &serde_json::to_vec(&snapshot()).unwrap())
+            .unwrap_err();
+        assert!(err.to_string().contains("not keyid"), "{err}");
+        wrong_key.remove("signature");
+        assert!(
+            verify_http_signature(&wrong_key, &serde_json::to_vec(&snapshot()).unwrap()).is_err()
+        );
+    }
+}
@@ -3,17 +3,26 @@
 //! Uses local IPFS daemon via CLI or HTTP API.
 
 use anyhow::{Context, Result};
+use std::io::Write;
 use std::process::Command;
 
-/// Add content to IPFS and return the CID
+/// Add content to IPFS and return the CID. The content is written to the
+/// stdin of `ipfs add -`, which reads until the pipe is closed.
 pub fn add(content: &str) -> Result<String> {
-    let output = Command::new("ipfs")
+    let mut child = Command::new("ipfs")
         .args(["add", "-Q", "--cid-version=1", "-"])
         .stdin(std::process::Stdio::piped())
         .stdout(std::process::Stdio::piped())
         .spawn()
-        .context("Failed to spawn ipfs command")?
-        .wait_with_output()?;
+        .context("Failed to spawn ipfs command")?;
+
+    // Dropping stdin after the write closes the pipe so ipfs sees EOF
+    child
+        .stdin
+        .take()
+        .context("Failed to open ipfs stdin")?
+        .write_all(content.as_bytes())?;
+    let output = child.wait_with_output()?;
 
     if !output.status.success() {
         anyhow::bail!("ipfs add failed: {}", String::from_utf8_lossy(&output.stderr));
@@ -0,0 +1,230 @@
+//! JCS (RFC 8785) canonical form, `signing.canonical_version` 2
+//!
+//! Version 1 is serde_json's output with sorted keys. Version 2 follows the
+//! JSON Canonicalization Scheme so other languages can reproduce the bytes:
+//! keys sort by UTF-16 code units and numbers print as ECMAScript's
+//! `Number.prototype.toString`. Every number written is checked against the
+//! ES6 grammar, so a non-canonical spelling (leading zero, leading `+`,
+//! redundant exponent) can never reach the hash.
+
+use anyhow::{anyhow, Result};
+use serde_json::Number;
+use std::cmp::Ordering;
+
+/// Largest integer every IEEE-754 double parser reads back exactly
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Compare object keys by UTF-16 code units, as RFC 8785 requires
+pub fn key_order(a: &str, b: &str) -> Ordering {
+    a.encode_utf16().cmp(b.encode_utf16())
+}
+
+/// ES6 number serialization of `n`. Integers outside ±(2^53 - 1) are
+/// rejected rather than silently rounded to the nearest double.
+pub fn format_number(n: &Number) -> Result<String> {
+    let out = if let Some(i) = n.as_i64() {
+        if i.unsigned_abs() > MAX_SAFE_INTEGER {
+            return Err(anyhow!(
+                "integer {i} is not exactly representable as an IEEE-754 double"
+            ));
+        }
+        i.to_string()
+    } else if let Some(u) = n.as_u64() {
+        if u > MAX_SAFE_INTEGER {
+            return Err(anyhow!(
+                "integer {u} is not exactly representable as an IEEE-754 double"
+            ));
+        }
+        u.to_string()
+    } else {
+        let f = n
+            .as_f64()
+            .ok_or_else(|| anyhow!("number {n} is not finite"))?;
+        format_f64(f)?
+    };
+    validate_number_literal(&out)?;
+    Ok(out)
+}
+
+fn format_f64(f: f64) -> Result<String> {
+    if !f.is_finite() {
+        return Err(anyhow!("number {f} is not finite"));
+    }
+    // ryu-js implements Number.prototype.toString, including its
+    // round-half-even choice between equally short digit strings and "0"
+    // for negative zero
+    Ok(ryu_js::Buffer::new().format_finite(f).to_string())
+}
+
+/// Check `lit` is a number exactly as ES6 would print it: optional `-`, no
+/// leading `+` or zeros, no trailing fractional zeros, and an exponent only
+/// in the form `d[.ddd]e±N` with a single non-zero leading digit.
+pub fn validate_number_literal(lit: &str) -> Result<()> {
+    let bad = |why: &str| Err(anyhow!("non-canonical number {lit:?}: {why}"));
+    let unsigned = lit.strip_prefix('-').unwrap_or(lit);
+    if unsigned.starts_with('+') {
+        return bad("leading '+'");
+    }
+    if unsigned == "0" {
+        return if lit == "-0" {
+            bad("negative zero")
+        } else {
+            Ok(())
+        };
+    }
+
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((m, e)) => (m, Some(e)),
+        None => (unsigned, None),
+    };
+    let (int, frac) = match mantissa.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (mantissa, None),
+    };
+
+    if int.is_empty() || !int.bytes().all(|b| b.is_ascii_digit()) {
+        return bad("malformed integer part");
+    }
+    if int.len() > 1 && int.starts_with('0') {
+        return bad("leading zero");
+    }
+    if let Some(frac) = frac {
+        if frac.is_empty() || !frac.bytes().all(|b| b.is_ascii_digit()) {
+            return bad("malformed fraction");
+        }
+        if frac.ends_with('0') {
+            return bad("trailing zero in fraction");
+        }
+    }
+
+    if let Some(exp) = exponent {
+        if lit.contains('E') {
+            return bad("uppercase exponent");
+        }
+        if int.len() != 1 || int == "0" {
+            return bad("exponent form must have a single non-zero leading digit");
+        }
+        let digits = match exp.strip_prefix(['+', '-']) {
+            Some(d) => d,
+            None => return bad("exponent without sign"),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return bad("malformed exponent");
+        }
+        if digits.starts_with('0') {
+            return bad("redundant exponent");
+        }
+        let e: i32 = digits
+            .parse()
+            .map_err(|_| anyhow!("non-canonical number {lit:?}: exponent out of range"))?;
+        // ES6 only switches to exponent form outside [1e-7, 1e21)
+        let positive = exp.starts_with('+');
+        if (positive && e < 21) || (!positive && e < 7) {
+            return bad("exponent used inside the plain-decimal range");
+        }
+    } else if frac.is_none() && int.len() > 21 {
+        return bad("integer of 22+ digits must use exponent form");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fmt(v: serde_json::Value) -> String {
+        format_number(v.as_number().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn formats_like_es6() {
+        assert_eq!(fmt(json!(0)), "0");
+        assert_eq!(fmt(json!(-0.0)), "0");
+        assert_eq!(fmt(json!(1.0)), "1");
+        assert_eq!(fmt(json!(123.456)), "123.456");
+        assert_eq!(fmt(json!(-0.5)), "-0.5");
+        assert_eq!(fmt(json!(0.000001)), "0.000001");
+        assert_eq!(fmt(json!(1.5e-7)), "1.5e-7");
+        assert_eq!(fmt(json!(1e20)), "100000000000000000000");
+        assert_eq!(fmt(json!(1e21)), "1e+21");
+        assert_eq!(fmt(json!(-1.2345e30)), "-1.2345e+30");
+        assert_eq!(fmt(json!(9007199254740991u64)), "9007199254740991");
+    }
+
+    #[test]
+    fn rejects_unsafe_integers() {
+        let err = format_number(json!(9007199254740993u64).as_number().unwrap()).unwrap_err();
+        assert!(
+            err.to_string().contains("not exactly representable"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn rejects_non_canonical_literals() {
+        for bad in [
+            "01",
+            "-01",
+            "+1",
+            "+0.5",
+            "1.50",
+            "1.",
+            ".5",
+            "-0",
+            "1e5",
+            "1E+21",
+            "1e21",
+            "1e+021",
+            "10e+21",
+            "1.5e-07",
+            "0e+21",
+            "1.5e-6",
+            "0123456789012345678901",
+        ] {
+            assert!(validate_number_literal(bad).is_err(), "{bad} accepted");
+        }
+        for good in [
+            "0", "-1", "1.5", "0.000001", "1e+21", "1.5e-7", "-2.5e+30", "100",
+        ] {
+            validate_number_literal(good).unwrap();
+        }
+    }
+
+    #[test]
+    fn v2_preimage_uses_jcs_and_names_bad_path() {
+        use crate::canonical::CanonicalProfile;
+        use crate::hash::payload_preimage;
+
+        let snap = json!({
+            "body": { "ratio": 1.0, "tiny": 1.5e-7 },
+            "signing": { "canonical_version": 2 }
+        });
+        assert_eq!(
+            String::from_utf8(payload_preimage(&snap, &CanonicalProfile::default()).unwrap())
+                .unwrap(),
+            r#"{"body":{"ratio":1,"tiny":1.5e-7},"signing":{"canonical_version":2}}"#
+        );
+
+        let unsafe_int = json!({
+            "body": { "counts": [1, 9007199254740993u64] },
+            "signing": { "canonical_version": 2 }
+        });
+        let err = payload_preimage(&unsafe_int, &CanonicalProfile::default()).unwrap_err();
+        assert!(err.to_string().ends_with("at /body/counts/1"), "{err}");
+
+        // Version 1 keeps serde_json's spelling
+        let v1 = json!({ "body": { "ratio": 1.0 } });
+        assert_eq!(
+            payload_preimage(&v1, &CanonicalProfile::default()).unwrap(),
+            br#"{"body":{"ratio":1.0}}"#
+        );
+    }
+
+    #[test]
+    fn keys_sort_by_utf16() {
+        // U+FB01 sorts after U+1F600 by code point but before it in UTF-16
+        assert_eq!(key_order("\u{fb01}", "\u{1f600}"), Ordering::Greater);
+        assert_eq!("\u{fb01}".cmp("\u{1f600}"), Ordering::Less);
+    }
+}
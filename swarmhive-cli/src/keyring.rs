@@ -0,0 +1,227 @@
+//! Signed keyrings: distributable lists of authorized signers
+//!
+//! A coordinator publishes one snapshot-shaped `KEYRING` document listing
+//! the addresses allowed to sign, signed with its own key. A verifier that
+//! trusts only the coordinator's address checks the keyring with
+//! `load_keyring` and then holds each snapshot to the list it carried with
+//! `verify_against_allowlist`.
+
+use crate::signing;
+use crate::verify::{self, VerifyOptions};
+use anyhow::{anyhow, Context, Result};
+use ethers::core::types::Address;
+use ethers::signers::LocalWallet;
+use ethers::utils::to_checksum;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+
+/// `type` of a keyring snapshot
+pub const KEYRING_TYPE: &str = "KEYRING";
+
+/// Build and sign a keyring listing `signers` (sorted, without duplicates),
+/// issued at `now`
+pub async fn export_keyring(
+    signers: &[Address],
+    coordinator: &LocalWallet,
+    now: i64,
+) -> Result<Value> {
+    let mut signers = signers.to_vec();
+    signers.sort();
+    signers.dedup();
+    let mut keyring = json!({
+        "type": KEYRING_TYPE,
+        "body": { "signers": signers },
+        "signing": { "scheme": "eip191", "issued_at": now }
+    });
+    signing::sign_snapshot(&mut keyring, coordinator).await?;
+    Ok(keyring)
+}
+
+/// The allow-list carried by `keyring`, once it verifies as a keyring
+/// signed by `coordinator`
+pub fn load_keyring(keyring: &Value, coordinator: Address, now: i64) -> Result<Vec<Address>> {
+    let opts = VerifyOptions {
+        expected_address: Some(coordinator),
+        ..Default::default()
+    };
+    let report = verify::verify_report(keyring, &opts, now);
+    if !report.is_valid() {
+        return Err(anyhow!(
+            "keyring does not verify: {}",
+            report.errors.join("; ")
+        ));
+    }
+    if keyring["type"] != KEYRING_TYPE {
+        return Err(anyhow!("not a keyring (type {})", keyring["type"]));
+    }
+    keyring["body"]["signers"]
+        .as_array()
+        .ok_or_else(|| anyhow!("keyring missing body.signers"))?
+        .iter()
+        .map(|s| {
+            s.as_str()
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(|| anyhow!("bad keyring signer {s}"))
+        })
+        .collect()
+}
+
+/// Verify `snapshot` and require its signer to be on `allowlist`
+pub fn verify_against_allowlist(snapshot: &Value, allowlist: &[Address]) -> Result<Address> {
+    let signer = signing::recover_signer(snapshot)?;
+    if allowlist.contains(&signer) {
+        Ok(signer)
+    } else {
+        Err(anyhow!("signer {signer:?} is not on the allow-list"))
+    }
+}
+
+/// Addresses in a signers file, one per line, sorted and without
+/// duplicates. Surrounding whitespace, blank lines and lines starting with
+/// `#` are ignored. Every malformed line is reported, with its line number.
+pub fn load_allowlist(path: &str) -> Result<Vec<Address>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+    parse_allowlist(&text).map_err(|bad| {
+        let lines: Vec<String> = bad.iter().map(|e| format!("{path}:{e}")).collect();
+        anyhow!("{}", lines.join("\n"))
+    })
+}
+
+/// `load_allowlist` on text already read; the error is one
+/// `"<line>: <problem>"` per malformed line
+pub fn parse_allowlist(text: &str) -> std::result::Result<Vec<Address>, Vec<String>> {
+    let mut signers = BTreeSet::new();
+    let mut bad = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_allowlist_entry(line) {
+            Ok(addr) => {
+                signers.insert(addr);
+            }
+            Err(e) => bad.push(format!("{}: {e}", n + 1)),
+        }
+    }
+    if bad.is_empty() {
+        Ok(signers.into_iter().collect())
+    } else {
+        Err(bad)
+    }
+}
+
+/// A 0x-prefixed address; mixed case must be a valid EIP-55 checksum
+fn parse_allowlist_entry(entry: &str) -> Result<Address> {
+    let hex = entry
+        .strip_prefix("0x")
+        .filter(|hex| hex.len() == 40 && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+        .ok_or_else(|| anyhow!("{entry:?} is not a 0x-prefixed 20-byte address"))?;
+    let addr: Address = hex
+        .parse()
+        .map_err(|_| anyhow!("{entry:?} is not an address"))?;
+    let mixed_case =
+        hex.bytes().any(|b| b.is_ascii_lowercase()) && hex.bytes().any(|b| b.is_ascii_uppercase());
+    if mixed_case && to_checksum(&addr, None) != entry {
+        return Err(anyhow!("{entry:?} fails its EIP-55 checksum"));
+    }
+    Ok(addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{signed, wallet, KEY_A, KEY_B, KEY_C};
+    use ethers::signers::Signer;
+
+    const NOW: i64 = 1_700_000_000;
+
+    async fn signed_by(key: &str) -> Value {
+        signed(
+            json!({ "id": "snap-1", "signing": { "scheme": "eip191" } }),
+            key,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn exported_keyring_bootstraps_allowlist() {
+        let coordinator = wallet(KEY_C);
+        let dir = tempfile::tempdir().unwrap();
+        let signers = dir.path().join("a.txt");
+        let member = wallet(KEY_A).address();
+        std::fs::write(&signers, format!("{member:?}\n\n{member:?}\n")).unwrap();
+
+        let keyring = export_keyring(
+            &load_allowlist(signers.to_str().unwrap()).unwrap(),
+            &coordinator,
+            NOW,
+        )
+        .await
+        .unwrap();
+        let allowlist = load_keyring(&keyring, coordinator.address(), NOW).unwrap();
+        assert_eq!(allowlist, vec![member]);
+
+        assert_eq!(
+            verify_against_allowlist(&signed_by(KEY_A).await, &allowlist).unwrap(),
+            member
+        );
+        let err = verify_against_allowlist(&signed_by(KEY_B).await, &allowlist).unwrap_err();
+        assert!(err.to_string().contains("not on the allow-list"));
+    }
+
+    #[test]
+    fn allowlist_file_is_forgiving_but_strict_about_addresses() {
+        let a = wallet(KEY_A).address();
+        let b = wallet(KEY_B).address();
+        let text = format!(
+            "# coordinators\n\n  {}  \n{a:?}\n# {b:?} retired\n{}\n",
+            to_checksum(&a, None),
+            format!("{b:?}").to_uppercase().replacen("0X", "0x", 1),
+        );
+        let mut expected = vec![a, b];
+        expected.sort();
+        assert_eq!(parse_allowlist(&text).unwrap(), expected);
+
+        // Checksum broken by flipping the case of one letter, and a short address
+        let mut flipped = to_checksum(&a, None);
+        let at = flipped
+            .rfind(|c: char| c.is_ascii_alphabetic() && c != 'x')
+            .unwrap();
+        let c = flipped.as_bytes()[at] as char;
+        let swapped = if c.is_ascii_uppercase() {
+            c.to_ascii_lowercase()
+        } else {
+            c.to_ascii_uppercase()
+        };
+        flipped.replace_range(at..=at, &swapped.to_string());
+        let text = format!("{a:?}\n\n{flipped}\n# ok\n0x1234\n");
+        let errors = parse_allowlist(&text).unwrap_err();
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(
+            errors[0].starts_with("3: ") && errors[0].contains("EIP-55"),
+            "{errors:?}"
+        );
+        assert!(errors[1].starts_with("5: "), "{errors:?}");
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("signers.txt");
+        std::fs::write(&path, text).unwrap();
+        let err = load_allowlist(path.to_str().unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("signers.txt:3: "), "{err}");
+    }
+
+    #[tokio::test]
+    async fn keyring_must_come_from_coordinator() {
+        let keyring = export_keyring(&[wallet(KEY_B).address()], &wallet(KEY_A), NOW)
+            .await
+            .unwrap();
+        assert!(load_keyring(&keyring, wallet(KEY_C).address(), NOW).is_err());
+
+        // A valid snapshot from the coordinator that is not a keyring
+        let err = load_keyring(&signed_by(KEY_C).await, wallet(KEY_C).address(), NOW).unwrap_err();
+        assert!(err.to_string().contains("not a keyring"));
+    }
+}
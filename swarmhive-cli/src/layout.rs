@@ -0,0 +1,177 @@
+//! Signing-field placement
+//!
+//! By default the payload hash, signature and scheme live in the top-level
+//! `signing` object. Protocols that nest or rename these fields implement
+//! `SigningLayout` and pass it to the `*_with_layout` functions in
+//! `signing` instead of forking the crate.
+
+use crate::signing::{canonical_json_bytes, payload_hash_keccak, UNHASHED_SIGNING_KEYS};
+use anyhow::{anyhow, Result};
+use ethers::utils::keccak256;
+use serde_json::{Map, Value};
+
+/// Where a snapshot keeps its signing fields
+pub trait SigningLayout {
+    fn payload_hash<'a>(&self, snapshot: &'a Value) -> Option<&'a str>;
+    fn signature<'a>(&self, snapshot: &'a Value) -> Option<&'a str>;
+    fn scheme<'a>(&self, snapshot: &'a Value) -> Option<&'a str>;
+
+    fn set_payload_hash(&self, snapshot: &mut Value, payload_hash: &str) -> Result<()>;
+    fn set_signature(&self, snapshot: &mut Value, signature: &str) -> Result<()>;
+    fn set_scheme(&self, snapshot: &mut Value, scheme: &str) -> Result<()>;
+
+    /// Remove the fields that cannot be part of their own preimage (the
+    /// payload hash and signature); the scheme stays and is hashed
+    fn strip(&self, snapshot: &mut Value);
+
+    /// keccak256 of the canonical bytes of the stripped snapshot
+    fn payload_hash_keccak(&self, snapshot: &Value) -> Result<[u8; 32]> {
+        let mut stripped = snapshot.clone();
+        self.strip(&mut stripped);
+        Ok(keccak256(canonical_json_bytes(&stripped)?))
+    }
+}
+
+/// Today's layout: `signing.payload_hash`, `signing.signature`, `signing.scheme`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultLayout;
+
+impl DefaultLayout {
+    fn field<'a>(snapshot: &'a Value, key: &str) -> Option<&'a str> {
+        snapshot.get("signing")?.get(key)?.as_str()
+    }
+
+    fn set(snapshot: &mut Value, key: &str, value: &str) -> Result<()> {
+        signing_object_mut(snapshot)?.insert(key.to_string(), Value::String(value.to_string()));
+        Ok(())
+    }
+}
+
+impl SigningLayout for DefaultLayout {
+    fn payload_hash<'a>(&self, snapshot: &'a Value) -> Option<&'a str> {
+        Self::field(snapshot, "payload_hash")
+    }
+
+    fn signature<'a>(&self, snapshot: &'a Value) -> Option<&'a str> {
+        Self::field(snapshot, "signature")
+    }
+
+    fn scheme<'a>(&self, snapshot: &'a Value) -> Option<&'a str> {
+        Self::field(snapshot, "scheme")
+    }
+
+    fn set_payload_hash(&self, snapshot: &mut Value, payload_hash: &str) -> Result<()> {
+        Self::set(snapshot, "payload_hash", payload_hash)
+    }
+
+    fn set_signature(&self, snapshot: &mut Value, signature: &str) -> Result<()> {
+        Self::set(snapshot, "signature", signature)
+    }
+
+    fn set_scheme(&self, snapshot: &mut Value, scheme: &str) -> Result<()> {
+        Self::set(snapshot, "scheme", scheme)
+    }
+
+    fn strip(&self, snapshot: &mut Value) {
+        if let Some(signing) = snapshot.get_mut("signing").and_then(|v| v.as_object_mut()) {
+            for key in UNHASHED_SIGNING_KEYS {
+                signing.remove(key);
+            }
+        }
+    }
+
+    /// The streaming, non-cloning hash (also honours canonical_version)
+    fn payload_hash_keccak(&self, snapshot: &Value) -> Result<[u8; 32]> {
+        payload_hash_keccak(snapshot)
+    }
+}
+
+fn signing_object_mut(snapshot: &mut Value) -> Result<&mut Map<String, Value>> {
+    snapshot
+        .get_mut("signing")
+        .ok_or_else(|| anyhow!("snapshot missing signing object"))?
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("signing must be object"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::{
+        self, attach_signature_with_layout, verify_eip191_with_layout, SigningScheme,
+    };
+    use crate::test_support::{wallet, KEY_A};
+    use ethers::signers::Signer;
+    use serde_json::json;
+
+    /// `proof.hash`, `proof.sig`, `proof.alg`
+    struct ProofLayout;
+
+    impl ProofLayout {
+        fn proof(snapshot: &mut Value) -> Result<&mut Map<String, Value>> {
+            snapshot
+                .as_object_mut()
+                .ok_or_else(|| anyhow!("snapshot must be a JSON object"))?
+                .entry("proof")
+                .or_insert_with(|| json!({}))
+                .as_object_mut()
+                .ok_or_else(|| anyhow!("proof must be object"))
+        }
+    }
+
+    impl SigningLayout for ProofLayout {
+        fn payload_hash<'a>(&self, snapshot: &'a Value) -> Option<&'a str> {
+            snapshot.pointer("/proof/hash")?.as_str()
+        }
+        fn signature<'a>(&self, snapshot: &'a Value) -> Option<&'a str> {
+            snapshot.pointer("/proof/sig")?.as_str()
+        }
+        fn scheme<'a>(&self, snapshot: &'a Value) -> Option<&'a str> {
+            snapshot.pointer("/proof/alg")?.as_str()
+        }
+        fn set_payload_hash(&self, snapshot: &mut Value, payload_hash: &str) -> Result<()> {
+            Self::proof(snapshot)?.insert("hash".to_string(), json!(payload_hash));
+            Ok(())
+        }
+        fn set_signature(&self, snapshot: &mut Value, signature: &str) -> Result<()> {
+            Self::proof(snapshot)?.insert("sig".to_string(), json!(signature));
+            Ok(())
+        }
+        fn set_scheme(&self, snapshot: &mut Value, scheme: &str) -> Result<()> {
+            Self::proof(snapshot)?.insert("alg".to_string(), json!(scheme));
+            Ok(())
+        }
+        fn strip(&self, snapshot: &mut Value) {
+            if let Some(proof) = snapshot.get_mut("proof").and_then(|v| v.as_object_mut()) {
+                proof.remove("hash");
+                proof.remove("sig");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn alternate_layout_round_trip() {
+        let wallet = wallet(KEY_A);
+        let layout = ProofLayout;
+        let mut snap = json!({ "id": "job-1", "result": { "ok": true } });
+        layout.set_scheme(&mut snap, "eip191").unwrap();
+
+        let hash = layout.payload_hash_keccak(&snap).unwrap();
+        let (sig, addr) = signing::sign_hash(&wallet, hash, SigningScheme::Eip191)
+            .await
+            .unwrap();
+        attach_signature_with_layout(&mut snap, hash, &sig, SigningScheme::Eip191, &layout)
+            .unwrap();
+
+        assert!(snap.get("signing").is_none());
+        assert!(snap["proof"]["sig"]
+            .as_str()
+            .unwrap()
+            .starts_with("eip191:0x"));
+        verify_eip191_with_layout(&snap, addr, &layout).unwrap();
+        assert_eq!(addr, wallet.address());
+
+        snap["result"]["ok"] = json!(false);
+        assert!(verify_eip191_with_layout(&snap, addr, &layout).is_err());
+    }
+}
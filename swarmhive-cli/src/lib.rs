@@ -0,0 +1,86 @@
+//! SwarmHive sovereign compute CLI
+//!
+//! The binary lives in `main.rs`; everything else is exposed here so the
+//! signing and canonicalization helpers can be used as a library. With
+//! `default-features = false` only the ethers-free hashing modules
+//! (`canonical`, `hash`, `jcs`, `gzip`, `protobuf`, `cache`) plus
+//! `artifacts`, `config`, `ipfs` and `throttle` are built. The `no_std`
+//! feature adds `core_hash`, which needs only `core` and `alloc`.
+
+#[cfg(any(feature = "no_std", test))]
+extern crate alloc;
+
+#[cfg(feature = "ethers")]
+pub mod account;
+#[cfg(feature = "ethers")]
+pub mod address;
+#[cfg(feature = "ethers")]
+pub mod anchor;
+pub mod artifacts;
+#[cfg(feature = "bls")]
+pub mod bls;
+pub mod cache;
+pub mod canonical;
+#[cfg(feature = "ethers")]
+pub mod claims;
+#[cfg(feature = "ethers")]
+pub mod cli;
+#[cfg(feature = "ethers")]
+pub mod commands;
+#[cfg(feature = "ethers")]
+pub mod commitment;
+pub mod config;
+#[cfg(any(feature = "no_std", test))]
+pub mod core_hash;
+#[cfg(feature = "ethers")]
+pub mod cosign;
+#[cfg(feature = "ethers")]
+pub mod dns;
+#[cfg(feature = "ethers")]
+pub mod eip1271;
+#[cfg(feature = "ethers")]
+pub mod ens;
+pub mod gzip;
+pub mod hash;
+#[cfg(feature = "ethers")]
+pub mod hd;
+#[cfg(feature = "ethers")]
+pub mod history;
+#[cfg(feature = "ethers")]
+pub mod http_signature;
+pub mod ipfs;
+pub mod jcs;
+#[cfg(feature = "ethers")]
+pub mod keyring;
+#[cfg(feature = "ethers")]
+pub mod layout;
+#[cfg(feature = "ethers")]
+pub mod nonce;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
+#[cfg(feature = "ethers")]
+pub mod policy;
+pub mod protobuf;
+#[cfg(feature = "ethers")]
+pub mod provenance;
+#[cfg(feature = "ethers")]
+pub mod quorum;
+#[cfg(feature = "http-signer")]
+pub mod remote_signer;
+#[cfg(feature = "ethers")]
+pub mod retry;
+#[cfg(feature = "ethers")]
+pub mod rotation;
+#[cfg(feature = "ethers")]
+pub mod sections;
+#[cfg(feature = "ethers")]
+pub mod signing;
+#[cfg(feature = "ethers")]
+pub mod snapshot;
+#[cfg(all(test, feature = "ethers"))]
+pub(crate) mod test_support;
+pub mod throttle;
+#[cfg(feature = "ethers")]
+pub mod verifier;
+#[cfg(feature = "ethers")]
+pub mod verify;
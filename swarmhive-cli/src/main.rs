@@ -1,11 +1,6 @@
-mod cli;
-mod commands;
-mod config;
-mod ipfs;
-mod signing;
-
-use cli::Cli;
 use clap::Parser;
+use swarmhive::cli::{self, Cli};
+use swarmhive::commands;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -18,5 +13,28 @@ async fn main() -> anyhow::Result<()> {
         cli::Commands::Claim(cmd) => commands::claim::run(cmd).await,
         cli::Commands::Prove(cmd) => commands::prove::run(cmd).await,
         cli::Commands::Seal(cmd) => commands::seal::run(cmd).await,
+        cli::Commands::Merge(cmd) => commands::merge::run(cmd).await,
+        cli::Commands::Sign(cmd) => commands::sign::run(cmd).await,
+        cli::Commands::Verify(cmd) => commands::verify::run(cmd).await,
+        cli::Commands::VerifyStream(cmd) => commands::verify_stream::run(cmd).await,
+        cli::Commands::AuditAges(cmd) => commands::audit_ages::run(cmd).await,
+        cli::Commands::Reencode(cmd) => commands::reencode::run(cmd).await,
+        cli::Commands::VerifyBatch(cmd) => commands::verify_batch::run(cmd).await,
+        cli::Commands::Keygen(cmd) => commands::keygen::run(cmd).await,
+        cli::Commands::Auto(cmd) => commands::auto::run(cmd).await,
+        cli::Commands::Bundle(cmd) => commands::bundle::run(cmd).await,
+        cli::Commands::Lint(cmd) => commands::lint::run(cmd).await,
+        cli::Commands::Touch(cmd) => commands::touch::run(cmd).await,
+        cli::Commands::Same(cmd) => commands::same::run(cmd).await,
+        cli::Commands::Inspect(cmd) => commands::inspect::run(cmd).await,
+        cli::Commands::SignManifest(cmd) => commands::sign_manifest::run(cmd).await,
+        cli::Commands::MigrateCanonical(cmd) => commands::migrate_canonical::run(cmd).await,
+        cli::Commands::CanonDiff(cmd) => commands::canon_diff::run(cmd).await,
+        cli::Commands::Unsign(cmd) => commands::unsign::run(cmd).await,
+        cli::Commands::Signers(cmd) => commands::signers::run(cmd).await,
+        cli::Commands::Extract(cmd) => commands::extract::run(cmd).await,
+        cli::Commands::KeyringExport(cmd) => commands::keyring_export::run(cmd).await,
+        cli::Commands::VerifyArchive(cmd) => commands::verify_archive::run(cmd).await,
+        cli::Commands::CheckAnchor(cmd) => commands::check_anchor::run(cmd).await,
     }
 }
@@ -0,0 +1,198 @@
+//! Nonce replay protection
+//!
+//! A `NonceStore` remembers every `(signer, signing.nonce)` pair it has
+//! accepted. `verify::verify_report_with_nonces` consults one after the
+//! other checks pass, so a replayed snapshot fails instead of the nonce
+//! being merely advisory. Implement the trait to keep nonces in your own
+//! database; `MemoryNonceStore` and `FileNonceStore` are provided.
+
+use crate::signing;
+use anyhow::{anyhow, Context, Result};
+use ethers::core::types::Address;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Where seen nonces are recorded
+pub trait NonceStore: Send + Sync {
+    /// Record `nonce` for `signer`. Returns false, recording nothing, if the
+    /// pair was already seen.
+    fn check_and_insert(&self, signer: Address, nonce: &str) -> Result<bool>;
+}
+
+/// Nonces held in memory for the life of the process
+#[derive(Debug, Default)]
+pub struct MemoryNonceStore {
+    seen: Mutex<HashMap<Address, HashSet<String>>>,
+}
+
+impl MemoryNonceStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NonceStore for MemoryNonceStore {
+    fn check_and_insert(&self, signer: Address, nonce: &str) -> Result<bool> {
+        let mut seen = self
+            .seen
+            .lock()
+            .map_err(|_| anyhow!("nonce store lock poisoned"))?;
+        Ok(seen.entry(signer).or_default().insert(nonce.to_string()))
+    }
+}
+
+/// Nonces persisted to a file, one JSON `[signer, nonce]` pair per line.
+/// The file is read once on open and appended to on every new pair.
+#[derive(Debug)]
+pub struct FileNonceStore {
+    path: PathBuf,
+    memory: MemoryNonceStore,
+}
+
+impl FileNonceStore {
+    /// Open or create the store at `path`
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let memory = MemoryNonceStore::new();
+        if path.exists() {
+            let file = std::fs::File::open(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            for (i, line) in BufReader::new(file).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let (signer, nonce): (Address, String) =
+                    serde_json::from_str(&line).with_context(|| {
+                        format!("{}:{}: malformed nonce record", path.display(), i + 1)
+                    })?;
+                memory.check_and_insert(signer, &nonce)?;
+            }
+        }
+        Ok(Self { path, memory })
+    }
+}
+
+impl NonceStore for FileNonceStore {
+    fn check_and_insert(&self, signer: Address, nonce: &str) -> Result<bool> {
+        // Hold the memory lock across the append so concurrent callers
+        // cannot both accept the same pair
+        let mut seen = self
+            .memory
+            .seen
+            .lock()
+            .map_err(|_| anyhow!("nonce store lock poisoned"))?;
+        if seen
+            .get(&signer)
+            .is_some_and(|nonces| nonces.contains(nonce))
+        {
+            return Ok(false);
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&(signer, nonce))?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        seen.entry(signer).or_default().insert(nonce.to_string());
+        Ok(true)
+    }
+}
+
+/// signing.nonce as text: a string as is, an unsigned integer in decimal
+pub fn snapshot_nonce(snapshot: &Value) -> Result<String> {
+    match signing::signing_object(snapshot)?.get("nonce") {
+        Some(Value::String(s)) if !s.is_empty() => Ok(s.clone()),
+        Some(Value::Number(n)) if n.is_u64() => Ok(n.to_string()),
+        None | Some(Value::Null) => Err(anyhow!("missing signing.nonce")),
+        Some(_) => Err(anyhow!(
+            "signing.nonce must be a non-empty string or unsigned integer"
+        )),
+    }
+}
+
+/// Fail if `signer` has already used this snapshot's nonce, recording it otherwise
+pub fn check_nonce(snapshot: &Value, signer: Address, store: &dyn NonceStore) -> Result<()> {
+    let nonce = snapshot_nonce(snapshot)?;
+    if store.check_and_insert(signer, &nonce)? {
+        Ok(())
+    } else {
+        Err(anyhow!("replayed nonce {nonce:?} from {signer:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{signed, KEY_A};
+    use crate::verify::{verify_report_with_nonces, VerifyOptions};
+    use serde_json::json;
+
+    async fn with_nonce(nonce: Value) -> Value {
+        signed(json!({ "id": "snap-1", "body": { "n": 1 }, "signing": { "scheme": "eip191", "nonce": nonce } }), KEY_A).await
+    }
+
+    fn check_replays(store: &dyn NonceStore, first: &Value, second: &Value) {
+        let opts = VerifyOptions::default();
+        let report = verify_report_with_nonces(first, &opts, 0, store);
+        assert!(report.is_valid(), "{report:?}");
+        assert_eq!(report.nonce_ok, Some(true));
+
+        let report = verify_report_with_nonces(first, &opts, 0, store);
+        assert!(!report.is_valid());
+        assert_eq!(report.nonce_ok, Some(false));
+        assert!(
+            report.errors[0].contains("replayed nonce"),
+            "{:?}",
+            report.errors
+        );
+
+        assert!(verify_report_with_nonces(second, &opts, 0, store).is_valid());
+    }
+
+    #[tokio::test]
+    async fn memory_store_rejects_replays() {
+        check_replays(
+            &MemoryNonceStore::new(),
+            &with_nonce(json!("n-1")).await,
+            &with_nonce(json!(2)).await,
+        );
+    }
+
+    #[tokio::test]
+    async fn file_store_persists_across_opens() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nonces.jsonl");
+        let first = with_nonce(json!("n-1")).await;
+        check_replays(
+            &FileNonceStore::open(&path).unwrap(),
+            &first,
+            &with_nonce(json!("n-2")).await,
+        );
+
+        let reopened = FileNonceStore::open(&path).unwrap();
+        assert!(
+            !verify_report_with_nonces(&first, &VerifyOptions::default(), 0, &reopened).is_valid()
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_nonce_fails_and_forgeries_record_nothing() {
+        let store = MemoryNonceStore::new();
+        let mut snap = with_nonce(json!(null)).await;
+        let report = verify_report_with_nonces(&snap, &VerifyOptions::default(), 0, &store);
+        assert_eq!(report.nonce_ok, Some(false));
+
+        // A tampered snapshot does not burn its nonce
+        snap = with_nonce(json!("n-1")).await;
+        let mut tampered = snap.clone();
+        tampered["body"]["n"] = json!(2);
+        let report = verify_report_with_nonces(&tampered, &VerifyOptions::default(), 0, &store);
+        assert_eq!(report.nonce_ok, None);
+        assert!(verify_report_with_nonces(&snap, &VerifyOptions::default(), 0, &store).is_valid());
+    }
+}
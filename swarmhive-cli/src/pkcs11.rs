@@ -0,0 +1,387 @@
+//! Signing with a key held in a PKCS#11 HSM
+//!
+//! The private key never leaves the token. `Pkcs11Signer::open` loads the
+//! vendor's PKCS#11 module, logs in to a slot with the user PIN and finds
+//! the secp256k1 key pair by label; the Ethereum address is derived from
+//! the public key's CKA_EC_POINT. Tokens return raw `r || s` CKM_ECDSA
+//! signatures, so `s` is normalized to the low half and the recovery id is
+//! found by recovering against that address, which also catches a token
+//! that signed with some other key.
+//!
+//! HSMs cannot run in CI; the tests drive `Pkcs11Signer` through a mock
+//! `HsmKey`. To check a real module by hand, with SoftHSM2:
+//!
+//! ```text
+//! softhsm2-util --init-token --free --label swarmhive --pin 1234 --so-pin 0000
+//! pkcs11-tool --module /usr/lib/softhsm/libsofthsm2.so --login --pin 1234 \
+//!     --keypairgen --key-type EC:secp256k1 --label eth-key
+//! echo 1234 > pin.txt
+//! swarmhive sign snap.json --pkcs11-module /usr/lib/softhsm/libsofthsm2.so \
+//!     --pkcs11-slot <slot id from softhsm2-util --show-slots> \
+//!     --pkcs11-label eth-key --pkcs11-pin-file pin.txt
+//! swarmhive verify snap.json --address <the "Signer:" address printed above>
+//! ```
+//!
+//! The PIN is held as an `AuthPin`, whose `Debug` output is redacted, and
+//! never appears in error messages.
+
+use crate::address::{AddressDeriver, EthereumDeriver};
+use crate::signing::HashSigner;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, AttributeType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::types::AuthPin;
+use ethers::core::types::transaction::eip2718::TypedTransaction;
+use ethers::core::types::transaction::eip712::Eip712;
+use ethers::core::types::{Address, Signature, H256, U256};
+use ethers::signers::{to_eip155_v, Signer};
+use ethers::utils::hash_message;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Where to find the key
+#[derive(Debug, Clone)]
+pub struct Pkcs11Config {
+    /// The vendor's PKCS#11 shared library
+    pub module: PathBuf,
+    /// Slot id of the token holding the key
+    pub slot: u64,
+    /// CKA_LABEL shared by the private and public key objects
+    pub label: String,
+    /// User PIN
+    pub pin: AuthPin,
+}
+
+/// The two operations `Pkcs11Signer` needs from a token
+pub trait HsmKey: Send + Sync {
+    /// Uncompressed SEC1 public key (`0x04 || x || y`), bare or wrapped in
+    /// a DER OCTET STRING as CKA_EC_POINT usually is
+    fn public_point(&self) -> Result<Vec<u8>>;
+    /// Raw 64-byte `r || s` ECDSA signature over a 32-byte prehash
+    fn sign_prehash(&self, digest: &[u8; 32]) -> Result<Vec<u8>>;
+}
+
+/// A key pair on a logged-in cryptoki session
+struct CryptokiKey {
+    session: Mutex<Session>,
+    private: ObjectHandle,
+    public: ObjectHandle,
+}
+
+impl CryptokiKey {
+    fn open(config: &Pkcs11Config) -> Result<Self> {
+        let pkcs11 = Pkcs11::new(&config.module).with_context(|| {
+            format!("failed to load PKCS#11 module {}", config.module.display())
+        })?;
+        pkcs11.initialize(CInitializeArgs::OsThreads)?;
+        let slot = pkcs11
+            .get_slots_with_token()?
+            .into_iter()
+            .find(|slot| slot.id() == config.slot)
+            .ok_or_else(|| anyhow!("no token in PKCS#11 slot {}", config.slot))?;
+        let session = pkcs11.open_ro_session(slot)?;
+        // The error names the slot, never the PIN
+        session
+            .login(UserType::User, Some(&config.pin))
+            .map_err(|e| anyhow!("PKCS#11 login to slot {} failed: {e}", config.slot))?;
+        let find = |class| -> Result<ObjectHandle> {
+            let found = session.find_objects(&[
+                Attribute::Class(class),
+                Attribute::Label(config.label.as_bytes().to_vec()),
+            ])?;
+            match found.as_slice() {
+                [handle] => Ok(*handle),
+                [] => Err(anyhow!(
+                    "no {class} labelled {:?} in slot {}",
+                    config.label,
+                    config.slot
+                )),
+                _ => Err(anyhow!(
+                    "several {class} objects labelled {:?} in slot {}",
+                    config.label,
+                    config.slot
+                )),
+            }
+        };
+        let private = find(ObjectClass::PRIVATE_KEY)?;
+        let public = find(ObjectClass::PUBLIC_KEY)?;
+        Ok(Self {
+            session: Mutex::new(session),
+            private,
+            public,
+        })
+    }
+}
+
+impl HsmKey for CryptokiKey {
+    fn public_point(&self) -> Result<Vec<u8>> {
+        let session = self
+            .session
+            .lock()
+            .map_err(|_| anyhow!("PKCS#11 session poisoned"))?;
+        match session
+            .get_attributes(self.public, &[AttributeType::EcPoint])?
+            .pop()
+        {
+            Some(Attribute::EcPoint(point)) => Ok(point),
+            _ => Err(anyhow!("public key has no CKA_EC_POINT")),
+        }
+    }
+
+    fn sign_prehash(&self, digest: &[u8; 32]) -> Result<Vec<u8>> {
+        let session = self
+            .session
+            .lock()
+            .map_err(|_| anyhow!("PKCS#11 session poisoned"))?;
+        Ok(session.sign(&Mechanism::Ecdsa, self.private, digest)?)
+    }
+}
+
+/// Signer backed by a PKCS#11 token
+#[derive(Clone)]
+pub struct Pkcs11Signer {
+    key: Arc<dyn HsmKey>,
+    address: Address,
+    chain_id: u64,
+}
+
+impl fmt::Debug for Pkcs11Signer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pkcs11Signer")
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Error from `Pkcs11Signer`'s `Signer` implementation
+#[derive(Debug)]
+pub struct Pkcs11SignerError(anyhow::Error);
+
+impl fmt::Display for Pkcs11SignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PKCS#11 signer: {:#}", self.0)
+    }
+}
+
+impl std::error::Error for Pkcs11SignerError {}
+
+/// secp256k1 group order
+const SECP256K1_N: &str = "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+
+impl Pkcs11Signer {
+    /// Log in to the token described by `config` and load its key
+    pub fn open(config: &Pkcs11Config) -> Result<Self> {
+        Self::from_key(CryptokiKey::open(config)?)
+    }
+
+    /// Sign with `key`, deriving the address from its public point
+    pub fn from_key(key: impl HsmKey + 'static) -> Result<Self> {
+        let point = key.public_point()?;
+        let point = match point.as_slice() {
+            [0x04, 0x41, rest @ ..] if rest.len() == 65 => rest,
+            bare => bare,
+        };
+        let public_key: &[u8; 64] = match point {
+            [0x04, xy @ ..] => xy.try_into().ok(),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            anyhow!(
+                "public key is not an uncompressed secp256k1 point ({} bytes)",
+                point.len()
+            )
+        })?;
+        Ok(Self {
+            address: EthereumDeriver.derive(public_key),
+            key: Arc::new(key),
+            chain_id: 1,
+        })
+    }
+
+    /// Have the token sign `digest`, returning a low-s signature that
+    /// recovers to this signer's address
+    pub fn sign_digest_hsm(&self, digest: H256) -> Result<Signature> {
+        let raw = self.key.sign_prehash(digest.as_fixed_bytes())?;
+        if raw.len() != 64 {
+            return Err(anyhow!(
+                "expected a 64-byte ECDSA signature, got {} bytes",
+                raw.len()
+            ));
+        }
+        let n = U256::from_str_radix(SECP256K1_N, 16).expect("valid constant");
+        let r = U256::from_big_endian(&raw[..32]);
+        let mut s = U256::from_big_endian(&raw[32..]);
+        if s > n / 2 {
+            s = n - s;
+        }
+        [27, 28]
+            .into_iter()
+            .map(|v| Signature { r, s, v })
+            .find(|sig| sig.recover(digest).is_ok_and(|addr| addr == self.address))
+            .ok_or_else(|| anyhow!("token signature does not recover to {:?}", self.address))
+    }
+}
+
+#[async_trait]
+impl HashSigner for Pkcs11Signer {
+    fn signer_address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_digest(&self, digest: H256) -> Result<Signature> {
+        self.sign_digest_hsm(digest)
+    }
+}
+
+#[async_trait]
+impl Signer for Pkcs11Signer {
+    type Error = Pkcs11SignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, Self::Error> {
+        self.sign_digest_hsm(hash_message(message))
+            .map_err(Pkcs11SignerError)
+    }
+
+    /// Same chain id handling as `LocalWallet`
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        let chain_id = tx.chain_id().map(|id| id.as_u64()).unwrap_or(self.chain_id);
+        let mut tx = tx.clone();
+        tx.set_chain_id(chain_id);
+        let mut sig = self
+            .sign_digest_hsm(tx.sighash())
+            .map_err(Pkcs11SignerError)?;
+        sig.v = to_eip155_v(sig.v as u8 - 27, chain_id);
+        Ok(sig)
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        let encoded = payload
+            .encode_eip712()
+            .map_err(|e| Pkcs11SignerError(anyhow!("EIP-712 encoding failed: {e}")))?;
+        self.sign_digest_hsm(H256::from(encoded))
+            .map_err(Pkcs11SignerError)
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn with_chain_id<T: Into<u64>>(mut self, chain_id: T) -> Self {
+        self.chain_id = chain_id.into();
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing;
+    use crate::test_support::{wallet, KEY_A, KEY_B};
+    use ethers::core::k256::ecdsa::SigningKey;
+    use serde_json::json;
+
+    /// Token holding `public`'s point but signing with `signing`, returning
+    /// high-s signatures when asked, as some HSMs do
+    struct MockHsm {
+        public: SigningKey,
+        signing: SigningKey,
+        high_s: bool,
+    }
+
+    impl MockHsm {
+        fn new(key: &str) -> Self {
+            let key = wallet(key).signer().clone();
+            MockHsm {
+                public: key.clone(),
+                signing: key,
+                high_s: false,
+            }
+        }
+    }
+
+    impl HsmKey for MockHsm {
+        fn public_point(&self) -> Result<Vec<u8>> {
+            // DER OCTET STRING wrapping, as CKA_EC_POINT is returned
+            let mut der = vec![0x04, 0x41];
+            der.extend_from_slice(
+                self.public
+                    .verifying_key()
+                    .to_encoded_point(false)
+                    .as_bytes(),
+            );
+            Ok(der)
+        }
+
+        fn sign_prehash(&self, digest: &[u8; 32]) -> Result<Vec<u8>> {
+            let (sig, _) = self.signing.sign_prehash_recoverable(digest)?;
+            let mut raw = sig.to_bytes().to_vec();
+            if self.high_s {
+                let n = U256::from_str_radix(SECP256K1_N, 16).unwrap();
+                let s = n - U256::from_big_endian(&raw[32..]);
+                s.to_big_endian(&mut raw[32..]);
+            }
+            Ok(raw)
+        }
+    }
+
+    #[tokio::test]
+    async fn hsm_signature_verifies() {
+        let wallet = wallet(KEY_A);
+        for high_s in [false, true] {
+            let signer = Pkcs11Signer::from_key(MockHsm {
+                high_s,
+                ..MockHsm::new(KEY_A)
+            })
+            .unwrap();
+            assert_eq!(signer.signer_address(), wallet.address());
+
+            let mut snap = json!({ "id": "snap-1", "signing": { "scheme": "eip191" } });
+            signing::sign_snapshot(&mut snap, &signer).await.unwrap();
+            signing::verify_signature(&snap, wallet.address()).unwrap();
+            assert_eq!(
+                signer.sign_message(b"hello").await.unwrap(),
+                wallet.sign_message(b"hello").await.unwrap()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn signature_from_another_key_is_rejected() {
+        let signer = Pkcs11Signer::from_key(MockHsm {
+            signing: MockHsm::new(KEY_B).signing,
+            ..MockHsm::new(KEY_A)
+        })
+        .unwrap();
+        let err = signing::sign_snapshot(&mut json!({ "signing": {} }), &signer)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("does not recover"), "{err}");
+    }
+
+    #[test]
+    fn pin_is_not_printed() {
+        let config = Pkcs11Config {
+            module: "/usr/lib/softhsm/libsofthsm2.so".into(),
+            slot: 0,
+            label: "eth-key".to_string(),
+            pin: AuthPin::new("123456".to_string()),
+        };
+        assert!(!format!("{config:?}").contains("123456"));
+        let err = Pkcs11Signer::open(&config).unwrap_err();
+        assert!(!format!("{err:#}").contains("123456"), "{err:#}");
+    }
+}
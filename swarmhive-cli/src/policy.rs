@@ -0,0 +1,228 @@
+//! Composable verification policies
+//!
+//! Each check is a `VerifyPolicy`; a `PolicyChain` runs them in order and
+//! stops at the first failure, naming the policy that rejected the snapshot.
+//! Library users assemble exactly the checks they need.
+
+use crate::signing;
+use crate::verify;
+use anyhow::{anyhow, Context, Result};
+use ethers::core::types::Address;
+use jsonschema::JSONSchema;
+use serde_json::Value;
+use std::time::Duration;
+
+/// Inputs shared by every policy in a chain
+#[derive(Debug, Clone)]
+pub struct VerificationContext {
+    /// Current time, Unix seconds
+    pub now: i64,
+}
+
+impl VerificationContext {
+    pub fn new(now: i64) -> Self {
+        Self { now }
+    }
+}
+
+/// One verification check
+pub trait VerifyPolicy: Send + Sync {
+    /// Short name used when reporting a failure
+    fn name(&self) -> &str;
+    fn check(&self, snapshot: &Value, ctx: &VerificationContext) -> Result<()>;
+}
+
+/// Policies run in order; the first failure ends the run
+#[derive(Default)]
+pub struct PolicyChain {
+    policies: Vec<Box<dyn VerifyPolicy>>,
+}
+
+impl PolicyChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a policy to the end of the chain
+    pub fn with(mut self, policy: impl VerifyPolicy + 'static) -> Self {
+        self.policies.push(Box::new(policy));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.policies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    /// Run every policy; the error names the first one that failed
+    pub fn check(&self, snapshot: &Value, ctx: &VerificationContext) -> Result<()> {
+        for policy in &self.policies {
+            policy
+                .check(snapshot, ctx)
+                .with_context(|| format!("{} policy failed", policy.name()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Signature recovers and content matches signing.payload_hash; optionally
+/// the signer must be `expected`
+#[derive(Debug, Clone, Default)]
+pub struct CryptoPolicy {
+    pub expected: Option<Address>,
+}
+
+impl VerifyPolicy for CryptoPolicy {
+    fn name(&self) -> &str {
+        "crypto"
+    }
+
+    fn check(&self, snapshot: &Value, _ctx: &VerificationContext) -> Result<()> {
+        let signer = signing::recover_signer(snapshot)?;
+        match self.expected {
+            Some(expected) if expected != signer => {
+                Err(anyhow!("signed by {signer:?}, expected {expected:?}"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// signing.issued_at within `max_age` and signing.valid_until not passed
+#[derive(Debug, Clone, Default)]
+pub struct FreshnessPolicy {
+    pub max_age: Option<Duration>,
+    /// Allowance for a future issued_at; `None` is `DEFAULT_CLOCK_SKEW`
+    pub clock_skew: Option<Duration>,
+}
+
+impl VerifyPolicy for FreshnessPolicy {
+    fn name(&self) -> &str {
+        "freshness"
+    }
+
+    fn check(&self, snapshot: &Value, ctx: &VerificationContext) -> Result<()> {
+        verify::verify_freshness(
+            snapshot,
+            self.max_age,
+            self.clock_skew.unwrap_or(verify::DEFAULT_CLOCK_SKEW),
+            ctx.now,
+        )
+    }
+}
+
+/// Recovered signer must be one of `signers`
+#[derive(Debug, Clone, Default)]
+pub struct AllowListPolicy {
+    pub signers: Vec<Address>,
+}
+
+impl VerifyPolicy for AllowListPolicy {
+    fn name(&self) -> &str {
+        "allow-list"
+    }
+
+    fn check(&self, snapshot: &Value, _ctx: &VerificationContext) -> Result<()> {
+        let signer = signing::recover_signer(snapshot)?;
+        if self.signers.contains(&signer) {
+            Ok(())
+        } else {
+            Err(anyhow!("signer {signer:?} is not on the allow-list"))
+        }
+    }
+}
+
+/// Snapshot must validate against a JSON Schema
+pub struct SchemaPolicy {
+    schema: JSONSchema,
+}
+
+impl SchemaPolicy {
+    pub fn new(schema: &Value) -> Result<Self> {
+        let schema =
+            JSONSchema::compile(schema).map_err(|e| anyhow!("invalid JSON Schema: {e}"))?;
+        Ok(Self { schema })
+    }
+}
+
+impl VerifyPolicy for SchemaPolicy {
+    fn name(&self) -> &str {
+        "schema"
+    }
+
+    fn check(&self, snapshot: &Value, _ctx: &VerificationContext) -> Result<()> {
+        self.schema.validate(snapshot).map_err(|errors| {
+            let messages: Vec<String> = errors
+                .map(|e| format!("{}: {e}", e.instance_path))
+                .collect();
+            anyhow!("schema violation: {}", messages.join("; "))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{address, signed, KEY_A};
+    use serde_json::json;
+
+    const NOW: i64 = 1_700_000_000;
+
+    async fn expiring(valid_until: i64) -> (Value, Address) {
+        let snap = json!({
+            "id": "snap-1",
+            "body": { "n": 1 },
+            "signing": { "scheme": "eip191", "issued_at": NOW - 60, "valid_until": valid_until }
+        });
+        (signed(snap, KEY_A).await, address(KEY_A))
+    }
+
+    fn chain(signer: Address) -> PolicyChain {
+        PolicyChain::new()
+            .with(CryptoPolicy::default())
+            .with(FreshnessPolicy {
+                max_age: Some(Duration::from_secs(300)),
+                ..Default::default()
+            })
+            .with(AllowListPolicy {
+                signers: vec![signer],
+            })
+    }
+
+    #[tokio::test]
+    async fn chain_reports_failing_policy() {
+        let ctx = VerificationContext::new(NOW);
+
+        let (fresh, signer) = expiring(NOW + 60).await;
+        chain(signer).check(&fresh, &ctx).unwrap();
+
+        let (expired, _) = expiring(NOW - 1).await;
+        let err = chain(signer).check(&expired, &ctx).unwrap_err();
+        assert_eq!(err.to_string(), "freshness policy failed");
+        assert!(format!("{err:#}").contains("expired"), "{err:#}");
+
+        let err = chain(Address::repeat_byte(0x11))
+            .check(&fresh, &ctx)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "allow-list policy failed");
+    }
+
+    #[tokio::test]
+    async fn schema_policy_names_violation() {
+        let (snap, _) = expiring(NOW + 60).await;
+        let policy = SchemaPolicy::new(&json!({
+            "type": "object",
+            "required": ["id", "body"],
+            "properties": { "body": { "properties": { "n": { "type": "string" } } } }
+        }))
+        .unwrap();
+
+        let err = policy
+            .check(&snap, &VerificationContext::new(NOW))
+            .unwrap_err();
+        assert!(err.to_string().contains("/body/n"), "{err}");
+    }
+}
@@ -0,0 +1,284 @@
+//! Deterministic protobuf for `signing.encoding = "protobuf"`
+//!
+//! Protobuf-native pipelines hash the protobuf form of a snapshot instead
+//! of its JSON bytes. Protobuf has no canonical encoding, so the schema in
+//! `proto/snapshot.proto` is serialized one pinned way: fields in number
+//! order (prost's order), no unknown fields, object members sorted by key
+//! without duplicates, and each number in exactly one of the integer or
+//! double fields. `decode` rejects any blob that is not in that form.
+
+#[cfg(feature = "ethers")]
+use crate::signing::{self, SigningScheme};
+use anyhow::{anyhow, Context, Result};
+#[cfg(feature = "ethers")]
+use ethers::core::types::{Address, Signature};
+use prost::Message;
+use serde_json::{Map, Number, Value as Json};
+
+/// `swarmhive.v1.Value`
+#[derive(Clone, PartialEq, Message)]
+pub struct Value {
+    #[prost(oneof = "Kind", tags = "1, 2, 3, 4, 5, 6, 7, 8")]
+    pub kind: Option<Kind>,
+}
+
+/// `swarmhive.v1.Value.kind`
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum Kind {
+    #[prost(bool, tag = "1")]
+    NullValue(bool),
+    #[prost(bool, tag = "2")]
+    BoolValue(bool),
+    #[prost(uint64, tag = "3")]
+    UintValue(u64),
+    #[prost(sint64, tag = "4")]
+    IntValue(i64),
+    #[prost(double, tag = "5")]
+    DoubleValue(f64),
+    #[prost(string, tag = "6")]
+    StringValue(String),
+    #[prost(message, tag = "7")]
+    ListValue(List),
+    #[prost(message, tag = "8")]
+    ObjectValue(Object),
+}
+
+/// `swarmhive.v1.List`
+#[derive(Clone, PartialEq, Message)]
+pub struct List {
+    #[prost(message, repeated, tag = "1")]
+    pub values: Vec<Value>,
+}
+
+/// `swarmhive.v1.Object`
+#[derive(Clone, PartialEq, Message)]
+pub struct Object {
+    #[prost(message, repeated, tag = "1")]
+    pub members: Vec<Member>,
+}
+
+/// `swarmhive.v1.Member`
+#[derive(Clone, PartialEq, Message)]
+pub struct Member {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(message, optional, tag = "2")]
+    pub value: Option<Value>,
+}
+
+/// Protobuf bytes of a JSON value in the pinned form
+pub fn encode(v: &Json) -> Vec<u8> {
+    to_proto(v).encode_to_vec()
+}
+
+/// Parse a protobuf blob back to JSON, failing unless the blob is exactly
+/// what `encode` would write for the result
+pub fn decode(blob: &[u8]) -> Result<Json> {
+    let message = Value::decode(blob).context("payload is not valid protobuf")?;
+    let json = from_proto(&message)?;
+    if encode(&json) != blob {
+        return Err(anyhow!(
+            "protobuf payload is not deterministically encoded (field order, unknown fields or defaults differ)"
+        ));
+    }
+    Ok(json)
+}
+
+fn to_proto(v: &Json) -> Value {
+    let kind = match v {
+        Json::Null => Kind::NullValue(true),
+        Json::Bool(b) => Kind::BoolValue(*b),
+        Json::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => Kind::UintValue(u),
+            (None, Some(i)) => Kind::IntValue(i),
+            _ => Kind::DoubleValue(n.as_f64().unwrap_or_default()),
+        },
+        Json::String(s) => Kind::StringValue(s.clone()),
+        Json::Array(arr) => Kind::ListValue(List {
+            values: arr.iter().map(to_proto).collect(),
+        }),
+        Json::Object(map) => {
+            let mut members: Vec<Member> = map
+                .iter()
+                .map(|(k, child)| Member {
+                    key: k.clone(),
+                    value: Some(to_proto(child)),
+                })
+                .collect();
+            members.sort_by(|a, b| a.key.as_bytes().cmp(b.key.as_bytes()));
+            Kind::ObjectValue(Object { members })
+        }
+    };
+    Value { kind: Some(kind) }
+}
+
+fn from_proto(v: &Value) -> Result<Json> {
+    Ok(
+        match v
+            .kind
+            .as_ref()
+            .ok_or_else(|| anyhow!("protobuf Value with no kind"))?
+        {
+            Kind::NullValue(true) => Json::Null,
+            Kind::NullValue(false) => return Err(anyhow!("protobuf null_value must be true")),
+            Kind::BoolValue(b) => Json::Bool(*b),
+            Kind::UintValue(u) => Json::from(*u),
+            Kind::IntValue(i) if *i < 0 => Json::from(*i),
+            Kind::IntValue(i) => {
+                return Err(anyhow!("non-negative integer {i} must be a uint_value"))
+            }
+            Kind::DoubleValue(f) => {
+                Json::Number(Number::from_f64(*f).ok_or_else(|| anyhow!("non-finite double {f}"))?)
+            }
+            Kind::StringValue(s) => Json::String(s.clone()),
+            Kind::ListValue(list) => {
+                Json::Array(list.values.iter().map(from_proto).collect::<Result<_>>()?)
+            }
+            Kind::ObjectValue(object) => {
+                let mut map = Map::new();
+                for pair in object.members.windows(2) {
+                    if pair[0].key.as_bytes() >= pair[1].key.as_bytes() {
+                        return Err(anyhow!(
+                            "object members not strictly sorted at {:?}",
+                            pair[1].key
+                        ));
+                    }
+                }
+                for member in &object.members {
+                    let value = member
+                        .value
+                        .as_ref()
+                        .ok_or_else(|| anyhow!("member {:?} has no value", member.key))?;
+                    map.insert(member.key.clone(), from_proto(value)?);
+                }
+                Json::Object(map)
+            }
+        },
+    )
+}
+
+/// Check a transmitted protobuf payload and its signature: decode it,
+/// recompute the snapshot's payload hash from the decoded content, require
+/// it to equal keccak256(blob), and recover the signer. Returns the
+/// decoded snapshot (without its signature) and the signer.
+#[cfg(feature = "ethers")]
+pub fn verify_protobuf(
+    blob: &[u8],
+    signature: &Signature,
+    scheme: SigningScheme,
+) -> Result<(Json, Address)> {
+    let snapshot = decode(blob)?;
+
+    let encoding = signing::snapshot_payload_encoding(&snapshot)?;
+    if encoding != signing::PayloadEncoding::Protobuf {
+        return Err(anyhow!(
+            "decoded snapshot declares signing.encoding {:?}",
+            encoding.as_str()
+        ));
+    }
+
+    let hash = crate::hash::keccak256(blob);
+    if signing::payload_hash_keccak(&snapshot)? != hash {
+        return Err(anyhow!(
+            "protobuf payload does not match its canonical content"
+        ));
+    }
+
+    let signer = signing::recover_hash_signer(hash, signature, scheme)?;
+    Ok((snapshot, signer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn snapshot() -> Json {
+        json!({
+            "id": "snap-1",
+            "body": { "z": [1, -2, 2.5, null, true, "s"], "a": { "nested": {} } },
+            "signing": { "scheme": "eip191", "encoding": "protobuf" }
+        })
+    }
+
+    #[test]
+    fn encoding_is_stable_and_round_trips() {
+        let blob = encode(&snapshot());
+        // Key order in the input does not matter
+        let reordered: Json = serde_json::from_str(
+            r#"{"signing":{"encoding":"protobuf","scheme":"eip191"},"body":{"a":{"nested":{}},"z":[1,-2,2.5,null,true,"s"]},"id":"snap-1"}"#,
+        )
+        .unwrap();
+        assert_eq!(encode(&reordered), blob);
+        assert_eq!(decode(&blob).unwrap(), snapshot());
+        assert_eq!(
+            crate::hash::keccak256(encode(&decode(&blob).unwrap())),
+            crate::hash::keccak256(&blob)
+        );
+    }
+
+    #[test]
+    fn non_deterministic_blobs_are_rejected() {
+        // Members out of order
+        let unsorted = Value {
+            kind: Some(Kind::ObjectValue(Object {
+                members: vec![
+                    Member {
+                        key: "b".into(),
+                        value: Some(to_proto(&json!(1))),
+                    },
+                    Member {
+                        key: "a".into(),
+                        value: Some(to_proto(&json!(2))),
+                    },
+                ],
+            })),
+        };
+        assert!(decode(&unsorted.encode_to_vec())
+            .unwrap_err()
+            .to_string()
+            .contains("not strictly sorted"));
+
+        // A non-negative number in the signed-integer field
+        let wrong_field = Value {
+            kind: Some(Kind::IntValue(5)),
+        };
+        assert!(decode(&wrong_field.encode_to_vec()).is_err());
+
+        // Unknown field 15 appended to a valid blob
+        let mut unknown = encode(&json!({ "a": 1 }));
+        unknown.extend_from_slice(&[0x78, 0x01]);
+        let err = decode(&unknown).unwrap_err();
+        assert!(
+            err.to_string().contains("not deterministically encoded"),
+            "{err}"
+        );
+    }
+
+    #[cfg(feature = "ethers")]
+    #[tokio::test]
+    async fn sign_and_verify_over_protobuf() {
+        use crate::canonical::CanonicalProfile;
+        use crate::hash::payload_preimage;
+        use crate::test_support::{address, wallet, KEY_A};
+
+        let mut snap = snapshot();
+        let (hash, addr) = signing::sign_snapshot(&mut snap, &wallet(KEY_A))
+            .await
+            .unwrap();
+        signing::verify_signature(&snap, address(KEY_A)).unwrap();
+
+        // The hashed bytes are the protobuf form of the unsigned content
+        let preimage = payload_preimage(&snap, &CanonicalProfile::default()).unwrap();
+        let blob = signing::PayloadEncoding::Protobuf.encode(preimage).unwrap();
+        assert_eq!(crate::hash::keccak256(&blob), hash);
+
+        let (_, signature) = signing::signed_parts(&snap).unwrap();
+        let (content, signer) = verify_protobuf(&blob, &signature, SigningScheme::Eip191).unwrap();
+        assert_eq!(signer, addr);
+        assert_eq!(content["body"], snap["body"]);
+
+        snap["body"]["z"][0] = json!(7);
+        assert!(signing::verify_signature(&snap, address(KEY_A)).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+//! Producer provenance
+//!
+//! `sign --provenance` records which build of the producer made a snapshot
+//! in `signing.provenance`. Unlike `sections` or `history`, the field is
+//! part of the payload hash: where a snapshot came from is a claim the
+//! signer makes, and must not be editable afterwards.
+
+use crate::signing;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Which code produced a snapshot. Unknown parts are recorded as null.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Commit of the producer's source tree
+    pub git_commit: Option<String>,
+    /// Who or what built the producer (a CI job, a host)
+    pub builder: Option<String>,
+    /// When the producer was built, as the builder reported it
+    pub built_at: Option<String>,
+}
+
+impl Provenance {
+    /// From the environment of the signing process. `SWARMHIVE_GIT_COMMIT`,
+    /// `SWARMHIVE_BUILDER` and `SWARMHIVE_BUILT_AT` win; otherwise the
+    /// commit is taken from `GITHUB_SHA` or `CI_COMMIT_SHA`, and the builder
+    /// defaults to this binary's name and version.
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    /// `from_env` over any variable source
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let first = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| lookup(key).filter(|v| !v.is_empty()))
+        };
+        Self {
+            git_commit: first(&["SWARMHIVE_GIT_COMMIT", "GITHUB_SHA", "CI_COMMIT_SHA"]),
+            builder: first(&["SWARMHIVE_BUILDER"]).or_else(|| {
+                Some(format!(
+                    "{} {}",
+                    env!("CARGO_PKG_NAME"),
+                    env!("CARGO_PKG_VERSION")
+                ))
+            }),
+            built_at: first(&["SWARMHIVE_BUILT_AT"]),
+        }
+    }
+
+    /// One-line summary of the known parts
+    pub fn summary(&self) -> String {
+        let parts: Vec<String> = [
+            ("git_commit", &self.git_commit),
+            ("builder", &self.builder),
+            ("built_at", &self.built_at),
+        ]
+        .into_iter()
+        .filter_map(|(key, v)| v.as_ref().map(|v| format!("{key}={v}")))
+        .collect();
+        if parts.is_empty() {
+            "unknown".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// signing.provenance, if the snapshot has one
+pub fn snapshot_provenance(snapshot: &Value) -> Result<Option<Provenance>> {
+    match signing::signing_object(snapshot)?.get("provenance") {
+        None | Some(Value::Null) => Ok(None),
+        Some(v) => serde_json::from_value(v.clone())
+            .map(Some)
+            .map_err(|e| anyhow!("bad signing.provenance: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn explicit_variables_win_over_ci_defaults() {
+        let env = HashMap::from([
+            ("GITHUB_SHA", "ci-commit"),
+            ("SWARMHIVE_GIT_COMMIT", "0badc0de"),
+            ("SWARMHIVE_BUILT_AT", "2024-05-01T12:00:00Z"),
+        ]);
+        let p = Provenance::from_lookup(|key| env.get(key).map(|v| v.to_string()));
+        assert_eq!(p.git_commit.as_deref(), Some("0badc0de"));
+        assert_eq!(
+            p.builder,
+            Some(format!("swarmhive {}", env!("CARGO_PKG_VERSION")))
+        );
+        assert_eq!(p.built_at.as_deref(), Some("2024-05-01T12:00:00Z"));
+
+        let bare = Provenance::from_lookup(|_| None);
+        assert_eq!(bare.git_commit, None);
+        assert_eq!(
+            serde_json::to_value(&bare).unwrap()["built_at"],
+            Value::Null
+        );
+    }
+}
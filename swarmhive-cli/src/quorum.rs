@@ -0,0 +1,251 @@
+//! On-chain multisig quorum
+//!
+//! `verify_onchain_quorum` reads the owners and threshold of a Gnosis Safe
+//! and requires that many owners among a snapshot's co-signatures, so the
+//! authorization source of truth stays on-chain. The reads are behind
+//! `SafeReader` so an RPC provider can be swapped for a mock.
+
+use crate::cosign;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::abi::{self, ParamType, Token};
+use ethers::core::types::transaction::eip2718::TypedTransaction;
+use ethers::core::types::{Address, Bytes, TransactionRequest};
+use ethers::providers::{JsonRpcClient, Middleware, Provider, RpcError};
+use serde_json::Value;
+use std::fmt;
+
+/// `getOwners()` selector
+const GET_OWNERS: [u8; 4] = [0xa0, 0xe6, 0x7e, 0x2b];
+/// `getThreshold()` selector
+const GET_THRESHOLD: [u8; 4] = [0xe7, 0x52, 0x35, 0xb8];
+
+/// Source of a Safe's owner configuration
+#[async_trait]
+pub trait SafeReader {
+    async fn owners(&self, safe: Address) -> Result<Vec<Address>>;
+    async fn threshold(&self, safe: Address) -> Result<u64>;
+}
+
+#[async_trait]
+impl<P: JsonRpcClient> SafeReader for Provider<P> {
+    async fn owners(&self, safe: Address) -> Result<Vec<Address>> {
+        let out = safe_call(self, safe, GET_OWNERS).await?;
+        match abi::decode(&[ParamType::Array(Box::new(ParamType::Address))], &out)?.pop() {
+            Some(Token::Array(tokens)) => tokens
+                .into_iter()
+                .map(|t| {
+                    t.into_address()
+                        .ok_or_else(|| anyhow!("getOwners returned a non-address"))
+                })
+                .collect(),
+            _ => Err(anyhow!("getOwners returned an unexpected value")),
+        }
+    }
+
+    async fn threshold(&self, safe: Address) -> Result<u64> {
+        let out = safe_call(self, safe, GET_THRESHOLD).await?;
+        match abi::decode(&[ParamType::Uint(256)], &out)?
+            .pop()
+            .and_then(Token::into_uint)
+        {
+            Some(n) if n <= u64::MAX.into() => Ok(n.as_u64()),
+            _ => Err(anyhow!("getThreshold returned an unexpected value")),
+        }
+    }
+}
+
+async fn safe_call<P: JsonRpcClient>(
+    provider: &Provider<P>,
+    safe: Address,
+    selector: [u8; 4],
+) -> Result<Bytes> {
+    eth_call(provider, safe, selector.to_vec()).await
+}
+
+/// Read-only call of `to` with calldata `data`. A call the contract
+/// reverted fails with a `Reverted` error.
+pub(crate) async fn eth_call<P: JsonRpcClient>(
+    provider: &Provider<P>,
+    to: Address,
+    data: Vec<u8>,
+) -> Result<Bytes> {
+    let tx: TypedTransaction = TransactionRequest::new().to(to).data(data).into();
+    provider
+        .call(&tx, None)
+        .await
+        .map_err(|e| match e.as_error_response() {
+            // Code 3 is geth's; other nodes only say so in the message
+            Some(rpc) if rpc.code == 3 || rpc.is_revert() => Reverted {
+                to,
+                message: rpc.message.clone(),
+            }
+            .into(),
+            _ => anyhow!("eth_call to {to:?} failed: {e}"),
+        })
+}
+
+/// The contract itself reverted a call. The answer is final: retrying the
+/// same call returns the same revert.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reverted {
+    pub to: Address,
+    /// The node's error message, e.g. "execution reverted: GS026"
+    pub message: String,
+}
+
+impl fmt::Display for Reverted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "call to {:?} reverted: {}", self.to, self.message)
+    }
+}
+
+impl std::error::Error for Reverted {}
+
+/// The `Reverted` behind `err`, if the contract reverted
+pub fn reverted(err: &anyhow::Error) -> Option<&Reverted> {
+    err.chain().find_map(|e| e.downcast_ref::<Reverted>())
+}
+
+/// Why an on-chain quorum check failed
+#[derive(Debug)]
+pub enum QuorumError {
+    /// The Safe's configuration could not be read; says nothing about the snapshot
+    Rpc(anyhow::Error),
+    /// The Safe's on-chain configuration cannot be satisfied, such as a
+    /// threshold of 0; says nothing about the snapshot either
+    Config(anyhow::Error),
+    /// The snapshot's signatures do not meet the Safe's threshold
+    Insufficient(anyhow::Error),
+}
+
+impl fmt::Display for QuorumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuorumError::Rpc(e) => write!(f, "could not read Safe configuration: {e}"),
+            QuorumError::Config(e) => write!(f, "unusable Safe configuration: {e}"),
+            QuorumError::Insufficient(e) => write!(f, "quorum not met: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for QuorumError {}
+
+/// Verify `snapshot` carries co-signatures from at least the Safe's
+/// threshold of its owners. Returns the owners that signed.
+pub async fn verify_onchain_quorum<R: SafeReader + ?Sized>(
+    snapshot: &Value,
+    safe_addr: Address,
+    provider: &R,
+) -> Result<Vec<Address>, QuorumError> {
+    let owners = provider.owners(safe_addr).await.map_err(QuorumError::Rpc)?;
+    let threshold = provider
+        .threshold(safe_addr)
+        .await
+        .map_err(QuorumError::Rpc)?;
+    let threshold = usize::try_from(threshold)
+        .map_err(|_| QuorumError::Rpc(anyhow!("Safe threshold {threshold} out of range")))?;
+    if threshold == 0 {
+        return Err(QuorumError::Config(anyhow!(
+            "Safe {safe_addr:?} reports a threshold of 0"
+        )));
+    }
+    cosign::verify_threshold(snapshot, &owners, threshold).map_err(QuorumError::Insufficient)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cosign::add_cosignature;
+    use crate::test_support::{address, signed, wallet, KEY_A, KEY_B, KEY_C};
+    use serde_json::json;
+
+    struct MockSafe {
+        owners: Vec<Address>,
+        threshold: u64,
+        down: bool,
+    }
+
+    #[async_trait]
+    impl SafeReader for MockSafe {
+        async fn owners(&self, _safe: Address) -> Result<Vec<Address>> {
+            if self.down {
+                return Err(anyhow!("connection refused"));
+            }
+            Ok(self.owners.clone())
+        }
+
+        async fn threshold(&self, _safe: Address) -> Result<u64> {
+            Ok(self.threshold)
+        }
+    }
+
+    async fn signed_by(keys: &[&str]) -> Value {
+        let mut snap = signed(
+            json!({ "id": "snap-1", "body": { "n": 1 }, "signing": { "scheme": "eip191" } }),
+            keys[0],
+        )
+        .await;
+        for key in &keys[1..] {
+            add_cosignature(&mut snap, &wallet(key)).await.unwrap();
+        }
+        snap
+    }
+
+    fn safe(down: bool) -> MockSafe {
+        MockSafe {
+            owners: vec![address(KEY_A), address(KEY_B), address(KEY_C)],
+            threshold: 2,
+            down,
+        }
+    }
+
+    #[test]
+    fn selectors_match_safe_abi() {
+        use ethers::utils::keccak256;
+        assert_eq!(keccak256("getOwners()")[..4], GET_OWNERS);
+        assert_eq!(keccak256("getThreshold()")[..4], GET_THRESHOLD);
+    }
+
+    #[tokio::test]
+    async fn quorum_meeting_signatures_verify() {
+        let snap = signed_by(&[KEY_A, KEY_C]).await;
+        let signers = verify_onchain_quorum(&snap, Address::repeat_byte(0x5a), &safe(false))
+            .await
+            .unwrap();
+        assert_eq!(signers, vec![address(KEY_A), address(KEY_C)]);
+    }
+
+    #[tokio::test]
+    async fn sub_quorum_and_rpc_failures_are_distinct() {
+        let snap = signed_by(&[KEY_B]).await;
+        let err = verify_onchain_quorum(&snap, Address::repeat_byte(0x5a), &safe(false))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, QuorumError::Insufficient(_)), "{err}");
+        assert!(
+            err.to_string().contains("only 1 of the required 2"),
+            "{err}"
+        );
+
+        let snap = signed_by(&[KEY_A, KEY_B]).await;
+        let err = verify_onchain_quorum(&snap, Address::repeat_byte(0x5a), &safe(true))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, QuorumError::Rpc(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn zero_threshold_is_a_config_error() {
+        let snap = signed_by(&[KEY_A, KEY_B]).await;
+        let safe = MockSafe {
+            threshold: 0,
+            ..safe(false)
+        };
+        let err = verify_onchain_quorum(&snap, Address::repeat_byte(0x5a), &safe)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, QuorumError::Config(_)), "{err}");
+        assert!(err.to_string().contains("threshold of 0"), "{err}");
+    }
+}
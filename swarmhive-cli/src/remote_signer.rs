@@ -0,0 +1,309 @@
+//! Signing through a remote HTTP signing service
+//!
+//! For deployments where the key never leaves an internal signer. The
+//! protocol is deliberately small: POST the 32-byte digest as
+//! `application/octet-stream` to the configured URL, get the 65-byte
+//! `r || s || v` signature back in the response body. Every signature is
+//! recovered and checked against the address the service advertises
+//! before it is used, so a misbehaving service cannot slip in another
+//! key's signature.
+
+use crate::signing::HashSigner;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use ethers::core::types::transaction::eip2718::TypedTransaction;
+use ethers::core::types::transaction::eip712::Eip712;
+use ethers::core::types::{Address, Signature, H256};
+use ethers::signers::{to_eip155_v, Signer};
+use ethers::utils::hash_message;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use std::fmt;
+
+/// Signer backed by a remote HTTP service
+#[derive(Debug, Clone)]
+pub struct RemoteHttpSigner {
+    url: String,
+    address: Address,
+    chain_id: u64,
+    headers: HeaderMap,
+    client: reqwest::Client,
+}
+
+/// Error from `RemoteHttpSigner`'s `Signer` implementation
+#[derive(Debug)]
+pub struct RemoteSignerError(anyhow::Error);
+
+impl fmt::Display for RemoteSignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "remote signer: {:#}", self.0)
+    }
+}
+
+impl std::error::Error for RemoteSignerError {}
+
+impl RemoteHttpSigner {
+    /// Sign via the service at `url`, which must sign as `address`
+    pub fn new(url: impl Into<String>, address: Address) -> Self {
+        Self {
+            url: url.into(),
+            address,
+            chain_id: 1,
+            headers: HeaderMap::new(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Send `name: value` with every request (e.g. an Authorization token)
+    pub fn with_header(mut self, name: &str, value: &str) -> Result<Self> {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .with_context(|| format!("bad header name {name:?}"))?;
+        let mut value =
+            HeaderValue::from_str(value).with_context(|| format!("bad value for header {name}"))?;
+        value.set_sensitive(true);
+        self.headers.insert(name, value);
+        Ok(self)
+    }
+
+    /// Parse a `Name: value` header as given on the command line
+    pub fn with_header_line(self, line: &str) -> Result<Self> {
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow!("header must be \"Name: value\""))?;
+        self.with_header(name.trim(), value.trim())
+    }
+
+    /// POST `digest`, returning the service's signature once it recovers
+    /// to the advertised address. Services may answer with a v of 0/1 or
+    /// 27/28; the result always has 27/28.
+    pub async fn sign_digest_remote(&self, digest: H256) -> Result<Signature> {
+        let response = self
+            .client
+            .post(&self.url)
+            .headers(self.headers.clone())
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .body(digest.as_bytes().to_vec())
+            .send()
+            .await
+            .with_context(|| format!("request to {} failed", self.url))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(anyhow!("{} answered {status}", self.url));
+        }
+        let body = response.bytes().await?;
+        if body.len() != 65 {
+            return Err(anyhow!(
+                "expected a 65-byte signature, got {} bytes",
+                body.len()
+            ));
+        }
+        let mut sig = Signature::try_from(body.as_ref())?;
+        let recovered = sig.recover(digest)?;
+        if recovered != self.address {
+            return Err(anyhow!(
+                "service signature recovers to {recovered:?}, not the advertised {:?}",
+                self.address
+            ));
+        }
+        sig.v = 27 + u64::from(sig.recovery_id()?.to_byte());
+        Ok(sig)
+    }
+}
+
+#[async_trait]
+impl HashSigner for RemoteHttpSigner {
+    fn signer_address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_digest(&self, digest: H256) -> Result<Signature> {
+        self.sign_digest_remote(digest).await
+    }
+}
+
+#[async_trait]
+impl Signer for RemoteHttpSigner {
+    type Error = RemoteSignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, Self::Error> {
+        self.sign_digest_remote(hash_message(message))
+            .await
+            .map_err(RemoteSignerError)
+    }
+
+    /// Same chain id handling as `LocalWallet`
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        let chain_id = tx.chain_id().map(|id| id.as_u64()).unwrap_or(self.chain_id);
+        let mut tx = tx.clone();
+        tx.set_chain_id(chain_id);
+        let mut sig = self
+            .sign_digest_remote(tx.sighash())
+            .await
+            .map_err(RemoteSignerError)?;
+        sig.v = to_eip155_v(sig.v as u8 - 27, chain_id);
+        Ok(sig)
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        let encoded = payload
+            .encode_eip712()
+            .map_err(|e| RemoteSignerError(anyhow!("EIP-712 encoding failed: {e}")))?;
+        self.sign_digest_remote(H256::from(encoded))
+            .await
+            .map_err(RemoteSignerError)
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn with_chain_id<T: Into<u64>>(mut self, chain_id: T) -> Self {
+        self.chain_id = chain_id.into();
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::{self, SigningScheme};
+    use crate::test_support::{wallet, KEY_A, KEY_B};
+    use ethers::signers::LocalWallet;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const TOKEN: &str = "Bearer test-token";
+
+    /// One-connection-at-a-time HTTP server that signs posted digests with
+    /// `wallet`, refusing requests without the expected Authorization header
+    async fn mock_service(wallet: LocalWallet) -> String {
+        mock_service_with_v(wallet, 27).await
+    }
+
+    /// `mock_service` answering with v counted from `v_base` (0 or 27)
+    async fn mock_service_with_v(wallet: LocalWallet, v_base: u8) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/sign", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut conn, _) = listener.accept().await.unwrap();
+                let request = read_request(&mut conn).await;
+                let head = String::from_utf8_lossy(&request).to_lowercase();
+                let response = if head.contains(&format!("authorization: {}", TOKEN.to_lowercase()))
+                {
+                    let digest = H256::from_slice(&request[request.len() - 32..]);
+                    let mut sig = wallet.sign_hash(digest).unwrap().to_vec();
+                    sig[64] = sig[64] - 27 + v_base;
+                    let mut r = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        sig.len()
+                    )
+                    .into_bytes();
+                    r.extend(sig);
+                    r
+                } else {
+                    b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .to_vec()
+                };
+                conn.write_all(&response).await.unwrap();
+            }
+        });
+        url
+    }
+
+    /// Headers plus a 32-byte body
+    async fn read_request(conn: &mut tokio::net::TcpStream) -> Vec<u8> {
+        let mut request = Vec::new();
+        let mut buf = [0u8; 1024];
+        loop {
+            if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                if request.len() >= end + 4 + 32 {
+                    return request;
+                }
+            }
+            let n = conn.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+    }
+
+    fn unsigned() -> serde_json::Value {
+        json!({ "id": "snap-1", "body": { "n": 1 }, "signing": { "scheme": SigningScheme::Eip191.as_str() } })
+    }
+
+    #[tokio::test]
+    async fn remote_signature_verifies() {
+        let wallet = wallet(KEY_A);
+        let address = Signer::address(&wallet);
+        let remote = RemoteHttpSigner::new(mock_service(wallet.clone()).await, address)
+            .with_header_line(&format!("Authorization: {TOKEN}"))
+            .unwrap();
+
+        let mut snap = unsigned();
+        let (_, signer) = signing::sign_snapshot(&mut snap, &remote).await.unwrap();
+        assert_eq!(signer, address);
+        signing::verify_signature(&snap, address).unwrap();
+
+        // The ethers Signer side produces what a local key would
+        assert_eq!(
+            remote.sign_message(b"hello").await.unwrap(),
+            wallet.sign_message(b"hello").await.unwrap()
+        );
+
+        let unauthenticated = RemoteHttpSigner::new(remote.url.clone(), address);
+        let err = signing::sign_snapshot(&mut unsigned(), &unauthenticated)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("401"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn raw_recovery_id_is_normalised() {
+        let wallet = wallet(KEY_A);
+        let remote = RemoteHttpSigner::new(
+            mock_service_with_v(wallet.clone(), 0).await,
+            Signer::address(&wallet),
+        )
+        .with_header("Authorization", TOKEN)
+        .unwrap();
+
+        assert_eq!(
+            remote.sign_message(b"hello").await.unwrap(),
+            wallet.sign_message(b"hello").await.unwrap()
+        );
+        let tx: TypedTransaction = ethers::core::types::TransactionRequest::new()
+            .to(Address::repeat_byte(0x11))
+            .value(1)
+            .chain_id(5)
+            .into();
+        assert_eq!(
+            remote.sign_transaction(&tx).await.unwrap(),
+            wallet.sign_transaction(&tx).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn signature_from_another_key_is_rejected() {
+        let advertised = Signer::address(&wallet(KEY_A));
+        let impostor = wallet(KEY_B);
+        let remote = RemoteHttpSigner::new(mock_service(impostor).await, advertised)
+            .with_header("Authorization", TOKEN)
+            .unwrap();
+
+        let mut snap = unsigned();
+        let err = signing::sign_snapshot(&mut snap, &remote)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not the advertised"), "{err}");
+        assert_eq!(snap["signing"].get("signature"), None);
+    }
+}
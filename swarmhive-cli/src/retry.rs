@@ -0,0 +1,266 @@
+//! Retrying on-chain reads
+//!
+//! RPC endpoints drop connections and time out. `Retrying` wraps any of the
+//! reader traits the on-chain checks use and retries a call that errored,
+//! with exponential backoff, up to a `RetryPolicy`'s attempt limit. Only
+//! transport errors are retried: a call that returns, even with an answer
+//! such as "no such name", is final, and so is a call the contract
+//! reverted, as many wallets do to reject a signature.
+
+use crate::account::AccountReader;
+use crate::anchor::AnchorReader;
+use crate::eip1271::ContractSignatureReader;
+use crate::ens::EnsResolver;
+use crate::quorum::{self, SafeReader};
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::core::types::{Address, U256};
+use std::future::Future;
+use std::time::Duration;
+
+/// How often and how patiently to retry a failing call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total tries, including the first; at least 1
+    pub max_attempts: u32,
+    /// Wait before the first retry
+    pub initial_backoff: Duration,
+    /// Each later wait is twice the previous one, up to this
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// Three tries, 250ms then 500ms apart
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// One try, no retries
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Run `call` until it succeeds, the contract reverts or the attempts
+    /// run out. The error is the last attempt's, noting how many were made.
+    pub async fn run<T, F, Fut>(&self, mut call: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let attempts = self.max_attempts.max(1);
+        let mut backoff = self.initial_backoff;
+        let mut attempt = 1;
+        loop {
+            match call().await {
+                Ok(value) => return Ok(value),
+                Err(e) if quorum::reverted(&e).is_some() => return Err(e),
+                Err(e) if attempt >= attempts => {
+                    return Err(if attempts > 1 {
+                        e.context(format!("failed after {attempts} attempts"))
+                    } else {
+                        e
+                    })
+                }
+                Err(_) => {
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(self.max_backoff);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+/// A reader whose calls are retried under `policy`
+#[derive(Debug, Clone)]
+pub struct Retrying<R> {
+    pub inner: R,
+    pub policy: RetryPolicy,
+}
+
+impl<R> Retrying<R> {
+    pub fn new(inner: R, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+}
+
+#[async_trait]
+impl<R: EnsResolver + Sync> EnsResolver for Retrying<R> {
+    async fn lookup_address(&self, addr: Address) -> Result<Option<String>> {
+        self.policy.run(|| self.inner.lookup_address(addr)).await
+    }
+
+    async fn resolve_name(&self, name: &str) -> Result<Option<Address>> {
+        self.policy.run(|| self.inner.resolve_name(name)).await
+    }
+}
+
+#[async_trait]
+impl<R: AccountReader + Sync> AccountReader for Retrying<R> {
+    async fn balance(&self, addr: Address) -> Result<U256> {
+        self.policy.run(|| self.inner.balance(addr)).await
+    }
+
+    async fn transaction_count(&self, addr: Address) -> Result<U256> {
+        self.policy.run(|| self.inner.transaction_count(addr)).await
+    }
+}
+
+#[async_trait]
+impl<R: AnchorReader + Sync> AnchorReader for Retrying<R> {
+    async fn anchored_hash(&self, contract: Address, key: [u8; 32]) -> Result<[u8; 32]> {
+        self.policy
+            .run(|| self.inner.anchored_hash(contract, key))
+            .await
+    }
+}
+
+#[async_trait]
+impl<R: ContractSignatureReader + Sync> ContractSignatureReader for Retrying<R> {
+    async fn is_valid_signature(
+        &self,
+        contract: Address,
+        hash: [u8; 32],
+        signature: &[u8],
+    ) -> Result<[u8; 4]> {
+        self.policy
+            .run(|| self.inner.is_valid_signature(contract, hash, signature))
+            .await
+    }
+
+    async fn domain_separator(&self, safe: Address) -> Result<[u8; 32]> {
+        self.policy.run(|| self.inner.domain_separator(safe)).await
+    }
+}
+
+#[async_trait]
+impl<R: SafeReader + Sync> SafeReader for Retrying<R> {
+    async fn owners(&self, safe: Address) -> Result<Vec<Address>> {
+        self.policy.run(|| self.inner.owners(safe)).await
+    }
+
+    async fn threshold(&self, safe: Address) -> Result<u64> {
+        self.policy.run(|| self.inner.threshold(safe)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eip1271::{self, EIP1271_MAGIC};
+    use crate::quorum::Reverted;
+    use anyhow::anyhow;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Times out `failures` times, then answers `answer`, or reverts if
+    /// there is none
+    struct FlakyContract {
+        failures: u32,
+        answer: Option<[u8; 4]>,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl ContractSignatureReader for FlakyContract {
+        async fn is_valid_signature(
+            &self,
+            contract: Address,
+            _hash: [u8; 32],
+            _signature: &[u8],
+        ) -> Result<[u8; 4]> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(anyhow!("eth_call timed out"));
+            }
+            self.answer.ok_or_else(|| {
+                Reverted {
+                    to: contract,
+                    message: "execution reverted: GS026".to_string(),
+                }
+                .into()
+            })
+        }
+
+        async fn domain_separator(&self, _safe: Address) -> Result<[u8; 32]> {
+            unreachable!("only eip1271 snapshots are checked")
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        }
+    }
+
+    fn reader(failures: u32, answer: Option<[u8; 4]>) -> Retrying<FlakyContract> {
+        Retrying::new(
+            FlakyContract {
+                failures,
+                answer,
+                calls: AtomicU32::new(0),
+            },
+            policy(),
+        )
+    }
+
+    fn contract_signed() -> serde_json::Value {
+        let mut snap = json!({
+            "id": "snap-1",
+            "signing": { "scheme": eip1271::EIP1271_SCHEME, "contract": format!("{:?}", Address::repeat_byte(0x5a)) }
+        });
+        eip1271::attach_contract_signature(&mut snap, &[0xab; 65]).unwrap();
+        snap
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried() {
+        let flaky = reader(2, Some(EIP1271_MAGIC));
+        eip1271::verify_contract_signature(&contract_signed(), &flaky)
+            .await
+            .unwrap();
+        assert_eq!(flaky.inner.calls.load(Ordering::SeqCst), 3);
+
+        let down = reader(5, Some(EIP1271_MAGIC));
+        let err = eip1271::verify_contract_signature(&contract_signed(), &down)
+            .await
+            .unwrap_err();
+        assert!(
+            format!("{err:#}").contains("failed after 3 attempts: eth_call timed out"),
+            "{err:#}"
+        );
+        assert_eq!(down.inner.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn invalid_signature_is_not_retried() {
+        let rejecting = reader(0, Some([0xff; 4]));
+        let err = eip1271::verify_contract_signature(&contract_signed(), &rejecting)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("rejected the signature"), "{err}");
+        assert_eq!(rejecting.inner.calls.load(Ordering::SeqCst), 1);
+
+        // A wallet that rejects by reverting, after one timeout
+        let reverting = reader(1, None);
+        let err = eip1271::verify_contract_signature(&contract_signed(), &reverting)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("rejected the signature (call to"),
+            "{err}"
+        );
+        assert!(err.to_string().contains("GS026"), "{err}");
+        assert_eq!(reverting.inner.calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,202 @@
+//! Signer key rotation
+//!
+//! A rotation proof is a small snapshot, signed by the old key, stating
+//! that a new address now signs for it. A verifier that trusts the old
+//! address can follow one or more proofs to accept the new key without
+//! any out-of-band coordination.
+
+use crate::signing::{self, SigningScheme};
+use anyhow::{anyhow, Result};
+use ethers::core::types::Address;
+use ethers::signers::{LocalWallet, Signer};
+use serde_json::{json, Value};
+
+/// `type` of a rotation proof
+pub const ROTATION_TYPE: &str = "key-rotation";
+
+/// Build and sign a proof that `old` hands signing over to `new`
+pub async fn rotation_proof(old: &LocalWallet, new: Address, issued_at: i64) -> Result<Value> {
+    let mut proof = json!({
+        "type": ROTATION_TYPE,
+        "rotation": {
+            "from": format!("{:?}", old.address()),
+            "to": format!("{new:?}"),
+        },
+        "signing": {
+            "scheme": SigningScheme::Eip191.as_str(),
+            "issued_at": issued_at,
+        }
+    });
+    signing::sign_snapshot(&mut proof, old).await?;
+    Ok(proof)
+}
+
+/// Check `proof` is a rotation signed by `from` and return the address it
+/// authorizes
+pub fn verify_rotation_proof(proof: &Value, from: Address) -> Result<Address> {
+    if proof.get("type").and_then(|v| v.as_str()) != Some(ROTATION_TYPE) {
+        return Err(anyhow!("not a {ROTATION_TYPE} proof"));
+    }
+    let rotation = proof
+        .get("rotation")
+        .ok_or_else(|| anyhow!("rotation proof missing rotation object"))?;
+    let field = |key: &str| -> Result<Address> {
+        rotation
+            .get(key)
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("rotation proof missing rotation.{key}"))?
+            .parse()
+            .map_err(|e| anyhow!("rotation.{key} is not an address: {e}"))
+    };
+
+    let claimed_from = field("from")?;
+    if claimed_from != from {
+        return Err(anyhow!(
+            "rotation proof is from {claimed_from:?}, expected {from:?}"
+        ));
+    }
+    signing::verify_signature(proof, from)
+        .map_err(|e| anyhow!("rotation proof from {from:?} is not valid: {e}"))?;
+    field("to")
+}
+
+/// Verify `new_snapshot` is signed by a key that `old_addr` rotated to via
+/// `rotation_proof`. Returns the new signer.
+pub fn verify_rotation(
+    new_snapshot: &Value,
+    rotation_proof: &Value,
+    old_addr: Address,
+) -> Result<Address> {
+    verify_rotation_chain(new_snapshot, std::slice::from_ref(rotation_proof), old_addr)
+}
+
+/// Like `verify_rotation`, following several rotations in order: each
+/// proof must be signed by the key the previous one authorized, starting
+/// from `root`, and the snapshot by the last authorized key
+pub fn verify_rotation_chain(snapshot: &Value, proofs: &[Value], root: Address) -> Result<Address> {
+    let mut current = root;
+    for (i, proof) in proofs.iter().enumerate() {
+        current = verify_rotation_proof(proof, current)
+            .map_err(|e| anyhow!("rotation #{}: {e}", i + 1))?;
+    }
+    signing::verify_signature(snapshot, current)
+        .map_err(|e| anyhow!("snapshot is not signed by rotated key {current:?}: {e}"))?;
+    Ok(current)
+}
+
+/// Verify `current` against the signer its predecessor in a chain handed
+/// over to: `predecessor` must carry a valid signature and name the next
+/// key in its signing.next_signer (covered by its hash), and `current` must
+/// recover to that key. Returns the current signer.
+pub fn verify_with_predecessor(current: &Value, predecessor: &Value) -> Result<Address> {
+    let previous = signing::recover_signer(predecessor).map_err(|e| anyhow!("predecessor: {e}"))?;
+    let next = signing::string_field(signing::signing_object(predecessor)?, "next_signer")
+        .map_err(|e| anyhow!("predecessor: {e}"))?
+        .ok_or_else(|| {
+            anyhow!("predecessor signed by {previous:?} names no signing.next_signer")
+        })?;
+    let next: Address = next
+        .parse()
+        .map_err(|e| anyhow!("predecessor signing.next_signer {next:?} is not an address: {e}"))?;
+    signing::verify_signature(current, next).map_err(|e| {
+        anyhow!("snapshot is not signed by {next:?}, the signer named by its predecessor: {e}")
+    })?;
+    Ok(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{wallet, KEY_A, KEY_B};
+
+    async fn signed_by(wallet: &LocalWallet) -> Value {
+        let mut snap =
+            json!({ "id": "snap-1", "body": { "n": 1 }, "signing": { "scheme": "eip191" } });
+        signing::sign_snapshot(&mut snap, wallet).await.unwrap();
+        snap
+    }
+
+    #[tokio::test]
+    async fn rotated_key_chains_back_to_old_key() {
+        let old = wallet(KEY_A);
+        let new = wallet(KEY_B);
+
+        let proof = rotation_proof(&old, new.address(), 1_700_000_000)
+            .await
+            .unwrap();
+        let snap = signed_by(&new).await;
+
+        assert_eq!(
+            verify_rotation(&snap, &proof, old.address()).unwrap(),
+            new.address()
+        );
+        // The new key's snapshot does not verify for the old address directly
+        assert!(signing::verify_signature(&snap, old.address()).is_err());
+    }
+
+    #[tokio::test]
+    async fn forged_rotation_proof_fails() {
+        let old = wallet(KEY_A);
+        let new = wallet(KEY_B);
+        let snap = signed_by(&new).await;
+
+        // The new key vouches for itself, claiming to be the old key's rotation
+        let mut forged = rotation_proof(&new, new.address(), 1_700_000_000)
+            .await
+            .unwrap();
+        forged["rotation"]["from"] = json!(format!("{:?}", old.address()));
+        let err = verify_rotation(&snap, &forged, old.address()).unwrap_err();
+        assert!(err.to_string().contains("is not valid"), "{err}");
+
+        // A genuine proof retargeted at another address after signing
+        let mut altered = rotation_proof(&old, Address::repeat_byte(0x11), 1_700_000_000)
+            .await
+            .unwrap();
+        altered["rotation"]["to"] = json!(format!("{:?}", new.address()));
+        assert!(verify_rotation(&snap, &altered, old.address()).is_err());
+    }
+
+    async fn handing_over_to(wallet: &LocalWallet, next: Address) -> Value {
+        let mut snap = json!({
+            "id": "snap-1",
+            "body": { "n": 1 },
+            "signing": { "scheme": "eip191", "next_signer": format!("{next:?}") }
+        });
+        signing::sign_snapshot(&mut snap, wallet).await.unwrap();
+        snap
+    }
+
+    #[tokio::test]
+    async fn predecessor_names_current_signer() {
+        let old = wallet(KEY_A);
+        let new = wallet(KEY_B);
+        let current = signed_by(&new).await;
+
+        let predecessor = handing_over_to(&old, new.address()).await;
+        assert_eq!(
+            verify_with_predecessor(&current, &predecessor).unwrap(),
+            new.address()
+        );
+
+        let elsewhere = handing_over_to(&old, Address::repeat_byte(0x22)).await;
+        let err = verify_with_predecessor(&current, &elsewhere).unwrap_err();
+        assert!(
+            err.to_string().contains("named by its predecessor"),
+            "{err}"
+        );
+
+        // The handover only counts while the predecessor's signature covers it
+        let mut forged = predecessor.clone();
+        forged["signing"]["next_signer"] = json!(format!("{:?}", Address::repeat_byte(0x33)));
+        assert!(verify_with_predecessor(&current, &forged)
+            .unwrap_err()
+            .to_string()
+            .starts_with("predecessor:"));
+
+        let err = verify_with_predecessor(&current, &signed_by(&old).await).unwrap_err();
+        assert!(
+            err.to_string().contains("names no signing.next_signer"),
+            "{err}"
+        );
+    }
+}
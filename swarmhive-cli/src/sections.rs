@@ -0,0 +1,192 @@
+//! Per-section signatures
+//!
+//! Parts of one document can be signed independently, e.g. `/metadata` by
+//! the operator and `/results` by the worker. Each signature covers only its
+//! section and is stored in `signing.sections`, keyed by JSON Pointer:
+//!
+//! ```text
+//! "sections": { "/results": { "payload_hash": "keccak256:..", "signature": "eip191:0x..", "signer": "0x.." } }
+//! ```
+//!
+//! The section hash binds the pointer as well as the value, so a signed
+//! section cannot be moved elsewhere in the document. `signing.sections` is
+//! not part of the whole-document payload hash.
+
+use crate::hash::keccak256;
+use crate::signing::{
+    self, hash_str, parse_payload_hash, parse_signature, signature_str, SigningScheme,
+};
+use anyhow::{anyhow, Result};
+use ethers::core::types::Address;
+use ethers::signers::LocalWallet;
+use serde_json::{json, Map, Value};
+
+/// keccak256 of canonical `{"pointer": <pointer>, "value": <section>}`
+pub fn section_hash(snapshot: &Value, pointer: &str) -> Result<[u8; 32]> {
+    let value = snapshot
+        .pointer(pointer)
+        .ok_or_else(|| anyhow!("no section at {pointer}"))?;
+    if pointer.is_empty() || pointer == "/signing" || pointer.starts_with("/signing/") {
+        return Err(anyhow!(
+            "section {pointer:?} must be a part of the content, not the whole or signing"
+        ));
+    }
+    let version = signing::snapshot_canonical_version(snapshot)?;
+    let bound = json!({ "pointer": pointer, "value": value });
+    Ok(keccak256(signing::canonical_json_bytes_with(
+        &bound, version,
+    )?))
+}
+
+/// Sign the section at `pointer` with `wallet`, replacing any previous
+/// signature for that section. Other sections are untouched.
+pub async fn sign_section(
+    snapshot: &mut Value,
+    pointer: &str,
+    wallet: &LocalWallet,
+) -> Result<Address> {
+    let hash = section_hash(snapshot, pointer)?;
+    let (sig, signer) = signing::sign_hash(wallet, hash, SigningScheme::Eip191).await?;
+
+    let sections = snapshot
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("snapshot must be a JSON object"))?
+        .entry("signing")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("signing must be object"))?
+        .entry("sections")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("signing.sections must be an object"))?;
+    sections.insert(
+        pointer.to_string(),
+        json!({
+            "payload_hash": hash_str(hash),
+            "signature": signature_str(&sig),
+            "signer": format!("{signer:?}"),
+        }),
+    );
+    Ok(signer)
+}
+
+/// Verify every entry in signing.sections against its section's current
+/// content. Returns (pointer, signer) pairs; fails on the first bad section.
+pub fn verify_sections(snapshot: &Value) -> Result<Vec<(String, Address)>> {
+    let sections = match signing::signing_object(snapshot)?.get("sections") {
+        Some(v) => v
+            .as_object()
+            .ok_or_else(|| anyhow!("signing.sections must be an object"))?,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut verified = Vec::new();
+    for (pointer, entry) in sections {
+        let field = |key: &str| {
+            entry
+                .get(key)
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow!("section {pointer}: missing {key}"))
+        };
+        let stored = parse_payload_hash(field("payload_hash")?)?;
+        if section_hash(snapshot, pointer)? != stored {
+            return Err(anyhow!(
+                "section {pointer}: content changed since it was signed"
+            ));
+        }
+        let signer = signing::recover_hash_signer(
+            stored,
+            &parse_signature(field("signature")?)?,
+            SigningScheme::Eip191,
+        )?;
+        let claimed: Address = field("signer")?
+            .parse()
+            .map_err(|e| anyhow!("section {pointer}: bad signer: {e}"))?;
+        if claimed != signer {
+            return Err(anyhow!(
+                "section {pointer}: recovers to {signer:?}, not the listed {claimed:?}"
+            ));
+        }
+        verified.push((pointer.clone(), signer));
+    }
+    Ok(verified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{wallet, KEY_A, KEY_B};
+    use ethers::signers::Signer;
+
+    async fn two_sections() -> (Value, Address, Address) {
+        let a = wallet(KEY_A);
+        let b = wallet(KEY_B);
+        let mut snap = json!({
+            "id": "job-1-report",
+            "metadata": { "operator": "a.eth", "model": "llama-7b" },
+            "results": { "tokens": 512, "ok": true }
+        });
+        sign_section(&mut snap, "/metadata", &a).await.unwrap();
+        sign_section(&mut snap, "/results", &b).await.unwrap();
+        (snap, a.address(), b.address())
+    }
+
+    #[tokio::test]
+    async fn two_keys_two_sections() {
+        let (snap, a, b) = two_sections().await;
+        assert_eq!(
+            verify_sections(&snap).unwrap(),
+            vec![("/metadata".to_string(), a), ("/results".to_string(), b)]
+        );
+
+        // Re-signing one section leaves the other valid
+        let mut resigned = snap.clone();
+        let c = signing::generate_keypair();
+        sign_section(&mut resigned, "/results", &c).await.unwrap();
+        assert_eq!(
+            verify_sections(&resigned).unwrap()[0],
+            ("/metadata".to_string(), a)
+        );
+    }
+
+    #[tokio::test]
+    async fn tampering_one_section_is_caught() {
+        let (mut snap, _, _) = two_sections().await;
+        snap["results"]["tokens"] = json!(4096);
+        let err = verify_sections(&snap).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "section /results: content changed since it was signed"
+        );
+
+        // The untouched section alone still verifies
+        snap["signing"]["sections"]
+            .as_object_mut()
+            .unwrap()
+            .remove("/results");
+        assert_eq!(verify_sections(&snap).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn sections_do_not_affect_document_hash() {
+        let mut plain =
+            json!({ "id": "x", "results": { "n": 1 }, "signing": { "scheme": "eip191" } });
+        let before = signing::payload_hash_keccak(&plain).unwrap();
+        sign_section(&mut plain, "/results", &signing::generate_keypair())
+            .await
+            .unwrap();
+        assert_eq!(signing::payload_hash_keccak(&plain).unwrap(), before);
+    }
+
+    #[tokio::test]
+    async fn invalid_canonical_version_is_not_hashed_as_v1() {
+        let (mut snap, _, _) = two_sections().await;
+        snap["signing"]["canonical_version"] = json!("two");
+        let err = verify_sections(&snap).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("canonical_version must be an integer"),
+            "{err:#}"
+        );
+    }
+}
@@ -1,53 +1,122 @@
 // src/signing.rs
+use crate::address::{self, AddressDeriver, EthereumDeriver};
+use crate::canonical::CanonicalProfile;
+use crate::layout::{DefaultLayout, SigningLayout};
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use ethers::core::k256::ecdsa::SigningKey;
 use ethers::core::types::{Address, Signature, H256};
 use ethers::signers::{LocalWallet, Signer};
-use ethers::utils::keccak256;
-use serde_json::Value;
+use ethers::utils::{hash_message, keccak256};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::str::FromStr;
 
-pub fn canonical_json_bytes(v: &Value) -> Result<Vec<u8>> {
-    // Canonical JSON: stable key order, no whitespace.
-    // serde_json preserves map order as inserted; we must sort keys.
-    let normalized = sort_json(v);
-    Ok(serde_json::to_vec(&normalized)?)
+pub(crate) use crate::hash::UNHASHED_SIGNING_KEYS;
+pub use crate::hash::{
+    alg_hash_str, assert_canonical, canonical_json_bytes, canonical_json_bytes_with, hash_str,
+    parse_alg_hash, parse_payload_hash, payload_hash, payload_hash_keccak,
+    payload_hash_keccak_with_len, payload_hash_keccak_with_profile, payload_hash_with_alg,
+    payload_preimage, snapshot_canonical_profile, snapshot_canonical_version,
+    snapshot_payload_encoding, CanonicalVersion, HashAlg, PayloadEncoding,
+};
+
+/// Signature scheme recorded in `signing.scheme`.
+/// Selects which message bytes were handed to `sign_message`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningScheme {
+    /// EIP-191 personal message over the 32-byte payload hash
+    Eip191,
+    /// EIP-191 personal message over keccak256(payload hash).
+    /// Some integrations hash the payload hash once more before calling
+    /// `sign_message`; this scheme names that quirk instead of guessing.
+    Eip191Prehash,
+    /// Legacy Trezor "sign message" over the 32-byte payload hash. Older
+    /// Trezor firmware wrote the message length as a Bitcoin-style varint
+    /// (CompactSize) rather than decimal ASCII, so the signed digest is
+    /// keccak256("\x19Ethereum Signed Message:\n" || varint(32) || hash),
+    /// i.e. a single 0x20 length byte where EIP-191 has the characters "32".
+    Trezor,
+    /// EIP-191 version 0x00 ("data with intended validator"): the digest is
+    /// keccak256(0x19 || 0x00 || validator || payload hash), scoping the
+    /// signature to one validator contract. The address is read from
+    /// signing.validator, which is part of the payload hash.
+    Eip191Validator(Address),
 }
 
-fn sort_json(v: &Value) -> Value {
-    match v {
-        Value::Object(map) => {
-            let mut keys: Vec<_> = map.keys().cloned().collect();
-            keys.sort();
-            let mut new_map = serde_json::Map::new();
-            for k in keys {
-                new_map.insert(k.clone(), sort_json(&map[&k]));
+impl SigningScheme {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SigningScheme::Eip191 => "eip191",
+            SigningScheme::Eip191Prehash => "eip191-prehash",
+            SigningScheme::Trezor => "trezor",
+            SigningScheme::Eip191Validator(_) => "eip191-validator",
+        }
+    }
+
+    /// Digest the signature is made over for a given payload hash
+    fn digest(&self, payload_hash: [u8; 32]) -> H256 {
+        match self {
+            SigningScheme::Eip191 => hash_message(payload_hash),
+            SigningScheme::Eip191Prehash => hash_message(keccak256(payload_hash)),
+            SigningScheme::Trezor => {
+                let mut preimage = TREZOR_PREFIX.to_vec();
+                preimage.extend(compact_size(payload_hash.len()));
+                preimage.extend_from_slice(&payload_hash);
+                H256(keccak256(preimage))
+            }
+            SigningScheme::Eip191Validator(validator) => {
+                let mut preimage = vec![0x19, 0x00];
+                preimage.extend_from_slice(validator.as_bytes());
+                preimage.extend_from_slice(&payload_hash);
+                H256(keccak256(preimage))
             }
-            Value::Object(new_map)
         }
-        Value::Array(arr) => Value::Array(arr.iter().map(sort_json).collect()),
-        _ => v.clone(),
     }
 }
 
-/// Compute keccak256 over canonical JSON bytes, excluding signing.signature.
-/// You should pass a snapshot JSON where signing.signature is empty or removed.
-pub fn payload_hash_keccak(snapshot: &Value) -> Result<[u8; 32]> {
-    let mut snap = snapshot.clone();
+/// Magic shared by EIP-191 and the legacy Trezor convention
+const TREZOR_PREFIX: &[u8] = b"\x19Ethereum Signed Message:\n";
 
-    // Remove signing.signature if present
-    if let Some(signing) = snap.get_mut("signing") {
-        if let Some(obj) = signing.as_object_mut() {
-            obj.remove("signature");
-        }
+/// Bitcoin CompactSize encoding of a length
+fn compact_size(len: usize) -> Vec<u8> {
+    match len {
+        0..=0xfc => vec![len as u8],
+        0xfd..=0xffff => [&[0xfd][..], &(len as u16).to_le_bytes()].concat(),
+        _ => [&[0xfe][..], &(len as u32).to_le_bytes()].concat(),
     }
+}
 
-    let bytes = canonical_json_bytes(&snap)?;
-    Ok(keccak256(bytes))
+impl fmt::Display for SigningScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
-/// Convert [u8;32] to "keccak256:<hex>"
-pub fn hash_str(hash: [u8; 32]) -> String {
-    format!("keccak256:{}", hex::encode(hash))
+impl FromStr for SigningScheme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "eip191" => Ok(SigningScheme::Eip191),
+            "eip191-prehash" => Ok(SigningScheme::Eip191Prehash),
+            "trezor" => Ok(SigningScheme::Trezor),
+            "eip191-validator" => Err(anyhow!(
+                "eip191-validator needs a validator address (signing.validator)"
+            )),
+            "eip1271" | "safe-eip1271" => Err(anyhow!(
+                "{s} is a contract signature; verify it with eip1271::verify_contract_signature"
+            )),
+            other => Err(anyhow!("unsupported signing scheme: {other}")),
+        }
+    }
+}
+
+/// Generate a fresh random keypair
+pub fn generate_keypair() -> LocalWallet {
+    LocalWallet::new(&mut ethers::core::rand::thread_rng())
 }
 
 /// Load LocalWallet from a hex private key (0x... or raw hex)
@@ -63,89 +132,1266 @@ pub fn wallet_from_private_key_hex(pk_hex: &str) -> Result<LocalWallet> {
     Ok(wallet)
 }
 
+/// Signs a 32-byte digest exactly as given (no message prefix is added;
+/// `SigningScheme::digest` has already applied it). Implemented for
+/// `LocalWallet` and for keys held outside the process. Object safe.
+#[async_trait]
+pub trait HashSigner: Send + Sync {
+    /// Address the signatures must recover to
+    fn signer_address(&self) -> Address;
+    async fn sign_digest(&self, digest: H256) -> Result<Signature>;
+    /// Whether this backend can produce signatures under `scheme`. Raw
+    /// digest signers can sign anything; a device that only signs
+    /// personal messages would accept eip191 alone.
+    fn supports_scheme(&self, _scheme: &SigningScheme) -> bool {
+        true
+    }
+}
+
+/// Schemes `negotiate_scheme` chooses between. eip191-validator is not one:
+/// it needs a validator address from the snapshot.
+pub const NEGOTIABLE_SCHEMES: [SigningScheme; 3] = [
+    SigningScheme::Eip191,
+    SigningScheme::Eip191Prehash,
+    SigningScheme::Trezor,
+];
+
+/// The first of `preferred` that is in `supported`, falling back to eip191,
+/// which every verifier and key backend handles
+pub fn negotiate_scheme(preferred: &[SigningScheme], supported: &[SigningScheme]) -> SigningScheme {
+    preferred
+        .iter()
+        .find(|scheme| supported.contains(scheme))
+        .copied()
+        .unwrap_or(SigningScheme::Eip191)
+}
+
+/// `negotiate_scheme` against what `signer` supports
+pub fn negotiate_scheme_for(
+    preferred: &[SigningScheme],
+    signer: &(impl HashSigner + ?Sized),
+) -> SigningScheme {
+    let supported: Vec<SigningScheme> = NEGOTIABLE_SCHEMES
+        .into_iter()
+        .filter(|scheme| signer.supports_scheme(scheme))
+        .collect();
+    negotiate_scheme(preferred, &supported)
+}
+
+/// A signing backend chosen at runtime: a local key, a remote signer, an
+/// HSM. Every function taking `&(impl HashSigner + ?Sized)` accepts one.
+pub type BoxedSigner = Box<dyn HashSigner>;
+
+#[async_trait]
+impl<S: HashSigner + ?Sized> HashSigner for Box<S> {
+    fn signer_address(&self) -> Address {
+        (**self).signer_address()
+    }
+
+    async fn sign_digest(&self, digest: H256) -> Result<Signature> {
+        (**self).sign_digest(digest).await
+    }
+
+    fn supports_scheme(&self, scheme: &SigningScheme) -> bool {
+        (**self).supports_scheme(scheme)
+    }
+}
+
+#[async_trait]
+impl HashSigner for LocalWallet {
+    fn signer_address(&self) -> Address {
+        self.address()
+    }
+
+    async fn sign_digest(&self, digest: H256) -> Result<Signature> {
+        Ok(self.sign_hash(digest)?)
+    }
+}
+
 /// EIP-191 signing of 32-byte hash (as message bytes)
 /// Returns signature bytes (65) and recovered address
-pub async fn sign_eip191_hash(wallet: &LocalWallet, hash: [u8; 32]) -> Result<(Signature, Address)> {
-    // EIP-191: sign_message applies the "\x19Ethereum Signed Message:\n" prefix
-    let sig = wallet.sign_message(hash).await?;
-    let addr = wallet.address();
+pub async fn sign_eip191_hash(
+    wallet: &(impl HashSigner + ?Sized),
+    hash: [u8; 32],
+) -> Result<(Signature, Address)> {
+    sign_hash(wallet, hash, SigningScheme::Eip191).await
+}
+
+/// Sign a payload hash under the given scheme.
+/// Returns signature bytes (65) and recovered address
+pub async fn sign_hash(
+    wallet: &(impl HashSigner + ?Sized),
+    hash: [u8; 32],
+    scheme: SigningScheme,
+) -> Result<(Signature, Address)> {
+    // The digest carries the scheme's "\x19Ethereum Signed Message:\n" prefix
+    let digest = scheme.digest(hash);
+    let sig = wallet.sign_digest(digest).await?;
+    let addr = wallet.signer_address();
 
     // Safety check: ensure signature recovers to addr
-    let recovered = sig.recover(hash)?;
+    let recovered = sig.recover(digest)?;
     if recovered != addr {
         return Err(anyhow!("signature recovery mismatch"));
     }
     Ok((sig, addr))
 }
 
-/// Attach signing fields into snapshot:
-/// - signing.payload_hash
-/// - signing.signature = eip191:0x...
-pub fn attach_signature(snapshot: &mut Value, payload_hash: [u8; 32], sig: &Signature) -> Result<()> {
+/// Hash, sign and attach in one step, under the scheme already named in
+/// signing.scheme (eip191 if unset). Returns the payload hash and signer.
+pub async fn sign_snapshot(
+    snapshot: &mut Value,
+    wallet: &(impl HashSigner + ?Sized),
+) -> Result<([u8; 32], Address)> {
+    let signing = snapshot
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("snapshot must be a JSON object"))?
+        .entry("signing")
+        .or_insert_with(|| Value::Object(Map::new()));
+    if !signing.is_object() {
+        return Err(anyhow!("signing must be object"));
+    }
+    let scheme = snapshot_scheme(snapshot)?;
+    snapshot["signing"]["scheme"] = Value::String(scheme.to_string());
+
+    let hash = payload_hash_keccak(snapshot)?;
+    let (sig, addr) = sign_hash(wallet, hash, scheme).await?;
+    attach_signature_with_scheme(snapshot, hash, &sig, scheme)?;
+    Ok((hash, addr))
+}
+
+/// Like `sign_snapshot`, hashing under `profile` and recording it in
+/// signing.canonical_profile so verifiers apply the same rules without
+/// being told. The recorded profile is itself hashed.
+pub async fn sign_snapshot_with_profile(
+    snapshot: &mut Value,
+    wallet: &(impl HashSigner + ?Sized),
+    profile: &CanonicalProfile,
+) -> Result<([u8; 32], Address)> {
     let signing = snapshot
-        .get_mut("signing")
-        .ok_or_else(|| anyhow!("snapshot missing signing object"))?
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("snapshot must be a JSON object"))?
+        .entry("signing")
+        .or_insert_with(|| Value::Object(Map::new()))
         .as_object_mut()
         .ok_or_else(|| anyhow!("signing must be object"))?;
+    if *profile == CanonicalProfile::default() {
+        signing.remove("canonical_profile");
+    } else {
+        signing.insert("canonical_profile".to_string(), profile.to_value());
+    }
+    sign_snapshot(snapshot, wallet).await
+}
 
-    signing.insert("payload_hash".to_string(), Value::String(hash_str(payload_hash)));
+/// Sign over the `primary` hash and publish each of `algs` in
+/// signing.payload_hashes, for swarms migrating between hash algorithms.
+/// signing.primary_hash names the hash the signature covers and is itself
+/// hashed. With a keccak256 primary, signing.payload_hash is written too so
+/// verifiers that only know that field keep working.
+pub async fn sign_snapshot_multi_hash(
+    snapshot: &mut Value,
+    wallet: &(impl HashSigner + ?Sized),
+    algs: &[HashAlg],
+    primary: HashAlg,
+) -> Result<Address> {
+    if !algs.contains(&primary) {
+        return Err(anyhow!(
+            "primary hash {} must be one of the published hashes",
+            primary.as_str()
+        ));
+    }
+    let signing = snapshot
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("snapshot must be a JSON object"))?
+        .entry("signing")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("signing must be object"))?;
     signing.insert(
-        "signature".to_string(),
-        Value::String(format!("eip191:0x{}", sig.to_string().trim_start_matches("0x"))),
+        "primary_hash".to_string(),
+        Value::String(primary.as_str().to_string()),
     );
-    signing.insert("scheme".to_string(), Value::String("eip191".to_string()));
+    let scheme = snapshot_scheme(snapshot)?;
+    snapshot["signing"]["scheme"] = Value::String(scheme.to_string());
+
+    let hashes = algs
+        .iter()
+        .map(|alg| {
+            Ok(Value::String(alg_hash_str(
+                *alg,
+                payload_hash_with_alg(snapshot, *alg)?,
+            )))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let hash = payload_hash_with_alg(snapshot, primary)?;
+    let (sig, addr) = sign_hash(wallet, hash, scheme).await?;
+
+    if primary == HashAlg::Keccak256 {
+        attach_signature_with_scheme(snapshot, hash, &sig, scheme)?;
+    }
+    let signing = snapshot["signing"]
+        .as_object_mut()
+        .expect("signing checked above");
+    if primary != HashAlg::Keccak256 {
+        signing.remove("payload_hash");
+        signing.insert("signature".to_string(), Value::String(signature_str(&sig)));
+    }
+    signing.insert("payload_hashes".to_string(), Value::Array(hashes));
+    Ok(addr)
+}
+
+/// Verify a snapshot signed by `sign_snapshot_multi_hash`: every entry of
+/// signing.payload_hashes recomputes, the signing.primary_hash algorithm is
+/// among them, and the signature over that hash recovers to
+/// `expected_addr`. Returns the algorithms checked, in listed order.
+pub fn verify_payload_hashes(snapshot: &Value, expected_addr: Address) -> Result<Vec<HashAlg>> {
+    let signing = signing_object(snapshot)?;
+    let primary: HashAlg = string_field(signing, "primary_hash")?
+        .ok_or_else(|| anyhow!("missing signing.primary_hash"))?
+        .parse()?;
+    let entries = signing
+        .get("payload_hashes")
+        .ok_or_else(|| anyhow!("missing signing.payload_hashes"))?
+        .as_array()
+        .ok_or_else(|| anyhow!("signing.payload_hashes must be an array"))?;
+
+    let mut algs = Vec::with_capacity(entries.len());
+    let mut primary_hash = None;
+    for (i, entry) in entries.iter().enumerate() {
+        let entry = entry.as_str().ok_or_else(|| {
+            anyhow!(
+                "signing.payload_hashes[{i}] must be a string, found {}",
+                json_type(entry)
+            )
+        })?;
+        let (alg, stored) = parse_alg_hash(entry)?;
+        if payload_hash_with_alg(snapshot, alg)? != stored {
+            return Err(anyhow!(
+                "{} payload hash mismatch: snapshot content changed",
+                alg.as_str()
+            ));
+        }
+        if alg == primary {
+            primary_hash = Some(stored);
+        }
+        algs.push(alg);
+    }
+    let hash = primary_hash.ok_or_else(|| {
+        anyhow!(
+            "primary hash {} is not listed in signing.payload_hashes",
+            primary.as_str()
+        )
+    })?;
+
+    let sig = parse_signature_value(
+        signing
+            .get("signature")
+            .ok_or_else(|| anyhow!("missing signing.signature"))?,
+    )?;
+    let recovered = recover_hash_signer(hash, &sig, snapshot_scheme(snapshot)?)?;
+    if recovered != expected_addr {
+        return Err(anyhow!("signature does not recover to expected address"));
+    }
+    Ok(algs)
+}
+
+/// Attach signing fields into snapshot:
+/// - signing.payload_hash
+/// - signing.signature = eip191:0x...
+pub fn attach_signature(snapshot: &mut Value, payload_hash: [u8; 32], sig: &Signature) -> Result<()> {
+    attach_signature_with_scheme(snapshot, payload_hash, sig, SigningScheme::Eip191)
+}
 
+/// Like `attach_signature`, recording `scheme` in signing.scheme.
+/// The scheme is part of the payload hash, so it must already be set on the
+/// snapshot before hashing.
+pub fn attach_signature_with_scheme(
+    snapshot: &mut Value,
+    payload_hash: [u8; 32],
+    sig: &Signature,
+    scheme: SigningScheme,
+) -> Result<()> {
+    attach_signature_with_layout(snapshot, payload_hash, sig, scheme, &DefaultLayout)
+}
+
+/// Like `attach_signature_with_scheme`, writing the fields where `layout`
+/// keeps them
+pub fn attach_signature_with_layout(
+    snapshot: &mut Value,
+    payload_hash: [u8; 32],
+    sig: &Signature,
+    scheme: SigningScheme,
+    layout: &dyn SigningLayout,
+) -> Result<()> {
+    layout.set_payload_hash(snapshot, &hash_str(payload_hash))?;
+    layout.set_signature(snapshot, &signature_str(sig))?;
+    layout.set_scheme(snapshot, scheme.as_str())?;
     Ok(())
 }
 
+/// Verify a snapshot under whichever scheme its signing.scheme names.
+/// A missing scheme is treated as eip191.
+pub fn verify_signature(snapshot: &Value, expected_addr: Address) -> Result<()> {
+    verify_signature_with_profile(snapshot, expected_addr, &CanonicalProfile::default())
+}
+
+/// Like `verify_signature`, recomputing the payload hash under `profile`
+pub fn verify_signature_with_profile(
+    snapshot: &Value,
+    expected_addr: Address,
+    profile: &CanonicalProfile,
+) -> Result<()> {
+    let scheme = snapshot_scheme(snapshot)?;
+    verify_with_scheme(snapshot, expected_addr, scheme, profile)
+}
+
 /// Verify signature matches payload hash and expected address.
 /// (ENS owner verification is a later layer; this verifies crypto correctness.)
 pub fn verify_eip191(snapshot: &Value, expected_addr: Address) -> Result<()> {
-    let signing = snapshot
+    verify_with_scheme(
+        snapshot,
+        expected_addr,
+        SigningScheme::Eip191,
+        &CanonicalProfile::default(),
+    )
+}
+
+/// Verify a snapshot whose signing fields are placed by `layout`, under the
+/// scheme the layout reports (eip191 when absent)
+pub fn verify_eip191_with_layout(
+    snapshot: &Value,
+    expected_addr: Address,
+    layout: &dyn SigningLayout,
+) -> Result<()> {
+    require_nonzero_expected(expected_addr)?;
+    let hash = parse_payload_hash(
+        layout
+            .payload_hash(snapshot)
+            .ok_or_else(|| anyhow!("missing payload hash"))?,
+    )?;
+    let sig = parse_signature(
+        layout
+            .signature(snapshot)
+            .ok_or_else(|| anyhow!("missing signature"))?,
+    )?;
+    let scheme = match layout.scheme(snapshot) {
+        Some(s) => s.parse()?,
+        None => SigningScheme::Eip191,
+    };
+
+    if recover_hash_signer(hash, &sig, scheme)? != expected_addr {
+        return Err(anyhow!("signature does not recover to expected address"));
+    }
+    if layout.payload_hash_keccak(snapshot)? != hash {
+        return Err(anyhow!("payload_hash mismatch: snapshot content changed"));
+    }
+    Ok(())
+}
+
+/// Verify a signature made over keccak256(payload hash)
+pub fn verify_eip191_prehash(snapshot: &Value, expected_addr: Address) -> Result<()> {
+    verify_with_scheme(
+        snapshot,
+        expected_addr,
+        SigningScheme::Eip191Prehash,
+        &CanonicalProfile::default(),
+    )
+}
+
+/// Recover the address that signed `snapshot`, checking that the content
+/// still hashes to signing.payload_hash.
+pub fn recover_signer(snapshot: &Value) -> Result<Address> {
+    recover_signer_with(snapshot, &EthereumDeriver)
+}
+
+/// Like `recover_signer`, mapping the recovered public key to an address
+/// with `deriver` instead of the Ethereum rule
+pub fn recover_signer_with(snapshot: &Value, deriver: &dyn AddressDeriver) -> Result<Address> {
+    let (hash, sig) = signed_parts(snapshot)?;
+    let digest = snapshot_scheme(snapshot)?.digest(hash);
+    let public_key = address::recover_public_key(digest.0, &sig)?;
+    check_content_hash(snapshot, hash, &CanonicalProfile::default())?;
+    nonzero_signer(deriver.derive(&public_key))
+}
+
+/// Recover the signer of a payload hash under `scheme`
+pub fn recover_hash_signer(
+    hash: [u8; 32],
+    sig: &Signature,
+    scheme: SigningScheme,
+) -> Result<Address> {
+    nonzero_signer(sig.recover(scheme.digest(hash))?)
+}
+
+/// The zero address has no key; recovering to it means the signature is
+/// malformed, never that someone signed
+fn nonzero_signer(addr: Address) -> Result<Address> {
+    if addr.is_zero() {
+        return Err(anyhow!("signature recovers to the zero address"));
+    }
+    Ok(addr)
+}
+
+/// Reject the zero address as an expected signer before doing any work
+fn require_nonzero_expected(expected_addr: Address) -> Result<()> {
+    if expected_addr.is_zero() {
+        return Err(anyhow!(
+            "expected signer is the zero address, which can never sign"
+        ));
+    }
+    Ok(())
+}
+
+/// Forensics only: every address an (r, s) signature over payload hash
+/// `hash` could have come from under eip191, trying v = 27 and v = 28.
+/// For a signature whose recovery byte is missing or corrupted; a 65-byte
+/// input has its v ignored. Never use this to accept a signature.
+pub fn recover_all_candidates(hash: [u8; 32], sig_bytes: &[u8]) -> Result<Vec<Address>> {
+    if sig_bytes.len() != 64 && sig_bytes.len() != 65 {
+        return Err(anyhow!(
+            "expected a 64-byte (r, s) signature, got {} bytes",
+            sig_bytes.len()
+        ));
+    }
+    let digest = SigningScheme::Eip191.digest(hash);
+    let mut candidates = Vec::new();
+    for v in [27u8, 28] {
+        let mut bytes = sig_bytes[..64].to_vec();
+        bytes.push(v);
+        // A candidate can fail to exist (r not an x-coordinate for that parity)
+        if let Ok(addr) = Signature::try_from(bytes.as_slice()).and_then(|sig| sig.recover(digest))
+        {
+            candidates.push(addr);
+        }
+    }
+    Ok(candidates)
+}
+
+/// Scheme named by signing.scheme (eip191 when absent). For
+/// eip191-validator the address comes from signing.validator.
+pub fn snapshot_scheme(snapshot: &Value) -> Result<SigningScheme> {
+    let signing = signing_object(snapshot)?;
+    match string_field(signing, "scheme")? {
+        Some("eip191-validator") => {
+            let validator = signing
+                .get("validator")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow!("eip191-validator scheme requires signing.validator"))?;
+            let validator = validator
+                .parse()
+                .map_err(|e| anyhow!("bad signing.validator {validator:?}: {e}"))?;
+            Ok(SigningScheme::Eip191Validator(validator))
+        }
+        Some(s) => s.parse(),
+        None => Ok(SigningScheme::Eip191),
+    }
+}
+
+/// Verify a snapshot signed under eip191-validator for `validator`.
+/// A signature scoped to another validator, or not scoped at all, fails.
+pub fn verify_for_validator(
+    snapshot: &Value,
+    expected_addr: Address,
+    validator: Address,
+) -> Result<()> {
+    match snapshot_scheme(snapshot)? {
+        SigningScheme::Eip191Validator(scoped) if scoped == validator => {}
+        SigningScheme::Eip191Validator(scoped) => {
+            return Err(anyhow!(
+                "signature is scoped to validator {scoped:?}, not {validator:?}"
+            ))
+        }
+        other => return Err(anyhow!("signing.scheme is {other}, not eip191-validator")),
+    }
+    verify_signature(snapshot, expected_addr)
+}
+
+/// signing.reason, the signer's memo for why they signed. It is hashed
+/// like any other signing field, so it cannot be edited after signing.
+pub fn snapshot_reason(snapshot: &Value) -> Result<Option<&str>> {
+    match signing_object(snapshot)?.get("reason") {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(anyhow!("signing.reason must be a string")),
+    }
+}
+
+/// String forms of a 65-byte signature. All carry the same r, s and v;
+/// only the representation differs, so converting never needs the key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureEncoding {
+    /// `eip191:0x<r||s||v>`, the canonical form written by `sign`
+    Hex,
+    /// `eip2098:0x<r||yParityAndS>`, the 64-byte EIP-2098 form
+    Compact,
+    /// `rsv:0x<r>:0x<s>:<v>`
+    Rsv,
+    /// `b64url:<r||s||v>` in unpadded base64url
+    B64url,
+}
+
+impl SignatureEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignatureEncoding::Hex => "hex",
+            SignatureEncoding::Compact => "compact",
+            SignatureEncoding::Rsv => "rsv",
+            SignatureEncoding::B64url => "b64url",
+        }
+    }
+
+    /// Encoding of a signing.signature string, judged by its prefix
+    pub fn detect(sig_str: &str) -> Result<Self> {
+        if sig_str.starts_with("eip191:0x") {
+            Ok(SignatureEncoding::Hex)
+        } else if sig_str.starts_with("eip2098:0x") {
+            Ok(SignatureEncoding::Compact)
+        } else if sig_str.starts_with("rsv:") {
+            Ok(SignatureEncoding::Rsv)
+        } else if sig_str.starts_with("b64url:") {
+            Ok(SignatureEncoding::B64url)
+        } else {
+            Err(anyhow!(
+                "unrecognized signature encoding (expected eip191:0x, eip2098:0x, rsv: or b64url:)"
+            ))
+        }
+    }
+}
+
+impl fmt::Display for SignatureEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SignatureEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "hex" => Ok(SignatureEncoding::Hex),
+            "compact" => Ok(SignatureEncoding::Compact),
+            "rsv" => Ok(SignatureEncoding::Rsv),
+            "b64url" => Ok(SignatureEncoding::B64url),
+            other => Err(anyhow!("unsupported signature encoding: {other}")),
+        }
+    }
+}
+
+/// Parse a signature in any `SignatureEncoding`
+pub fn parse_signature(sig_str: &str) -> Result<Signature> {
+    let sig_bytes = match SignatureEncoding::detect(sig_str)? {
+        SignatureEncoding::Hex => hex::decode(&sig_str["eip191:0x".len()..])?,
+        SignatureEncoding::Compact => decode_compact(&sig_str["eip2098:0x".len()..])?,
+        SignatureEncoding::Rsv => decode_rsv(&sig_str["rsv:".len()..])?,
+        SignatureEncoding::B64url => URL_SAFE_NO_PAD
+            .decode(&sig_str["b64url:".len()..])
+            .map_err(|e| anyhow!("bad base64url signature: {e}"))?,
+    };
+    signature_from_bytes(&sig_bytes)
+}
+
+/// Parse signing.signature in either stored shape: a string in one of the
+/// `SignatureEncoding` forms, or an object `{r, s, recovery_id}` as returned
+/// by wallets that do not pack v. `recovery_id` may be 0/1 or 27/28; v is stored as 27/28.
+pub fn parse_signature_value(v: &Value) -> Result<Signature> {
+    match v {
+        Value::String(s) => parse_signature(s),
+        Value::Object(parts) => {
+            let mut bytes = Vec::with_capacity(65);
+            for name in ["r", "s"] {
+                let word = parts
+                    .get(name)
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow!("signature object missing {name}"))?;
+                let word = hex::decode(word.strip_prefix("0x").unwrap_or(word))
+                    .map_err(|e| anyhow!("signature {name} is not hex: {e}"))?;
+                if word.len() != 32 {
+                    return Err(anyhow!("signature {name} must be 32 bytes"));
+                }
+                bytes.extend_from_slice(&word);
+            }
+            let recovery_id = parts
+                .get("recovery_id")
+                .and_then(|v| v.as_u64())
+                .ok_or_else(|| anyhow!("signature object missing integer recovery_id"))?;
+            let v = match recovery_id {
+                0 | 1 => 27 + recovery_id as u8,
+                27 | 28 => recovery_id as u8,
+                other => {
+                    return Err(anyhow!(
+                        "signature recovery_id {other} must be 0/1 or 27/28"
+                    ))
+                }
+            };
+            bytes.push(v);
+            signature_from_bytes(&bytes)
+        }
+        _ => Err(anyhow!(
+            "signature must be a string or an {{r, s, recovery_id}} object"
+        )),
+    }
+}
+
+/// Whether a signing.signature value holds a signature at all; unsigned
+/// templates carry "" or null
+pub fn signature_present(v: &Value) -> bool {
+    !v.is_null() && v.as_str() != Some("")
+}
+
+fn signature_from_bytes(sig_bytes: &[u8]) -> Result<Signature> {
+    if sig_bytes.len() != 65 {
+        return Err(anyhow!("signature must be 65 bytes"));
+    }
+    // Message signatures carry v in {27, 28} (or raw {0, 1}). Larger values
+    // are EIP-155 chain-id encodings, which only apply to transactions.
+    let v = sig_bytes[64];
+    if !matches!(v, 0 | 1 | 27 | 28) {
+        return Err(anyhow!(
+            "signature v={v} is not a message-signing recovery id (expected 27/28 or 0/1; EIP-155 v is for transactions)"
+        ));
+    }
+    Ok(Signature::try_from(sig_bytes)?)
+}
+
+/// Encode a signature as "eip191:0x<hex>"
+pub fn signature_str(sig: &Signature) -> String {
+    encode_signature(sig, SignatureEncoding::Hex)
+}
+
+/// Encode a signature in the given representation
+pub fn encode_signature(sig: &Signature, encoding: SignatureEncoding) -> String {
+    let bytes = sig.to_vec();
+    match encoding {
+        SignatureEncoding::Hex => format!("eip191:0x{}", hex::encode(&bytes)),
+        SignatureEncoding::Compact => {
+            let mut compact = bytes[..64].to_vec();
+            if recovery_parity(bytes[64]) == 1 {
+                compact[32] |= 0x80;
+            }
+            format!("eip2098:0x{}", hex::encode(compact))
+        }
+        SignatureEncoding::Rsv => format!(
+            "rsv:0x{}:0x{}:{}",
+            hex::encode(&bytes[..32]),
+            hex::encode(&bytes[32..64]),
+            bytes[64]
+        ),
+        SignatureEncoding::B64url => format!("b64url:{}", URL_SAFE_NO_PAD.encode(&bytes)),
+    }
+}
+
+fn recovery_parity(v: u8) -> u8 {
+    if v >= 27 {
+        v - 27
+    } else {
+        v
+    }
+}
+
+/// EIP-2098: the top bit of s carries the y parity; yields r||s||v (v = 27/28)
+fn decode_compact(hex_str: &str) -> Result<Vec<u8>> {
+    let mut bytes = hex::decode(hex_str)?;
+    if bytes.len() != 64 {
+        return Err(anyhow!("compact signature must be 64 bytes"));
+    }
+    let parity = bytes[32] >> 7;
+    bytes[32] &= 0x7f;
+    bytes.push(27 + parity);
+    Ok(bytes)
+}
+
+fn decode_rsv(s: &str) -> Result<Vec<u8>> {
+    let parts: Vec<&str> = s.split(':').collect();
+    let [r, s, v] = parts.as_slice() else {
+        return Err(anyhow!("rsv signature must be rsv:0x<r>:0x<s>:<v>"));
+    };
+    let mut bytes = Vec::with_capacity(65);
+    for (name, part) in [("r", r), ("s", s)] {
+        let word = hex::decode(part.strip_prefix("0x").unwrap_or(part))?;
+        if word.len() != 32 {
+            return Err(anyhow!("rsv signature {name} must be 32 bytes"));
+        }
+        bytes.extend_from_slice(&word);
+    }
+    bytes.push(
+        v.parse()
+            .map_err(|e| anyhow!("rsv signature bad v {v:?}: {e}"))?,
+    );
+    Ok(bytes)
+}
+
+/// The snapshot's signing object
+pub fn signing_object(snapshot: &Value) -> Result<&Map<String, Value>> {
+    snapshot
         .get("signing")
         .and_then(|v| v.as_object())
-        .ok_or_else(|| anyhow!("missing signing object"))?;
+        .ok_or_else(|| anyhow!("missing signing object"))
+}
 
-    let payload_hash_str = signing
-        .get("payload_hash")
-        .and_then(|v| v.as_str())
+/// A string field of the signing object: `None` when absent, an error
+/// naming the actual type when present but not a string
+pub fn string_field<'a>(signing: &'a Map<String, Value>, key: &str) -> Result<Option<&'a str>> {
+    match signing.get(key) {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(other) => Err(anyhow!(
+            "signing.{key} must be a string, found {}",
+            json_type(other)
+        )),
+    }
+}
+
+/// JSON type name of a value, for error messages
+pub fn json_type(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Stored payload hash and signature of a signed snapshot
+pub fn signed_parts(snapshot: &Value) -> Result<([u8; 32], Signature)> {
+    let signing = signing_object(snapshot)?;
+
+    let payload_hash_str = string_field(signing, "payload_hash")?
         .ok_or_else(|| anyhow!("missing signing.payload_hash"))?;
 
-    let sig_str = signing
+    let sig = signing
         .get("signature")
-        .and_then(|v| v.as_str())
         .ok_or_else(|| anyhow!("missing signing.signature"))?;
-
-    let hash_hex = payload_hash_str
-        .strip_prefix("keccak256:")
-        .ok_or_else(|| anyhow!("payload_hash must start with keccak256:"))?;
-    let hash_bytes = hex::decode(hash_hex)?;
-    if hash_bytes.len() != 32 {
-        return Err(anyhow!("payload hash must be 32 bytes"));
+    if !matches!(sig, Value::String(_) | Value::Object(_)) {
+        return Err(anyhow!(
+            "signing.signature must be a string, found {}",
+            json_type(sig)
+        ));
     }
-    let mut hash = [0u8; 32];
-    hash.copy_from_slice(&hash_bytes);
 
-    let sig_hex = sig_str
-        .strip_prefix("eip191:0x")
-        .ok_or_else(|| anyhow!("signature must start with eip191:0x"))?;
-    let sig_bytes = hex::decode(sig_hex)?;
-    if sig_bytes.len() != 65 {
-        return Err(anyhow!("signature must be 65 bytes"));
+    Ok((
+        parse_payload_hash(payload_hash_str)?,
+        parse_signature_value(sig)?,
+    ))
+}
+
+/// Recompute payload hash from snapshot content (excluding signature)
+fn check_content_hash(snapshot: &Value, hash: [u8; 32], profile: &CanonicalProfile) -> Result<()> {
+    let recomputed = payload_hash_keccak_with_profile(snapshot, profile)?;
+    if recomputed != hash {
+        return Err(anyhow!("payload_hash mismatch: snapshot content changed"));
     }
+    Ok(())
+}
 
-    let sig = Signature::try_from(sig_bytes.as_slice())?;
-    let recovered = sig.recover(H256::from(hash))?;
+fn verify_with_scheme(
+    snapshot: &Value,
+    expected_addr: Address,
+    scheme: SigningScheme,
+    profile: &CanonicalProfile,
+) -> Result<()> {
+    require_nonzero_expected(expected_addr)?;
+    let (hash, sig) = signed_parts(snapshot)?;
+    let recovered = recover_hash_signer(hash, &sig, scheme)?;
 
     if recovered != expected_addr {
         return Err(anyhow!("signature does not recover to expected address"));
     }
 
-    // Recompute payload hash from snapshot content (excluding signature)
-    let recomputed = payload_hash_keccak(snapshot)?;
-    if recomputed != hash {
-        return Err(anyhow!("payload_hash mismatch: snapshot content changed"));
+    check_content_hash(snapshot, hash, profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{address, wallet, KEY_A};
+    use serde_json::json;
+
+    fn unsigned_snapshot(scheme: SigningScheme) -> Value {
+        json!({
+            "type": "CLAIM",
+            "id": "claim-job-1-1700000000",
+            "body": { "job_id": "job-1", "lease_seconds": 900 },
+            "signing": {
+                "scheme": scheme.as_str(),
+                "payload_hash": "",
+                "signature": ""
+            }
+        })
     }
 
-    Ok(())
+    #[tokio::test]
+    async fn eip191_round_trip() {
+        let wallet = wallet(KEY_A);
+        let mut snap = unsigned_snapshot(SigningScheme::Eip191);
+        let hash = payload_hash_keccak(&snap).unwrap();
+        let (sig, addr) = sign_eip191_hash(&wallet, hash).await.unwrap();
+        attach_signature(&mut snap, hash, &sig).unwrap();
+
+        verify_eip191(&snap, addr).unwrap();
+        verify_signature(&snap, addr).unwrap();
+    }
+
+    #[tokio::test]
+    async fn prehash_verifies_only_under_prehash_scheme() {
+        let wallet = wallet(KEY_A);
+        let mut snap = unsigned_snapshot(SigningScheme::Eip191Prehash);
+        let hash = payload_hash_keccak(&snap).unwrap();
+
+        // Sign the double-hashed form directly, as the integrations do
+        let sig = wallet.sign_message(keccak256(hash)).await.unwrap();
+        attach_signature_with_scheme(&mut snap, hash, &sig, SigningScheme::Eip191Prehash).unwrap();
+
+        verify_eip191_prehash(&snap, wallet.address()).unwrap();
+        verify_signature(&snap, wallet.address()).unwrap();
+
+        let err = verify_eip191(&snap, wallet.address()).unwrap_err();
+        assert!(err.to_string().contains("does not recover"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn validator_scheme_is_scoped_to_its_validator() {
+        let wallet = wallet(KEY_A);
+        let validator = Address::repeat_byte(0xaa);
+        let other = Address::repeat_byte(0xbb);
+        let mut snap = json!({
+            "id": "snap-1",
+            "body": { "n": 1 },
+            "signing": { "scheme": "eip191-validator", "validator": format!("{validator:?}") }
+        });
+        let (hash, addr) = sign_snapshot(&mut snap, &wallet).await.unwrap();
+        assert_eq!(
+            snapshot_scheme(&snap).unwrap(),
+            SigningScheme::Eip191Validator(validator)
+        );
+
+        // The digest is the EIP-191 0x00 form
+        let mut preimage = vec![0x19, 0x00];
+        preimage.extend_from_slice(validator.as_bytes());
+        preimage.extend_from_slice(&hash);
+        let (_, sig) = signed_parts(&snap).unwrap();
+        assert_eq!(sig.recover(H256(keccak256(preimage))).unwrap(), addr);
+
+        verify_signature(&snap, addr).unwrap();
+        verify_for_validator(&snap, addr, validator).unwrap();
+        let err = verify_for_validator(&snap, addr, other).unwrap_err();
+        assert!(err.to_string().contains("scoped to validator"), "{err}");
+
+        // Re-pointing the snapshot at another validator breaks the signature
+        let mut moved = snap.clone();
+        moved["signing"]["validator"] = json!(format!("{other:?}"));
+        assert!(verify_for_validator(&moved, addr, other).is_err());
+        assert!(
+            recover_hash_signer(hash, &sig, SigningScheme::Eip191Validator(other)).unwrap() != addr
+        );
+
+        // Plain eip191 signatures are not scoped to anything
+        let mut plain = unsigned_snapshot(SigningScheme::Eip191);
+        sign_snapshot(&mut plain, &wallet).await.unwrap();
+        assert!(verify_for_validator(&plain, addr, validator).is_err());
+    }
+
+    #[tokio::test]
+    async fn recorded_profile_is_applied_and_bound() {
+        let wallet = wallet(KEY_A);
+        let profile = CanonicalProfile {
+            drop_nulls: true,
+            normalize_addresses: true,
+            ..Default::default()
+        };
+        let mut snap = json!({
+            "id": "snap-1",
+            "body": { "miner": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "note": null },
+            "signing": { "scheme": "eip191" }
+        });
+        let (_, addr) = sign_snapshot_with_profile(&mut snap, &wallet, &profile)
+            .await
+            .unwrap();
+        assert_eq!(
+            snap["signing"]["canonical_profile"],
+            json!({ "drop_nulls": true, "normalize_addresses": true })
+        );
+        assert_eq!(
+            snapshot_canonical_profile(&snap).unwrap(),
+            Some(profile.clone())
+        );
+
+        // Verifiers pick the profile up from the snapshot: rewrites it
+        // allows do not break the signature
+        verify_signature(&snap, addr).unwrap();
+        let mut relaxed = snap.clone();
+        relaxed["body"]["miner"] = json!("0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+        relaxed["body"].as_object_mut().unwrap().remove("note");
+        assert_eq!(recover_signer(&relaxed).unwrap(), addr);
+
+        // Asking for other rules, or dropping the recorded profile, fails
+        let other = CanonicalProfile {
+            drop_empty: true,
+            ..Default::default()
+        };
+        assert!(verify_signature_with_profile(&snap, addr, &other).is_err());
+        let mut unrecorded = relaxed.clone();
+        unrecorded["signing"]
+            .as_object_mut()
+            .unwrap()
+            .remove("canonical_profile");
+        assert!(verify_signature(&unrecorded, addr).is_err());
+
+        // Tampering with the recorded profile breaks the hash
+        for tampered_profile in [
+            json!({ "drop_nulls": true }),
+            json!({ "drop_nulls": true, "normalize_addresses": true, "drop_empty": true }),
+        ] {
+            let mut tampered = snap.clone();
+            tampered["signing"]["canonical_profile"] = tampered_profile;
+            let err = verify_signature(&tampered, addr).unwrap_err();
+            assert!(err.to_string().contains("payload_hash mismatch"), "{err}");
+        }
+    }
+
+    async fn signed_with_v(v: impl Fn(u8) -> u8) -> (Value, Address) {
+        let wallet = wallet(KEY_A);
+        let mut snap = unsigned_snapshot(SigningScheme::Eip191);
+        let (_, addr) = sign_snapshot(&mut snap, &wallet).await.unwrap();
+
+        let mut bytes = parse_signature(snap["signing"]["signature"].as_str().unwrap())
+            .unwrap()
+            .to_vec();
+        bytes[64] = v(bytes[64]);
+        snap["signing"]["signature"] = json!(format!("eip191:0x{}", hex::encode(bytes)));
+        (snap, addr)
+    }
+
+    #[tokio::test]
+    async fn accepts_message_recovery_ids() {
+        // As produced: 27 or 28
+        let (snap, addr) = signed_with_v(|v| v).await;
+        verify_eip191(&snap, addr).unwrap();
+
+        // Raw recovery id: 0 or 1
+        let (snap, addr) = signed_with_v(|v| v - 27).await;
+        verify_eip191(&snap, addr).unwrap();
+    }
+
+    #[tokio::test]
+    async fn accepts_v_27_and_28() {
+        let wallet = wallet(KEY_A);
+        let mut seen = std::collections::BTreeSet::new();
+        for i in 0..64 {
+            let mut snap = unsigned_snapshot(SigningScheme::Eip191);
+            snap["id"] = json!(format!("claim-{i}"));
+            let (_, addr) = sign_snapshot(&mut snap, &wallet).await.unwrap();
+            verify_eip191(&snap, addr).unwrap();
+            seen.insert(
+                parse_signature(snap["signing"]["signature"].as_str().unwrap())
+                    .unwrap()
+                    .v,
+            );
+            if seen.len() == 2 {
+                break;
+            }
+        }
+        assert_eq!(seen.into_iter().collect::<Vec<_>>(), vec![27, 28]);
+    }
+
+    #[tokio::test]
+    async fn rejects_eip155_v() {
+        // Chain id 1: v = recovery_id + 35 + 2 * 1
+        let (snap, addr) = signed_with_v(|v| v - 27 + 37).await;
+        let err = verify_eip191(&snap, addr).unwrap_err();
+        assert!(err.to_string().contains("EIP-155"), "{err}");
+    }
+
+    /// Legacy Trezor digest of the fixture below, signed with KEY_A:
+    /// keccak256("\x19Ethereum Signed Message:\n" || 0x20 || payload hash)
+    const TREZOR_SIGNATURE: &str = "eip191:0xed39bddf8c7795355b0ada898292d09b477c5957fd11ac9357cc8ab0fb33cfd67c171a8e781c894699432855a6db0913d0002b7ea062e55fa110a54006c1c8fb1b";
+
+    #[test]
+    fn trezor_vector_verifies_only_under_trezor_scheme() {
+        let mut snap = json!({
+            "type": "CLAIM",
+            "id": "claim-job-1-1700000000",
+            "body": { "job_id": "job-1", "lease_seconds": 900 },
+            "signing": {
+                "scheme": "trezor",
+                "payload_hash": "keccak256:0545b8768dbd6d67df40a945bfbae09bea2e8c670a7978379b440809e7b405a8",
+                "signature": TREZOR_SIGNATURE
+            }
+        });
+        let addr = address(KEY_A);
+
+        verify_signature(&snap, addr).unwrap();
+        assert!(verify_eip191(&snap, addr).is_err());
+
+        // Relabelled as eip191 the hash changes too, so it cannot pass
+        snap["signing"]["scheme"] = json!("eip191");
+        assert!(verify_signature(&snap, addr).is_err());
+    }
+
+    #[tokio::test]
+    async fn trezor_round_trip() {
+        let wallet = wallet(KEY_A);
+        let mut snap = unsigned_snapshot(SigningScheme::Trezor);
+        let (_, addr) = sign_snapshot(&mut snap, &wallet).await.unwrap();
+        verify_signature(&snap, addr).unwrap();
+        assert_eq!(recover_signer(&snap).unwrap(), addr);
+    }
+
+    #[tokio::test]
+    async fn verifies_shared_snapshot_from_many_threads() {
+        let wallet = wallet(KEY_A);
+        let mut snap = unsigned_snapshot(SigningScheme::Eip191);
+        let (_, addr) = sign_snapshot(&mut snap, &wallet).await.unwrap();
+        let before = snap.clone();
+
+        let snap = &snap;
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(move || {
+                    for _ in 0..25 {
+                        verify_eip191(snap, addr).unwrap();
+                    }
+                });
+            }
+        });
+        assert_eq!(*snap, before);
+    }
+
+    #[test]
+    fn preimage_excludes_only_top_level_signing_fields() {
+        let snap = json!({
+            "body": { "signing": { "signature": "kept" } },
+            "signing": { "scheme": "eip191", "signature": "x", "signatures": [], "payload_hash": "y" }
+        });
+        let preimage = payload_preimage(&snap, &CanonicalProfile::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(preimage).unwrap(),
+            r#"{"body":{"signing":{"signature":"kept"}},"signing":{"scheme":"eip191"}}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn candidates_include_true_signer() {
+        let wallet = wallet(KEY_A);
+        let mut snap = unsigned_snapshot(SigningScheme::Eip191);
+        let (hash, addr) = sign_snapshot(&mut snap, &wallet).await.unwrap();
+        let sig = parse_signature(snap["signing"]["signature"].as_str().unwrap()).unwrap();
+
+        let candidates = recover_all_candidates(hash, &sig.to_vec()[..64]).unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert!(candidates.contains(&addr));
+        assert!(candidates.iter().any(|c| *c != addr));
+
+        assert!(recover_all_candidates(hash, &[0u8; 10]).is_err());
+    }
+
+    #[tokio::test]
+    async fn split_signature_object_verifies_like_packed() {
+        let wallet = wallet(KEY_A);
+        let mut packed = unsigned_snapshot(SigningScheme::Eip191);
+        let (_, addr) = sign_snapshot(&mut packed, &wallet).await.unwrap();
+        let sig = parse_signature(packed["signing"]["signature"].as_str().unwrap()).unwrap();
+        let bytes = sig.to_vec();
+
+        let mut split = packed.clone();
+        split["signing"]["signature"] = json!({
+            "r": format!("0x{}", hex::encode(&bytes[..32])),
+            "s": format!("0x{}", hex::encode(&bytes[32..64])),
+            "recovery_id": recovery_parity(bytes[64]),
+        });
+        assert_eq!(
+            parse_signature_value(&split["signing"]["signature"]).unwrap(),
+            sig
+        );
+        assert_eq!(
+            payload_hash_keccak(&split).unwrap(),
+            payload_hash_keccak(&packed).unwrap()
+        );
+        verify_signature(&packed, addr).unwrap();
+        verify_signature(&split, addr).unwrap();
+        assert_eq!(recover_signer(&split).unwrap(), addr);
+
+        split["signing"]["signature"]["recovery_id"] = json!(5);
+        assert!(verify_signature(&split, addr).is_err());
+        split["signing"]["signature"] = json!({ "r": "0x00" });
+        assert!(verify_signature(&split, addr).is_err());
+    }
+
+    #[test]
+    fn unknown_scheme_is_rejected() {
+        let mut snap = unsigned_snapshot(SigningScheme::Eip191);
+        snap["signing"]["scheme"] = json!("eip191-typo");
+        let err = verify_signature(&snap, Address::zero()).unwrap_err();
+        assert!(
+            err.to_string().contains("unsupported signing scheme"),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn wrongly_typed_fields_name_their_type() {
+        let wallet = wallet(KEY_A);
+        let mut signed = unsigned_snapshot(SigningScheme::Eip191);
+        sign_snapshot(&mut signed, &wallet).await.unwrap();
+
+        for (field, value, found) in [
+            ("signature", json!(["0xabc"]), "array"),
+            ("signature", json!(7), "number"),
+            ("payload_hash", json!({ "h": "0x00" }), "object"),
+            ("payload_hash", json!(true), "boolean"),
+            ("scheme", json!(["eip191"]), "array"),
+            ("scheme", Value::Null, "null"),
+        ] {
+            let mut snap = signed.clone();
+            snap["signing"][field] = value;
+            let err = verify_signature(&snap, wallet.address()).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("signing.{field} must be a string, found {found}")
+            );
+        }
+
+        // Absent is still reported as missing
+        let mut snap = signed.clone();
+        snap["signing"]
+            .as_object_mut()
+            .unwrap()
+            .remove("payload_hash");
+        let err = verify_signature(&snap, wallet.address()).unwrap_err();
+        assert_eq!(err.to_string(), "missing signing.payload_hash");
+    }
+
+    #[tokio::test]
+    async fn keccak_and_sha256_hashes_both_recompute() {
+        let wallet = wallet(KEY_A);
+        let both = [HashAlg::Keccak256, HashAlg::Sha256];
+
+        for primary in both {
+            let mut snap = unsigned_snapshot(SigningScheme::Eip191);
+            let addr = sign_snapshot_multi_hash(&mut snap, &wallet, &both, primary)
+                .await
+                .unwrap();
+            assert_eq!(
+                snap["signing"]["payload_hashes"].as_array().unwrap().len(),
+                2
+            );
+            assert_eq!(verify_payload_hashes(&snap, addr).unwrap(), both);
+            // Legacy verification works exactly when the primary is keccak256
+            assert_eq!(
+                verify_signature(&snap, addr).is_ok(),
+                primary == HashAlg::Keccak256
+            );
+
+            let mut tampered = snap.clone();
+            tampered["body"] = json!({ "n": 2 });
+            let err = verify_payload_hashes(&tampered, addr).unwrap_err();
+            assert!(
+                err.to_string().contains("keccak256 payload hash mismatch"),
+                "{err}"
+            );
+        }
+
+        // sha256 is listed, but the signature covers keccak256: rewriting
+        // which one is primary is caught by the hash
+        let mut snap = unsigned_snapshot(SigningScheme::Eip191);
+        let addr = sign_snapshot_multi_hash(&mut snap, &wallet, &both, HashAlg::Keccak256)
+            .await
+            .unwrap();
+        snap["signing"]["primary_hash"] = json!("sha256");
+        assert!(verify_payload_hashes(&snap, addr).is_err());
+    }
+
+    #[tokio::test]
+    async fn zero_address_is_never_a_signer() {
+        struct ZeroDeriver;
+        impl AddressDeriver for ZeroDeriver {
+            fn derive(&self, _public_key: &[u8; 64]) -> Address {
+                Address::zero()
+            }
+        }
+
+        let wallet = wallet(KEY_A);
+        let mut snap = unsigned_snapshot(SigningScheme::Eip191);
+        sign_snapshot(&mut snap, &wallet).await.unwrap();
+
+        let err = recover_signer_with(&snap, &ZeroDeriver).unwrap_err();
+        assert_eq!(err.to_string(), "signature recovers to the zero address");
+
+        for err in [
+            verify_eip191(&snap, Address::zero()),
+            verify_signature(&snap, Address::zero()),
+        ] {
+            assert!(err
+                .unwrap_err()
+                .to_string()
+                .contains("expected signer is the zero address"));
+        }
+        // Up front: even an unsigned snapshot gets the same error
+        let err =
+            verify_eip191(&unsigned_snapshot(SigningScheme::Eip191), Address::zero()).unwrap_err();
+        assert!(err.to_string().contains("zero address"), "{err}");
+    }
+
+    /// A backend that, like a hardware wallet's personal-message signing,
+    /// only does plain eip191
+    struct PersonalMessageOnly(LocalWallet);
+
+    #[async_trait]
+    impl HashSigner for PersonalMessageOnly {
+        fn signer_address(&self) -> Address {
+            self.0.address()
+        }
+
+        async fn sign_digest(&self, digest: H256) -> Result<Signature> {
+            self.0.sign_digest(digest).await
+        }
+
+        fn supports_scheme(&self, scheme: &SigningScheme) -> bool {
+            *scheme == SigningScheme::Eip191
+        }
+    }
+
+    #[test]
+    fn negotiation_takes_first_supported_preference() {
+        use SigningScheme::*;
+        assert_eq!(
+            negotiate_scheme(&[Trezor, Eip191Prehash], &[Eip191, Eip191Prehash]),
+            Eip191Prehash
+        );
+        assert_eq!(
+            negotiate_scheme(&[Eip191Prehash, Trezor], &NEGOTIABLE_SCHEMES),
+            Eip191Prehash
+        );
+        assert_eq!(negotiate_scheme(&[Trezor], &[Eip191]), Eip191);
+        assert_eq!(negotiate_scheme(&[], &NEGOTIABLE_SCHEMES), Eip191);
+
+        let wallet = wallet(KEY_A);
+        assert_eq!(negotiate_scheme_for(&[Trezor, Eip191], &wallet), Trezor);
+        assert_eq!(
+            negotiate_scheme_for(&[Trezor, Eip191Prehash], &PersonalMessageOnly(wallet)),
+            Eip191
+        );
+    }
+
+    #[tokio::test]
+    async fn boxed_signers_sign_through_one_type() {
+        let a = wallet(KEY_A);
+        let b = generate_keypair();
+        let expected = [a.address(), b.address(), a.address()];
+        let backends: Vec<BoxedSigner> = vec![
+            Box::new(a.clone()),
+            Box::new(b),
+            Box::new(PersonalMessageOnly(a)),
+        ];
+
+        for (signer, expected) in backends.iter().zip(expected) {
+            let mut snap = json!({ "id": "snap-1", "signing": { "scheme": "eip191" } });
+            let (_, addr) = sign_snapshot(&mut snap, signer).await.unwrap();
+            assert_eq!(addr, expected);
+            assert_eq!(recover_signer(&snap).unwrap(), expected);
+        }
+        assert!(!backends[2].supports_scheme(&SigningScheme::Trezor));
+    }
 }
@@ -0,0 +1,168 @@
+//! Snapshot construction
+//!
+//! `SnapshotBuilder` is the safe way to assemble a snapshot `Value` for
+//! signing: it always emits a `signing` object and puts the signing-level
+//! fields (scheme, chain id, issue time, claims) inside it rather than at the top.
+
+use crate::claims::Claims;
+use crate::signing::{CanonicalVersion, SigningScheme};
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+
+/// Media type for SwarmHive snapshots in signing.content_type
+pub const SNAPSHOT_CONTENT_TYPE: &str = "application/vnd.swarmhive.snapshot+json";
+
+/// Top-level keys the builder manages itself
+const RESERVED: &[&str] = &["id", "signing"];
+
+/// Builder for unsigned snapshots
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotBuilder {
+    id: Option<String>,
+    fields: Map<String, Value>,
+    scheme: Option<SigningScheme>,
+    chain_id: Option<u64>,
+    issued_at: Option<i64>,
+    claims: Option<Claims>,
+    canonical_version: Option<CanonicalVersion>,
+    content_type: Option<String>,
+}
+
+impl SnapshotBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snapshot id (required)
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Top-level content field
+    pub fn field(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.fields.insert(key.into(), value.into());
+        self
+    }
+
+    /// signing.scheme (defaults to eip191)
+    pub fn scheme(mut self, scheme: SigningScheme) -> Self {
+        self.scheme = Some(scheme);
+        self
+    }
+
+    /// signing.chain_id
+    pub fn chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// signing.issued_at, in Unix seconds
+    pub fn issued_at(mut self, ts: i64) -> Self {
+        self.issued_at = Some(ts);
+        self
+    }
+
+    /// signing.canonical_version (defaults to v1, left unset)
+    pub fn canonical_version(mut self, version: CanonicalVersion) -> Self {
+        self.canonical_version = Some(version);
+        self
+    }
+
+    /// signing.claims, covered by the payload hash
+    pub fn claims(mut self, claims: Claims) -> Self {
+        self.claims = Some(claims);
+        self
+    }
+
+    /// signing.content_type, covered by the payload hash so a signature for
+    /// one kind of artifact cannot be passed off as another
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Produce the snapshot, ready for `sign_snapshot` / `attach_signature`
+    pub fn build(self) -> Result<Value> {
+        let id = self.id.ok_or_else(|| anyhow!("snapshot id is required"))?;
+        if let Some(key) = self.fields.keys().find(|k| RESERVED.contains(&k.as_str())) {
+            return Err(anyhow!(
+                "field {key:?} is reserved; use the builder method instead"
+            ));
+        }
+
+        let mut signing = Map::new();
+        let scheme = self.scheme.unwrap_or(SigningScheme::Eip191);
+        signing.insert("scheme".to_string(), Value::String(scheme.to_string()));
+        if let Some(chain_id) = self.chain_id {
+            signing.insert("chain_id".to_string(), Value::from(chain_id));
+        }
+        if let Some(ts) = self.issued_at {
+            signing.insert("issued_at".to_string(), Value::from(ts));
+        }
+        if let Some(version) = self.canonical_version {
+            signing.insert(
+                "canonical_version".to_string(),
+                Value::from(version.as_u64()),
+            );
+        }
+        if let Some(content_type) = self.content_type {
+            signing.insert("content_type".to_string(), Value::String(content_type));
+        }
+        if let Some(claims) = self.claims {
+            signing.insert("claims".to_string(), serde_json::to_value(claims)?);
+        }
+
+        let mut snapshot = self.fields;
+        snapshot.insert("id".to_string(), Value::String(id));
+        snapshot.insert("signing".to_string(), Value::Object(signing));
+        Ok(Value::Object(snapshot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing;
+    use crate::test_support::{wallet, KEY_A};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn build_sign_verify() {
+        let mut snap = SnapshotBuilder::new()
+            .id("proof-job-1")
+            .field("type", "PROOF")
+            .field("body", json!({ "job_id": "job-1", "compute_seconds": 42 }))
+            .chain_id(8453)
+            .issued_at(1_700_000_000)
+            .build()
+            .unwrap();
+
+        assert_eq!(snap["signing"]["chain_id"], json!(8453));
+        assert_eq!(snap["signing"]["issued_at"], json!(1_700_000_000));
+        assert!(snap["signing"].get("signature").is_none());
+
+        let wallet = wallet(KEY_A);
+        let (_, addr) = signing::sign_snapshot(&mut snap, &wallet).await.unwrap();
+        signing::verify_signature(&snap, addr).unwrap();
+
+        // Signing-level fields are covered by the hash
+        snap["signing"]["chain_id"] = json!(1);
+        assert!(signing::verify_signature(&snap, addr).is_err());
+    }
+
+    #[test]
+    fn requires_id_and_protects_reserved_keys() {
+        assert!(SnapshotBuilder::new()
+            .field("type", "PROOF")
+            .build()
+            .is_err());
+
+        let err = SnapshotBuilder::new()
+            .id("x")
+            .field("signing", json!({}))
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("reserved"), "{err}");
+    }
+}
@@ -0,0 +1,26 @@
+//! Keys and fixtures shared by the unit tests
+
+use crate::signing;
+use ethers::core::types::Address;
+use ethers::signers::{LocalWallet, Signer};
+use serde_json::Value;
+
+pub(crate) const KEY_A: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+pub(crate) const KEY_B: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+pub(crate) const KEY_C: &str = "0x5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdab365a";
+
+pub(crate) fn wallet(key: &str) -> LocalWallet {
+    signing::wallet_from_private_key_hex(key).unwrap()
+}
+
+pub(crate) fn address(key: &str) -> Address {
+    wallet(key).address()
+}
+
+/// `snapshot` signed with `key` under whatever signing.scheme it names
+pub(crate) async fn signed(mut snapshot: Value, key: &str) -> Value {
+    signing::sign_snapshot(&mut snapshot, &wallet(key))
+        .await
+        .unwrap();
+    snapshot
+}
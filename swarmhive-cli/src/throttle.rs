@@ -0,0 +1,116 @@
+//! Signer call throttling
+//!
+//! Remote signing backends (KMS, signing services) rate-limit callers. A
+//! `Throttle` wraps each signer call in a batch, bounding how many run at
+//! once and spacing their starts by a minimum interval. The default is
+//! unlimited, which is right for local keys.
+
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::time::Instant;
+
+/// Limits on concurrent signer calls
+#[derive(Debug, Default)]
+pub struct Throttle {
+    permits: Option<Semaphore>,
+    min_interval: Duration,
+    next_start: Mutex<Option<Instant>>,
+}
+
+impl Throttle {
+    /// No limits: calls run as soon as they are made
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// At most `max_concurrency` calls in flight (unbounded if None), and
+    /// at least `min_interval` between successive call starts
+    pub fn new(max_concurrency: Option<usize>, min_interval: Option<Duration>) -> Self {
+        Self {
+            permits: max_concurrency.map(|n| Semaphore::new(n.max(1))),
+            min_interval: min_interval.unwrap_or_default(),
+            next_start: Mutex::new(None),
+        }
+    }
+
+    /// Run `call` once the limits allow it
+    pub async fn run<F: Future>(&self, call: F) -> F::Output {
+        let _permit = match &self.permits {
+            Some(permits) => Some(
+                permits
+                    .acquire()
+                    .await
+                    .expect("throttle semaphore is never closed"),
+            ),
+            None => None,
+        };
+        if !self.min_interval.is_zero() {
+            // Held while sleeping, so waiting callers start one interval apart
+            let mut next_start = self.next_start.lock().await;
+            if let Some(at) = *next_start {
+                tokio::time::sleep_until(at).await;
+            }
+            *next_start = Some(Instant::now() + self.min_interval);
+        }
+        call.await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex as StdMutex;
+
+    /// Stands in for a remote signer: records when each call started and
+    /// how many were in flight at once
+    #[derive(Default)]
+    struct MockSigner {
+        started: StdMutex<Vec<Instant>>,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    impl MockSigner {
+        async fn sign(&self, hash: [u8; 32]) -> [u8; 32] {
+            self.started.lock().unwrap().push(Instant::now());
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            hash
+        }
+    }
+
+    async fn sign_all(throttle: &Throttle, signer: &MockSigner, n: u8) {
+        let calls = (0..n).map(|i| throttle.run(signer.sign([i; 32])));
+        let out = futures::future::join_all(calls).await;
+        assert_eq!(out.len(), n as usize);
+    }
+
+    #[tokio::test]
+    async fn minimum_interval_is_respected() {
+        let interval = Duration::from_millis(50);
+        let throttle = Throttle::new(None, Some(interval));
+        let signer = MockSigner::default();
+        sign_all(&throttle, &signer, 4).await;
+
+        let started = signer.started.lock().unwrap();
+        assert_eq!(started.len(), 4);
+        for pair in started.windows(2) {
+            assert!(pair[1] - pair[0] >= interval, "{:?}", pair[1] - pair[0]);
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrency_is_bounded() {
+        let signer = MockSigner::default();
+        sign_all(&Throttle::new(Some(2), None), &signer, 6).await;
+        assert_eq!(signer.max_in_flight.load(Ordering::SeqCst), 2);
+
+        let signer = MockSigner::default();
+        sign_all(&Throttle::unlimited(), &signer, 6).await;
+        assert_eq!(signer.max_in_flight.load(Ordering::SeqCst), 6);
+    }
+}
@@ -0,0 +1,137 @@
+//! Reusable verification context for long-running services
+//!
+//! A `Verifier` is built once at startup from the deployment's policy
+//! (options, allow-list, extra policies, nonce store, provider) and then
+//! checks each incoming snapshot with a single call.
+
+use crate::account::AccountReader;
+use crate::ens::EnsResolver;
+use crate::nonce::NonceStore;
+use crate::policy::{AllowListPolicy, PolicyChain, VerificationContext, VerifyPolicy};
+use crate::verify::{self, MaybeProvider, VerificationReport, VerifyOptions};
+use chrono::Utc;
+use ethers::core::types::Address;
+use ethers::providers::{Http, Provider};
+use serde_json::Value;
+
+/// Verification policy held for the lifetime of a service
+#[derive(Default)]
+pub struct Verifier<R = Provider<Http>> {
+    opts: VerifyOptions,
+    policies: PolicyChain,
+    nonces: Option<Box<dyn NonceStore>>,
+    provider: MaybeProvider<R>,
+}
+
+impl<R: EnsResolver + AccountReader + Sync> Verifier<R> {
+    /// Offline verifier running the checks in `opts`
+    pub fn new(opts: VerifyOptions) -> Self {
+        Self {
+            opts,
+            policies: PolicyChain::new(),
+            nonces: None,
+            provider: MaybeProvider::Offline,
+        }
+    }
+
+    /// Only accept snapshots signed by one of `signers`
+    pub fn allow_signers(self, signers: Vec<Address>) -> Self {
+        self.with_policy(AllowListPolicy { signers })
+    }
+
+    /// Run `policy` after the built-in checks; a failure is reported as
+    /// `policy_ok = false`
+    pub fn with_policy(mut self, policy: impl VerifyPolicy + 'static) -> Self {
+        self.policies = self.policies.with(policy);
+        self
+    }
+
+    /// Reject replays: each valid snapshot's signing.nonce is recorded
+    pub fn nonce_store(mut self, store: impl NonceStore + 'static) -> Self {
+        self.nonces = Some(Box::new(store));
+        self
+    }
+
+    /// Enable the on-chain checks (ENS, signer balance and activity)
+    pub fn provider(mut self, provider: R) -> Self {
+        self.provider = MaybeProvider::Online(provider);
+        self
+    }
+
+    /// Verify `snapshot` now
+    pub async fn verify(&self, snapshot: &Value) -> VerificationReport {
+        self.verify_at(snapshot, Utc::now().timestamp()).await
+    }
+
+    /// Verify `snapshot` at time `now` (Unix seconds). The nonce is
+    /// checked last, and only when everything else passed.
+    pub async fn verify_at(&self, snapshot: &Value, now: i64) -> VerificationReport {
+        let mut report = verify::verify_report(snapshot, &self.opts, now);
+        if !self.policies.is_empty() {
+            report.policy_ok = report.record(
+                self.policies
+                    .check(snapshot, &VerificationContext::new(now)),
+            );
+        }
+        verify::run_online_checks(&mut report, &self.opts, &self.provider).await;
+        if let Some(store) = &self.nonces {
+            verify::consume_nonce(&mut report, snapshot, store.as_ref());
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nonce::MemoryNonceStore;
+    use crate::test_support::{address, signed, KEY_A, KEY_B};
+    use serde_json::json;
+    use std::time::Duration;
+
+    const NOW: i64 = 1_700_000_000;
+
+    async fn signed_by(key: &str, issued_at: i64, nonce: &str) -> Value {
+        let snap = json!({
+            "id": "snap-1",
+            "body": { "n": 1 },
+            "signing": { "scheme": "eip191", "issued_at": issued_at, "nonce": nonce }
+        });
+        signed(snap, key).await
+    }
+
+    #[tokio::test]
+    async fn one_verifier_checks_many_snapshots() {
+        let allowed = address(KEY_A);
+        let verifier: Verifier = Verifier::new(VerifyOptions {
+            max_age: Some(Duration::from_secs(300)),
+            ..Default::default()
+        })
+        .allow_signers(vec![allowed])
+        .nonce_store(MemoryNonceStore::default());
+
+        let fresh = signed_by(KEY_A, NOW - 10, "n-1").await;
+        let report = verifier.verify_at(&fresh, NOW).await;
+        assert!(report.is_valid(), "{report:?}");
+        assert_eq!(report.policy_ok, Some(true));
+        assert_eq!(report.nonce_ok, Some(true));
+
+        // Same snapshot again is a replay
+        let report = verifier.verify_at(&fresh, NOW).await;
+        assert_eq!(report.failed_checks(), vec!["Nonce"]);
+
+        let stale = signed_by(KEY_A, NOW - 3600, "n-2").await;
+        let report = verifier.verify_at(&stale, NOW).await;
+        assert_eq!(report.failed_checks(), vec!["Freshness"]);
+        assert_eq!(report.nonce_ok, None);
+
+        let stranger = signed_by(KEY_B, NOW - 10, "n-3").await;
+        let report = verifier.verify_at(&stranger, NOW).await;
+        assert_eq!(report.failed_checks(), vec!["Policy"]);
+        assert!(
+            report.errors[0].contains("allow-list policy failed"),
+            "{:?}",
+            report.errors
+        );
+    }
+}
@@ -0,0 +1,861 @@
+//! Snapshot verification reports
+//!
+//! `verify_report` runs every configured check and records each outcome
+//! instead of stopping at the first failure. A check that was not requested
+//! (no expected address, no freshness window, no chain id) is `None`.
+//!
+//! Verification is offline by default. Checks that need the chain (ENS,
+//! signer balance and activity) run only through `verify_report_online`
+//! with `MaybeProvider::Online`; offline they are `None` and named in
+//! `VerificationReport::skipped`.
+
+use crate::account::{self, AccountReader};
+use crate::ens::EnsResolver;
+use crate::nonce::{self, NonceStore};
+use crate::signing::{
+    self, parse_payload_hash, parse_signature_value, payload_hash_keccak, CanonicalVersion,
+};
+use anyhow::{anyhow, Result};
+use ethers::core::types::{Address, U256};
+use ethers::providers::{Http, Provider};
+use rayon::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+
+/// What to check beyond the signature itself
+#[derive(Debug, Clone, Default)]
+pub struct VerifyOptions {
+    /// Signer the snapshot must recover to
+    pub expected_address: Option<Address>,
+    /// Maximum age of signing.issued_at
+    pub max_age: Option<Duration>,
+    /// How far in the future signing.issued_at may be before the freshness
+    /// check fails; `None` is `DEFAULT_CLOCK_SKEW`
+    pub clock_skew: Option<Duration>,
+    /// Required signing.chain_id
+    pub chain_id: Option<u64>,
+    /// Required signing.content_type
+    pub content_type: Option<String>,
+    /// Required signing.canonical_version (absent means v1). Checked
+    /// first; on a mismatch no cryptographic checks are run.
+    pub canonical_version: Option<CanonicalVersion>,
+    /// ENS name that must resolve to the signer (needs a provider)
+    pub ens_name: Option<String>,
+    /// Minimum balance, in wei, the signer must hold (needs a provider)
+    pub min_balance: Option<U256>,
+    /// Require the signer to have sent a transaction (needs a provider)
+    pub require_activity: bool,
+}
+
+impl VerifyOptions {
+    fn wants_activity_check(&self) -> bool {
+        self.min_balance.is_some() || self.require_activity
+    }
+}
+
+/// Whether on-chain checks can be made. Offline is pure cryptography.
+#[derive(Debug, Default)]
+pub enum MaybeProvider<R = Provider<Http>> {
+    #[default]
+    Offline,
+    Online(R),
+}
+
+/// Name under which the ENS check is reported and listed when skipped
+pub const ENS_CHECK: &str = "ENS";
+
+/// Name under which the balance/activity check is reported and listed
+/// when skipped
+pub const ACTIVITY_CHECK: &str = "Signer activity";
+
+/// Outcome of every check performed on one snapshot
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct VerificationReport {
+    pub signature_valid: Option<bool>,
+    pub content_hash_matches: Option<bool>,
+    pub address_matches: Option<bool>,
+    pub freshness_ok: Option<bool>,
+    pub chain_id_ok: Option<bool>,
+    pub content_type_ok: Option<bool>,
+    pub canonical_version_ok: Option<bool>,
+    /// Extra `VerifyPolicy` checks run by a `Verifier`
+    pub policy_ok: Option<bool>,
+    /// External files re-hashed against the signed artifacts[]
+    pub artifacts_ok: Option<bool>,
+    pub nonce_ok: Option<bool>,
+    pub ens_ok: Option<bool>,
+    /// Signer balance and transaction count against the configured minimums
+    pub activity_ok: Option<bool>,
+    /// Requested checks that were not run because no provider was given
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<&'static str>,
+    /// Address the signature recovers to
+    pub signer: Option<Address>,
+    /// signing.reason, when the content hash (which covers it) matched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub errors: Vec<String>,
+}
+
+impl VerificationReport {
+    /// True when no performed check failed
+    pub fn is_valid(&self) -> bool {
+        self.checks().iter().all(|(_, check)| check.unwrap_or(true))
+    }
+
+    /// Each check's display name and outcome, in report order
+    pub fn checks(&self) -> [(&'static str, Option<bool>); 12] {
+        [
+            ("Canonical version", self.canonical_version_ok),
+            ("Signature", self.signature_valid),
+            ("Content hash", self.content_hash_matches),
+            ("Address", self.address_matches),
+            ("Freshness", self.freshness_ok),
+            ("Chain id", self.chain_id_ok),
+            ("Content type", self.content_type_ok),
+            ("Policy", self.policy_ok),
+            ("Artifacts", self.artifacts_ok),
+            ("Nonce", self.nonce_ok),
+            (ENS_CHECK, self.ens_ok),
+            (ACTIVITY_CHECK, self.activity_ok),
+        ]
+    }
+
+    /// Names of the checks that were performed and failed
+    pub fn failed_checks(&self) -> Vec<&'static str> {
+        self.checks()
+            .into_iter()
+            .filter(|(_, check)| *check == Some(false))
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    pub(crate) fn record(&mut self, result: Result<()>) -> Option<bool> {
+        match result {
+            Ok(()) => Some(true),
+            Err(e) => {
+                self.errors.push(e.to_string());
+                Some(false)
+            }
+        }
+    }
+}
+
+/// Run every check in `opts` against `snapshot` at time `now` (Unix seconds)
+pub fn verify_report(snapshot: &Value, opts: &VerifyOptions, now: i64) -> VerificationReport {
+    let mut report = VerificationReport::default();
+
+    if let Some(required) = opts.canonical_version {
+        report.canonical_version_ok = report.record(verify_canonical_version(snapshot, required));
+        if report.canonical_version_ok == Some(false) {
+            return report;
+        }
+    }
+
+    let stored_hash = signing::signing_object(snapshot).and_then(|signing| {
+        let s = signing::string_field(signing, "payload_hash")?
+            .ok_or_else(|| anyhow!("missing signing.payload_hash"))?;
+        parse_payload_hash(s)
+    });
+
+    let content = match &stored_hash {
+        Ok(hash) => payload_hash_keccak(snapshot).and_then(|recomputed| {
+            if recomputed == *hash {
+                Ok(())
+            } else {
+                Err(anyhow!("payload_hash mismatch: snapshot content changed"))
+            }
+        }),
+        Err(e) => Err(anyhow!("{e}")),
+    };
+    report.content_hash_matches = report.record(content);
+    if report.content_hash_matches == Some(true) {
+        report.reason = signing::snapshot_reason(snapshot)
+            .ok()
+            .flatten()
+            .map(str::to_string);
+    }
+
+    let recovered = stored_hash.and_then(|hash| {
+        let sig = signing::signing_object(snapshot)?
+            .get("signature")
+            .ok_or_else(|| anyhow!("missing signing.signature"))?;
+        let sig = parse_signature_value(sig)?;
+        signing::recover_hash_signer(hash, &sig, signing::snapshot_scheme(snapshot)?)
+    });
+    let signature = match recovered {
+        Ok(signer) => {
+            report.signer = Some(signer);
+            Ok(())
+        }
+        Err(e) => Err(e),
+    };
+    report.signature_valid = report.record(signature);
+
+    if let Some(expected) = opts.expected_address {
+        let check = match report.signer {
+            Some(signer) if signer == expected => Ok(()),
+            Some(signer) => Err(anyhow!("signed by {signer:?}, expected {expected:?}")),
+            None => Err(anyhow!(
+                "no recoverable signer to compare with {expected:?}"
+            )),
+        };
+        report.address_matches = report.record(check);
+    }
+
+    if opts.max_age.is_some() || has_signing_field(snapshot, "valid_until") {
+        report.freshness_ok = report.record(verify_freshness(
+            snapshot,
+            opts.max_age,
+            opts.clock_skew.unwrap_or(DEFAULT_CLOCK_SKEW),
+            now,
+        ));
+    }
+
+    if let Some(chain_id) = opts.chain_id {
+        report.chain_id_ok = report.record(verify_chain_id(snapshot, chain_id));
+    }
+
+    if let Some(content_type) = &opts.content_type {
+        report.content_type_ok = report.record(verify_content_type(snapshot, content_type));
+    }
+
+    if opts.ens_name.is_some() {
+        report.skipped.push(ENS_CHECK);
+    }
+    if opts.wants_activity_check() {
+        report.skipped.push(ACTIVITY_CHECK);
+    }
+
+    report
+}
+
+/// Like `verify_report`, then run the on-chain checks if `provider` is
+/// online
+pub async fn verify_report_online<R: EnsResolver + AccountReader + Sync>(
+    snapshot: &Value,
+    opts: &VerifyOptions,
+    now: i64,
+    provider: &MaybeProvider<R>,
+) -> VerificationReport {
+    let mut report = verify_report(snapshot, opts, now);
+    run_online_checks(&mut report, opts, provider).await;
+    report
+}
+
+/// Run the checks `verify_report` skipped for want of a provider, on a
+/// report it produced. Offline this leaves the report as is. The signer's
+/// account is only looked up once its signature and content hash have
+/// checked out; a snapshot failing either has already failed.
+pub async fn run_online_checks<R: EnsResolver + AccountReader + Sync>(
+    report: &mut VerificationReport,
+    opts: &VerifyOptions,
+    provider: &MaybeProvider<R>,
+) {
+    let MaybeProvider::Online(resolver) = provider else {
+        return;
+    };
+    if let Some(name) = &opts.ens_name {
+        report.skipped.retain(|check| *check != ENS_CHECK);
+        let check = match (resolver.resolve_name(name).await, report.signer) {
+            (Ok(Some(owner)), Some(signer)) if owner == signer => Ok(()),
+            (Ok(Some(owner)), Some(signer)) => Err(anyhow!(
+                "{name} resolves to {owner:?}, not the signer {signer:?}"
+            )),
+            (Ok(None), _) => Err(anyhow!("{name} does not resolve to an address")),
+            (Err(e), _) => Err(e),
+            (Ok(Some(_)), None) => Err(anyhow!("no recoverable signer to compare with {name}")),
+        };
+        report.ens_ok = report.record(check);
+    }
+    if opts.wants_activity_check() {
+        report.skipped.retain(|check| *check != ACTIVITY_CHECK);
+        let verified =
+            report.signature_valid == Some(true) && report.content_hash_matches == Some(true);
+        if let (true, Some(signer)) = (verified, report.signer) {
+            let check =
+                account::check_activity(signer, opts.min_balance, opts.require_activity, resolver)
+                    .await;
+            report.activity_ok = report.record(check);
+        }
+    }
+}
+
+/// Like `verify_report`, then `consume_nonce`. Snapshots that fail another
+/// check never reach the store, so they cannot burn a nonce.
+pub fn verify_report_with_nonces(
+    snapshot: &Value,
+    opts: &VerifyOptions,
+    now: i64,
+    store: &dyn NonceStore,
+) -> VerificationReport {
+    let mut report = verify_report(snapshot, opts, now);
+    consume_nonce(&mut report, snapshot, store);
+    report
+}
+
+/// If every check in `report` passed, require the signer's signing.nonce
+/// to be new to `store` and record it. Run it after all other checks,
+/// online ones included.
+pub fn consume_nonce(report: &mut VerificationReport, snapshot: &Value, store: &dyn NonceStore) {
+    if let (true, Some(signer)) = (report.is_valid(), report.signer) {
+        report.nonce_ok = report.record(nonce::check_nonce(snapshot, signer, store));
+    }
+}
+
+/// Verify many snapshots in parallel. Reports are returned in input order.
+pub fn verify_batch(
+    snapshots: &[Value],
+    opts: &VerifyOptions,
+    now: i64,
+) -> Vec<VerificationReport> {
+    verify_batch_with(snapshots, opts, now, |_| {})
+}
+
+/// Like `verify_batch`, calling `on_each` from the worker thread as each
+/// report completes (e.g. to advance a progress bar)
+pub fn verify_batch_with<F>(
+    snapshots: &[Value],
+    opts: &VerifyOptions,
+    now: i64,
+    on_each: F,
+) -> Vec<VerificationReport>
+where
+    F: Fn(&VerificationReport) + Sync,
+{
+    snapshots
+        .par_iter()
+        .map(|snapshot| {
+            let report = verify_report(snapshot, opts, now);
+            on_each(&report);
+            report
+        })
+        .collect()
+}
+
+/// Tolerated clock difference between signer and verifier
+pub const DEFAULT_CLOCK_SKEW: Duration = Duration::from_secs(60);
+
+/// Check signing.issued_at is no older than `max_age` and no more than
+/// `skew` in the future and, when present, that signing.valid_until has not
+/// passed.
+pub fn verify_freshness(
+    snapshot: &Value,
+    max_age: Option<Duration>,
+    skew: Duration,
+    now: i64,
+) -> Result<()> {
+    let signing = signing::signing_object(snapshot)?;
+
+    if let Some(max_age) = max_age {
+        let issued_at = signing
+            .get("issued_at")
+            .and_then(|v| v.as_i64())
+            .ok_or_else(|| anyhow!("missing signing.issued_at"))?;
+        let age = now - issued_at;
+        if -age > skew.as_secs() as i64 {
+            return Err(anyhow!(
+                "snapshot issued in the future: {}s ahead, clock skew allowance {}s",
+                -age,
+                skew.as_secs()
+            ));
+        }
+        if age > max_age.as_secs() as i64 {
+            return Err(anyhow!(
+                "snapshot is stale: issued {age}s ago, max age {}s",
+                max_age.as_secs()
+            ));
+        }
+    }
+
+    if let Some(valid_until) = signing.get("valid_until") {
+        let valid_until = valid_until
+            .as_i64()
+            .ok_or_else(|| anyhow!("signing.valid_until must be Unix seconds"))?;
+        if now > valid_until {
+            return Err(anyhow!("snapshot expired at {valid_until}"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check signing.chain_id equals `expected`
+pub fn verify_chain_id(snapshot: &Value, expected: u64) -> Result<()> {
+    match signing::signing_object(snapshot)?
+        .get("chain_id")
+        .and_then(|v| v.as_u64())
+    {
+        Some(id) if id == expected => Ok(()),
+        Some(id) => Err(anyhow!("chain id {id} does not match expected {expected}")),
+        None => Err(anyhow!("missing signing.chain_id")),
+    }
+}
+
+/// Check signing.canonical_version (v1 when absent) is `required`
+pub fn verify_canonical_version(snapshot: &Value, required: CanonicalVersion) -> Result<()> {
+    let found = signing::snapshot_canonical_version(snapshot)?;
+    if found != required {
+        return Err(anyhow!(
+            "snapshot uses canonical_version {}, but {} is required",
+            found.as_u64(),
+            required.as_u64()
+        ));
+    }
+    Ok(())
+}
+
+/// Check signing.content_type equals `expected`. The field is part of the
+/// payload hash, so a matching value is also a signed one.
+pub fn verify_content_type(snapshot: &Value, expected: &str) -> Result<()> {
+    match signing::signing_object(snapshot)?.get("content_type") {
+        Some(Value::String(found)) if found == expected => Ok(()),
+        Some(Value::String(found)) => Err(anyhow!(
+            "content type {found:?} does not match expected {expected:?}"
+        )),
+        Some(_) => Err(anyhow!("signing.content_type must be a string")),
+        None => Err(anyhow!(
+            "missing signing.content_type, expected {expected:?}"
+        )),
+    }
+}
+
+/// Where a mutual-attestation snapshot names its counterpart's address
+pub const PEER_POINTER: &str = "/body/peer";
+
+/// Verify a mutual attestation: both signatures recover, and each
+/// snapshot's body.peer names the other's signer. Returns the signers of
+/// `a` and `b`.
+pub fn verify_mutual(a: &Value, b: &Value) -> Result<(Address, Address)> {
+    let signer_a = signing::recover_signer(a).map_err(|e| anyhow!("first snapshot: {e}"))?;
+    let signer_b = signing::recover_signer(b).map_err(|e| anyhow!("second snapshot: {e}"))?;
+    check_peer(a, signer_b).map_err(|e| anyhow!("first snapshot: {e}"))?;
+    check_peer(b, signer_a).map_err(|e| anyhow!("second snapshot: {e}"))?;
+    Ok((signer_a, signer_b))
+}
+
+fn check_peer(snapshot: &Value, expected: Address) -> Result<()> {
+    let peer = snapshot
+        .pointer(PEER_POINTER)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("missing body.peer"))?;
+    let peer: Address = peer
+        .parse()
+        .map_err(|e| anyhow!("bad body.peer {peer:?}: {e}"))?;
+    if peer != expected {
+        return Err(anyhow!(
+            "body.peer is {peer:?}, but the counterpart was signed by {expected:?}"
+        ));
+    }
+    Ok(())
+}
+
+fn has_signing_field(snapshot: &Value, key: &str) -> bool {
+    snapshot.get("signing").and_then(|s| s.get(key)).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::{SnapshotBuilder, SNAPSHOT_CONTENT_TYPE};
+    use crate::test_support::{address, signed, wallet, KEY_A, KEY_B};
+    use serde_json::json;
+
+    const NOW: i64 = 1_700_000_000;
+
+    async fn issued(issued_at: i64) -> Value {
+        let snap = SnapshotBuilder::new()
+            .id("snap-1")
+            .field("body", json!({ "n": 1 }))
+            .chain_id(8453)
+            .issued_at(issued_at)
+            .content_type(SNAPSHOT_CONTENT_TYPE)
+            .build()
+            .unwrap();
+        signed(snap, KEY_A).await
+    }
+
+    fn all_checks() -> VerifyOptions {
+        VerifyOptions {
+            expected_address: Some(address(KEY_A)),
+            max_age: Some(Duration::from_secs(300)),
+            clock_skew: None,
+            chain_id: Some(8453),
+            content_type: Some(SNAPSHOT_CONTENT_TYPE.to_string()),
+            canonical_version: None,
+            ens_name: None,
+            min_balance: None,
+            require_activity: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn fully_checked_report_json_shape() {
+        let report = verify_report(&issued(NOW - 10).await, &all_checks(), NOW);
+        assert!(report.is_valid(), "{report:?}");
+
+        let value = serde_json::to_value(&report).unwrap();
+        let keys: Vec<&String> = value.as_object().unwrap().keys().collect();
+        assert_eq!(
+            keys,
+            vec![
+                "activity_ok",
+                "address_matches",
+                "artifacts_ok",
+                "canonical_version_ok",
+                "chain_id_ok",
+                "content_hash_matches",
+                "content_type_ok",
+                "ens_ok",
+                "errors",
+                "freshness_ok",
+                "nonce_ok",
+                "policy_ok",
+                "signature_valid",
+                "signer"
+            ]
+        );
+        for key in [
+            "signature_valid",
+            "content_hash_matches",
+            "address_matches",
+            "freshness_ok",
+            "chain_id_ok",
+            "content_type_ok",
+        ] {
+            assert_eq!(value[key], json!(true), "{key}");
+        }
+        assert_eq!(
+            value["signer"],
+            json!(format!("{:?}", all_checks().expected_address.unwrap()))
+        );
+        assert_eq!(value["errors"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn unrequested_checks_are_null() {
+        let report = verify_report(&issued(NOW).await, &VerifyOptions::default(), NOW);
+        let value = serde_json::to_value(&report).unwrap();
+        assert_eq!(value["signature_valid"], json!(true));
+        assert_eq!(value["address_matches"], Value::Null);
+        assert_eq!(value["freshness_ok"], Value::Null);
+        assert_eq!(value["chain_id_ok"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn future_issued_at_within_clock_skew_passes() {
+        let max_age = Some(Duration::from_secs(300));
+        let skew = Duration::from_secs(60);
+        verify_freshness(&issued(NOW + 30).await, max_age, skew, NOW).unwrap();
+        verify_freshness(&issued(NOW - 120).await, max_age, skew, NOW).unwrap();
+
+        let err = verify_freshness(&issued(NOW + 120).await, max_age, skew, NOW).unwrap_err();
+        assert!(err.to_string().contains("issued in the future"), "{err}");
+
+        // Through the report, the default allowance applies unless overridden
+        assert!(verify_report(&issued(NOW + 30).await, &all_checks(), NOW).is_valid());
+        let opts = VerifyOptions {
+            clock_skew: Some(Duration::ZERO),
+            ..all_checks()
+        };
+        assert_eq!(
+            verify_report(&issued(NOW + 30).await, &opts, NOW).freshness_ok,
+            Some(false)
+        );
+    }
+
+    #[tokio::test]
+    async fn records_each_failure() {
+        let mut snap = issued(NOW - 3600).await;
+        snap["body"]["n"] = json!(2);
+        let opts = VerifyOptions {
+            chain_id: Some(1),
+            ..all_checks()
+        };
+
+        let report = verify_report(&snap, &opts, NOW);
+        assert!(!report.is_valid());
+        assert_eq!(report.signature_valid, Some(true));
+        assert_eq!(report.content_hash_matches, Some(false));
+        assert_eq!(report.freshness_ok, Some(false));
+        assert_eq!(report.chain_id_ok, Some(false));
+        assert_eq!(report.errors.len(), 3, "{:?}", report.errors);
+    }
+
+    #[tokio::test]
+    async fn wrongly_typed_payload_hash_is_not_reported_missing() {
+        let mut snap = issued(NOW).await;
+        snap["signing"]["payload_hash"] = json!({ "h": "0x00" });
+
+        let report = verify_report(&snap, &VerifyOptions::default(), NOW);
+        assert_eq!(report.content_hash_matches, Some(false));
+        assert!(
+            report.errors[0].contains("signing.payload_hash must be a string, found object"),
+            "{:?}",
+            report.errors
+        );
+    }
+
+    #[tokio::test]
+    async fn content_type_is_hashed_and_checked() {
+        let snap = issued(NOW).await;
+        let opts = VerifyOptions {
+            content_type: Some(SNAPSHOT_CONTENT_TYPE.to_string()),
+            ..Default::default()
+        };
+        assert!(verify_report(&snap, &opts, NOW).is_valid());
+
+        // Relabelling a signed snapshot breaks the payload hash
+        let mut relabelled = snap.clone();
+        relabelled["signing"]["content_type"] = json!("application/vnd.swarmhive.receipt+json");
+        let report = verify_report(&relabelled, &VerifyOptions::default(), NOW);
+        assert_eq!(report.content_hash_matches, Some(false));
+
+        // An intact snapshot of another kind is rejected by name
+        let receipt = VerifyOptions {
+            content_type: Some("application/vnd.swarmhive.receipt+json".to_string()),
+            ..Default::default()
+        };
+        let report = verify_report(&snap, &receipt, NOW);
+        assert_eq!(report.content_type_ok, Some(false));
+        assert!(
+            report.errors[0].contains("does not match expected"),
+            "{:?}",
+            report.errors
+        );
+
+        let untyped = VerifyOptions::default();
+        assert_eq!(verify_report(&snap, &untyped, NOW).content_type_ok, None);
+    }
+
+    #[tokio::test]
+    async fn required_canonical_version_is_enforced_first() {
+        let wallet = wallet(KEY_A);
+        let v2_only = VerifyOptions {
+            canonical_version: Some(CanonicalVersion::V2),
+            ..Default::default()
+        };
+
+        let v1 = issued(NOW).await;
+        let report = verify_report(&v1, &v2_only, NOW);
+        assert_eq!(report.canonical_version_ok, Some(false));
+        assert_eq!(report.signature_valid, None);
+        assert_eq!(report.content_hash_matches, None);
+        assert!(
+            report.errors[0].contains("canonical_version 1, but 2 is required"),
+            "{:?}",
+            report.errors
+        );
+        assert!(!report.is_valid());
+
+        let mut v2 = SnapshotBuilder::new()
+            .id("snap-2")
+            .field("body", json!({ "n": 1 }))
+            .canonical_version(CanonicalVersion::V2)
+            .build()
+            .unwrap();
+        signing::sign_snapshot(&mut v2, &wallet).await.unwrap();
+        let report = verify_report(&v2, &v2_only, NOW);
+        assert!(report.is_valid(), "{report:?}");
+        assert_eq!(report.canonical_version_ok, Some(true));
+        assert_eq!(report.signature_valid, Some(true));
+    }
+
+    async fn attestation(key: &str, peer: Address) -> Value {
+        signed(json!({ "id": "handshake", "body": { "peer": format!("{peer:?}") }, "signing": { "scheme": "eip191" } }), key).await
+    }
+
+    #[tokio::test]
+    async fn mutual_pair_verifies() {
+        let a = address(KEY_A);
+        let b = address(KEY_B);
+        let snap_a = attestation(KEY_A, b).await;
+        let snap_b = attestation(KEY_B, a).await;
+
+        assert_eq!(verify_mutual(&snap_a, &snap_b).unwrap(), (a, b));
+        assert_eq!(verify_mutual(&snap_b, &snap_a).unwrap(), (b, a));
+    }
+
+    #[tokio::test]
+    async fn mismatched_pair_fails() {
+        let a = address(KEY_A);
+        let snap_a = attestation(KEY_A, Address::repeat_byte(0x11)).await;
+        let snap_b = attestation(KEY_B, a).await;
+
+        let err = verify_mutual(&snap_a, &snap_b).unwrap_err();
+        assert!(
+            err.to_string().starts_with("first snapshot: body.peer is"),
+            "{err}"
+        );
+
+        // A valid reference is no help if the signature is broken
+        let mut tampered = attestation(KEY_B, a).await;
+        tampered["id"] = json!("other");
+        assert!(verify_mutual(&attestation(KEY_A, Address::zero()).await, &tampered).is_err());
+    }
+
+    struct MockEns(Address);
+
+    #[async_trait::async_trait]
+    impl EnsResolver for MockEns {
+        async fn lookup_address(&self, _addr: Address) -> Result<Option<String>> {
+            Ok(None)
+        }
+
+        async fn resolve_name(&self, name: &str) -> Result<Option<Address>> {
+            Ok((name == "node.swarmhive.eth").then_some(self.0))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AccountReader for MockEns {
+        async fn balance(&self, _addr: Address) -> Result<U256> {
+            unreachable!("ENS tests do not look up balances")
+        }
+
+        async fn transaction_count(&self, _addr: Address) -> Result<U256> {
+            unreachable!("ENS tests do not look up transaction counts")
+        }
+    }
+
+    /// Every account holds `balance` wei and has sent `nonce` transactions;
+    /// `lookups` counts the queries made
+    struct MockChain {
+        balance: U256,
+        nonce: U256,
+        lookups: std::sync::atomic::AtomicUsize,
+    }
+
+    impl MockChain {
+        fn new(balance: U256, nonce: u64) -> MaybeProvider<Self> {
+            MaybeProvider::Online(MockChain {
+                balance,
+                nonce: nonce.into(),
+                lookups: Default::default(),
+            })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl EnsResolver for MockChain {
+        async fn lookup_address(&self, _addr: Address) -> Result<Option<String>> {
+            Ok(None)
+        }
+
+        async fn resolve_name(&self, _name: &str) -> Result<Option<Address>> {
+            Ok(None)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AccountReader for MockChain {
+        async fn balance(&self, _addr: Address) -> Result<U256> {
+            self.lookups
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(self.balance)
+        }
+
+        async fn transaction_count(&self, _addr: Address) -> Result<U256> {
+            self.lookups
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(self.nonce)
+        }
+    }
+
+    fn lookups(provider: &MaybeProvider<MockChain>) -> usize {
+        let MaybeProvider::Online(chain) = provider else {
+            unreachable!()
+        };
+        chain.lookups.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    fn ens_opts() -> VerifyOptions {
+        VerifyOptions {
+            ens_name: Some("node.swarmhive.eth".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn offline_skips_and_notes_ens() {
+        let snap = issued(NOW).await;
+        let report =
+            verify_report_online(&snap, &ens_opts(), NOW, &MaybeProvider::<MockEns>::Offline).await;
+        assert!(report.is_valid());
+        assert_eq!(report.ens_ok, None);
+        assert_eq!(report.skipped, vec![ENS_CHECK]);
+        assert_eq!(
+            serde_json::to_value(&report).unwrap()["skipped"],
+            json!(["ENS"])
+        );
+
+        // Nothing requested, nothing skipped
+        let report = verify_report(&snap, &VerifyOptions::default(), NOW);
+        assert!(report.skipped.is_empty());
+    }
+
+    #[tokio::test]
+    async fn online_performs_ens_check() {
+        let snap = issued(NOW).await;
+        let signer = address(KEY_A);
+
+        let online = MaybeProvider::Online(MockEns(signer));
+        let report = verify_report_online(&snap, &ens_opts(), NOW, &online).await;
+        assert!(report.is_valid(), "{report:?}");
+        assert_eq!(report.ens_ok, Some(true));
+        assert!(report.skipped.is_empty());
+
+        let elsewhere = MaybeProvider::Online(MockEns(Address::repeat_byte(0x11)));
+        let report = verify_report_online(&snap, &ens_opts(), NOW, &elsewhere).await;
+        assert!(!report.is_valid());
+        assert_eq!(report.failed_checks(), vec![ENS_CHECK]);
+    }
+
+    #[tokio::test]
+    async fn signer_balance_and_activity_thresholds() {
+        let snap = issued(NOW).await;
+        let opts = VerifyOptions {
+            min_balance: Some(account::parse_eth_amount("0.01eth").unwrap()),
+            require_activity: true,
+            ..Default::default()
+        };
+        let centi = U256::exp10(16);
+
+        let report = verify_report(&snap, &opts, NOW);
+        assert_eq!(report.skipped, vec![ACTIVITY_CHECK]);
+
+        let funded = MockChain::new(centi * 2, 3);
+        let report = verify_report_online(&snap, &opts, NOW, &funded).await;
+        assert!(report.is_valid(), "{report:?}");
+        assert_eq!(report.activity_ok, Some(true));
+        assert!(report.skipped.is_empty());
+
+        let poor = MockChain::new(centi - 1, 3);
+        let report = verify_report_online(&snap, &opts, NOW, &poor).await;
+        assert_eq!(report.failed_checks(), vec![ACTIVITY_CHECK]);
+        assert!(
+            report.errors[0].contains("below the required 0.010000000000000000 ETH"),
+            "{:?}",
+            report.errors
+        );
+
+        let fresh = MockChain::new(centi, 0);
+        let report = verify_report_online(&snap, &opts, NOW, &fresh).await;
+        assert_eq!(report.failed_checks(), vec![ACTIVITY_CHECK]);
+        assert!(
+            report.errors[0].contains("never sent a transaction"),
+            "{:?}",
+            report.errors
+        );
+
+        // A crypto-invalid snapshot fails before the account is looked up
+        let mut tampered = snap.clone();
+        tampered["id"] = json!("other");
+        let before = lookups(&funded);
+        let report = verify_report_online(&tampered, &opts, NOW, &funded).await;
+        assert!(!report.is_valid());
+        assert_eq!(report.activity_ok, None);
+        assert_eq!(lookups(&funded), before);
+    }
+}
@@ -0,0 +1,63 @@
+//! Golden-file tests for canonical JSON serialization
+//!
+//! Each directory under tests/golden/ holds an `input.json` and the
+//! `expected_canonical.bytes` that `canonical_json_bytes` must reproduce
+//! byte-for-byte. Regenerate after an intentional change with:
+//!
+//!     UPDATE_GOLDEN=1 cargo test --test canonical_golden
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use swarmhive::hash::canonical_json_bytes;
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn golden_cases() -> Vec<PathBuf> {
+    let mut cases: Vec<PathBuf> = fs::read_dir(golden_dir())
+        .expect("tests/golden must exist")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.join("input.json").is_file())
+        .collect();
+    cases.sort();
+    cases
+}
+
+#[test]
+fn canonical_bytes_match_goldens() {
+    let update = std::env::var("UPDATE_GOLDEN").is_ok_and(|v| v == "1");
+    let cases = golden_cases();
+    assert!(!cases.is_empty(), "no golden cases found");
+
+    let mut failures = Vec::new();
+    for case in &cases {
+        let name = case.file_name().unwrap().to_string_lossy().to_string();
+        let input = fs::read_to_string(case.join("input.json")).unwrap();
+        let value: serde_json::Value =
+            serde_json::from_str(&input).unwrap_or_else(|e| panic!("{name}: bad input.json: {e}"));
+        let actual = canonical_json_bytes(&value).unwrap();
+
+        let expected_path = case.join("expected_canonical.bytes");
+        if update {
+            fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+
+        let expected = fs::read(&expected_path)
+            .unwrap_or_else(|e| panic!("{name}: missing expected_canonical.bytes: {e}"));
+        if actual != expected {
+            failures.push(format!(
+                "{name}:\n  expected: {}\n  actual:   {}",
+                String::from_utf8_lossy(&expected),
+                String::from_utf8_lossy(&actual)
+            ));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "canonical output diverged from goldens (UPDATE_GOLDEN=1 to accept):\n{}",
+        failures.join("\n")
+    );
+}
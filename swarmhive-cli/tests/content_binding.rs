@@ -0,0 +1,117 @@
+//! Property test: a signature never survives a change to the content
+//!
+//! Each case signs a generated snapshot, changes exactly one value, key or
+//! element somewhere under `body` (never the signing fields), and requires
+//! `verify_eip191` to fail with the content-hash mismatch. Mutations are
+//! chosen by index into the snapshot's nodes, so failures shrink towards
+//! the first node and the simplest edit.
+#![cfg(feature = "ethers")]
+
+use ethers::signers::Signer;
+use proptest::prelude::*;
+use proptest::sample::Index;
+use serde_json::{json, Map, Value};
+use swarmhive::signing;
+
+const TEST_KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+fn arb_leaf() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i32>().prop_map(|n| json!(n)),
+        "[a-zA-Z0-9 é✓]{0,12}".prop_map(Value::String),
+    ]
+}
+
+fn arb_json() -> impl Strategy<Value = Value> {
+    arb_leaf().prop_recursive(4, 48, 6, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+            prop::collection::btree_map("[a-z]{1,6}", inner, 0..6)
+                .prop_map(|m| Value::Object(m.into_iter().collect::<Map<_, _>>())),
+        ]
+    })
+}
+
+/// JSON Pointers of every node under /body, the body itself included
+fn pointers(v: &Value, at: String, out: &mut Vec<String>) {
+    match v {
+        Value::Object(map) => {
+            for (k, child) in map {
+                pointers(
+                    child,
+                    format!("{at}/{}", k.replace('~', "~0").replace('/', "~1")),
+                    out,
+                );
+            }
+        }
+        Value::Array(arr) => {
+            for (i, child) in arr.iter().enumerate() {
+                pointers(child, format!("{at}/{i}"), out);
+            }
+        }
+        _ => {}
+    }
+    out.push(at);
+}
+
+/// Change `v` in one place. `pos` picks a character or member; `grow`
+/// picks between adding and removing for containers.
+fn mutate(v: &mut Value, pos: Index, grow: bool) {
+    match v {
+        Value::Null => *v = Value::Bool(false),
+        Value::Bool(b) => *b = !*b,
+        Value::Number(n) => {
+            let n = n.as_i64().expect("generated numbers are integers");
+            *v = json!(n.wrapping_add(1));
+        }
+        Value::String(s) if s.is_empty() => s.push('x'),
+        Value::String(s) => {
+            let mut chars: Vec<char> = s.chars().collect();
+            let i = pos.index(chars.len());
+            chars[i] = if chars[i] == 'x' { 'y' } else { 'x' };
+            *s = chars.into_iter().collect();
+        }
+        Value::Array(arr) if grow || arr.is_empty() => arr.push(Value::Null),
+        Value::Array(arr) => {
+            arr.remove(pos.index(arr.len()));
+        }
+        // Generated keys are lowercase letters, so this one is always new
+        Value::Object(map) if grow || map.is_empty() => {
+            map.insert("_mutated".to_string(), Value::Null);
+        }
+        Value::Object(map) => {
+            let key = map.keys().nth(pos.index(map.len())).unwrap().clone();
+            map.remove(&key);
+        }
+    }
+}
+
+fn signed(body: Value) -> (Value, ethers::core::types::Address) {
+    let wallet = signing::wallet_from_private_key_hex(TEST_KEY).unwrap();
+    let mut snap = json!({ "id": "snap-1", "body": body, "signing": { "scheme": "eip191" } });
+    futures::executor::block_on(signing::sign_snapshot(&mut snap, &wallet)).unwrap();
+    (snap, wallet.address())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn single_mutation_never_verifies(body in arb_json(), node in any::<Index>(), pos in any::<Index>(), grow in any::<bool>()) {
+        let (snap, signer) = signed(body);
+        signing::verify_eip191(&snap, signer).unwrap();
+
+        let mut nodes = Vec::new();
+        pointers(&snap["body"], "/body".to_string(), &mut nodes);
+        let target = &nodes[node.index(nodes.len())];
+
+        let mut mutated = snap.clone();
+        mutate(mutated.pointer_mut(target).unwrap(), pos, grow);
+        prop_assert_ne!(&mutated, &snap);
+
+        let err = signing::verify_eip191(&mutated, signer).expect_err("mutated snapshot verified");
+        prop_assert!(err.to_string().contains("payload_hash mismatch"), "{} at {}: {}", err, target, mutated);
+    }
+}
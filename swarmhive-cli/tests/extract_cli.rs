@@ -0,0 +1,83 @@
+//! End-to-end tests of `swarmhive extract` as a pipeline gate
+//!
+//! The built binary is run on signed fixtures: a valid snapshot must come
+//! out on stdout intact, anything else must produce no stdout at all and
+//! a nonzero exit status.
+#![cfg(feature = "ethers")]
+
+use ethers::signers::Signer;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::process::{Command, Output};
+use swarmhive::signing;
+
+const TEST_KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+const OTHER_KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+async fn signed_fixture(dir: &Path) -> (String, Value) {
+    let mut snap = json!({ "id": "snap-1", "body": { "nodes": [1, 2, 3] }, "signing": { "scheme": "eip191" } });
+    signing::sign_snapshot(
+        &mut snap,
+        &signing::wallet_from_private_key_hex(TEST_KEY).unwrap(),
+    )
+    .await
+    .unwrap();
+    let path = dir.join("snap.json");
+    std::fs::write(&path, snap.to_string()).unwrap();
+    (path.to_string_lossy().to_string(), snap)
+}
+
+fn extract(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_swarmhive"))
+        .arg("extract")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn address(key: &str) -> String {
+    format!(
+        "{:?}",
+        signing::wallet_from_private_key_hex(key).unwrap().address()
+    )
+}
+
+#[tokio::test]
+async fn valid_snapshot_is_forwarded() {
+    let dir = tempfile::tempdir().unwrap();
+    let (path, snap) = signed_fixture(dir.path()).await;
+
+    let out = extract(&[&path, "--address", &address(TEST_KEY)]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(serde_json::from_slice::<Value>(&out.stdout).unwrap(), snap);
+
+    let out = extract(&[&path, "--strip-signing"]);
+    assert!(out.status.success());
+    assert_eq!(
+        serde_json::from_slice::<Value>(&out.stdout).unwrap(),
+        json!({ "id": "snap-1", "body": { "nodes": [1, 2, 3] } })
+    );
+}
+
+#[tokio::test]
+async fn invalid_snapshot_produces_no_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let (path, mut snap) = signed_fixture(dir.path()).await;
+
+    // Signed by someone else
+    let out = extract(&[&path, "--address", &address(OTHER_KEY)]);
+    assert!(!out.status.success());
+    assert!(out.stdout.is_empty());
+
+    // Content changed after signing
+    snap["body"]["nodes"][0] = json!(9);
+    std::fs::write(&path, snap.to_string()).unwrap();
+    let out = extract(&[&path]);
+    assert!(!out.status.success());
+    assert!(out.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("verification failed"));
+}
@@ -0,0 +1,73 @@
+//! End-to-end tests of `swarmhive verify --nonce-store`
+//!
+//! The nonce is consumed only by a snapshot that passes every other check,
+//! including the ones the command runs after the offline report, such as
+//! `--check-file`.
+#![cfg(feature = "ethers")]
+
+use serde_json::json;
+use std::path::Path;
+use std::process::{Command, Output};
+use swarmhive::{artifacts, signing};
+
+const TEST_KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+fn verify(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_swarmhive"))
+        .arg("verify")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn store_lines(path: &Path) -> usize {
+    std::fs::read_to_string(path)
+        .map(|s| s.lines().count())
+        .unwrap_or(0)
+}
+
+#[tokio::test]
+async fn failed_artifact_check_keeps_nonce_unused() {
+    let dir = tempfile::tempdir().unwrap();
+    let weights = dir.path().join("weights.bin");
+    std::fs::write(&weights, b"signed weights").unwrap();
+    let mut snap = json!({ "id": "snap-1", "body": { "n": 1 }, "signing": { "scheme": "eip191", "nonce": "n-1" } });
+    artifacts::attach_artifact(&mut snap, &weights).unwrap();
+    signing::sign_snapshot(
+        &mut snap,
+        &signing::wallet_from_private_key_hex(TEST_KEY).unwrap(),
+    )
+    .await
+    .unwrap();
+    let snap_path = dir.path().join("snap.json");
+    std::fs::write(&snap_path, snap.to_string()).unwrap();
+    let store = dir.path().join("nonces.jsonl");
+    let args = |weights: &Path| {
+        vec![
+            snap_path.to_str().unwrap().to_string(),
+            "--nonce-store".to_string(),
+            store.to_str().unwrap().to_string(),
+            "--check-file".to_string(),
+            weights.to_str().unwrap().to_string(),
+        ]
+    };
+    let run =
+        |weights: &Path| verify(&args(weights).iter().map(String::as_str).collect::<Vec<_>>());
+
+    // A tampered copy of the artifact fails, and the nonce stays unused
+    let tampered = dir.path().join("tampered").join("weights.bin");
+    std::fs::create_dir(tampered.parent().unwrap()).unwrap();
+    std::fs::write(&tampered, b"other weights").unwrap();
+    assert!(!run(&tampered).status.success());
+    assert_eq!(store_lines(&store), 0);
+
+    // The genuine artifact passes and uses the nonce up
+    let out = run(&weights);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stdout)
+    );
+    assert_eq!(store_lines(&store), 1);
+    assert!(!run(&weights).status.success());
+}